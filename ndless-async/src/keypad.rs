@@ -1,10 +1,10 @@
 //! Listens for button presses and releases from the keypad.
 //!
 //! To get started, [create a `KeypadListener`][KeypadListener::new] and read
-//! its documentation.
+//! its documentation. If you only need key presses, [`key_stream`] is simpler.
 
 use alloc::rc::{Rc, Weak};
-use core::cell::{Ref, RefCell};
+use core::cell::{Cell, Ref, RefCell};
 use core::future::Future;
 use core::mem;
 use core::pin::Pin;
@@ -19,13 +19,27 @@ use ndless::input::{iter_keys, Key};
 use ndless::prelude::*;
 use ndless::timer::{get_ticks, Ticks, TICKS_PER_SECOND};
 
+use crate::task::current_timer;
 use crate::timer::TimerListener;
 
 /// The state of the key, either pressed or released.
+///
+/// `LongPressed` and `DoublePressed` are only sent if enabled with
+/// [`KeypadListener::long_press`] and [`KeypadListener::double_press`]. They
+/// are sent in addition to the normal `Pressed` and `Released` events.
+///
+/// More states may be added, so matches need a `_` arm.
 #[derive(Eq, PartialEq, Copy, Clone, Debug, Hash)]
+#[non_exhaustive]
 pub enum KeyState {
 	Pressed,
 	Released,
+	/// The key has been held down for the long-press duration. This is sent
+	/// once per press, while the key is still held.
+	LongPressed,
+	/// The key was pressed again soon after it was last pressed. This is sent
+	/// right after the second `Pressed` event.
+	DoublePressed,
 }
 
 /// One event representing a key press or release.
@@ -42,13 +56,89 @@ struct SharedKeyQueue {
 	waker: AtomicWaker,
 }
 
+/// When a held key was pressed, for long-press detection.
+struct HeldKey {
+	key: Key,
+	pressed_at: u32,
+	long_pressed: bool,
+}
+
 #[derive(Default)]
 struct KeypadListenerInner {
 	queues: RefCell<Vec<Rc<SharedKeyQueue>>>,
 	keys: RefCell<Vec<Key>>,
+	long_press: Cell<Option<u32>>,
+	double_press: Cell<Option<u32>>,
+	held: RefCell<Vec<HeldKey>>,
+	/// The last time each key was pressed, for double-press detection
+	last_pressed: RefCell<Vec<(Key, u32)>>,
 }
 
 impl KeypadListenerInner {
+	fn push(queues: &[Rc<SharedKeyQueue>], event: KeyEvent) {
+		queues
+			.iter()
+			.for_each(|queue| queue.queue.push(event).ignore());
+	}
+
+	fn pressed(&self, queues: &[Rc<SharedKeyQueue>], key: Key, tick_at: u32) {
+		if self.long_press.get().is_some() {
+			self.held.borrow_mut().push(HeldKey {
+				key,
+				pressed_at: tick_at,
+				long_pressed: false,
+			});
+		}
+		if let Some(window) = self.double_press.get() {
+			let mut last_pressed = self.last_pressed.borrow_mut();
+			match last_pressed.iter().position(|(other, _)| *other == key) {
+				Some(i) if tick_at.wrapping_sub(last_pressed[i].1) <= window => {
+					// Start over, so that a triple press is only one double press
+					last_pressed.swap_remove(i);
+					Self::push(
+						queues,
+						KeyEvent {
+							key,
+							state: KeyState::DoublePressed,
+							tick_at,
+						},
+					);
+				}
+				Some(i) => last_pressed[i].1 = tick_at,
+				None => last_pressed.push((key, tick_at)),
+			}
+		}
+	}
+
+	fn released(&self, key: Key) {
+		self.held.borrow_mut().retain(|held| held.key != key);
+	}
+
+	/// Sends long-press events for keys that have been held long enough.
+	/// Returns true if any were sent.
+	fn check_long_presses(&self, queues: &[Rc<SharedKeyQueue>], tick_at: u32) -> bool {
+		let duration = match self.long_press.get() {
+			Some(duration) => duration,
+			None => return false,
+		};
+		let mut sent = false;
+		for held in self.held.borrow_mut().iter_mut() {
+			if !held.long_pressed && tick_at.wrapping_sub(held.pressed_at) >= duration {
+				held.long_pressed = true;
+				sent = true;
+				Self::push(
+					queues,
+					KeyEvent {
+						key: held.key,
+						state: KeyState::LongPressed,
+						tick_at,
+					},
+				);
+			}
+		}
+		sent
+	}
+
 	fn poll(&self) {
 		let mut queues = self.queues.borrow_mut();
 		queues.retain(|queue| Rc::strong_count(queue) > 1);
@@ -69,16 +159,15 @@ impl KeypadListenerInner {
 				change = true;
 				keys.push(key);
 				let tick_at = get_ticks();
-				queues.iter_mut().for_each(|queue| {
-					queue
-						.queue
-						.push(KeyEvent {
-							key,
-							state: KeyState::Pressed,
-							tick_at,
-						})
-						.ignore()
-				});
+				Self::push(
+					&queues,
+					KeyEvent {
+						key,
+						state: KeyState::Pressed,
+						tick_at,
+					},
+				);
+				self.pressed(&queues, key, tick_at);
 				if keys.len() > retain_i + 1 {
 					let (last, beginning) = keys.split_last_mut().unwrap();
 					mem::swap(&mut beginning[retain_i], last);
@@ -90,17 +179,17 @@ impl KeypadListenerInner {
 		for _ in retain_i..keys.len() {
 			change = true;
 			let key = keys.pop().unwrap();
-			queues.iter_mut().for_each(|queue| {
-				queue
-					.queue
-					.push(KeyEvent {
-						key,
-						state: KeyState::Released,
-						tick_at,
-					})
-					.ignore()
-			});
+			Self::push(
+				&queues,
+				KeyEvent {
+					key,
+					state: KeyState::Released,
+					tick_at,
+				},
+			);
+			self.released(key);
 		}
+		change |= self.check_long_presses(&queues, tick_at);
 		if change {
 			queues.iter_mut().for_each(|queue| queue.waker.wake());
 		}
@@ -201,6 +290,32 @@ impl<'a> KeypadListener<'a> {
 		self.interval.replace(Rc::downgrade(&interval));
 		interval
 	}
+	/// Enables [`LongPressed`][KeyState::LongPressed] events, sent when a key
+	/// has been held down for `dur`. Long presses can only be detected as
+	/// precisely as the keypad is polled.
+	///
+	/// ```rust
+	/// let keypad = KeypadListener::new(&listeners.timer())
+	///     .long_press(Duration::from_millis(1000));
+	/// // Hold escape to exit
+	/// keypad
+	///     .stream()
+	///     .filter(|event| {
+	///         future::ready(event.key == Key::Esc && event.state == KeyState::LongPressed)
+	///     })
+	///     .next()
+	///     .await;
+	/// ```
+	pub fn long_press(self, dur: Duration) -> Self {
+		self.inner.long_press.set(Some(dur.as_ticks()));
+		self
+	}
+	/// Enables [`DoublePressed`][KeyState::DoublePressed] events, sent when a
+	/// key is pressed twice within `dur`.
+	pub fn double_press(self, dur: Duration) -> Self {
+		self.inner.double_press.set(Some(dur.as_ticks()));
+		self
+	}
 	/// Polls the keypad. You shouldn't have to use this normally.
 	pub fn poll(&self) {
 		self.inner.poll();
@@ -294,3 +409,81 @@ impl Stream for KeyStream {
 		}
 	}
 }
+
+/// Returns a stream of keys as they are pressed, using the listeners passed to
+/// the running [`block_on`][crate::task::block_on]. Releases are skipped, as
+/// are presses that come right after the same key was released, which happens
+/// when a worn key bounces.
+///
+/// ```rust
+/// use ndless_async::keypad::key_stream;
+/// use ndless_async::task::{block_on, AsyncListeners};
+/// use ndless_async::StreamExt;
+/// use ndless::input::Key;
+///
+/// block_on(&AsyncListeners::new(), async {
+///     let mut keys = key_stream();
+///     while let Some(key) = keys.next().await {
+///         if key == Key::Esc {
+///             break;
+///         }
+///     }
+/// });
+/// ```
+///
+/// # Panics
+/// Panics if called outside of [`block_on`][crate::task::block_on].
+pub fn key_stream() -> KeyPresses {
+	KeyPresses {
+		stream: KeypadListener::new(current_timer()).stream(),
+		debounce: Duration::from_millis(20).as_ticks(),
+		released: Vec::new(),
+	}
+}
+
+/// A stream of pressed [`Key`]s. Use [`key_stream`] to get one.
+pub struct KeyPresses {
+	stream: KeyStream,
+	debounce: u32,
+	/// When each key was last released
+	released: Vec<(Key, u32)>,
+}
+
+impl KeyPresses {
+	/// Ignores presses within `dur` of the same key being released. Defaults to
+	/// 20ms.
+	pub fn debounce(mut self, dur: Duration) -> Self {
+		self.debounce = dur.as_ticks();
+		self
+	}
+}
+
+impl Stream for KeyPresses {
+	type Item = Key;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		loop {
+			let event = match Pin::new(&mut self.stream).poll_next(cx) {
+				Poll::Ready(Some(event)) => event,
+				Poll::Ready(None) => return Poll::Ready(None),
+				Poll::Pending => return Poll::Pending,
+			};
+			match event.state {
+				KeyState::Released => {
+					self.released.retain(|(key, _)| *key != event.key);
+					self.released.push((event.key, event.tick_at));
+				}
+				KeyState::Pressed => {
+					let debounce = self.debounce;
+					let bounced = self.released.iter().any(|(key, at)| {
+						*key == event.key && event.tick_at.wrapping_sub(*at) < debounce
+					});
+					if !bounced {
+						return Poll::Ready(Some(event.key));
+					}
+				}
+				_ => {}
+			}
+		}
+	}
+}
@@ -100,6 +100,7 @@ pub use yield_now::Yield;
 
 pub mod keypad;
 pub mod mpsc;
+mod spawn;
 pub mod task;
 pub mod timer;
 mod yield_now;
@@ -0,0 +1,119 @@
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use ndless::alloc::vec::Vec;
+
+use futures_util::task::{AtomicWaker, Context, Poll, Waker};
+
+struct Flag(AtomicBool);
+
+impl Wake for Flag {
+	fn wake(self: Arc<Self>) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	fn wake_by_ref(self: &Arc<Self>) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+}
+
+struct Task {
+	future: Pin<Box<dyn Future<Output = ()>>>,
+	woken: Arc<Flag>,
+}
+
+#[derive(Default)]
+pub(crate) struct Spawner {
+	tasks: RefCell<Vec<Task>>,
+}
+
+impl Spawner {
+	pub(crate) fn spawn<T: 'static>(
+		&self,
+		future: impl Future<Output = T> + 'static,
+	) -> JoinHandle<T> {
+		let state = Rc::new(JoinState {
+			result: RefCell::new(None),
+			waker: AtomicWaker::new(),
+		});
+		let task_state = state.clone();
+		self.tasks.borrow_mut().push(Task {
+			future: Box::pin(async move {
+				let result = future.await;
+				*task_state.result.borrow_mut() = Some(result);
+				task_state.waker.wake();
+			}),
+			woken: Arc::new(Flag(AtomicBool::new(true))),
+		});
+		JoinHandle { state }
+	}
+
+	/// Returns true if any spawned task needs to be polled
+	pub(crate) fn is_woken(&self) -> bool {
+		self.tasks
+			.borrow()
+			.iter()
+			.any(|task| task.woken.0.load(Ordering::Relaxed))
+	}
+
+	/// Polls each woken task, removing the ones that have completed
+	pub(crate) fn poll(&self) {
+		// Tasks may spawn more tasks while being polled
+		let tasks = core::mem::take(&mut *self.tasks.borrow_mut());
+		let mut tasks: Vec<Task> = tasks
+			.into_iter()
+			.filter_map(|mut task| {
+				if !task.woken.0.swap(false, Ordering::Relaxed) {
+					return Some(task);
+				}
+				let waker = Waker::from(task.woken.clone());
+				let mut context = Context::from_waker(&waker);
+				match task.future.as_mut().poll(&mut context) {
+					Poll::Ready(()) => None,
+					Poll::Pending => Some(task),
+				}
+			})
+			.collect();
+		let mut spawned = self.tasks.borrow_mut();
+		tasks.append(&mut spawned);
+		*spawned = tasks;
+	}
+}
+
+struct JoinState<T> {
+	result: RefCell<Option<T>>,
+	waker: AtomicWaker,
+}
+
+/// Resolves to the output of a task started with
+/// [`AsyncListeners::spawn_local`][crate::task::AsyncListeners::spawn_local].
+///
+/// Dropping the handle does not cancel the task; it keeps running in the
+/// background.
+pub struct JoinHandle<T> {
+	state: Rc<JoinState<T>>,
+}
+
+impl<T> JoinHandle<T> {
+	/// Returns true if the task has completed.
+	pub fn is_finished(&self) -> bool {
+		self.state.result.borrow().is_some()
+	}
+}
+
+impl<T> Future for JoinHandle<T> {
+	type Output = T;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+		self.state.waker.register(cx.waker());
+		match self.state.result.borrow_mut().take() {
+			Some(result) => Poll::Ready(result),
+			None => Poll::Pending,
+		}
+	}
+}
@@ -29,16 +29,26 @@ use alloc::{sync::Arc, task::Wake};
 use core::future::Future;
 use core::sync::atomic::{AtomicBool, Ordering};
 use core::task::{Context, Poll, Waker};
+use core::time::Duration;
 
+use futures_util::future::FutureExt;
 use futures_util::pin_mut;
 use ndless::hw::idle;
 use ndless::timer::disable_sleep;
 
-use crate::timer::TimerListener;
+use crate::select;
+pub use crate::spawn::JoinHandle;
+use crate::spawn::Spawner;
+use crate::timer::{TimeoutError, Timer, TimerListener};
 use crate::yield_now::{Yield, YieldListener};
 
 /// Spawns a task and blocks until the future resolves, returning its result.
+///
+/// Tasks started with [`AsyncListeners::spawn_local`] run alongside it, but
+/// are not waited for: once `task` completes, they stop being polled until the
+/// next call to `block_on`.
 pub fn block_on<T>(listeners: &AsyncListeners, task: impl Future<Output = T>) -> T {
+	let _current = CurrentGuard::set(listeners);
 	let wake_marker = Arc::new(TaskWaker {
 		wake_marker: AtomicBool::new(true),
 	});
@@ -49,16 +59,14 @@ pub fn block_on<T>(listeners: &AsyncListeners, task: impl Future<Output = T>) ->
 	loop {
 		listeners.timer.poll();
 		listeners.yielder.poll();
-		while wake_marker.wake_marker.load(Ordering::Relaxed) {
-			match task.as_mut().poll(&mut context) {
-				Poll::Ready(val) => {
+		while wake_marker.wake_marker.load(Ordering::Relaxed) || listeners.spawner.is_woken() {
+			if wake_marker.wake_marker.swap(false, Ordering::Relaxed) {
+				if let Poll::Ready(val) = task.as_mut().poll(&mut context) {
 					disable_sleep();
 					return val;
 				}
-				Poll::Pending => {
-					wake_marker.wake_marker.store(false, Ordering::Relaxed);
-				}
 			}
+			listeners.spawner.poll();
 			listeners.timer.poll();
 			listeners.yielder.poll();
 		}
@@ -68,6 +76,66 @@ pub fn block_on<T>(listeners: &AsyncListeners, task: impl Future<Output = T>) ->
 	}
 }
 
+/// The listeners passed to the innermost running [`block_on`]
+static mut CURRENT: Option<*const AsyncListeners> = None;
+
+struct CurrentGuard(Option<*const AsyncListeners>);
+
+impl CurrentGuard {
+	fn set(listeners: &AsyncListeners) -> Self {
+		CurrentGuard(unsafe { CURRENT.replace(listeners) })
+	}
+}
+
+impl Drop for CurrentGuard {
+	fn drop(&mut self) {
+		unsafe { CURRENT = self.0 }
+	}
+}
+
+pub(crate) fn current_timer() -> &'static TimerListener {
+	match unsafe { CURRENT } {
+		// Only used while block_on is still running
+		Some(listeners) => unsafe { &(*listeners).timer },
+		None => panic!("must be called inside block_on"),
+	}
+}
+
+/// Sleeps for the specified [`Duration`], using the listeners passed to the
+/// running [`block_on`]. This is the same as
+/// [`TimerListener::sleep`], without needing to pass the listeners around.
+///
+/// ```
+/// use ndless_async::task::{self, block_on, AsyncListeners};
+/// use core::time::Duration;
+///
+/// block_on(&AsyncListeners::new(), async {
+///     task::sleep(Duration::from_millis(500)).await;
+/// });
+/// ```
+///
+/// # Panics
+/// Panics if called outside of [`block_on`].
+pub fn sleep(dur: Duration) -> Timer {
+	current_timer().sleep(dur)
+}
+
+/// Awaits a future or times out after the specified [`Duration`], using the
+/// listeners passed to the running [`block_on`]. The future is dropped if it
+/// times out. See [`TimerListener::timeout`].
+///
+/// # Panics
+/// Panics if called outside of [`block_on`].
+pub async fn timeout<T>(dur: Duration, f: impl Future<Output = T>) -> Result<T, TimeoutError> {
+	let timer = sleep(dur);
+	let f = f.fuse();
+	pin_mut!(f);
+	select! {
+		x = f => Ok(x),
+		_ = timer.fuse() => Err(TimeoutError),
+	}
+}
+
 struct TaskWaker {
 	wake_marker: AtomicBool,
 }
@@ -96,6 +164,7 @@ impl Wake for TaskWaker {
 pub struct AsyncListeners {
 	timer: TimerListener,
 	yielder: YieldListener,
+	spawner: Spawner,
 }
 
 impl AsyncListeners {
@@ -116,4 +185,31 @@ impl AsyncListeners {
 	pub fn yield_now(&self) -> Yield {
 		self.yielder.yield_now()
 	}
+	/// Starts running a task in the background, without waiting for it to
+	/// complete. The returned [`JoinHandle`] can be awaited to get its result.
+	///
+	/// Spawned tasks only run while inside [`block_on`]. They must be
+	/// `'static`, so to use the listeners from a spawned task, allocate them
+	/// with `Box::leak` or keep them in a `static`.
+	///
+	/// ```
+	/// use ndless_async::task::{block_on, AsyncListeners};
+	///
+	/// let listeners: &'static AsyncListeners = Box::leak(Box::new(AsyncListeners::new()));
+	/// block_on(listeners, async move {
+	///     let music = listeners.spawn_local(async move {
+	///         loop {
+	///             listeners.timer().sleep_ms(250).await;
+	///             play_next_note();
+	///         }
+	///     });
+	///     run_game(listeners).await;
+	/// });
+	/// ```
+	pub fn spawn_local<T: 'static>(
+		&self,
+		future: impl Future<Output = T> + 'static,
+	) -> JoinHandle<T> {
+		self.spawner.spawn(future)
+	}
 }
@@ -0,0 +1,161 @@
+//! Build-time bitmap font generation
+//!
+//! This crate runs on the computer building a program, usually from its
+//! `build.rs`. It rasterizes a TrueType or OpenType font at a fixed size into
+//! the format read by `ndless::font::BitmapFont`, which is documented there.
+//!
+//! ```no_run
+//! // build.rs
+//! fn main() {
+//!     ndless_fontgen::FontGen::open("assets/Roboto-Regular.ttf")
+//!         .unwrap()
+//!         .size(12.0)
+//!         .build("roboto-12.nfnt")
+//!         .unwrap();
+//! }
+//! ```
+
+use std::convert::TryFrom;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use fontdue::{Font, FontSettings};
+
+const MAGIC: &[u8; 4] = b"NFNT";
+const VERSION: u8 = 1;
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn byte(value: usize, what: &str, ch: char) -> io::Result<u8> {
+	u8::try_from(value).map_err(|_| invalid(format!("{} of {:?} doesn't fit in a byte", what, ch)))
+}
+
+fn signed_byte(value: i32, what: &str, ch: char) -> io::Result<i8> {
+	i8::try_from(value).map_err(|_| invalid(format!("{} of {:?} doesn't fit in a byte", what, ch)))
+}
+
+/// Renders a font at one size.
+pub struct FontGen {
+	font: Font,
+	source: Option<PathBuf>,
+	size: f32,
+	chars: Vec<char>,
+	threshold: Option<u8>,
+}
+
+impl FontGen {
+	/// Loads a TrueType or OpenType font.
+	pub fn from_bytes(data: &[u8]) -> io::Result<Self> {
+		Ok(Self {
+			font: Font::from_bytes(data, FontSettings::default()).map_err(invalid)?,
+			source: None,
+			size: 12.,
+			chars: (' '..='~').collect(),
+			threshold: None,
+		})
+	}
+
+	/// Loads a TrueType or OpenType font from a file. When built with
+	/// [`build`][FontGen::build], Cargo reruns the build script if the file
+	/// changes.
+	pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+		let path = path.as_ref();
+		let mut font_gen = Self::from_bytes(&fs::read(path)?)?;
+		font_gen.source = Some(path.to_owned());
+		Ok(font_gen)
+	}
+
+	/// Sets the size in pixels. Defaults to 12.
+	pub fn size(mut self, px: f32) -> Self {
+		self.size = px;
+		self
+	}
+
+	/// Sets which characters to include. Defaults to printable ASCII.
+	/// Characters missing from the font are skipped.
+	pub fn chars(mut self, chars: impl IntoIterator<Item = char>) -> Self {
+		self.chars = chars.into_iter().collect();
+		self
+	}
+
+	/// Renders without anti-aliasing: pixels with coverage of at least
+	/// `threshold` become opaque, and the rest transparent. This looks better
+	/// on the grayscale screens of older models.
+	pub fn threshold(mut self, threshold: u8) -> Self {
+		self.threshold = Some(threshold);
+		self
+	}
+
+	/// Renders the font, returning the bytes of the bitmap font.
+	pub fn generate(&self) -> io::Result<Vec<u8>> {
+		let line = self
+			.font
+			.horizontal_line_metrics(self.size)
+			.ok_or_else(|| invalid("font has no horizontal metrics"))?;
+		let ascent = line.ascent.ceil() as i32;
+		let line_height = byte(line.new_line_size.ceil() as usize, "line height", ' ')?;
+
+		let mut chars = self.chars.clone();
+		chars.sort_unstable();
+		chars.dedup();
+		chars.retain(|&ch| self.font.lookup_glyph_index(ch) != 0);
+
+		let mut records = Vec::with_capacity(chars.len() * 16);
+		let mut bitmaps = Vec::new();
+		for &ch in &chars {
+			let (metrics, mut coverage) = self.font.rasterize(ch, self.size);
+			if let Some(threshold) = self.threshold {
+				for pixel in coverage.iter_mut() {
+					*pixel = if *pixel >= threshold { 255 } else { 0 };
+				}
+			}
+			let offset = u32::try_from(bitmaps.len()).map_err(|_| invalid("font is too large"))?;
+			records.extend_from_slice(&(ch as u32).to_le_bytes());
+			records.extend_from_slice(&offset.to_le_bytes());
+			records.push(byte(metrics.width, "width", ch)?);
+			records.push(byte(metrics.height, "height", ch)?);
+			records.push(signed_byte(metrics.xmin, "x offset", ch)? as u8);
+			let top = ascent - (metrics.ymin + metrics.height as i32);
+			records.push(signed_byte(top, "y offset", ch)? as u8);
+			records.push(byte(metrics.advance_width.round() as usize, "advance", ch)?);
+			records.extend_from_slice(&[0; 3]);
+			bitmaps.extend_from_slice(&coverage);
+		}
+
+		let count = u16::try_from(chars.len()).map_err(|_| invalid("too many characters"))?;
+		let mut out = Vec::with_capacity(10 + records.len() + bitmaps.len());
+		out.extend_from_slice(MAGIC);
+		out.push(VERSION);
+		out.push(line_height);
+		out.push(byte(ascent.max(0) as usize, "ascent", ' ')?);
+		out.push(0);
+		out.extend_from_slice(&count.to_le_bytes());
+		out.extend_from_slice(&records);
+		out.extend_from_slice(&bitmaps);
+		Ok(out)
+	}
+
+	/// Renders the font to a file.
+	pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		fs::write(path, self.generate()?)
+	}
+
+	/// Renders the font to `name` in the build script's `OUT_DIR`, where it can
+	/// be embedded with
+	/// `include_bytes!(concat!(env!("OUT_DIR"), "/name"))`. Must be called
+	/// from a build script.
+	pub fn build(&self, name: &str) -> io::Result<PathBuf> {
+		let out_dir = env::var_os("OUT_DIR")
+			.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set"))?;
+		let path = Path::new(&out_dir).join(name);
+		self.write(&path)?;
+		if let Some(source) = &self.source {
+			println!("cargo:rerun-if-changed={}", source.display());
+		}
+		Ok(path)
+	}
+}
@@ -1,10 +1,12 @@
-//! Taken from https://github.com/coolreader18/rsspire
+//! Taken from https://github.com/coolreader18/rsspire, with support for the
+//! statistics and arena in `ndless::heap`
 
 use core::alloc::{GlobalAlloc, Layout};
 use core::mem::size_of;
 use core::ptr;
 
 use cty::c_void;
+use ndless::heap;
 
 /// This allows for dynamic allocation, which calls the C functions `calloc` and
 /// `free`.
@@ -16,17 +18,29 @@ const MIN_ALIGN: usize = size_of::<usize>();
 unsafe impl GlobalAlloc for CAllocator {
 	#[inline]
 	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-		if layout.align() <= MIN_ALIGN && layout.align() <= layout.size() {
+		if let Some(ptr) = heap::__arena_alloc(layout) {
+			return record_alloc(ptr, layout.size());
+		}
+		let ptr = if layout.align() <= MIN_ALIGN && layout.align() <= layout.size() {
 			ndless_sys::malloc(layout.size()) as *mut u8
 		} else {
 			malloc_aligned(layout.align(), layout.size()) as *mut u8
-		}
+		};
+		record_alloc(ptr, layout.size())
 	}
 
 	#[inline]
 	unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+		if let Some(ptr) = heap::__arena_alloc(layout) {
+			// Arena memory may have been used before
+			ptr::write_bytes(ptr, 0, layout.size());
+			return record_alloc(ptr, layout.size());
+		}
 		if layout.align() <= MIN_ALIGN && layout.align() <= layout.size() {
-			ndless_sys::calloc(layout.size(), 1) as *mut u8
+			record_alloc(
+				ndless_sys::calloc(layout.size(), 1) as *mut u8,
+				layout.size(),
+			)
 		} else {
 			let ptr = self.alloc(layout.clone());
 			if !ptr.is_null() {
@@ -38,6 +52,10 @@ unsafe impl GlobalAlloc for CAllocator {
 
 	#[inline]
 	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		heap::__record_dealloc(layout.size());
+		if heap::__arena_dealloc(ptr, layout) {
+			return;
+		}
 		if layout.align() <= MIN_ALIGN && layout.align() <= layout.size() {
 			ndless_sys::free(ptr as *mut c_void);
 		} else {
@@ -47,9 +65,7 @@ unsafe impl GlobalAlloc for CAllocator {
 
 	#[inline]
 	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
-		if layout.align() <= MIN_ALIGN && layout.align() <= new_size {
-			ndless_sys::realloc(ptr as *mut c_void, new_size) as *mut u8
-		} else {
+		if heap::__arena_contains(ptr) {
 			// Docs for GlobalAlloc::realloc require this to be valid:
 			let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
 
@@ -59,9 +75,40 @@ unsafe impl GlobalAlloc for CAllocator {
 				ptr::copy_nonoverlapping(ptr, new_ptr, size);
 				GlobalAlloc::dealloc(self, ptr, layout);
 			}
+			return new_ptr;
+		}
+		let new_ptr = if layout.align() <= MIN_ALIGN && layout.align() <= new_size {
+			ndless_sys::realloc(ptr as *mut c_void, new_size) as *mut u8
+		} else {
+			let new_ptr = malloc_aligned(layout.align(), new_size) as *mut u8;
+			if !new_ptr.is_null() {
+				let size = core::cmp::min(layout.size(), new_size);
+				ptr::copy_nonoverlapping(ptr, new_ptr, size);
+				if layout.align() <= MIN_ALIGN && layout.align() <= layout.size() {
+					ndless_sys::free(ptr as *mut c_void);
+				} else {
+					free_aligned(ptr as *mut c_void);
+				}
+			}
 			new_ptr
+		};
+		if new_ptr.is_null() {
+			heap::__record_failure();
+		} else {
+			heap::__record_realloc(layout.size(), new_size);
 		}
+		new_ptr
+	}
+}
+
+/// Updates the heap statistics after an allocation
+fn record_alloc(ptr: *mut u8, size: usize) -> *mut u8 {
+	if ptr.is_null() {
+		heap::__record_failure();
+	} else {
+		heap::__record_alloc(size);
 	}
+	ptr
 }
 
 // All of this converted from the C code at https://stackoverflow.com/a/6563989/9236675
@@ -4,9 +4,6 @@
 #![feature(panic_info_message)]
 extern crate alloc;
 
-use alloc::format;
-use alloc::string::ToString;
-
 use crate::allocator::CAllocator;
 use core::slice;
 
@@ -23,39 +20,39 @@ fn lang_start<T: ndless::process::Termination + 'static>(
 	argc: isize,
 	argv: *const *const u8,
 ) -> isize {
+	#[allow(unused_mut)]
+	let mut startup = ndless::process::Startup::DEFAULT;
+	#[cfg(not(feature = "crash-handler"))]
+	{
+		startup.crash = ndless::process::CrashHandler::Os;
+	}
 	unsafe {
-		ndless::__init(slice::from_raw_parts(argv as *const _, argc as usize));
+		ndless::__init_with(
+			slice::from_raw_parts(argv as *const _, argc as usize),
+			startup,
+		);
 	}
-	main().report() as isize
+	let code = main().report() as isize;
+	ndless::process::__cleanup();
+	code
 }
 
 #[cfg(feature = "oom-handler")]
 #[alloc_error_handler]
-fn on_oom(_layout: core::alloc::Layout) -> ! {
-	unsafe {
-		ndless_sys::abort();
-	}
+fn on_oom(layout: core::alloc::Layout) -> ! {
+	ndless::heap::__alloc_error(layout);
+	ndless::process::abort();
 }
 
 #[cfg(feature = "panic-handler")]
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
-	{
-		let msg = match info.message() {
-			Some(err) => format!("An error occured: {}", err),
-			None => "An error occured!".to_string(),
-		};
-		let location = match info.location() {
-			Some(loc) => format!(
-				"In file {} at line {} column {}",
-				loc.file(),
-				loc.line(),
-				loc.column()
-			),
-			None => "".to_string(),
-		};
-		ndless::msg::msg("Error", &format!("{}\n{}", msg, location));
-	}
+	// Returns only if not inside ndless::panic::catch
+	ndless::panic::__unwind_if_caught(info);
+	// The panic screen can't be drawn if a program's Screen has the LCD
+	ndless::video::restore();
+	ndless::panic::show(info);
+	ndless::process::run_exit_hooks();
 	ndless::process::abort();
 }
 
@@ -5,7 +5,40 @@ use proc_macro::TokenStream;
 use proc_macro2::Span;
 use quote::quote;
 use syn::parse_macro_input;
-use syn::{parse, spanned::Spanned, ItemFn};
+use syn::{
+	parse, spanned::Spanned, AttributeArgs, DeriveInput, ItemFn, Lit, Meta, NestedMeta, Type,
+};
+
+/// An option of `entry` that sets a field of `ndless::process::Startup`.
+struct StartupOption {
+	field: &'static str,
+	ty: &'static str,
+	/// Each accepted string, and the variant it means
+	values: &'static [(&'static str, &'static str)],
+}
+
+const STARTUP_OPTIONS: &[StartupOption] = &[
+	StartupOption {
+		field: "lcd",
+		ty: "Lcd",
+		values: &[("restore", "Restore"), ("owned", "Owned")],
+	},
+	StartupOption {
+		field: "timers",
+		ty: "Timers",
+		values: &[("restore", "Restore"), ("off", "Off")],
+	},
+	StartupOption {
+		field: "alloc_error",
+		ty: "OnAllocError",
+		values: &[("abort", "Abort"), ("dialog", "Dialog")],
+	},
+	StartupOption {
+		field: "crash",
+		ty: "CrashHandler",
+		values: &[("screen", "Screen"), ("os", "Os")],
+	},
+];
 
 #[proc_macro_attribute]
 pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
@@ -27,11 +60,73 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
 		.into();
 	}
 
-	if !args.is_empty() {
-		return parse::Error::new(Span::call_site(), "This attribute accepts no arguments")
-			.to_compile_error()
-			.into();
+	let mut arena = None;
+	let mut startup = Vec::new();
+	for arg in parse_macro_input!(args as AttributeArgs) {
+		match arg {
+			NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("arena") => {
+				match &nv.lit {
+					Lit::Int(lit) => match lit.base10_parse::<usize>() {
+						Ok(size) => arena = Some(size),
+						Err(err) => return err.to_compile_error().into(),
+					},
+					lit => {
+						return parse::Error::new(lit.span(), "expected a size in bytes")
+							.to_compile_error()
+							.into()
+					}
+				}
+			}
+			NestedMeta::Meta(Meta::NameValue(ref nv)) => {
+				let option = STARTUP_OPTIONS
+					.iter()
+					.find(|option| nv.path.is_ident(option.field));
+				let option = match option {
+					Some(option) => option,
+					None => {
+						return parse::Error::new(
+							nv.path.span(),
+							"expected `arena`, `lcd`, `timers`, `alloc_error` or `crash`",
+						)
+						.to_compile_error()
+						.into()
+					}
+				};
+				let value = match &nv.lit {
+					Lit::Str(lit) => lit.value(),
+					lit => {
+						return parse::Error::new(lit.span(), "expected a string")
+							.to_compile_error()
+							.into()
+					}
+				};
+				let variant = match option.values.iter().find(|(text, _)| *text == value) {
+					Some((_, variant)) => variant,
+					None => {
+						let expected = option
+							.values
+							.iter()
+							.map(|(text, _)| format!("\"{}\"", text))
+							.collect::<Vec<_>>()
+							.join(" or ");
+						return parse::Error::new(nv.lit.span(), format!("expected {}", expected))
+							.to_compile_error()
+							.into();
+					}
+				};
+				let field = syn::Ident::new(option.field, Span::call_site());
+				let ty = syn::Ident::new(option.ty, Span::call_site());
+				let variant = syn::Ident::new(variant, Span::call_site());
+				startup.push(quote!(startup.#field = ::ndless::process::#ty::#variant;));
+			}
+			other => {
+				return parse::Error::new(other.span(), "expected `name = value`")
+					.to_compile_error()
+					.into()
+			}
+		}
 	}
+	let arena = arena.map(|size| quote!(::ndless::heap::__init_global_arena(#size);));
 
 	let attrs = f.attrs;
 	let stmts = f.block.stmts;
@@ -42,10 +137,17 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
 
 	quote!(
         #[export_name = "main"]
+        // `main` may return `!`
+        #[allow(unreachable_code)]
         unsafe fn __ndless_start(argc: ::ndless::cty::c_int, argv: *const *const ::ndless::cty::c_char) -> ::ndless::cty::c_int {
             let args: &[*const ::ndless::cty::c_char] = unsafe { ::core::slice::from_raw_parts(argv, argc as usize) };
-			::ndless::__init(args);
-			::ndless::process::Termination::report(#name())
+			#arena
+			let mut startup = ::ndless::process::Startup::DEFAULT;
+			#(#startup)*
+			::ndless::__init_with(args, startup);
+			let code = ::ndless::process::Termination::report(#name());
+			::ndless::process::__cleanup();
+			code
         }
 
         #(#attrs)*
@@ -55,3 +157,116 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
     )
 		.into()
 }
+
+/// Derives `ndless::versioned::Versioned` for a type stored in save files.
+/// See the `ndless::versioned` module for usage.
+#[proc_macro_derive(Versioned, attributes(versioned))]
+pub fn derive_versioned(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	let mut version = None;
+	let mut previous = None;
+	for attr in input
+		.attrs
+		.iter()
+		.filter(|attr| attr.path.is_ident("versioned"))
+	{
+		let list = match attr.parse_meta() {
+			Ok(Meta::List(list)) => list,
+			Ok(meta) => {
+				return parse::Error::new(meta.span(), "expected #[versioned(...)]")
+					.to_compile_error()
+					.into()
+			}
+			Err(err) => return err.to_compile_error().into(),
+		};
+		for nested in list.nested {
+			match nested {
+				NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("version") => {
+					match &nv.lit {
+						Lit::Int(lit) => match lit.base10_parse::<u32>() {
+							Ok(lit) => version = Some(lit),
+							Err(err) => return err.to_compile_error().into(),
+						},
+						lit => {
+							return parse::Error::new(lit.span(), "expected an integer")
+								.to_compile_error()
+								.into()
+						}
+					}
+				}
+				NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("previous") => {
+					match &nv.lit {
+						Lit::Str(lit) => match lit.parse::<Type>() {
+							Ok(ty) => previous = Some(ty),
+							Err(err) => return err.to_compile_error().into(),
+						},
+						lit => {
+							return parse::Error::new(lit.span(), "expected a type name in quotes")
+								.to_compile_error()
+								.into()
+						}
+					}
+				}
+				other => {
+					return parse::Error::new(
+						other.span(),
+						"expected `version = ...` or `previous = \"...\"`",
+					)
+					.to_compile_error()
+					.into()
+				}
+			}
+		}
+	}
+	let version = match version {
+		Some(version) => version,
+		None => {
+			return parse::Error::new(
+				Span::call_site(),
+				"missing #[versioned(version = ...)] attribute",
+			)
+			.to_compile_error()
+			.into()
+		}
+	};
+
+	let name = input.ident;
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+	let older = match &previous {
+		Some(previous) => quote!(
+			version if version < #version => {
+				<#previous as ::ndless::versioned::Versioned>::deserialize_version(version, deserializer)
+					.map(<Self as ::core::convert::From<#previous>>::from)
+			}
+		),
+		None => quote!(),
+	};
+	// Fails to compile if the previous version isn't older
+	let check = previous.as_ref().map(|previous| {
+		quote!(
+			const _: [(); 1] = [(); (<#previous as ::ndless::versioned::Versioned>::VERSION < #version) as usize];
+		)
+	});
+
+	quote!(
+		#check
+
+		impl #impl_generics ::ndless::versioned::Versioned for #name #ty_generics #where_clause {
+			const VERSION: u32 = #version;
+
+			fn deserialize_version<'de, D: ::ndless::serde::Deserializer<'de>>(
+				version: u32,
+				deserializer: D,
+			) -> ::core::result::Result<Self, D::Error> {
+				match version {
+					#version => <Self as ::ndless::serde::Deserialize<'de>>::deserialize(deserializer),
+					#older
+					version => ::core::result::Result::Err(<D::Error as ::ndless::serde::de::Error>::custom(
+						::core::format_args!("unsupported save version {}", version),
+					)),
+				}
+			}
+		}
+	)
+	.into()
+}
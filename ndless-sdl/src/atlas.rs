@@ -0,0 +1,278 @@
+//! # Cached bitmap text
+//! Draws text in a [`BitmapFont`] on SDL surfaces.
+//!
+//! A [`GlyphAtlas`] renders every glyph of a font once, into a single surface.
+//! A [`TextCache`] builds on it by keeping each line of text it draws as its own
+//! surface, so text that is drawn every frame, such as menus and HUD labels,
+//! takes one blit per line instead of one per character:
+//!
+//! ```
+//! use ndless::font::BitmapFont;
+//! use ndless_sdl::atlas::{GlyphAtlas, TextCache};
+//! use ndless_sdl::video::Color;
+//!
+//! let font = BitmapFont::parse(FONT).unwrap();
+//! let atlas = GlyphAtlas::new(font, Color::RGB(255, 255, 255)).unwrap();
+//! let mut text = TextCache::new(atlas, 32);
+//! loop {
+//!     text.draw(&screen, &format!("Score: {}", score), 4, 4);
+//!     // ...
+//! }
+//! ```
+
+use ndless::alloc::collections::BTreeMap;
+use ndless::alloc::string::String;
+use ndless::alloc::vec::Vec;
+use ndless::font::{BitmapFont, Glyph};
+
+use crate::video::{Color, Surface, SurfaceFlag};
+use crate::Rect;
+
+const ATLAS_WIDTH: u16 = 256;
+const R_MASK: u32 = 0x00FF_0000;
+const G_MASK: u32 = 0x0000_FF00;
+const B_MASK: u32 = 0x0000_00FF;
+const A_MASK: u32 = 0xFF00_0000;
+
+fn rgba_surface(w: u16, h: u16) -> Result<Surface, String> {
+	Surface::new(
+		&[SurfaceFlag::SWSurface, SurfaceFlag::SrcAlpha],
+		w.max(1) as isize,
+		h.max(1) as isize,
+		32,
+		R_MASK,
+		G_MASK,
+		B_MASK,
+		A_MASK,
+	)
+}
+
+/// Every glyph of a font, rendered in one color on a single surface.
+pub struct GlyphAtlas<'f> {
+	font: BitmapFont<'f>,
+	surface: Surface,
+	rects: BTreeMap<char, Rect>,
+}
+
+impl<'f> GlyphAtlas<'f> {
+	/// Renders each glyph of `font` in `color`.
+	pub fn new(font: BitmapFont<'f>, color: Color) -> Result<Self, String> {
+		let (r, g, b) = match color {
+			Color::RGB(r, g, b) | Color::RGBA(r, g, b, _) => (r, g, b),
+		};
+		let rgb = u32::from(r) << 16 | u32::from(g) << 8 | u32::from(b);
+
+		// Pack glyphs into rows, left to right
+		let mut rects = BTreeMap::new();
+		let (mut x, mut y, mut row_h) = (0u16, 0u16, 0u16);
+		for glyph in font.glyphs() {
+			let (w, h) = (u16::from(glyph.width), u16::from(glyph.height));
+			if x + w > ATLAS_WIDTH {
+				x = 0;
+				y += row_h;
+				row_h = 0;
+			}
+			rects.insert(
+				glyph.ch,
+				Rect {
+					x: x as i16,
+					y: y as i16,
+					w,
+					h,
+				},
+			);
+			x += w;
+			row_h = row_h.max(h);
+		}
+
+		let surface = rgba_surface(ATLAS_WIDTH, y + row_h)?;
+		let pitch = unsafe { (*surface.raw).pitch } as usize;
+		surface.with_lock(|pixels| {
+			for byte in pixels.iter_mut() {
+				*byte = 0;
+			}
+			for glyph in font.glyphs() {
+				let rect = rects[&glyph.ch];
+				for row in 0..glyph.height {
+					for col in 0..glyph.width {
+						let alpha = u32::from(glyph.coverage_at(col, row));
+						let offset = (rect.y as usize + row as usize) * pitch
+							+ (rect.x as usize + col as usize) * 4;
+						pixels[offset..offset + 4]
+							.copy_from_slice(&(alpha << 24 | rgb).to_ne_bytes());
+					}
+				}
+			}
+			true
+		});
+		Ok(Self {
+			font,
+			surface,
+			rects,
+		})
+	}
+
+	pub fn font(&self) -> &BitmapFont<'f> {
+		&self.font
+	}
+
+	/// Draws one glyph with the pen at `x`, and the top of the line at `y`.
+	/// Returns the glyph, or `None` if the font doesn't have it.
+	pub fn draw_glyph(&self, dest: &Surface, ch: char, x: i16, y: i16) -> Option<Glyph<'f>> {
+		let glyph = self.font.glyph(ch)?;
+		let rect = *self.rects.get(&ch)?;
+		dest.blit_rect(
+			&self.surface,
+			Some(rect),
+			Some(Rect {
+				x: x + i16::from(glyph.x_offset),
+				y: y + i16::from(glyph.y_offset),
+				w: rect.w,
+				h: rect.h,
+			}),
+		);
+		Some(glyph)
+	}
+
+	/// Draws a single line of text, one glyph at a time. Characters missing
+	/// from the font are skipped. Returns the width of the line.
+	pub fn draw_line(&self, dest: &Surface, text: &str, x: i16, y: i16) -> u16 {
+		let mut pen = x;
+		for ch in text.chars() {
+			if let Some(glyph) = self.draw_glyph(dest, ch, pen, y) {
+				pen += i16::from(glyph.advance);
+			}
+		}
+		(pen - x) as u16
+	}
+}
+
+/// How well a [`TextCache`] is working. Useful for debug overlays.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, Default)]
+pub struct CacheStats {
+	/// Lines drawn from the cache
+	pub hits: u32,
+	/// Lines that had to be rendered
+	pub misses: u32,
+	/// Lines removed to make room for others
+	pub evictions: u32,
+	/// Lines currently cached
+	pub entries: u32,
+}
+
+struct CachedLine {
+	text: String,
+	surface: Surface,
+	width: u16,
+	last_used: u32,
+}
+
+/// Caches rendered lines of text, so that each line is drawn with a single
+/// blit.
+pub struct TextCache<'f> {
+	atlas: GlyphAtlas<'f>,
+	lines: Vec<CachedLine>,
+	capacity: usize,
+	frame: u32,
+	stats: CacheStats,
+}
+
+impl<'f> TextCache<'f> {
+	/// Creates a cache that holds up to `capacity` lines. When full, the line
+	/// that was drawn least recently is removed.
+	pub fn new(atlas: GlyphAtlas<'f>, capacity: usize) -> Self {
+		Self {
+			atlas,
+			lines: Vec::with_capacity(capacity),
+			capacity: capacity.max(1),
+			frame: 0,
+			stats: CacheStats::default(),
+		}
+	}
+
+	pub fn atlas(&self) -> &GlyphAtlas<'f> {
+		&self.atlas
+	}
+
+	/// Finds or renders a line, returning its index in `lines`.
+	fn line(&mut self, text: &str) -> Option<usize> {
+		self.frame = self.frame.wrapping_add(1);
+		if let Some(i) = self.lines.iter().position(|line| line.text == text) {
+			self.stats.hits += 1;
+			self.lines[i].last_used = self.frame;
+			return Some(i);
+		}
+		self.stats.misses += 1;
+
+		let font = self.atlas.font();
+		let width = font.measure(text) as u16;
+		let surface = rgba_surface(width, u16::from(font.line_height())).ok()?;
+		// Copy the glyphs' alpha, rather than blending it with the empty line
+		self.atlas.surface.set_alpha(&[], 0);
+		self.atlas.draw_line(&surface, text, 0, 0);
+		self.atlas.surface.set_alpha(&[SurfaceFlag::SrcAlpha], 255);
+		surface.set_alpha(&[SurfaceFlag::SrcAlpha], 255);
+
+		let line = CachedLine {
+			text: text.into(),
+			surface,
+			width,
+			last_used: self.frame,
+		};
+		if self.lines.len() >= self.capacity {
+			let oldest = (0..self.lines.len())
+				.max_by_key(|&i| self.frame.wrapping_sub(self.lines[i].last_used))
+				.unwrap();
+			self.lines[oldest] = line;
+			self.stats.evictions += 1;
+			Some(oldest)
+		} else {
+			self.lines.push(line);
+			self.stats.entries = self.lines.len() as u32;
+			Some(self.lines.len() - 1)
+		}
+	}
+
+	/// Draws text with its top-left corner at (`x`, `y`). Each line is cached
+	/// separately. Returns the width and height of the text.
+	pub fn draw(&mut self, dest: &Surface, text: &str, x: i16, y: i16) -> (u16, u16) {
+		let line_height = u16::from(self.atlas.font().line_height());
+		let (mut width, mut height) = (0, 0);
+		for line in text.lines() {
+			if let Some(i) = self.line(line) {
+				let line = &self.lines[i];
+				dest.blit_at(&line.surface, x, y + height as i16);
+				width = width.max(line.width);
+			}
+			height += line_height;
+		}
+		(width, height)
+	}
+
+	/// Returns the width and height of text, without drawing it.
+	pub fn measure(&self, text: &str) -> (u16, u16) {
+		let font = self.atlas.font();
+		(
+			font.measure(text) as u16,
+			text.lines().count() as u16 * u16::from(font.line_height()),
+		)
+	}
+
+	/// Removes every cached line.
+	pub fn clear(&mut self) {
+		self.lines.clear();
+		self.stats.entries = 0;
+	}
+
+	pub fn stats(&self) -> CacheStats {
+		self.stats
+	}
+
+	/// Resets the hit, miss, and eviction counts.
+	pub fn reset_stats(&mut self) {
+		self.stats = CacheStats {
+			entries: self.stats.entries,
+			..CacheStats::default()
+		};
+	}
+}
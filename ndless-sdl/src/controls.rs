@@ -0,0 +1,75 @@
+//! # Controls screens on SDL surfaces
+//! Draws an [`ndless::ui::controls`] help screen on an SDL surface:
+//! ```
+//! let font = Font::new(FontOptions::Thin, 0, 0, 0);
+//! let mut canvas = KeypadSurface::new(&screen, &font);
+//! Controls::current()
+//!     .bind(Key::Enter, "Jump")
+//!     .draw(&mut canvas, 320, 240);
+//! screen.flip();
+//! ```
+
+use ndless::ui::controls::{KeyRect, KeypadCanvas};
+
+use crate::nsdl::Font;
+use crate::video::{Color, Surface};
+use crate::Rect;
+
+pub struct KeypadSurface<'a> {
+	screen: &'a Surface,
+	font: &'a Font,
+	border: Color,
+	key: Color,
+	bound: Color,
+}
+
+impl<'a> KeypadSurface<'a> {
+	pub fn new(screen: &'a Surface, font: &'a Font) -> Self {
+		Self {
+			screen,
+			font,
+			border: Color::RGB(0, 0, 0),
+			key: Color::RGB(220, 220, 220),
+			bound: Color::RGB(255, 230, 120),
+		}
+	}
+
+	/// Sets the colors of key borders, unbound keys, and bound keys.
+	pub fn colors(mut self, border: Color, key: Color, bound: Color) -> Self {
+		self.border = border;
+		self.key = key;
+		self.bound = bound;
+		self
+	}
+}
+
+impl KeypadCanvas for KeypadSurface<'_> {
+	fn draw_key(&mut self, rect: KeyRect, legend: &str, action: Option<&str>) {
+		let outer = Rect {
+			x: rect.x as i16,
+			y: rect.y as i16,
+			w: rect.w as u16,
+			h: rect.h as u16,
+		};
+		let inner = Rect {
+			x: outer.x + 1,
+			y: outer.y + 1,
+			w: outer.w.saturating_sub(2),
+			h: outer.h.saturating_sub(2),
+		};
+		self.screen.fill_rect(Some(outer), self.border);
+		let fill = if action.is_some() {
+			self.bound
+		} else {
+			self.key
+		};
+		self.screen.fill_rect(Some(inner), fill);
+		// Keep the text inside the key
+		self.screen.set_clip_rect(inner);
+		let text = action.unwrap_or(legend);
+		let x = rect.x + (rect.w as i32 - self.font.get_width(text)) / 2;
+		let y = rect.y + (rect.h as i32 - self.font.get_height(text)) / 2;
+		self.screen.draw_str(self.font, text, x, y);
+		self.screen.set_clip_rect(self.screen.get_rect());
+	}
+}
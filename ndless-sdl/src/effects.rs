@@ -0,0 +1,282 @@
+//! # Full-screen effects
+//! Cheap passes applied to a finished frame, just before it is flipped:
+//! palette cycling, screen shake, color tints and flashes, and scanlines.
+//!
+//! Each effect is off until it is turned on, and costs nothing while off:
+//!
+//! ```
+//! use ndless::time::Duration;
+//! use ndless_sdl::effects::PostEffects;
+//! use ndless_sdl::video::Color;
+//!
+//! let mut effects = PostEffects::new();
+//! effects.set_scanlines(true);
+//! loop {
+//!     if player.hit() {
+//!         effects.shake(4, Duration::from_millis(300));
+//!         effects.flash(Color::RGB(255, 0, 0), Duration::from_millis(150));
+//!     }
+//!     // draw...
+//!     effects.apply(&screen);
+//!     screen.flip();
+//! }
+//! ```
+
+use ndless::time::{Duration, Instant};
+
+use crate::video::{ll, Color, Surface, SurfaceFlag};
+use crate::Rect;
+
+/// The strongest a flash gets, out of 255.
+const FLASH_ALPHA: f32 = 192.;
+
+struct PaletteCycle {
+	start: u8,
+	len: u8,
+	period: Duration,
+	last: Instant,
+}
+
+/// An effect that fades out over time
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+struct Timed {
+	start: Instant,
+	duration: Duration,
+}
+
+impl Timed {
+	fn new(duration: Duration) -> Self {
+		Self {
+			start: Instant::now(),
+			duration,
+		}
+	}
+
+	/// How much of the effect is left, from 1 down to 0. Returns `None` once it
+	/// is over.
+	fn remaining(&self) -> Option<f32> {
+		let elapsed = self.start.elapsed();
+		if elapsed >= self.duration {
+			None
+		} else {
+			Some(1. - elapsed.as_secs_f32() / self.duration.as_secs_f32())
+		}
+	}
+}
+
+/// Post-processing for a frame. See the [module documentation][self].
+#[derive(Default)]
+pub struct PostEffects {
+	cycle: Option<PaletteCycle>,
+	shake: Option<(u8, Timed)>,
+	flash: Option<(Color, Timed)>,
+	tint: Option<(Color, u8)>,
+	scanlines: bool,
+	/// State of the random number generator for shaking
+	seed: u32,
+	/// A copy of the frame while shaking
+	scratch: Option<Surface>,
+	/// A solid surface blended over the frame for tints and flashes
+	overlay: Option<Surface>,
+}
+
+impl PostEffects {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Rotates `len` palette entries, starting at `start`, by one every
+	/// `period`. Only has an effect on screens with a palette, as used on the
+	/// classic models.
+	pub fn cycle_palette(&mut self, start: u8, len: u8, period: Duration) {
+		self.cycle = Some(PaletteCycle {
+			start,
+			len,
+			period,
+			last: Instant::now(),
+		});
+	}
+
+	pub fn stop_palette_cycle(&mut self) {
+		self.cycle = None;
+	}
+
+	/// Shakes the screen by up to `magnitude` pixels, calming down over
+	/// `duration`.
+	pub fn shake(&mut self, magnitude: u8, duration: Duration) {
+		self.shake = Some((magnitude, Timed::new(duration)));
+	}
+
+	/// Covers the screen with `color`, fading out over `duration`.
+	pub fn flash(&mut self, color: Color, duration: Duration) {
+		self.flash = Some((color, Timed::new(duration)));
+	}
+
+	/// Blends `color` over every frame, with `alpha` out of 255, until turned
+	/// off with `None`.
+	pub fn set_tint(&mut self, tint: Option<(Color, u8)>) {
+		self.tint = tint;
+	}
+
+	/// Darkens every other row, like an old CRT screen.
+	pub fn set_scanlines(&mut self, enabled: bool) {
+		self.scanlines = enabled;
+	}
+
+	/// Returns true if any effect is on.
+	pub fn is_active(&self) -> bool {
+		self.cycle.is_some()
+			|| self.shake.is_some()
+			|| self.flash.is_some()
+			|| self.tint.is_some()
+			|| self.scanlines
+	}
+
+	/// Applies the effects that are on to `screen`. Call this once per frame,
+	/// after drawing and before flipping.
+	pub fn apply(&mut self, screen: &Surface) {
+		if let Some(cycle) = &mut self.cycle {
+			let steps = (cycle.last.elapsed().as_micros() / cycle.period.as_micros().max(1)) as u32;
+			if steps > 0 {
+				cycle.last = Instant::now();
+				rotate_palette(screen, cycle.start, cycle.len, steps);
+			}
+		}
+		if let Some((magnitude, timed)) = self.shake {
+			match timed.remaining() {
+				Some(remaining) => {
+					let magnitude = (f32::from(magnitude) * remaining) as i16;
+					let dx = self.random(magnitude);
+					let dy = self.random(magnitude);
+					self.offset(screen, dx, dy);
+				}
+				None => self.shake = None,
+			}
+		}
+		if let Some((color, alpha)) = self.tint {
+			self.blend(screen, color, alpha);
+		}
+		if let Some((color, timed)) = self.flash {
+			match timed.remaining() {
+				Some(remaining) => self.blend(screen, color, (FLASH_ALPHA * remaining) as u8),
+				None => self.flash = None,
+			}
+		}
+		if self.scanlines {
+			scanlines(screen);
+		}
+		if self.shake.is_none() && self.tint.is_none() && self.flash.is_none() {
+			self.scratch = None;
+			self.overlay = None;
+		}
+	}
+
+	/// Returns a number from `-magnitude` to `magnitude`.
+	fn random(&mut self, magnitude: i16) -> i16 {
+		if magnitude <= 0 {
+			return 0;
+		}
+		// xorshift32
+		let mut x = if self.seed == 0 {
+			0x2545_F491
+		} else {
+			self.seed
+		};
+		x ^= x << 13;
+		x ^= x >> 17;
+		x ^= x << 5;
+		self.seed = x;
+		(x % (magnitude as u32 * 2 + 1)) as i16 - magnitude
+	}
+
+	/// Scratch surface the same size and format as `screen`
+	fn scratch<'a>(slot: &'a mut Option<Surface>, screen: &Surface) -> Option<&'a Surface> {
+		let fits = match slot {
+			Some(surface) => surface.get_size() == screen.get_size(),
+			None => false,
+		};
+		if !fits {
+			*slot = screen.try_clone().ok();
+		}
+		slot.as_ref()
+	}
+
+	fn offset(&mut self, screen: &Surface, dx: i16, dy: i16) {
+		if dx == 0 && dy == 0 {
+			return;
+		}
+		if let Some(copy) = Self::scratch(&mut self.scratch, screen) {
+			copy.blit(screen);
+			screen.clear();
+			screen.blit_at(copy, dx, dy);
+		}
+	}
+
+	fn blend(&mut self, screen: &Surface, color: Color, alpha: u8) {
+		if alpha == 0 {
+			return;
+		}
+		if let Some(overlay) = Self::scratch(&mut self.overlay, screen) {
+			overlay.fill(color);
+			overlay.set_alpha(&[SurfaceFlag::SrcAlpha], alpha);
+			screen.blit(overlay);
+		}
+	}
+}
+
+fn rotate_palette(screen: &Surface, start: u8, len: u8, steps: u32) {
+	unsafe {
+		let palette = (*(*screen.raw).format).palette;
+		if palette.is_null() || len < 2 {
+			return;
+		}
+		let ncolors = (*palette).ncolors as usize;
+		let start = usize::from(start);
+		let end = (start + usize::from(len)).min(ncolors);
+		if end <= start + 1 {
+			return;
+		}
+		let colors = core::slice::from_raw_parts((*palette).colors, ncolors);
+		let mut range = colors[start..end].to_vec();
+		let len = range.len();
+		range.rotate_right(steps as usize % len);
+		ll::SDL_SetColors(screen.raw, range.as_mut_ptr(), start as _, len as _);
+	}
+}
+
+fn scanlines(screen: &Surface) {
+	let (w, h) = screen.get_size();
+	let (is_rgb565, pitch) = unsafe {
+		let format = *(*screen.raw).format;
+		(
+			format.BytesPerPixel == 2 && format.Rmask == 0xF800 && format.Gmask == 0x07E0,
+			(*screen.raw).pitch as usize,
+		)
+	};
+	if !is_rgb565 {
+		// Without a known pixel layout to halve, draw black lines instead
+		for y in (1..h).step_by(2) {
+			screen.fill_rect(
+				Some(Rect {
+					x: 0,
+					y: y as i16,
+					w,
+					h: 1,
+				}),
+				Color::RGB(0, 0, 0),
+			);
+		}
+		return;
+	}
+	screen.with_lock(|pixels| {
+		for y in (1..h as usize).step_by(2) {
+			let row = &mut pixels[y * pitch..y * pitch + w as usize * 2];
+			for pixel in row.chunks_exact_mut(2) {
+				// Halve each channel of an RGB565 pixel
+				let value = u16::from_ne_bytes([pixel[0], pixel[1]]);
+				pixel.copy_from_slice(&((value >> 1) & 0x7BEF).to_ne_bytes());
+			}
+		}
+		true
+	});
+}
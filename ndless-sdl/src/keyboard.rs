@@ -0,0 +1,99 @@
+//! # On-screen keyboards on SDL surfaces
+//! Draws an [`ndless::ui::keyboard`] on an SDL surface:
+//! ```
+//! let font = Font::new(FontOptions::Thin, 0, 0, 0);
+//! let mut canvas = KeyboardSurface::new(&screen, &font);
+//! let name = VirtualKeyboard::new().run(&mut canvas, 320, 240);
+//! ```
+
+use ndless::ui::controls::KeyRect;
+use ndless::ui::keyboard::KeyboardCanvas;
+
+use crate::nsdl::Font;
+use crate::video::{Color, Surface};
+use crate::Rect;
+
+const PADDING: i32 = 4;
+
+fn to_rect(rect: KeyRect) -> Rect {
+	Rect {
+		x: rect.x as i16,
+		y: rect.y as i16,
+		w: rect.w as u16,
+		h: rect.h as u16,
+	}
+}
+
+pub struct KeyboardSurface<'a> {
+	screen: &'a Surface,
+	font: &'a Font,
+	border: Color,
+	key: Color,
+	selected: Color,
+}
+
+impl<'a> KeyboardSurface<'a> {
+	pub fn new(screen: &'a Surface, font: &'a Font) -> Self {
+		Self {
+			screen,
+			font,
+			border: Color::RGB(0, 0, 0),
+			key: Color::RGB(220, 220, 220),
+			selected: Color::RGB(255, 230, 120),
+		}
+	}
+
+	/// Sets the colors of key borders, keys, and the selected key.
+	pub fn colors(mut self, border: Color, key: Color, selected: Color) -> Self {
+		self.border = border;
+		self.key = key;
+		self.selected = selected;
+		self
+	}
+}
+
+impl KeyboardCanvas for KeyboardSurface<'_> {
+	fn draw_text(&mut self, rect: KeyRect, text: &str) {
+		let outer = to_rect(rect);
+		self.screen
+			.fill_rect(Some(outer), Color::RGB(255, 255, 255));
+		self.screen.set_clip_rect(outer);
+		// Keep the end of the text, where the cursor is, in view
+		let width = self.font.get_width(text);
+		let x = (rect.w as i32 - PADDING - width).min(PADDING) + rect.x;
+		let y = rect.y + (rect.h as i32 - self.font.get_height("M")) / 2;
+		self.screen.draw_str(self.font, text, x, y);
+		self.screen.fill_rect(
+			Some(Rect {
+				x: (x + width + 1) as i16,
+				y: y as i16,
+				w: 1,
+				h: self.font.get_height("M") as u16,
+			}),
+			self.border,
+		);
+		self.screen.set_clip_rect(self.screen.get_rect());
+	}
+
+	fn draw_cell(&mut self, rect: KeyRect, label: &str, selected: bool) {
+		let outer = to_rect(rect);
+		let inner = Rect {
+			x: outer.x + 1,
+			y: outer.y + 1,
+			w: outer.w.saturating_sub(2),
+			h: outer.h.saturating_sub(2),
+		};
+		self.screen.fill_rect(Some(outer), self.border);
+		let fill = if selected { self.selected } else { self.key };
+		self.screen.fill_rect(Some(inner), fill);
+		self.screen.set_clip_rect(inner);
+		let x = rect.x + (rect.w as i32 - self.font.get_width(label)) / 2;
+		let y = rect.y + (rect.h as i32 - self.font.get_height(label)) / 2;
+		self.screen.draw_str(self.font, label, x, y);
+		self.screen.set_clip_rect(self.screen.get_rect());
+	}
+
+	fn present(&mut self) {
+		self.screen.flip();
+	}
+}
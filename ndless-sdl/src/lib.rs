@@ -28,16 +28,26 @@ extern crate num;
 
 pub use sdl::*;
 
+pub mod atlas;
+pub mod controls;
+pub mod effects;
 pub mod event;
 pub mod gl;
+pub mod keyboard;
 pub mod keysym;
+pub mod menu;
 pub mod mouse;
+pub mod ninepatch;
 pub mod nsdl;
+pub mod picker;
+pub mod readline;
 pub mod video;
 pub mod wm;
 
 pub mod image;
 pub mod sdl;
 pub mod text;
+pub mod toast;
+pub mod transition;
 
 pub mod gfx;
@@ -0,0 +1,100 @@
+//! # Menus on SDL surfaces
+//! Draws an [`ndless::ui::menu`] on an SDL surface:
+//! ```
+//! let font = Font::new(FontOptions::Thin, 0, 0, 0);
+//! let mut canvas = MenuSurface::new(&screen, &font);
+//! let choice = Menu::new("Game").item("Play").item("Quit").run(&mut canvas, 320, 240);
+//! ```
+
+use ndless::ui::controls::KeyRect;
+use ndless::ui::menu::{ItemState, MenuCanvas};
+
+use crate::nsdl::Font;
+use crate::video::{Color, Surface};
+use crate::Rect;
+
+const PADDING: i32 = 4;
+
+fn to_rect(rect: KeyRect) -> Rect {
+	Rect {
+		x: rect.x as i16,
+		y: rect.y as i16,
+		w: rect.w as u16,
+		h: rect.h as u16,
+	}
+}
+
+pub struct MenuSurface<'a> {
+	screen: &'a Surface,
+	font: &'a Font,
+	disabled_font: &'a Font,
+	title: Color,
+	background: Color,
+	selected: Color,
+}
+
+impl<'a> MenuSurface<'a> {
+	pub fn new(screen: &'a Surface, font: &'a Font) -> Self {
+		Self {
+			screen,
+			font,
+			disabled_font: font,
+			title: Color::RGB(200, 200, 200),
+			background: Color::RGB(255, 255, 255),
+			selected: Color::RGB(255, 230, 120),
+		}
+	}
+
+	/// Sets the font for disabled items, such as one in gray. They use the
+	/// same font as the others by default.
+	pub fn disabled_font(mut self, font: &'a Font) -> Self {
+		self.disabled_font = font;
+		self
+	}
+
+	/// Sets the colors of the title, items, and the selected item.
+	pub fn colors(mut self, title: Color, background: Color, selected: Color) -> Self {
+		self.title = title;
+		self.background = background;
+		self.selected = selected;
+		self
+	}
+}
+
+impl MenuCanvas for MenuSurface<'_> {
+	fn draw_title(&mut self, rect: KeyRect, title: &str) {
+		let outer = to_rect(rect);
+		self.screen.fill_rect(Some(outer), self.title);
+		self.screen.set_clip_rect(outer);
+		let y = rect.y + (rect.h as i32 - self.font.get_height("M")) / 2;
+		self.screen.draw_str(self.font, title, rect.x + PADDING, y);
+		self.screen.set_clip_rect(self.screen.get_rect());
+	}
+
+	fn draw_item(&mut self, rect: KeyRect, label: &str, state: ItemState) {
+		let outer = to_rect(rect);
+		let fill = if state.selected {
+			self.selected
+		} else {
+			self.background
+		};
+		self.screen.fill_rect(Some(outer), fill);
+		self.screen.set_clip_rect(outer);
+		let y = rect.y + (rect.h as i32 - self.font.get_height("M")) / 2;
+		let font = if state.enabled {
+			self.font
+		} else {
+			self.disabled_font
+		};
+		self.screen.draw_str(font, label, rect.x + PADDING, y);
+		if state.submenu {
+			let x = rect.x + rect.w as i32 - PADDING - font.get_width(">");
+			self.screen.draw_str(font, ">", x, y);
+		}
+		self.screen.set_clip_rect(self.screen.get_rect());
+	}
+
+	fn present(&mut self) {
+		self.screen.flip();
+	}
+}
@@ -0,0 +1,244 @@
+//! # Nine-patch images
+//! Draws a small border image at any size, for panels and buttons with
+//! rounded or decorated edges.
+//!
+//! The image is split into nine parts by four insets. The corners are drawn as
+//! they are, the edges are stretched or tiled along one axis, and the center is
+//! stretched or tiled to fill the rest:
+//!
+//! ```
+//! use ndless_sdl::ninepatch::NinePatch;
+//! use ndless_sdl::video::Surface;
+//! use ndless_sdl::Rect;
+//!
+//! // A 24x24 image with 8 pixel corners
+//! let panel = NinePatch::new(Surface::from_bmp("/documents/game/panel.bmp.tns")?, 8, 8, 8, 8);
+//! panel.draw(&screen, Rect { x: 20, y: 20, w: 280, h: 120 });
+//! ```
+
+use core::cell::RefCell;
+
+use ndless::alloc::string::String;
+
+use crate::video::{ll, Surface, SurfaceFlag};
+use crate::Rect;
+
+/// How the edges and center of a [`NinePatch`] fill the space between the
+/// corners.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Fill {
+	/// Scale the parts, using the nearest pixel. The result is cached, so
+	/// drawing at the same size again is a single blit.
+	Stretch,
+	/// Repeat the parts, which is drawn with one blit per repetition.
+	Tile,
+}
+
+pub struct NinePatch {
+	image: Surface,
+	left: u16,
+	top: u16,
+	right: u16,
+	bottom: u16,
+	fill: Fill,
+	/// The last size drawn with `Fill::Stretch`
+	cache: RefCell<Option<Surface>>,
+}
+
+/// Maps a coordinate in the destination to one in the source, keeping the
+/// first `start` and last `end` pixels unscaled.
+fn map(pos: u16, dest_len: u16, src_len: u16, start: u16, end: u16) -> u16 {
+	let src_middle = src_len.saturating_sub(start + end);
+	let dest_middle = dest_len.saturating_sub(start + end);
+	let src = if pos < start {
+		pos
+	} else if pos >= dest_len.saturating_sub(end) {
+		src_len - (dest_len - pos)
+	} else if dest_middle == 0 {
+		start
+	} else {
+		start + ((u32::from(pos - start) * u32::from(src_middle)) / u32::from(dest_middle)) as u16
+	};
+	src.min(src_len.saturating_sub(1))
+}
+
+impl NinePatch {
+	/// Creates a nine-patch from an image with 16 or 32 bits per pixel. The
+	/// insets are the sizes of the borders, in pixels. The image's color key and
+	/// alpha are kept.
+	///
+	/// # Panics
+	///
+	/// Panics if `left` and `right` add up to more than the image's width, or
+	/// `top` and `bottom` to more than its height.
+	pub fn new(image: Surface, left: u16, top: u16, right: u16, bottom: u16) -> Self {
+		let (w, h) = image.get_size();
+		assert!(
+			u32::from(left) + u32::from(right) <= u32::from(w),
+			"nine-patch insets {} and {} are wider than the {} pixel image",
+			left,
+			right,
+			w
+		);
+		assert!(
+			u32::from(top) + u32::from(bottom) <= u32::from(h),
+			"nine-patch insets {} and {} are taller than the {} pixel image",
+			top,
+			bottom,
+			h
+		);
+		Self {
+			image,
+			left,
+			top,
+			right,
+			bottom,
+			fill: Fill::Stretch,
+			cache: RefCell::new(None),
+		}
+	}
+
+	/// Sets how the edges and center are filled. Defaults to
+	/// [`Fill::Stretch`].
+	pub fn fill(mut self, fill: Fill) -> Self {
+		self.fill = fill;
+		self
+	}
+
+	/// Draws the image, filling `rect`.
+	pub fn draw(&self, dest: &Surface, rect: Rect) -> bool {
+		match self.fill {
+			Fill::Stretch => {
+				let mut cache = self.cache.borrow_mut();
+				let cached = match cache.as_ref() {
+					Some(surface) => surface.get_size() == (rect.w, rect.h),
+					None => false,
+				};
+				if !cached {
+					*cache = self.render(rect.w, rect.h).ok();
+				}
+				match cache.as_ref() {
+					Some(surface) => dest.blit_at(surface, rect.x, rect.y),
+					None => false,
+				}
+			}
+			Fill::Tile => {
+				self.draw_tiled(dest, rect);
+				true
+			}
+		}
+	}
+
+	/// Renders the image stretched to `w` by `h`.
+	pub fn render(&self, w: u16, h: u16) -> Result<Surface, String> {
+		let (src_w, src_h) = self.image.get_size();
+		let (format, flags) = unsafe { (*(*self.image.raw).format, (*self.image.raw).flags) };
+		let surface = Surface::new(
+			&[SurfaceFlag::SWSurface],
+			w.max(1) as isize,
+			h.max(1) as isize,
+			format.BitsPerPixel as isize,
+			format.Rmask,
+			format.Gmask,
+			format.Bmask,
+			format.Amask,
+		)?;
+		let bytes = format.BytesPerPixel as usize;
+		let src_pitch = unsafe { (*self.image.raw).pitch } as usize;
+		let dest_pitch = unsafe { (*surface.raw).pitch } as usize;
+		self.image.with_lock(|src| {
+			surface.with_lock(|dest| {
+				for y in 0..h {
+					let src_y = map(y, h, src_h, self.top, self.bottom) as usize;
+					for x in 0..w {
+						let src_x = map(x, w, src_w, self.left, self.right) as usize;
+						let from = src_y * src_pitch + src_x * bytes;
+						let to = y as usize * dest_pitch + x as usize * bytes;
+						dest[to..to + bytes].copy_from_slice(&src[from..from + bytes]);
+					}
+				}
+				true
+			})
+		});
+		let keep = flags & (SurfaceFlag::SrcColorKey as u32 | SurfaceFlag::SrcAlpha as u32);
+		unsafe {
+			if keep & SurfaceFlag::SrcColorKey as u32 != 0 {
+				ll::SDL_SetColorKey(
+					surface.raw,
+					SurfaceFlag::SrcColorKey as u32,
+					format.colorkey,
+				);
+			}
+			ll::SDL_SetAlpha(
+				surface.raw,
+				keep & SurfaceFlag::SrcAlpha as u32,
+				format.alpha,
+			);
+		}
+		Ok(surface)
+	}
+
+	/// Blits `src` repeatedly to fill `dest_rect`, cutting off the last
+	/// repetitions.
+	fn tile(&self, dest: &Surface, src: Rect, dest_rect: Rect) {
+		if src.w == 0 || src.h == 0 {
+			return;
+		}
+		let mut y = 0;
+		while y < dest_rect.h {
+			let h = src.h.min(dest_rect.h - y);
+			let mut x = 0;
+			while x < dest_rect.w {
+				let w = src.w.min(dest_rect.w - x);
+				dest.blit_rect(
+					&self.image,
+					Some(Rect { w, h, ..src }),
+					Some(Rect {
+						x: dest_rect.x + x as i16,
+						y: dest_rect.y + y as i16,
+						w,
+						h,
+					}),
+				);
+				x += w;
+			}
+			y += h;
+		}
+	}
+
+	fn draw_tiled(&self, dest: &Surface, rect: Rect) {
+		let (src_w, src_h) = self.image.get_size();
+		let (l, t, r, b) = (self.left, self.top, self.right, self.bottom);
+		let src_cols = [(0, l), (l, src_w.saturating_sub(l + r)), (src_w - r, r)];
+		let src_rows = [(0, t), (t, src_h.saturating_sub(t + b)), (src_h - b, b)];
+		let dest_cols = [
+			(0, l),
+			(l, rect.w.saturating_sub(l + r)),
+			(rect.w.saturating_sub(r), r),
+		];
+		let dest_rows = [
+			(0, t),
+			(t, rect.h.saturating_sub(t + b)),
+			(rect.h.saturating_sub(b), b),
+		];
+		for (&(src_y, src_h), &(dest_y, dest_h)) in src_rows.iter().zip(dest_rows.iter()) {
+			for (&(src_x, src_w), &(dest_x, dest_w)) in src_cols.iter().zip(dest_cols.iter()) {
+				self.tile(
+					dest,
+					Rect {
+						x: src_x as i16,
+						y: src_y as i16,
+						w: src_w,
+						h: src_h,
+					},
+					Rect {
+						x: rect.x + dest_x as i16,
+						y: rect.y + dest_y as i16,
+						w: dest_w,
+						h: dest_h,
+					},
+				);
+			}
+		}
+	}
+}
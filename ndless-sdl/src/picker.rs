@@ -0,0 +1,86 @@
+//! # File pickers on SDL surfaces
+//! Draws an [`ndless::ui::picker`] on an SDL surface:
+//! ```
+//! let font = Font::new(FontOptions::Thin, 0, 0, 0);
+//! let mut canvas = PickerSurface::new(&screen, &font);
+//! let path = FilePicker::new().extension("png").run(&mut canvas, 320, 240);
+//! ```
+
+use ndless::alloc::format;
+use ndless::ui::controls::KeyRect;
+use ndless::ui::picker::PickerCanvas;
+
+use crate::nsdl::Font;
+use crate::video::{Color, Surface};
+use crate::Rect;
+
+const PADDING: i32 = 4;
+
+fn to_rect(rect: KeyRect) -> Rect {
+	Rect {
+		x: rect.x as i16,
+		y: rect.y as i16,
+		w: rect.w as u16,
+		h: rect.h as u16,
+	}
+}
+
+pub struct PickerSurface<'a> {
+	screen: &'a Surface,
+	font: &'a Font,
+	header: Color,
+	background: Color,
+	selected: Color,
+}
+
+impl<'a> PickerSurface<'a> {
+	pub fn new(screen: &'a Surface, font: &'a Font) -> Self {
+		Self {
+			screen,
+			font,
+			header: Color::RGB(200, 200, 200),
+			background: Color::RGB(255, 255, 255),
+			selected: Color::RGB(255, 230, 120),
+		}
+	}
+
+	/// Sets the colors of the header, entries, and the selected entry.
+	pub fn colors(mut self, header: Color, background: Color, selected: Color) -> Self {
+		self.header = header;
+		self.background = background;
+		self.selected = selected;
+		self
+	}
+
+	fn draw_row(&mut self, rect: KeyRect, fill: Color, text: &str) {
+		let outer = to_rect(rect);
+		self.screen.fill_rect(Some(outer), fill);
+		self.screen.set_clip_rect(outer);
+		let y = rect.y + (rect.h as i32 - self.font.get_height("M")) / 2;
+		self.screen.draw_str(self.font, text, rect.x + PADDING, y);
+		self.screen.set_clip_rect(self.screen.get_rect());
+	}
+}
+
+impl PickerCanvas for PickerSurface<'_> {
+	fn draw_header(&mut self, rect: KeyRect, path: &str) {
+		self.draw_row(rect, self.header, path);
+	}
+
+	fn draw_entry(&mut self, rect: KeyRect, name: &str, is_dir: bool, selected: bool) {
+		let fill = if selected {
+			self.selected
+		} else {
+			self.background
+		};
+		if is_dir {
+			self.draw_row(rect, fill, &format!("{}/", name));
+		} else {
+			self.draw_row(rect, fill, name);
+		}
+	}
+
+	fn present(&mut self) {
+		self.screen.flip();
+	}
+}
@@ -0,0 +1,113 @@
+//! # Line editing on SDL surfaces
+//! Draws an [`ndless::input::line`] editor on an SDL surface:
+//! ```
+//! let font = Font::new(FontOptions::Thin, 0, 0, 0);
+//! let mut view = SurfaceLineView::new(&screen, &font);
+//! let name = ndless::input::read_line("Name: ", &mut view);
+//! ```
+
+use ndless::alloc::string::String;
+use ndless::input::line::LineView;
+use ndless::input::shortcuts::Modifiers;
+
+use crate::nsdl::Font;
+use crate::video::{Color, Surface};
+use crate::Rect;
+
+const PADDING: i32 = 4;
+
+pub struct SurfaceLineView<'a> {
+	screen: &'a Surface,
+	font: &'a Font,
+	y: i16,
+	background: Color,
+	cursor: Color,
+}
+
+impl<'a> SurfaceLineView<'a> {
+	/// Creates a view that draws a line across the bottom of `screen`. The
+	/// text color is set when loading `font`.
+	pub fn new(screen: &'a Surface, font: &'a Font) -> Self {
+		let height = font.get_height("M") + PADDING * 2;
+		Self {
+			screen,
+			font,
+			y: screen.get_height() as i16 - height as i16,
+			background: Color::RGB(255, 255, 255),
+			cursor: Color::RGB(0, 0, 0),
+		}
+	}
+
+	/// Draws the line at `y` instead.
+	pub fn at(mut self, y: i16) -> Self {
+		self.y = y;
+		self
+	}
+
+	pub fn background(mut self, color: Color) -> Self {
+		self.background = color;
+		self
+	}
+
+	pub fn cursor_color(mut self, color: Color) -> Self {
+		self.cursor = color;
+		self
+	}
+}
+
+impl LineView for SurfaceLineView<'_> {
+	fn draw_line(&mut self, prompt: &str, text: &str, cursor: usize, modifiers: Modifiers) {
+		let height = self.font.get_height("M");
+		let width = self.screen.get_width();
+		self.screen.fill_rect(
+			Some(Rect {
+				x: 0,
+				y: self.y,
+				w: width,
+				h: (height + PADDING * 2) as u16,
+			}),
+			self.background,
+		);
+		let mut indicator = String::new();
+		if modifiers.ctrl {
+			indicator.push_str("ctrl ");
+		}
+		if modifiers.shift {
+			indicator.push_str("shift ");
+		}
+		let indicator_w = self.font.get_width(&indicator);
+		let prompt_w = self.font.get_width(prompt);
+		let y = i32::from(self.y) + PADDING;
+		// Scroll the text left to keep the cursor on screen
+		let before: String = text.chars().take(cursor).collect();
+		let available = i32::from(width) - PADDING * 3 - prompt_w - indicator_w;
+		let mut shown = &text[..];
+		let mut cursor_x = self.font.get_width(&before);
+		while cursor_x > available && !shown.is_empty() {
+			let skip = shown.chars().next().map_or(0, char::len_utf8);
+			cursor_x -= self.font.get_width(&shown[..skip]);
+			shown = &shown[skip..];
+		}
+		let text_x = PADDING + prompt_w;
+		self.font.draw(self.screen.raw, prompt, PADDING, y);
+		self.font.draw(self.screen.raw, shown, text_x, y);
+		self.screen.fill_rect(
+			Some(Rect {
+				x: (text_x + cursor_x) as i16,
+				y: y as i16,
+				w: 1,
+				h: height as u16,
+			}),
+			self.cursor,
+		);
+		if !indicator.is_empty() {
+			self.font.draw(
+				self.screen.raw,
+				&indicator,
+				i32::from(width) - PADDING - indicator_w,
+				y,
+			);
+		}
+		self.screen.flip();
+	}
+}
@@ -0,0 +1,76 @@
+//! # Toasts on SDL surfaces
+//! Draws [`ndless::ui::toast`] notifications on an SDL surface:
+//! ```
+//! let font = Font::new(FontOptions::Thin, 255, 255, 255);
+//! let mut overlay = ToastOverlay::new(&screen, &font);
+//! ndless::ui::toast::draw(&mut overlay);
+//! screen.flip();
+//! ```
+
+use ndless::ui::toast::Overlay;
+
+use crate::nsdl::Font;
+use crate::video::{Color, Surface, SurfaceFlag};
+use crate::Rect;
+
+const PADDING: i32 = 4;
+const MARGIN: i32 = 6;
+
+pub struct ToastOverlay<'a> {
+	screen: &'a Surface,
+	font: &'a Font,
+	background: Color,
+}
+
+impl<'a> ToastOverlay<'a> {
+	/// Creates an overlay that draws on `screen`. The text color is set when
+	/// loading `font`.
+	pub fn new(screen: &'a Surface, font: &'a Font) -> Self {
+		Self {
+			screen,
+			font,
+			background: Color::RGB(40, 40, 40),
+		}
+	}
+
+	pub fn background(mut self, color: Color) -> Self {
+		self.background = color;
+		self
+	}
+}
+
+impl Overlay for ToastOverlay<'_> {
+	fn draw_toast(&mut self, text: &str, index: usize, opacity: u8) {
+		let w = self.font.get_width(text) + PADDING * 2;
+		let h = self.font.get_height(text) + PADDING * 2;
+		let toast = match Surface::new(
+			&[SurfaceFlag::SWSurface],
+			w as isize,
+			h as isize,
+			16,
+			0,
+			0,
+			0,
+			0,
+		) {
+			Ok(toast) => toast,
+			Err(_) => return,
+		};
+		toast.fill(self.background);
+		toast.draw_str(self.font, text, PADDING, PADDING);
+		toast.set_alpha(&[SurfaceFlag::SrcAlpha], opacity);
+		let (screen_w, screen_h) = self.screen.get_size();
+		let x = (i32::from(screen_w) - w) / 2;
+		let y = i32::from(screen_h) - (h + MARGIN) * (index as i32 + 1);
+		self.screen.blit_rect(
+			&toast,
+			None,
+			Some(Rect {
+				x: x as i16,
+				y: y as i16,
+				w: w as u16,
+				h: h as u16,
+			}),
+		);
+	}
+}
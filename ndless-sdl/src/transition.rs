@@ -0,0 +1,184 @@
+//! # Scene transitions
+//! Animates the switch from one screen to the next, whatever each one draws.
+//!
+//! [`start`][Transition::start] a transition with the last frame of the old
+//! scene still on the screen. Then draw the new scene as usual, but call
+//! [`present`][Transition::present] instead of `flip`. It mixes in the old
+//! frame, flips the screen, and returns false once the transition is over:
+//!
+//! ```
+//! use ndless::time::Duration;
+//! use ndless_sdl::transition::{Effect, Transition};
+//!
+//! let mut transition = Transition::new(Effect::Dissolve, Duration::from_millis(400));
+//! transition.start(&screen);
+//! scene = Scene::Game;
+//! loop {
+//!     scene.draw(&screen);
+//!     transition.present(&screen);
+//! }
+//! ```
+
+use ndless::alloc::string::String;
+use ndless::time::{Duration, Instant};
+
+use crate::video::{Surface, SurfaceFlag};
+
+/// A 4x4 ordered dither matrix, used as the order in which pixels switch to
+/// the new frame for [`Effect::Dissolve`].
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// The side that the new scene enters from, for [`Effect::Slide`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Direction {
+	Left,
+	Right,
+	Up,
+	Down,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Effect {
+	/// Fades from the old scene to the new one.
+	Fade,
+	/// Pushes the old scene off the screen with the new one.
+	Slide(Direction),
+	/// Switches pixels to the new scene in a dithered pattern. This works
+	/// well on the grayscale screens, where fading does not.
+	Dissolve,
+}
+
+/// Animates between scenes. See the [module documentation][self].
+pub struct Transition {
+	effect: Effect,
+	duration: Duration,
+	start: Option<Instant>,
+	old: Option<Surface>,
+	/// A copy of the new frame while sliding
+	scratch: Option<Surface>,
+}
+
+impl Transition {
+	pub fn new(effect: Effect, duration: Duration) -> Self {
+		Self {
+			effect,
+			duration,
+			start: None,
+			old: None,
+			scratch: None,
+		}
+	}
+
+	pub fn effect(&self) -> Effect {
+		self.effect
+	}
+
+	pub fn set_effect(&mut self, effect: Effect) {
+		self.effect = effect;
+	}
+
+	/// Starts a transition away from what is currently on `screen`.
+	pub fn start(&mut self, screen: &Surface) -> Result<(), String> {
+		self.old = Some(screen.try_clone()?);
+		self.start = Some(Instant::now());
+		Ok(())
+	}
+
+	/// Returns true while a transition is running.
+	pub fn is_running(&self) -> bool {
+		self.old.is_some()
+	}
+
+	/// How far along the transition is, from 0 to 1.
+	pub fn progress(&self) -> f32 {
+		match self.start {
+			Some(start) if self.duration > Duration::from_secs(0) => {
+				(start.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.)
+			}
+			_ => 1.,
+		}
+	}
+
+	/// Mixes the old scene into the new one drawn on `screen`, and flips it.
+	/// Returns true if the transition is still running.
+	pub fn present(&mut self, screen: &Surface) -> bool {
+		let progress = self.progress();
+		if progress >= 1. {
+			self.old = None;
+			self.start = None;
+		}
+		if let Some(old) = &self.old {
+			match self.effect {
+				Effect::Fade => {
+					old.set_alpha(&[SurfaceFlag::SrcAlpha], ((1. - progress) * 255.) as u8);
+					screen.blit(old);
+				}
+				Effect::Slide(direction) => {
+					if self.scratch.is_none() {
+						self.scratch = screen.try_clone().ok();
+					}
+					if let Some(new) = &self.scratch {
+						new.blit(screen);
+						let (w, h) = screen.get_size();
+						let (dx, dy) = match direction {
+							Direction::Left => ((w as f32 * progress) as i16, 0),
+							Direction::Right => (-((w as f32 * progress) as i16), 0),
+							Direction::Up => (0, (h as f32 * progress) as i16),
+							Direction::Down => (0, -((h as f32 * progress) as i16)),
+						};
+						screen.blit_at(old, dx, dy);
+						let (nx, ny) = match direction {
+							Direction::Left => (dx - w as i16, 0),
+							Direction::Right => (dx + w as i16, 0),
+							Direction::Up => (0, dy - h as i16),
+							Direction::Down => (0, dy + h as i16),
+						};
+						screen.blit_at(new, nx, ny);
+					}
+				}
+				Effect::Dissolve => dissolve(screen, old, (progress * 16.) as u8),
+			}
+		} else {
+			self.scratch = None;
+		}
+		screen.flip();
+		self.old.is_some()
+	}
+}
+
+/// Copies the pixels of `old` whose dither threshold is at least `level` onto
+/// `screen`.
+fn dissolve(screen: &Surface, old: &Surface, level: u8) {
+	let (w, h) = screen.get_size();
+	let (bytes, pitch, old_pitch) = unsafe {
+		(
+			(*(*screen.raw).format).BytesPerPixel as usize,
+			(*screen.raw).pitch as usize,
+			(*old.raw).pitch as usize,
+		)
+	};
+	let (w, h) = (
+		w.min(old.get_width()) as usize,
+		h.min(old.get_height()) as usize,
+	);
+	old.with_lock(|from| {
+		screen.with_lock(|to| {
+			for y in 0..h {
+				for x in 0..w {
+					if BAYER[y % 4][x % 4] >= level {
+						let src = y * old_pitch + x * bytes;
+						let dest = y * pitch + x * bytes;
+						to[dest..dest + bytes].copy_from_slice(&from[src..src + bytes]);
+					}
+				}
+			}
+			true
+		})
+	});
+}
+
+impl Default for Transition {
+	fn default() -> Self {
+		Self::new(Effect::Fade, Duration::from_millis(300))
+	}
+}
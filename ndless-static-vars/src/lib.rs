@@ -19,6 +19,9 @@ pub static mut ARGUMENTS: Option<&[*const cty::c_char]> = None;
 pub static mut ORIG_DIVIDER: u32 = 0;
 pub static mut ORIG_CONTROL: u32 = 0;
 pub static mut ORIG_LOAD: u32 = 0;
+pub static mut ORIG_TICK_TIMER: Option<(u32, u32)> = None;
 
 pub static mut TICK_SUM: u32 = 0;
 pub static mut START_VALUE: u32 = 0;
+pub static mut LAST_TICKS: u32 = 0;
+pub static mut HIGH_TICKS: u64 = 0;
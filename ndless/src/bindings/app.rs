@@ -0,0 +1,5 @@
+//! # Application lifecycle
+//! This module contains helpers for the things that happen around a program's
+//! main loop, such as upgrading data saved by an older version.
+
+pub mod migrations;
@@ -0,0 +1,288 @@
+//! Upgrading data saved by older versions of a program
+//!
+//! The version of the program that last ran is stored in a small file. When a
+//! newer version starts, each migration between the two versions runs in
+//! order, such as to convert an old save format:
+//!
+//! ```
+//! use ndless::app::migrations::{Migrations, Outcome};
+//!
+//! let outcome = Migrations::new("/documents/game/version.tns", 3)
+//!     .migration(2, |tx| {
+//!         let old = tx.read("/documents/game/save.dat.tns")?;
+//!         tx.write("/documents/game/save.dat.tns", convert_v1_save(&old));
+//!         Ok(())
+//!     })
+//!     .migration(3, |tx| {
+//!         tx.remove("/documents/game/cache.tns");
+//!         Ok(())
+//!     })
+//!     .run();
+//! match outcome {
+//!     Ok(Outcome::FirstRun) => show_tutorial(),
+//!     Ok(_) => {}
+//!     Err(err) => ndless::ui::report_error(&err),
+//! }
+//! ```
+//!
+//! Migrations make their changes through a [`Transaction`], which holds them
+//! in memory until every migration has succeeded. If any migration fails,
+//! nothing is written, and the stored version is left unchanged so the
+//! migrations are attempted again next time. If writing one of the files
+//! fails, the ones already written are put back as they were, so the next
+//! attempt starts from the same data.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::autosave::write_atomic;
+use crate::error::Error;
+use crate::fs;
+use crate::io;
+use crate::path::{Path, PathBuf};
+
+/// What [`Migrations::run`] did.
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub enum Outcome {
+	/// No version was stored, so this is the first time the program has run.
+	/// No migrations were run.
+	FirstRun,
+	/// The stored version is the current version.
+	UpToDate,
+	/// The migrations for each version in `applied` were run, and their changes
+	/// saved.
+	Migrated { from: u32, applied: Vec<u32> },
+	/// Returned instead of `Migrated` in [dry-run mode][Migrations::dry_run].
+	/// The migrations ran, but none of the files in `changed` were written.
+	DryRun {
+		from: u32,
+		applied: Vec<u32>,
+		changed: Vec<PathBuf>,
+	},
+}
+
+/// A migration, or saving its changes, failed. No files were changed, as any
+/// that were already written are put back, and the stored version is left
+/// unchanged.
+#[derive(Debug)]
+pub struct MigrationError {
+	/// The version of the migration that failed, or `None` if saving failed.
+	pub version: Option<u32>,
+	pub error: io::Error,
+}
+
+impl fmt::Display for MigrationError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.version {
+			Some(version) => write!(f, "Migration to version {} failed", version),
+			None => write!(f, "Could not save migrated data"),
+		}
+	}
+}
+
+impl Error for MigrationError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		Some(&self.error)
+	}
+}
+
+/// Changes made by migrations, which are only written once every migration
+/// has succeeded.
+#[derive(Debug, Default)]
+pub struct Transaction {
+	/// `None` means the file is removed
+	changes: BTreeMap<PathBuf, Option<Vec<u8>>>,
+}
+
+impl Transaction {
+	/// Reads a file, including changes made earlier in the transaction.
+	pub fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+		match self.changes.get(path.as_ref()) {
+			Some(Some(contents)) => Ok(contents.clone()),
+			Some(None) => Err(io::Error::new(
+				io::ErrorKind::NotFound,
+				"file was removed by a migration",
+			)),
+			None => fs::read(path),
+		}
+	}
+
+	/// Returns true if the file exists, including changes made earlier in the
+	/// transaction.
+	pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+		match self.changes.get(path.as_ref()) {
+			Some(change) => change.is_some(),
+			None => path.as_ref().exists(),
+		}
+	}
+
+	/// Replaces the contents of a file, creating it if needed.
+	pub fn write<P: Into<PathBuf>, C: Into<Vec<u8>>>(&mut self, path: P, contents: C) {
+		self.changes.insert(path.into(), Some(contents.into()));
+	}
+
+	/// Removes a file. It is not an error if the file doesn't exist.
+	pub fn remove<P: Into<PathBuf>>(&mut self, path: P) {
+		self.changes.insert(path.into(), None);
+	}
+
+	/// Writes every change, then calls `finish`. If a change or `finish`
+	/// fails, the files are put back the way they were before.
+	fn commit(self, finish: impl FnOnce() -> io::Result<()>) -> io::Result<()> {
+		// Read up front, so that a file that can't be read changes nothing
+		let mut originals = Vec::with_capacity(self.changes.len());
+		for path in self.changes.keys() {
+			let original = if path.exists() {
+				Some(fs::read(path)?)
+			} else {
+				None
+			};
+			originals.push((path, original));
+		}
+		let rollback = |written: usize| {
+			for (path, original) in originals[..written].iter().rev() {
+				let _ = apply(path, original);
+			}
+		};
+		for (done, (path, change)) in self.changes.iter().enumerate() {
+			if let Err(error) = apply(path, change) {
+				// Including the one that failed, which may be half done
+				rollback(done + 1);
+				return Err(error);
+			}
+		}
+		finish().map_err(|error| {
+			rollback(originals.len());
+			error
+		})
+	}
+}
+
+/// Writes `contents` to `path`, or removes it if `contents` is `None`.
+fn apply(path: &Path, contents: &Option<Vec<u8>>) -> io::Result<()> {
+	match contents {
+		Some(contents) => write_atomic(path, contents),
+		None if path.exists() => fs::remove_file_unchecked(path),
+		None => Ok(()),
+	}
+}
+
+type Migration = Box<dyn FnMut(&mut Transaction) -> io::Result<()>>;
+
+/// Runs the migrations needed to bring saved data up to the current version.
+pub struct Migrations {
+	version_file: PathBuf,
+	current: u32,
+	dry_run: bool,
+	migrations: Vec<(u32, Migration)>,
+}
+
+impl Migrations {
+	/// Creates a runner that stores the last-run version in `version_file`.
+	/// `current` is the version of the running program.
+	pub fn new(version_file: impl Into<PathBuf>, current: u32) -> Self {
+		Self {
+			version_file: version_file.into(),
+			current,
+			dry_run: false,
+			migrations: Vec::new(),
+		}
+	}
+
+	/// Registers a migration that upgrades data to `version`. It runs when the
+	/// stored version is older than `version`, and `version` is no newer than
+	/// the current version. Migrations run in order of version, no matter the
+	/// order they were registered in.
+	pub fn migration(
+		mut self,
+		version: u32,
+		migration: impl FnMut(&mut Transaction) -> io::Result<()> + 'static,
+	) -> Self {
+		self.migrations.push((version, Box::new(migration)));
+		self
+	}
+
+	/// If enabled, migrations run but their changes, and the new version, are
+	/// not saved. Useful for checking that migrations succeed on real data.
+	pub fn dry_run(mut self, dry_run: bool) -> Self {
+		self.dry_run = dry_run;
+		self
+	}
+
+	/// Returns the stored version, or `None` if the program hasn't run before.
+	pub fn stored_version(&self) -> io::Result<Option<u32>> {
+		if !self.version_file.exists() {
+			return Ok(None);
+		}
+		let contents = fs::read_to_string(&self.version_file)?;
+		contents
+			.trim()
+			.parse()
+			.map(Some)
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid version file"))
+	}
+
+	/// Runs every pending migration, then stores the current version.
+	pub fn run(mut self) -> Result<Outcome, MigrationError> {
+		let from = match self.stored_version() {
+			Ok(from) => from,
+			Err(error) => {
+				return Err(MigrationError {
+					version: None,
+					error,
+				})
+			}
+		};
+		let from = match from {
+			Some(from) if from >= self.current => return Ok(Outcome::UpToDate),
+			Some(from) => from,
+			None => {
+				if !self.dry_run {
+					self.store_version()?;
+				}
+				return Ok(Outcome::FirstRun);
+			}
+		};
+		let current = self.current;
+		self.migrations
+			.retain(|(version, _)| *version > from && *version <= current);
+		self.migrations.sort_by_key(|(version, _)| *version);
+		let mut tx = Transaction::default();
+		let mut applied = Vec::new();
+		for (version, migration) in self.migrations.iter_mut() {
+			if let Err(error) = migration(&mut tx) {
+				return Err(MigrationError {
+					version: Some(*version),
+					error,
+				});
+			}
+			applied.push(*version);
+		}
+		if self.dry_run {
+			return Ok(Outcome::DryRun {
+				from,
+				applied,
+				changed: tx.changes.into_iter().map(|(path, _)| path).collect(),
+			});
+		}
+		// The new version is only stored once every file is written, and the
+		// files are put back if it can't be
+		let (version_file, version) = (&self.version_file, self.current);
+		tx.commit(|| write_atomic(version_file, version.to_string()))
+			.map_err(|error| MigrationError {
+				version: None,
+				error,
+			})?;
+		Ok(Outcome::Migrated { from, applied })
+	}
+
+	fn store_version(&self) -> Result<(), MigrationError> {
+		write_atomic(&self.version_file, self.current.to_string()).map_err(|error| MigrationError {
+			version: None,
+			error,
+		})
+	}
+}
@@ -0,0 +1,132 @@
+//! # Sound
+//! The calculator has no speaker, but a piezo buzzer or headphones wired to a
+//! GPIO line on the dock connector can play square waves:
+//!
+//! ```
+//! use ndless::audio;
+//! use ndless::hw::gpio::OutputPin;
+//! use ndless::time::Duration;
+//!
+//! audio::set_output(OutputPin::new(BUZZER_LINE).unwrap());
+//! // A4, then C5
+//! audio::tone(440, Duration::from_millis(200));
+//! audio::tone(523, Duration::from_millis(200));
+//! ```
+//!
+//! The wave is generated by a hardware timer's interrupt, so [`play`] keeps it
+//! going while the program does other things. This is only supported on CX
+//! models before the CX II. Elsewhere, or before an output is set,
+//! [`is_supported`] returns false, and playing does nothing, except that
+//! [`tone`] still waits, so that music keeps its timing.
+//!
+//! Sound stops when the program exits, including through
+//! [`process::exit`][crate::process::exit], [`abort`][crate::process::abort]
+//! or a panic.
+
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::hw::gpio::OutputPin;
+use crate::hw::{hw_revision, Revision};
+use crate::interrupt::{self, Irq, Registration};
+use crate::time::{sleep, Duration};
+use crate::timer::TICKS_PER_SECOND;
+
+/// The second half of the timer that counts ticks, which runs at the same
+/// 32768Hz
+const BASE: usize = 0x900C_0020;
+const LOAD: *mut u32 = BASE as *mut u32;
+const CONTROL: *mut u32 = (BASE + 0x08) as *mut u32;
+const INT_CLEAR: *mut u32 = (BASE + 0x0C) as *mut u32;
+const MASKED_STATUS: *const u32 = (BASE + 0x14) as *const u32;
+/// Enabled, periodic, with its interrupt, and 32 bits wide
+const CONTROL_RUN: u32 = 1 << 7 | 1 << 6 | 1 << 5 | 1 << 1;
+
+/// The highest frequency that can be played, in Hz.
+pub const MAX_FREQUENCY: u32 = TICKS_PER_SECOND / 2;
+
+static mut OUTPUT: Option<OutputPin> = None;
+static mut REGISTRATION: Option<Registration> = None;
+/// The timer's control register before playing started
+static mut SAVED_CONTROL: u32 = 0;
+
+fn on_interrupt() -> bool {
+	unsafe {
+		if read_volatile(MASKED_STATUS) & 1 == 0 {
+			return false;
+		}
+		write_volatile(INT_CLEAR, 1);
+		if let Some(output) = &mut OUTPUT {
+			output.toggle();
+		}
+	}
+	true
+}
+
+/// Sets the line to play on, replacing and returning the one set before.
+pub fn set_output(output: OutputPin) -> Option<OutputPin> {
+	stop();
+	unsafe { OUTPUT.replace(output) }
+}
+
+/// Stops playing, and returns the line that was set with [`set_output`].
+pub fn take_output() -> Option<OutputPin> {
+	stop();
+	unsafe { OUTPUT.take() }
+}
+
+/// Returns true if sound can be played: the calculator supports it, and an
+/// output has been set.
+pub fn is_supported() -> bool {
+	matches!(hw_revision(), Revision::Cx | Revision::CxHwW) && unsafe { OUTPUT.is_some() }
+}
+
+/// Returns true while a wave is being played.
+pub fn is_playing() -> bool {
+	unsafe { REGISTRATION.is_some() }
+}
+
+/// Starts playing a square wave of `frequency` Hz, up to [`MAX_FREQUENCY`],
+/// until [`stop`] is called or another one is played. Returns false if sound
+/// isn't supported.
+pub fn play(frequency: u32) -> bool {
+	if !is_supported() || frequency == 0 {
+		stop();
+		return false;
+	}
+	let half_period = (TICKS_PER_SECOND / (2 * frequency.min(MAX_FREQUENCY))).max(1);
+	interrupt::free(|| unsafe {
+		if REGISTRATION.is_none() {
+			REGISTRATION = interrupt::register(Irq::FIRST_TIMER, on_interrupt);
+			if REGISTRATION.is_none() {
+				return false;
+			}
+			SAVED_CONTROL = read_volatile(CONTROL);
+		}
+		write_volatile(CONTROL, 0);
+		write_volatile(LOAD, half_period);
+		write_volatile(INT_CLEAR, 1);
+		write_volatile(CONTROL, CONTROL_RUN);
+		true
+	})
+}
+
+/// Stops playing, leaving the output low.
+pub fn stop() {
+	interrupt::free(|| unsafe {
+		if REGISTRATION.take().is_some() {
+			write_volatile(CONTROL, SAVED_CONTROL);
+			write_volatile(INT_CLEAR, 1);
+		}
+		if let Some(output) = &mut OUTPUT {
+			output.set_low();
+		}
+	})
+}
+
+/// Plays a square wave of `frequency` Hz for `duration`, then stops. A
+/// frequency of 0 is silent.
+pub fn tone(frequency: u32, duration: Duration) {
+	play(frequency);
+	sleep(duration);
+	stop();
+}
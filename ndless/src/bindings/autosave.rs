@@ -0,0 +1,150 @@
+//! # Auto-saving
+//! This module periodically saves the program's data, so that the user doesn't
+//! lose their work if the calculator runs out of battery or the program
+//! crashes.
+//!
+//! Register a callback for each file that should be saved, then call
+//! [`poll`][AutoSave::poll] once per frame:
+//!
+//! ```
+//! use ndless::autosave::AutoSave;
+//! use ndless::time::Duration;
+//!
+//! let mut autosave = AutoSave::new(Duration::from_secs(60));
+//! autosave.save_when_idle(Duration::from_secs(10));
+//! autosave.register("/documents/game/save.dat.tns", move || Some(state.serialize()));
+//! loop {
+//!     // ...
+//!     autosave.poll();
+//! }
+//! ```
+//!
+//! Files are written with [`write_atomic`], so an interrupted save never
+//! leaves a half-written file behind. If a save fails, a
+//! [toast][crate::ui::toast] is shown.
+//!
+//! [`poll`][AutoSave::poll] also watches the battery with a
+//! [`BatteryMonitor`], and saves straight away when the charge becomes
+//! critical.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+
+use crate::fs;
+use crate::hw::battery::{BatteryEvent, BatteryMonitor};
+use crate::input::idle::idle_time;
+use crate::io;
+use crate::path::{Path, PathBuf};
+use crate::time::Duration;
+use crate::timer::{get_ticks, has_time_passed, Ticks};
+use crate::ui::toast;
+
+/// Writes `contents` to a temporary file next to `path`, then moves it into
+/// place. If the calculator loses power partway through, a complete copy of
+/// either the old or the new contents is left behind (the new one possibly
+/// still under the temporary name, with `.tmp` added to its extension), never
+/// a half-written file.
+pub fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+	let path = path.as_ref();
+	let tmp = path.with_extension(match path.extension() {
+		Some(ext) => format!("{}.tmp", ext.to_string_lossy()),
+		None => "tmp".into(),
+	});
+	fs::write(&tmp, contents)?;
+	if path.exists() {
+		fs::remove_file_unchecked(path)?;
+	}
+	fs::rename(&tmp, path)
+}
+
+struct Saver {
+	path: PathBuf,
+	save: Box<dyn FnMut() -> Option<Vec<u8>>>,
+}
+
+/// Runs registered save callbacks at regular intervals and when requested.
+pub struct AutoSave {
+	interval: u32,
+	next_save: u32,
+	idle_after: Option<Duration>,
+	saved_while_idle: bool,
+	battery: BatteryMonitor,
+	savers: Vec<Saver>,
+}
+
+impl AutoSave {
+	/// Creates an auto-saver that saves every `interval`.
+	pub fn new(interval: Duration) -> Self {
+		let interval = interval.as_ticks();
+		Self {
+			interval,
+			next_save: get_ticks().wrapping_add(interval),
+			idle_after: None,
+			saved_while_idle: false,
+			battery: BatteryMonitor::new(),
+			savers: Vec::new(),
+		}
+	}
+
+	/// Also saves once whenever there has been no input for `after`. See
+	/// [`input::idle`][crate::input::idle].
+	pub fn save_when_idle(&mut self, after: Duration) -> &mut Self {
+		self.idle_after = Some(after);
+		self
+	}
+
+	/// Registers a callback that returns the data to write to `path`, or `None`
+	/// if nothing has changed since the last save.
+	pub fn register(
+		&mut self,
+		path: impl Into<PathBuf>,
+		save: impl FnMut() -> Option<Vec<u8>> + 'static,
+	) -> &mut Self {
+		self.savers.push(Saver {
+			path: path.into(),
+			save: Box::new(save),
+		});
+		self
+	}
+
+	/// Saves if the interval has passed, the user has become idle, or the
+	/// battery has become critical. This should be called once per frame.
+	pub fn poll(&mut self) {
+		if has_time_passed(self.next_save) || self.battery.poll() == Some(BatteryEvent::Critical) {
+			self.save_now();
+			return;
+		}
+		if let Some(after) = self.idle_after {
+			if idle_time() >= after {
+				if !self.saved_while_idle {
+					self.save_now();
+					self.saved_while_idle = true;
+				}
+			} else {
+				self.saved_while_idle = false;
+			}
+		}
+	}
+
+	/// Saves immediately, such as when switching scenes or exiting, and
+	/// restarts the interval. Returns the number of files that failed to save.
+	pub fn save_now(&mut self) -> usize {
+		self.next_save = get_ticks().wrapping_add(self.interval);
+		let mut failed = 0;
+		for saver in self.savers.iter_mut() {
+			if let Some(contents) = (saver.save)() {
+				if let Err(err) = write_atomic(&saver.path, contents) {
+					failed += 1;
+					let name = saver
+						.path
+						.file_name()
+						.map(|name| name.to_string_lossy().into_owned())
+						.unwrap_or_default();
+					toast(format!("Could not save {}: {}", name, err));
+				}
+			}
+		}
+		failed
+	}
+}
@@ -0,0 +1,7 @@
+//! # Collections
+//! The collections from `alloc`, like `std::collections`, plus [`pool`]
+//! types that never allocate.
+
+pub mod pool;
+
+pub use alloc::collections::*;
@@ -0,0 +1,504 @@
+//! Fixed-capacity collections
+//!
+//! Collections that store their elements inline, up to a capacity chosen at
+//! compile time, and never allocate. They can be used where the heap can't,
+//! such as in [interrupt handlers][crate::interrupt], or in `static`s:
+//!
+//! ```
+//! use ndless::collections::pool::{RingBuffer, String};
+//! use ndless::interrupt;
+//!
+//! static mut RECEIVED: RingBuffer<u8, 64> = RingBuffer::new();
+//!
+//! fn on_uart() -> bool {
+//!     // In the handler
+//!     let _ = unsafe { RECEIVED.push_back(read_byte()) };
+//!     true
+//! }
+//!
+//! // In the main loop
+//! while let Some(byte) = interrupt::free(|| unsafe { RECEIVED.pop_front() }) {
+//!     handle(byte);
+//! }
+//!
+//! let mut label: String<16> = String::new();
+//! write!(label, "Score: {}", score)?;
+//! ```
+//!
+//! When a collection is full, adding to it fails instead of growing: methods
+//! return the value that didn't fit, or a [`CapacityError`]. The byte
+//! collections implement [`io::Read`] and [`io::Write`], and [`String`]
+//! implements [`fmt::Write`].
+//!
+//! The collections aren't synchronized. When one is shared with an interrupt
+//! handler, the rest of the program should only touch it inside
+//! [`interrupt::free`][crate::interrupt::free].
+
+use core::borrow::{Borrow, BorrowMut};
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+use core::{fmt, ptr, slice, str};
+
+use crate::error::Error;
+use crate::io;
+
+/// An error returned when a collection is too full to add to.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct CapacityError;
+
+impl fmt::Display for CapacityError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "insufficient capacity")
+	}
+}
+
+impl Error for CapacityError {}
+
+/// A `Vec` that holds up to `N` elements.
+pub struct Vec<T, const N: usize> {
+	buf: MaybeUninit<[T; N]>,
+	len: usize,
+}
+
+impl<T, const N: usize> Vec<T, N> {
+	pub const fn new() -> Self {
+		Self {
+			buf: MaybeUninit::uninit(),
+			len: 0,
+		}
+	}
+
+	fn as_ptr(&self) -> *const T {
+		self.buf.as_ptr() as *const T
+	}
+
+	fn as_mut_ptr(&mut self) -> *mut T {
+		self.buf.as_mut_ptr() as *mut T
+	}
+
+	pub const fn capacity(&self) -> usize {
+		N
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	pub fn is_full(&self) -> bool {
+		self.len == N
+	}
+
+	pub fn as_slice(&self) -> &[T] {
+		unsafe { slice::from_raw_parts(self.as_ptr(), self.len) }
+	}
+
+	pub fn as_mut_slice(&mut self) -> &mut [T] {
+		unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+	}
+
+	/// Appends `value`, or gives it back if the `Vec` is full.
+	pub fn push(&mut self, value: T) -> Result<(), T> {
+		if self.is_full() {
+			return Err(value);
+		}
+		unsafe { ptr::write(self.as_mut_ptr().add(self.len), value) };
+		self.len += 1;
+		Ok(())
+	}
+
+	pub fn pop(&mut self) -> Option<T> {
+		if self.is_empty() {
+			return None;
+		}
+		self.len -= 1;
+		Some(unsafe { ptr::read(self.as_ptr().add(self.len)) })
+	}
+
+	/// Inserts `value` at `index`, moving the elements after it along, or
+	/// gives it back if the `Vec` is full.
+	///
+	/// # Panics
+	/// If `index` is greater than the length.
+	pub fn insert(&mut self, index: usize, value: T) -> Result<(), T> {
+		assert!(index <= self.len, "insertion index is out of bounds");
+		if self.is_full() {
+			return Err(value);
+		}
+		unsafe {
+			let at = self.as_mut_ptr().add(index);
+			ptr::copy(at, at.add(1), self.len - index);
+			ptr::write(at, value);
+		}
+		self.len += 1;
+		Ok(())
+	}
+
+	/// Removes the element at `index`, moving the elements after it back.
+	///
+	/// # Panics
+	/// If `index` is out of bounds.
+	pub fn remove(&mut self, index: usize) -> T {
+		assert!(index < self.len, "removal index is out of bounds");
+		self.len -= 1;
+		unsafe {
+			let at = self.as_mut_ptr().add(index);
+			let value = ptr::read(at);
+			ptr::copy(at.add(1), at, self.len - index);
+			value
+		}
+	}
+
+	/// Removes the element at `index`, replacing it with the last element.
+	///
+	/// # Panics
+	/// If `index` is out of bounds.
+	pub fn swap_remove(&mut self, index: usize) -> T {
+		let len = self.len;
+		self.as_mut_slice().swap(index, len - 1);
+		self.pop().unwrap()
+	}
+
+	/// Drops the elements after the first `len`.
+	pub fn truncate(&mut self, len: usize) {
+		while self.len > len {
+			self.pop();
+		}
+	}
+
+	pub fn clear(&mut self) {
+		self.truncate(0);
+	}
+
+	/// Appends clones of every element of `other`. If they don't all fit,
+	/// nothing is added.
+	pub fn extend_from_slice(&mut self, other: &[T]) -> Result<(), CapacityError>
+	where
+		T: Clone,
+	{
+		if N - self.len < other.len() {
+			return Err(CapacityError);
+		}
+		for value in other {
+			let _ = self.push(value.clone());
+		}
+		Ok(())
+	}
+}
+
+impl<T, const N: usize> Drop for Vec<T, N> {
+	fn drop(&mut self) {
+		unsafe { ptr::drop_in_place(self.as_mut_slice()) }
+	}
+}
+
+impl<T, const N: usize> Default for Vec<T, N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: Clone, const N: usize> Clone for Vec<T, N> {
+	fn clone(&self) -> Self {
+		let mut vec = Self::new();
+		let _ = vec.extend_from_slice(self);
+		vec
+	}
+}
+
+impl<T, const N: usize> Deref for Vec<T, N> {
+	type Target = [T];
+
+	fn deref(&self) -> &[T] {
+		self.as_slice()
+	}
+}
+
+impl<T, const N: usize> DerefMut for Vec<T, N> {
+	fn deref_mut(&mut self) -> &mut [T] {
+		self.as_mut_slice()
+	}
+}
+
+impl<T, const N: usize> Borrow<[T]> for Vec<T, N> {
+	fn borrow(&self) -> &[T] {
+		self.as_slice()
+	}
+}
+
+impl<T, const N: usize> BorrowMut<[T]> for Vec<T, N> {
+	fn borrow_mut(&mut self) -> &mut [T] {
+		self.as_mut_slice()
+	}
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for Vec<T, N> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Debug::fmt(self.as_slice(), f)
+	}
+}
+
+impl<T: PartialEq, const N: usize, const M: usize> PartialEq<Vec<T, M>> for Vec<T, N> {
+	fn eq(&self, other: &Vec<T, M>) -> bool {
+		self.as_slice() == other.as_slice()
+	}
+}
+
+impl<T: Eq, const N: usize> Eq for Vec<T, N> {}
+
+impl<const N: usize> io::Write for Vec<u8, N> {
+	/// Appends as many bytes as fit. When the `Vec` is full, this writes 0
+	/// bytes, so [`write_all`][io::Write::write_all] fails with
+	/// [`WriteZero`][io::ErrorKind::WriteZero].
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let len = buf.len().min(N - self.len);
+		let _ = self.extend_from_slice(&buf[..len]);
+		Ok(len)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// A `String` that holds up to `N` bytes of UTF-8.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct String<const N: usize> {
+	vec: Vec<u8, N>,
+}
+
+impl<const N: usize> String<N> {
+	pub const fn new() -> Self {
+		Self { vec: Vec::new() }
+	}
+
+	pub const fn capacity(&self) -> usize {
+		N
+	}
+
+	pub fn len(&self) -> usize {
+		self.vec.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.vec.is_empty()
+	}
+
+	pub fn as_str(&self) -> &str {
+		// Only whole strs and chars are ever added
+		unsafe { str::from_utf8_unchecked(&self.vec) }
+	}
+
+	/// Appends `s`. If it doesn't fit, nothing is added.
+	pub fn push_str(&mut self, s: &str) -> Result<(), CapacityError> {
+		self.vec.extend_from_slice(s.as_bytes())
+	}
+
+	pub fn push(&mut self, ch: char) -> Result<(), CapacityError> {
+		self.push_str(ch.encode_utf8(&mut [0; 4]))
+	}
+
+	pub fn pop(&mut self) -> Option<char> {
+		let ch = self.as_str().chars().next_back()?;
+		self.vec.truncate(self.len() - ch.len_utf8());
+		Some(ch)
+	}
+
+	/// Shortens the string to `len` bytes.
+	///
+	/// # Panics
+	/// If `len` isn't on a character boundary.
+	pub fn truncate(&mut self, len: usize) {
+		if len < self.len() {
+			assert!(self.as_str().is_char_boundary(len));
+			self.vec.truncate(len);
+		}
+	}
+
+	pub fn clear(&mut self) {
+		self.vec.clear();
+	}
+}
+
+impl<const N: usize> Deref for String<N> {
+	type Target = str;
+
+	fn deref(&self) -> &str {
+		self.as_str()
+	}
+}
+
+impl<const N: usize> AsRef<str> for String<N> {
+	fn as_ref(&self) -> &str {
+		self.as_str()
+	}
+}
+
+impl<const N: usize> fmt::Write for String<N> {
+	/// Fails with [`fmt::Error`] if `s` doesn't fit, after writing whatever
+	/// fit before it.
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.push_str(s).map_err(|_| fmt::Error)
+	}
+}
+
+impl<const N: usize> fmt::Display for String<N> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Display::fmt(self.as_str(), f)
+	}
+}
+
+impl<const N: usize> fmt::Debug for String<N> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Debug::fmt(self.as_str(), f)
+	}
+}
+
+impl<const N: usize> PartialEq<str> for String<N> {
+	fn eq(&self, other: &str) -> bool {
+		self.as_str() == other
+	}
+}
+
+impl<const N: usize> PartialEq<&str> for String<N> {
+	fn eq(&self, other: &&str) -> bool {
+		self.as_str() == *other
+	}
+}
+
+/// A first-in, first-out queue that holds up to `N` elements.
+pub struct RingBuffer<T, const N: usize> {
+	buf: MaybeUninit<[T; N]>,
+	/// The index of the front element
+	head: usize,
+	len: usize,
+}
+
+impl<T, const N: usize> RingBuffer<T, N> {
+	pub const fn new() -> Self {
+		Self {
+			buf: MaybeUninit::uninit(),
+			head: 0,
+			len: 0,
+		}
+	}
+
+	fn slot(&mut self, index: usize) -> *mut T {
+		unsafe { (self.buf.as_mut_ptr() as *mut T).add((self.head + index) % N) }
+	}
+
+	pub const fn capacity(&self) -> usize {
+		N
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	pub fn is_full(&self) -> bool {
+		self.len == N
+	}
+
+	/// Adds `value` to the back, or gives it back if the buffer is full.
+	pub fn push_back(&mut self, value: T) -> Result<(), T> {
+		if self.is_full() {
+			return Err(value);
+		}
+		unsafe { ptr::write(self.slot(self.len), value) };
+		self.len += 1;
+		Ok(())
+	}
+
+	/// Removes the element at the front.
+	pub fn pop_front(&mut self) -> Option<T> {
+		if self.is_empty() {
+			return None;
+		}
+		let value = unsafe { ptr::read(self.slot(0)) };
+		self.head = (self.head + 1) % N;
+		self.len -= 1;
+		Some(value)
+	}
+
+	/// Returns the element at the front without removing it.
+	pub fn front(&self) -> Option<&T> {
+		if self.is_empty() {
+			None
+		} else {
+			Some(unsafe { &*(self.buf.as_ptr() as *const T).add(self.head) })
+		}
+	}
+
+	pub fn clear(&mut self) {
+		while self.pop_front().is_some() {}
+	}
+}
+
+impl<T, const N: usize> Drop for RingBuffer<T, N> {
+	fn drop(&mut self) {
+		self.clear();
+	}
+}
+
+impl<T, const N: usize> Default for RingBuffer<T, N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T, const N: usize> fmt::Debug for RingBuffer<T, N> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RingBuffer")
+			.field("len", &self.len)
+			.field("capacity", &N)
+			.finish()
+	}
+}
+
+impl<const N: usize> io::Read for RingBuffer<u8, N> {
+	/// Takes bytes from the front. Fails with
+	/// [`WouldBlock`][io::ErrorKind::WouldBlock] if the buffer is empty.
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		if self.is_empty() {
+			return Err(io::ErrorKind::WouldBlock.into());
+		}
+		let mut len = 0;
+		for (byte, value) in buf.iter_mut().zip(core::iter::from_fn(|| self.pop_front())) {
+			*byte = value;
+			len += 1;
+		}
+		Ok(len)
+	}
+}
+
+impl<const N: usize> io::Write for RingBuffer<u8, N> {
+	/// Adds as many bytes as fit to the back. Fails with
+	/// [`WouldBlock`][io::ErrorKind::WouldBlock] if the buffer is full.
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		if self.is_full() {
+			return Err(io::ErrorKind::WouldBlock.into());
+		}
+		let len = buf.len().min(N - self.len);
+		for &byte in &buf[..len] {
+			let _ = self.push_back(byte);
+		}
+		Ok(len)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
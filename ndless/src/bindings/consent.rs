@@ -0,0 +1,245 @@
+//! # Consent prompts
+//! Asks the user before a program does something sensitive, with the same
+//! prompt in every program.
+//!
+//! ```
+//! use ndless::consent::{self, Capability};
+//!
+//! if consent::request(Capability::TelemetryExport, "to show usage statistics as text") {
+//!     // ...
+//! }
+//! ```
+//!
+//! The user can allow a capability once, allow it permanently, or refuse it.
+//! Permanent choices are remembered for each program in a file in the
+//! documents folder, [`CONSENT_FILE`], so the user can review them with
+//! [`decisions`] or by opening the file, and take them back with [`revoke`].
+
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::autosave::write_atomic;
+use crate::env;
+use crate::fs;
+use crate::io;
+use crate::msg::{msg_3b, Button};
+use crate::path::{Component, Path, PathBuf};
+
+/// The file that permanent choices are stored in, relative to the documents
+/// folder.
+pub const CONSENT_FILE: &str = "ndless/consent.tns";
+
+/// Something a program must ask the user for.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Capability {
+	/// Deleting files outside the program's folder
+	DeleteOutsideProgram,
+	/// Using the serial port
+	Serial,
+	/// Exporting [telemetry][crate::telemetry]
+	TelemetryExport,
+	/// Defined by the program. The name is shown to the user, and used to
+	/// remember their choice.
+	Custom(&'static str),
+}
+
+impl Capability {
+	/// The name stored in [`CONSENT_FILE`]
+	pub fn name(self) -> &'static str {
+		match self {
+			Capability::DeleteOutsideProgram => "delete-outside-program",
+			Capability::Serial => "serial",
+			Capability::TelemetryExport => "telemetry-export",
+			Capability::Custom(name) => name,
+		}
+	}
+
+	fn description(self) -> &'static str {
+		match self {
+			Capability::DeleteOutsideProgram => "delete files outside its folder",
+			Capability::Serial => "use the serial port",
+			Capability::TelemetryExport => "export usage statistics",
+			Capability::Custom(name) => name,
+		}
+	}
+}
+
+/// A permanent choice, returned by [`decisions`].
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct Decision {
+	pub program: String,
+	pub capability: String,
+}
+
+fn consent_file() -> io::Result<PathBuf> {
+	Ok(env::get_documents_dir()?.join(CONSENT_FILE))
+}
+
+/// The name of the running program, used to remember its choices
+fn program() -> String {
+	env::args()
+		.next()
+		.as_ref()
+		.and_then(|path| Path::new(path).file_stem())
+		.map_or(Cow::Borrowed("unknown"), |name| name.to_string_lossy())
+		.into_owned()
+}
+
+/// Tabs, line breaks and backslashes are escaped, so that each choice is one
+/// line with a tab between its two fields
+fn escape(value: &str) -> String {
+	value
+		.replace('\\', "\\\\")
+		.replace('\t', "\\t")
+		.replace('\n', "\\n")
+		.replace('\r', "\\r")
+}
+
+fn unescape(value: &str) -> String {
+	let mut unescaped = String::with_capacity(value.len());
+	let mut chars = value.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			unescaped.push(c);
+			continue;
+		}
+		match chars.next() {
+			Some('t') => unescaped.push('\t'),
+			Some('n') => unescaped.push('\n'),
+			Some('r') => unescaped.push('\r'),
+			Some(c) => unescaped.push(c),
+			None => {}
+		}
+	}
+	unescaped
+}
+
+/// Returns every permanent choice, for every program.
+pub fn decisions() -> Vec<Decision> {
+	let contents = consent_file()
+		.and_then(fs::read_to_string)
+		.unwrap_or_default();
+	contents
+		.lines()
+		.filter_map(|line| {
+			let mut parts = line.splitn(2, '\t');
+			Some(Decision {
+				program: unescape(parts.next()?),
+				capability: unescape(parts.next()?),
+			})
+		})
+		.collect()
+}
+
+fn save(decisions: &[Decision]) -> io::Result<()> {
+	let path = consent_file()?;
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir)?;
+	}
+	let contents: String = decisions
+		.iter()
+		.map(|decision| {
+			format!(
+				"{}\t{}\n",
+				escape(&decision.program),
+				escape(&decision.capability)
+			)
+		})
+		.collect();
+	write_atomic(path, contents)
+}
+
+/// Returns true if the user has permanently allowed this program to use
+/// `capability`. Doesn't prompt.
+pub fn is_granted(capability: Capability) -> bool {
+	let program = program();
+	decisions()
+		.iter()
+		.any(|decision| decision.program == program && decision.capability == capability.name())
+}
+
+/// Asks the user whether the program may use `capability`, unless they have
+/// already allowed it permanently. `reason` is shown after a description of
+/// the capability, and should say why, such as "to show usage statistics".
+///
+/// Returns true if the program may go ahead.
+pub fn request(capability: Capability, reason: &str) -> bool {
+	if is_granted(capability) {
+		return true;
+	}
+	let program = program();
+	let text = format!(
+		"{} wants to {}, {}.\n\nAllow this?",
+		program,
+		capability.description(),
+		reason
+	);
+	match msg_3b("Permission", &text, "Once", "Always", "Don't allow") {
+		Button::One => true,
+		Button::Two => {
+			let mut decisions = decisions();
+			decisions.push(Decision {
+				program,
+				capability: capability.name().into(),
+			});
+			// The capability is still allowed this time if it can't be saved
+			let _ = save(&decisions);
+			true
+		}
+		Button::Three => false,
+	}
+}
+
+/// Forgets a permanent choice made for this program, so the user is asked
+/// again next time.
+pub fn revoke(capability: Capability) -> io::Result<()> {
+	let program = program();
+	let mut decisions = decisions();
+	decisions.retain(|decision| {
+		!(decision.program == program && decision.capability == capability.name())
+	});
+	save(&decisions)
+}
+
+/// Resolves `.` and `..` without looking at the file system, so that a path
+/// can't appear to be inside a folder while leading out of it
+fn normalize(path: &Path) -> PathBuf {
+	let mut normalized = PathBuf::new();
+	for component in path.components() {
+		match component {
+			Component::CurDir => {}
+			Component::ParentDir => {
+				normalized.pop();
+			}
+			component => normalized.push(component.as_os_str()),
+		}
+	}
+	normalized
+}
+
+/// Called by [`fs::remove_file`]. If `path` is outside the program's folder,
+/// the user is asked first with [`Capability::DeleteOutsideProgram`], and an
+/// error of the kind [`PermissionDenied`][io::ErrorKind::PermissionDenied] is
+/// returned if they refuse.
+pub(crate) fn check_remove(path: &Path) -> io::Result<()> {
+	let program_dir = env::args()
+		.next()
+		.and_then(|program| Path::new(&program).parent().map(Path::to_path_buf));
+	let inside = match (&program_dir, env::current_dir()) {
+		(Some(dir), Ok(cwd)) => normalize(&cwd.join(path)).starts_with(normalize(dir)),
+		_ => false,
+	};
+	if !inside
+		&& !request(
+			Capability::DeleteOutsideProgram,
+			&format!("to remove {}", path.display()),
+		) {
+		return Err(io::Error::new(
+			io::ErrorKind::PermissionDenied,
+			"the user did not allow deleting this file",
+		));
+	}
+	Ok(())
+}
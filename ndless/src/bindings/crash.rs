@@ -0,0 +1,289 @@
+//! # Crash screen
+//! Bugs in `unsafe` code, such as reading through a dangling pointer or
+//! jumping to a bad function pointer, make the CPU raise an exception rather
+//! than panic. The OS's handlers for these reboot the calculator, leaving
+//! nothing to go on.
+//!
+//! [`install`] replaces the handlers for undefined instructions, prefetch
+//! aborts and data aborts. Instead of rebooting, they show a screen like the
+//! [panic screen][crate::panic] with:
+//!
+//! - which exception it was, and the address that caused it
+//! - the CPU's registers when it happened, including the CPSR
+//! - addresses from the call stack
+//!
+//! The same report is appended to [`LOG_FILE`] in the documents directory.
+//! After a key is pressed, the program exits with
+//! [`abort`][crate::process::abort].
+//!
+//! ```
+//! ndless::crash::install();
+//! let value = unsafe { core::ptr::read_volatile(0xDEAD_0000 as *const u32) };
+//! ```
+//!
+//! They're installed at startup by default, whether the program uses
+//! `#[entry]` or not, as set by [`Startup::DEFAULT`][crate::process::Startup::DEFAULT].
+//! `#[entry(crash = "os")]`, or turning off `ndless-handler`'s `crash-handler`
+//! feature for programs without `#[entry]`, leaves the OS's handlers in place.
+//! The OS's handlers are put back when the program exits, and while the crash
+//! screen is shown, so a second crash reboots as before.
+//!
+//! See [the panic screen's documentation][crate::panic#finding-functions-from-addresses]
+//! for how to find functions from the addresses.
+
+use alloc::format;
+use core::fmt;
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::env::get_documents_dir;
+use crate::fs::OpenOptions;
+use crate::io::{self, Write};
+use crate::panic::{backtrace, show_report, wait_for_key, Registers};
+use crate::path::PathBuf;
+
+/// The name of the file, in the documents directory, that crashes are logged
+/// to.
+pub const LOG_FILE: &str = "crash.log.tns";
+
+/// The addresses that the exception vectors jump to are stored here.
+const UNDEFINED_VECTOR: *mut u32 = 0x24 as *mut u32;
+const PREFETCH_ABORT_VECTOR: *mut u32 = 0x2C as *mut u32;
+const DATA_ABORT_VECTOR: *mut u32 = 0x30 as *mut u32;
+
+/// The size of the stack the crash screen runs on, as the program's own stack
+/// may be what was corrupted
+const STACK_SIZE: usize = 16 * 1024;
+
+global_asm!(
+	"
+	.text
+	.arm
+	.global __ndless_undefined_entry
+__ndless_undefined_entry:
+	str r0, __ndless_crash_frame
+	adr r0, __ndless_crash_frame + 4
+	stmia r0, {r1-r12}
+	sub lr, lr, #4
+	mov r0, #0
+	b __ndless_crash_common
+	.global __ndless_prefetch_abort_entry
+__ndless_prefetch_abort_entry:
+	str r0, __ndless_crash_frame
+	adr r0, __ndless_crash_frame + 4
+	stmia r0, {r1-r12}
+	sub lr, lr, #4
+	mov r0, #1
+	b __ndless_crash_common
+	.global __ndless_data_abort_entry
+__ndless_data_abort_entry:
+	str r0, __ndless_crash_frame
+	adr r0, __ndless_crash_frame + 4
+	stmia r0, {r1-r12}
+	sub lr, lr, #8
+	mov r0, #2
+@ r0: the exception, lr: the instruction that caused it
+__ndless_crash_common:
+	adr r3, __ndless_crash_frame
+	str lr, [r3, #60]
+	mrs r1, spsr
+	str r1, [r3, #64]
+@ Switch to the mode that crashed, with interrupts disabled, to read its sp
+@ and lr. User mode can't switch back, so use system mode instead.
+	and r2, r1, #0x1F
+	cmp r2, #0x10
+	moveq r2, #0x1F
+	orr r2, r2, #0xC0
+	msr cpsr_c, r2
+	str sp, [r3, #52]
+	str lr, [r3, #56]
+	ldr sp, __ndless_crash_stack
+	b __ndless_crash
+	.global __ndless_crash_stack
+__ndless_crash_stack:
+	.word 0
+	.global __ndless_crash_frame
+__ndless_crash_frame:
+	.space 68
+"
+);
+
+extern "C" {
+	fn __ndless_undefined_entry();
+	fn __ndless_prefetch_abort_entry();
+	fn __ndless_data_abort_entry();
+	/// The top of the stack to run [`__ndless_crash`] on, stored next to the
+	/// entry points so that it can be loaded relative to the program counter
+	static mut __ndless_crash_stack: u32;
+	/// r0 to r12, sp, lr, pc and cpsr of the code that crashed
+	static __ndless_crash_frame: [u32; 17];
+}
+
+/// The OS's handlers, in the order of [`vectors`]
+static mut ORIGINAL: Option<[u32; 3]> = None;
+static mut STACK: [u64; STACK_SIZE / 8] = [0; STACK_SIZE / 8];
+
+/// A CPU exception caught by the crash handler.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Exception {
+	/// The CPU tried to run something that isn't an instruction, usually
+	/// after jumping to an address that doesn't hold code
+	UndefinedInstruction,
+	/// The CPU tried to run code at an address it can't read
+	PrefetchAbort,
+	/// An instruction read or wrote an address that it can't
+	DataAbort,
+}
+
+impl fmt::Display for Exception {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Exception::UndefinedInstruction => "undefined instruction",
+			Exception::PrefetchAbort => "prefetch abort",
+			Exception::DataAbort => "data abort",
+		})
+	}
+}
+
+/// What went wrong, and where.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Crash {
+	pub exception: Exception,
+	/// The address that couldn't be accessed. For a data abort, this is the
+	/// data's address, otherwise it's the instruction's.
+	pub address: u32,
+	/// The registers of the code that crashed. `pc` is the instruction that
+	/// caused the crash.
+	pub registers: Registers,
+}
+
+impl fmt::Display for Crash {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{} at {:08x} (pc = {:08x})",
+			self.exception, self.address, self.registers.pc
+		)
+	}
+}
+
+fn vectors() -> [(*mut u32, u32); 3] {
+	[
+		(UNDEFINED_VECTOR, __ndless_undefined_entry as usize as u32),
+		(
+			PREFETCH_ABORT_VECTOR,
+			__ndless_prefetch_abort_entry as usize as u32,
+		),
+		(DATA_ABORT_VECTOR, __ndless_data_abort_entry as usize as u32),
+	]
+}
+
+/// Replaces the OS's handlers for CPU exceptions with the crash screen. Does
+/// nothing if they're already installed. See the [module documentation][self].
+pub fn install() {
+	unsafe {
+		if ORIGINAL.is_some() {
+			return;
+		}
+		__ndless_crash_stack = STACK.as_ptr().add(STACK.len()) as usize as u32;
+		let mut original = [0; 3];
+		for (orig, (vector, entry)) in original.iter_mut().zip(&vectors()) {
+			*orig = read_volatile(*vector);
+			write_volatile(*vector, *entry);
+		}
+		ORIGINAL = Some(original);
+	}
+}
+
+/// Puts the OS's handlers back, if [`install`] replaced them. This is done
+/// when the program exits, so it rarely needs to be called directly.
+pub fn restore() {
+	unsafe {
+		if let Some(original) = ORIGINAL.take() {
+			for (orig, (vector, _)) in original.iter().zip(&vectors()) {
+				write_volatile(*vector, *orig);
+			}
+		}
+	}
+}
+
+/// Reads the Fault Address Register, which holds the address a data abort
+/// tried to access.
+fn fault_address() -> u32 {
+	let address: u32;
+	unsafe { llvm_asm!("mrc p15, 0, $0, c6, c0, 0" : "=r"(address) : : : "volatile") }
+	address
+}
+
+/// Appends `crash` and its call stack to [`LOG_FILE`], returning the path
+/// written to.
+fn log(crash: &Crash, frames: &[u32]) -> io::Result<PathBuf> {
+	let path = get_documents_dir()?.join(LOG_FILE);
+	let mut file = OpenOptions::new().append(true).create(true).open(&path)?;
+	writeln!(file, "crash: {}", crash)?;
+	for line in crash.registers.lines() {
+		writeln!(file, "  {}", line.trim_end())?;
+	}
+	write!(
+		file,
+		"  call stack (show = {:08x}):",
+		crate::panic::show as usize
+	)?;
+	for addr in frames {
+		write!(file, " {:08x}", addr)?;
+	}
+	writeln!(file)?;
+	Ok(path)
+}
+
+/// Called by the entry points on the crash stack, in the mode that crashed,
+/// with interrupts disabled.
+#[no_mangle]
+extern "C" fn __ndless_crash(exception: u32) -> ! {
+	// If showing the crash crashes too, let the OS handle it
+	restore();
+	let frame = unsafe { __ndless_crash_frame };
+	let mut r = [0; 13];
+	r.copy_from_slice(&frame[..13]);
+	let registers = Registers {
+		r,
+		sp: frame[13],
+		lr: frame[14],
+		pc: frame[15],
+		cpsr: frame[16],
+	};
+	let exception = match exception {
+		0 => Exception::UndefinedInstruction,
+		1 => Exception::PrefetchAbort,
+		_ => Exception::DataAbort,
+	};
+	let address = match exception {
+		Exception::DataAbort => fault_address(),
+		_ => registers.pc,
+	};
+	let crash = Crash {
+		exception,
+		address,
+		registers,
+	};
+	let frames = backtrace(registers.sp);
+
+	// The crash screen can't be drawn if a program's Screen has the LCD
+	crate::video::restore();
+	let message = format!("{}", crash);
+	let detail = match log(&crash, &frames) {
+		Ok(path) => format!("Saved to {}", path.display()),
+		Err(err) => format!("Could not save the log: {}", err),
+	};
+	if show_report(
+		"The program crashed",
+		&message,
+		Some(&detail),
+		&registers,
+		&frames,
+	) {
+		wait_for_key();
+	} else {
+		crate::msg::msg("Error", &format!("{}\n{}", message, detail));
+	}
+	crate::process::abort()
+}
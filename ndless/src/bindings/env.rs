@@ -3,13 +3,49 @@
 //! This module contains functions to inspect various aspects such as
 //! process arguments, the current directory, and various
 //! other important directories.
+//!
+//! # Arguments
+//! When a program is opened from the launcher, its only argument is its own
+//! path. When a document is opened with a program through a file
+//! association, the document's full path comes next, which
+//! [`opened_document`] returns. A program can set itself up to open a file
+//! extension with [`register_file_extension`]. Programs started by another
+//! program can be given any arguments, and [`parse_args`] splits them up in
+//! the usual command-line style:
+//!
+//! ```
+//! use ndless::env;
+//!
+//! // convert.tns /documents/photo.tns --gray scale=2
+//! let args = env::parse_args();
+//! let gray = args.flag("gray");
+//! let scale = args.value("scale").and_then(|s| s.parse().ok()).unwrap_or(1);
+//! if let Some(input) = args.document() {
+//!     convert(input, gray, scale);
+//! }
+//! ```
+//!
+//! # Environment variables
+//! There is no real environment, so [`var`], [`set_var`] and [`remove_var`]
+//! keep variables in a file in the documents folder, [`ENV_FILE`]. They stay
+//! set after the program exits, so a launcher can set options for the
+//! programs it runs with [`Command`][crate::process::Command]:
+//!
+//! ```
+//! use ndless::env;
+//! use ndless::process::Command;
+//!
+//! env::set_var("GAME_LEVEL", "3");
+//! Command::new("game.tns").status()?;
+//! ```
 
+use alloc::format;
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::vec::IntoIter;
 use alloc::vec::Vec;
 
-use cstr_core::CStr;
+use cstr_core::{CStr, CString};
 
 use crate::io;
 use crate::io::ErrorKind;
@@ -17,6 +53,10 @@ use crate::libc;
 use crate::path::Path;
 use crate::path::PathBuf;
 
+pub use vars::{remove_var, set_var, var, vars, VarError, Vars, ENV_FILE};
+
+mod vars;
+
 pub type Args = IntoIter<String>;
 
 /// Returns the arguments which this program was started with.
@@ -59,6 +99,142 @@ pub fn args() -> Args {
 		.into_iter()
 }
 
+/// Returns the path of the program's own file, its first argument.
+pub fn program_path() -> Option<PathBuf> {
+	args().next().map(PathBuf::from)
+}
+
+/// Returns the path of the document the program was opened with, through a
+/// file association. This is the second argument, if it's an absolute path.
+pub fn opened_document() -> Option<PathBuf> {
+	args()
+		.nth(1)
+		.filter(|arg| arg.starts_with('/'))
+		.map(PathBuf::from)
+}
+
+/// Makes this program open documents ending in `.{ext}.tns` when they're
+/// tapped in the document browser, by adding it to Ndless's configuration
+/// file. The document's path is then given to the program as
+/// [`opened_document`].
+///
+/// `ext` is given without a dot, such as `"png"`. Registering again replaces
+/// the entry, so this can be done every time the program runs.
+///
+/// # Errors
+///
+/// Returns an error if the program's path isn't known, or `ext` contains a
+/// nul byte.
+pub fn register_file_extension(ext: &str) -> io::Result<()> {
+	let program = program_path()
+		.and_then(|path| {
+			path.file_stem()
+				.and_then(|stem| stem.to_str())
+				.map(String::from)
+		})
+		.ok_or_else(|| io::Error::from(ErrorKind::NotFound))?;
+	let ext = CString::new(ext).map_err(|_| io::Error::from(ErrorKind::InvalidInput))?;
+	let program = CString::new(program).map_err(|_| io::Error::from(ErrorKind::InvalidInput))?;
+	unsafe { ndless_sys::cfg_register_fileext(ext.as_ptr(), program.as_ptr()) };
+	Ok(())
+}
+
+/// Returns the name of the program registered to open documents ending in
+/// `.{ext}.tns`, if any.
+pub fn file_extension_handler(ext: &str) -> Option<String> {
+	let key = CString::new(format!("ext.{}", ext)).ok()?;
+	unsafe {
+		ndless_sys::cfg_open();
+		let value = ndless_sys::cfg_get(key.as_ptr());
+		let handler = if value.is_null() {
+			None
+		} else {
+			Some(CStr::from_ptr(value).to_string_lossy().into_owned())
+		};
+		ndless_sys::cfg_close();
+		handler
+	}
+}
+
+/// Arguments split up by [`parse_args`].
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct ParsedArgs {
+	/// The names of flags, such as `verbose` from `--verbose`, or `v` and `q`
+	/// from `-vq`
+	pub flags: Vec<String>,
+	/// Keys and values, from `key=value` or `--key=value`
+	pub options: Vec<(String, String)>,
+	/// Everything else, in order, including the document's path
+	pub positional: Vec<String>,
+}
+
+impl ParsedArgs {
+	/// Splits up `args`, which shouldn't include the program's path. See
+	/// [`parse_args`].
+	pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Self {
+		let mut parsed = ParsedArgs::default();
+		let mut args = args.into_iter();
+		while let Some(arg) = args.next() {
+			if arg == "--" {
+				parsed.positional.extend(args);
+				break;
+			}
+			// Paths can contain `=`
+			if arg.starts_with('/') {
+				parsed.positional.push(arg);
+			} else if let Some(long) = arg.strip_prefix("--") {
+				match long.find('=') {
+					Some(i) => parsed.push_option(&long[..i], &long[i + 1..]),
+					None => parsed.flags.push(long.to_string()),
+				}
+			} else if let Some(i) = arg.find('=') {
+				parsed.push_option(&arg[..i], &arg[i + 1..]);
+			} else if arg.len() > 1 && arg.starts_with('-') {
+				parsed.flags.extend(arg[1..].chars().map(String::from));
+			} else {
+				parsed.positional.push(arg);
+			}
+		}
+		parsed
+	}
+
+	fn push_option(&mut self, key: &str, value: &str) {
+		self.options.push((key.to_string(), value.to_string()));
+	}
+
+	/// Returns true if the flag `name` was given.
+	pub fn flag(&self, name: &str) -> bool {
+		self.flags.iter().any(|flag| flag == name)
+	}
+
+	/// Returns the value given for `key`. If it was given more than once,
+	/// the last one is returned.
+	pub fn value(&self, key: &str) -> Option<&str> {
+		self.options
+			.iter()
+			.rev()
+			.find(|(k, _)| k == key)
+			.map(|(_, value)| value.as_str())
+	}
+
+	/// Returns the first positional argument that's an absolute path, such as
+	/// the document the program was opened with.
+	pub fn document(&self) -> Option<&Path> {
+		self.positional
+			.iter()
+			.find(|arg| arg.starts_with('/'))
+			.map(Path::new)
+	}
+}
+
+/// Splits the program's arguments, after its own path, into flags
+/// (`--name`, `-n`), options (`key=value`, `--key=value`) and positional
+/// arguments. Arguments starting with `/` are always positional, as they're
+/// paths, and everything after `--` is too.
+pub fn parse_args() -> ParsedArgs {
+	ParsedArgs::parse(args().skip(1))
+}
+
 /// Returns the current working directory as a [`PathBuf`].
 ///
 /// # Errors
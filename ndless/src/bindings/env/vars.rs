@@ -0,0 +1,168 @@
+use alloc::string::String;
+use alloc::vec::{IntoIter, Vec};
+use core::fmt;
+
+use crate::autosave::write_atomic;
+use crate::env::get_documents_dir;
+use crate::error::Error;
+use crate::fs;
+use crate::io;
+use crate::path::PathBuf;
+
+/// The file that environment variables are stored in, relative to the
+/// documents folder.
+pub const ENV_FILE: &str = "ndless/environment.tns";
+
+pub type Vars = IntoIter<(String, String)>;
+
+/// Returned by [`var`] when a variable isn't set.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum VarError {
+	NotPresent,
+}
+
+impl fmt::Display for VarError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			VarError::NotPresent => f.write_str("environment variable not found"),
+		}
+	}
+}
+
+impl Error for VarError {}
+
+fn env_file() -> io::Result<PathBuf> {
+	Ok(get_documents_dir()?.join(ENV_FILE))
+}
+
+/// Line breaks and backslashes are escaped, so that each variable is one line
+fn escape(value: &str) -> String {
+	value
+		.replace('\\', "\\\\")
+		.replace('\n', "\\n")
+		.replace('\r', "\\r")
+}
+
+fn unescape(value: &str) -> String {
+	let mut unescaped = String::with_capacity(value.len());
+	let mut chars = value.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			unescaped.push(c);
+			continue;
+		}
+		match chars.next() {
+			Some('n') => unescaped.push('\n'),
+			Some('r') => unescaped.push('\r'),
+			Some(c) => unescaped.push(c),
+			None => {}
+		}
+	}
+	unescaped
+}
+
+fn load() -> Vec<(String, String)> {
+	let contents = env_file().and_then(fs::read_to_string).unwrap_or_default();
+	contents
+		.lines()
+		.filter_map(|line| {
+			let i = line.find('=')?;
+			Some((String::from(&line[..i]), unescape(&line[i + 1..])))
+		})
+		.collect()
+}
+
+fn save(vars: &[(String, String)]) -> io::Result<()> {
+	let path = env_file()?;
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir)?;
+	}
+	let mut contents = String::new();
+	for (key, value) in vars {
+		contents.push_str(key);
+		contents.push('=');
+		contents.push_str(&escape(value));
+		contents.push('\n');
+	}
+	write_atomic(path, contents)
+}
+
+fn check_key(key: &str) {
+	assert!(
+		!key.is_empty() && !key.contains(&['=', '\n', '\0'][..]),
+		"invalid environment variable name {:?}",
+		key
+	);
+}
+
+/// Returns every environment variable, in the order they were first set.
+pub fn vars() -> Vars {
+	load().into_iter()
+}
+
+/// Returns the value of the environment variable `key`.
+///
+/// # Examples
+///
+/// ```
+/// use ndless::env;
+///
+/// let level = env::var("GAME_LEVEL").unwrap_or_else(|_| "1".into());
+/// ```
+pub fn var<K: AsRef<str>>(key: K) -> Result<String, VarError> {
+	let key = key.as_ref();
+	load()
+		.into_iter()
+		.find(|(k, _)| k == key)
+		.map(|(_, value)| value)
+		.ok_or(VarError::NotPresent)
+}
+
+/// Sets the environment variable `key` to `value`, for this program and every
+/// program run after it, until it's changed or removed. If [`ENV_FILE`] can't
+/// be written, nothing changes.
+///
+/// # Panics
+///
+/// Panics if `key` is empty, or contains `=`, a newline or a nul byte.
+pub fn set_var<K: AsRef<str>, V: AsRef<str>>(key: K, value: V) {
+	let (key, value) = (key.as_ref(), value.as_ref());
+	check_key(key);
+	let mut vars = load();
+	match vars.iter_mut().find(|(k, _)| k == key) {
+		Some((_, old)) => *old = String::from(value),
+		None => vars.push((String::from(key), String::from(value))),
+	}
+	let _ = save(&vars);
+}
+
+/// Removes the environment variable `key`, for this program and every
+/// program run after it.
+///
+/// # Panics
+///
+/// Panics if `key` is empty, or contains `=`, a newline or a nul byte.
+pub fn remove_var<K: AsRef<str>>(key: K) {
+	let key = key.as_ref();
+	check_key(key);
+	let mut vars = load();
+	let len = vars.len();
+	vars.retain(|(k, _)| k != key);
+	if vars.len() != len {
+		let _ = save(&vars);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn line_breaks_are_escaped() {
+		let value = "one\r\ntwo\rthree\\r";
+		let escaped = escape(value);
+		assert!(!escaped.contains(&['\n', '\r'][..]));
+		assert_eq!(escaped.lines().count(), 1);
+		assert_eq!(unescape(&escaped), value);
+	}
+}
@@ -0,0 +1,168 @@
+//! # Event loop
+//! Gathers everything an interactive program waits for into one stream of
+//! [`Event`]s: keys, the touchpad, a regular tick, and the USB cable being
+//! plugged in or removed.
+//!
+//! ```
+//! use ndless::event::{Event, EventLoop};
+//! use ndless::input::{Key, KeyState};
+//! use ndless::time::Duration;
+//!
+//! let mut events = EventLoop::new().tick_every(Duration::from_millis(500));
+//! loop {
+//!     match events.next_event() {
+//!         Event::Key(key) if key.key == Key::Esc => break,
+//!         Event::Key(key) => handle_key(key),
+//!         Event::Touchpad(report) => move_cursor(report.x, report.y),
+//!         Event::Tick => blink_cursor(),
+//!         Event::UsbConnected | Event::UsbDisconnected => redraw_status(),
+//!     }
+//! }
+//! ```
+//!
+//! [`EventLoop::next_event`] idles the CPU while nothing is happening, so a
+//! program built around it uses little battery. Callbacks registered with
+//! [`cooperative::every`][crate::timer::cooperative::every] and
+//! [`battery::on_change`][crate::hw::battery::on_change] keep running while it
+//! waits.
+
+use alloc::vec::Vec;
+
+use crate::hw::{battery, has_touchpad, usb_connected};
+use crate::input::events::{poll_events, KeyEvent};
+use crate::input::idle_until_input;
+use crate::input::touchpad::{touchpad_scan, TouchpadReport};
+use crate::time::Duration;
+use crate::timer::cooperative::dispatch;
+use crate::timer::{get_ticks, has_time_passed, Ticks};
+
+/// Returned by [`EventLoop::next_event`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Event {
+	/// A key was pressed, released, or repeated.
+	Key(KeyEvent),
+	/// The finger on the touchpad moved, touched, or lifted, or the touchpad
+	/// was pressed or released. Only sent if enabled with
+	/// [`EventLoop::touchpad`].
+	Touchpad(TouchpadReport),
+	/// The interval set with [`EventLoop::tick_every`] passed.
+	Tick,
+	/// A USB cable was plugged in.
+	UsbConnected,
+	/// The USB cable was removed.
+	UsbDisconnected,
+}
+
+/// Produces [`Event`]s. See the [module documentation][self].
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct EventLoop {
+	pending: Vec<Event>,
+	touchpad: Option<Option<TouchpadReport>>,
+	/// The tick interval, and when the next tick is due
+	tick: Option<(u32, u32)>,
+	usb: bool,
+}
+
+impl Default for EventLoop {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl EventLoop {
+	/// Creates an event loop that sends key and USB events.
+	pub fn new() -> Self {
+		Self {
+			pending: Vec::new(),
+			touchpad: None,
+			tick: None,
+			usb: usb_connected(),
+		}
+	}
+
+	/// Sends [`Event::Tick`] every `interval`. Ticks that are missed because
+	/// the program was busy are skipped, rather than sent all at once.
+	pub fn tick_every(mut self, interval: Duration) -> Self {
+		let interval = interval.as_ticks().max(1);
+		self.tick = Some((interval, get_ticks().wrapping_add(interval)));
+		self
+	}
+
+	/// Sends [`Event::Touchpad`] when the touchpad changes. Does nothing on
+	/// calculators without a touchpad.
+	pub fn touchpad(mut self, enabled: bool) -> Self {
+		self.touchpad = if enabled && has_touchpad() {
+			Some(None)
+		} else {
+			None
+		};
+		self
+	}
+
+	/// Checks every source, queueing whatever happened since the last check.
+	fn gather(&mut self) {
+		dispatch();
+		battery::poll();
+		self.pending.extend(poll_events().map(Event::Key));
+		if let Some(last) = &mut self.touchpad {
+			if let Ok(report) = touchpad_scan() {
+				// The velocity and proximity change constantly, even when the
+				// finger is still
+				let changed = last.map_or(true, |last| {
+					(last.contact, last.pressed, last.x, last.y, last.arrow)
+						!= (
+							report.contact,
+							report.pressed,
+							report.x,
+							report.y,
+							report.arrow,
+						)
+				});
+				if changed {
+					*last = Some(report);
+					self.pending.push(Event::Touchpad(report));
+				}
+			}
+		}
+		if let Some((interval, next)) = &mut self.tick {
+			if has_time_passed(*next) {
+				*next = next.wrapping_add(*interval);
+				if has_time_passed(*next) {
+					*next = get_ticks().wrapping_add(*interval);
+				}
+				self.pending.push(Event::Tick);
+			}
+		}
+		let usb = usb_connected();
+		if usb != self.usb {
+			self.usb = usb;
+			self.pending.push(if usb {
+				Event::UsbConnected
+			} else {
+				Event::UsbDisconnected
+			});
+		}
+	}
+
+	/// Returns the next event if there is one, without waiting.
+	pub fn poll_event(&mut self) -> Option<Event> {
+		if self.pending.is_empty() {
+			self.gather();
+		}
+		if self.pending.is_empty() {
+			None
+		} else {
+			Some(self.pending.remove(0))
+		}
+	}
+
+	/// Waits for the next event, idling the CPU in the meantime.
+	pub fn next_event(&mut self) -> Event {
+		loop {
+			if let Some(event) = self.poll_event() {
+				return event;
+			}
+			idle_until_input();
+		}
+	}
+}
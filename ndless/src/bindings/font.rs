@@ -0,0 +1,212 @@
+//! # Bitmap fonts
+//! Pre-rendered fonts that can be drawn without SDL_ttf or FreeType.
+//!
+//! Fonts are usually generated from a TrueType font at build time with the
+//! `ndless-fontgen` crate, and then embedded in the program:
+//!
+//! ```
+//! use ndless::font::BitmapFont;
+//!
+//! let font = BitmapFont::parse(include_bytes!(concat!(env!("OUT_DIR"), "/roboto-12.nfnt")))
+//!     .expect("invalid font");
+//! let glyph = font.glyph('A').unwrap();
+//! ```
+//!
+//! # Format
+//! All numbers are little-endian.
+//!
+//! | Size | Contents |
+//! |------|----------|
+//! | 4 | The magic bytes `NFNT` |
+//! | 1 | Format version, currently 1 |
+//! | 1 | Line height in pixels |
+//! | 1 | Ascent: pixels from the top of a line to the baseline |
+//! | 1 | Reserved, 0 |
+//! | 2 | Number of glyphs |
+//! | 16 × glyphs | Glyph records, sorted by character |
+//! | rest | Coverage bitmaps |
+//!
+//! Each glyph record contains the character as a `u32`, the offset of its
+//! bitmap from the start of the bitmap section as a `u32`, its width and
+//! height as `u8`s, its x and y offset from the pen position at the top of the
+//! line as `i8`s, the horizontal advance as a `u8`, and 3 reserved bytes.
+//!
+//! Bitmaps are `width × height` bytes in row-major order, each byte the
+//! coverage of a pixel from 0 (transparent) to 255 (opaque).
+
+use core::convert::TryInto;
+use core::fmt;
+
+use crate::error::Error;
+
+const MAGIC: &[u8; 4] = b"NFNT";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 10;
+const RECORD_LEN: usize = 16;
+
+/// An error returned by [`BitmapFont::parse`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum FontError {
+	/// The data does not start with `NFNT`.
+	BadMagic,
+	/// The font was made for a newer version of this crate.
+	UnsupportedVersion(u8),
+	/// The data ends early, or a glyph's bitmap is out of bounds.
+	Truncated,
+}
+
+impl fmt::Display for FontError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			FontError::BadMagic => write!(f, "not a bitmap font"),
+			FontError::UnsupportedVersion(version) => {
+				write!(f, "unsupported bitmap font version {}", version)
+			}
+			FontError::Truncated => write!(f, "bitmap font is truncated"),
+		}
+	}
+}
+
+impl Error for FontError {}
+
+/// A single pre-rendered character.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Glyph<'a> {
+	pub ch: char,
+	pub width: u8,
+	pub height: u8,
+	/// Horizontal offset from the pen position to the left of the bitmap.
+	pub x_offset: i8,
+	/// Vertical offset from the top of the line to the top of the bitmap.
+	pub y_offset: i8,
+	/// How far to move the pen after drawing this glyph.
+	pub advance: u8,
+	/// The coverage of each pixel, row by row.
+	pub coverage: &'a [u8],
+}
+
+impl Glyph<'_> {
+	/// Returns the coverage of a pixel, from 0 (transparent) to 255 (opaque),
+	/// or 0 if it is outside of the bitmap.
+	pub fn coverage_at(&self, x: u8, y: u8) -> u8 {
+		if x >= self.width || y >= self.height {
+			return 0;
+		}
+		self.coverage[y as usize * self.width as usize + x as usize]
+	}
+}
+
+/// A font in the format described in the [module-level documentation][self].
+/// Parsing doesn't copy or allocate, so fonts can be used straight from
+/// `include_bytes!`.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct BitmapFont<'a> {
+	line_height: u8,
+	ascent: u8,
+	records: &'a [u8],
+	bitmaps: &'a [u8],
+}
+
+impl<'a> BitmapFont<'a> {
+	/// Checks the header and every glyph record.
+	pub fn parse(data: &'a [u8]) -> Result<Self, FontError> {
+		if data.len() < HEADER_LEN {
+			return Err(if data.starts_with(&MAGIC[..data.len().min(4)]) {
+				FontError::Truncated
+			} else {
+				FontError::BadMagic
+			});
+		}
+		if &data[..4] != MAGIC {
+			return Err(FontError::BadMagic);
+		}
+		if data[4] != VERSION {
+			return Err(FontError::UnsupportedVersion(data[4]));
+		}
+		let count = u16::from_le_bytes([data[8], data[9]]) as usize;
+		let records_end = HEADER_LEN + count * RECORD_LEN;
+		if data.len() < records_end {
+			return Err(FontError::Truncated);
+		}
+		let font = Self {
+			line_height: data[5],
+			ascent: data[6],
+			records: &data[HEADER_LEN..records_end],
+			bitmaps: &data[records_end..],
+		};
+		for i in 0..count {
+			font.record(i).ok_or(FontError::Truncated)?;
+		}
+		Ok(font)
+	}
+
+	/// The distance between the tops of consecutive lines, in pixels.
+	pub fn line_height(&self) -> u8 {
+		self.line_height
+	}
+
+	/// The distance from the top of a line to the baseline, in pixels.
+	pub fn ascent(&self) -> u8 {
+		self.ascent
+	}
+
+	/// The number of glyphs in the font.
+	pub fn len(&self) -> usize {
+		self.records.len() / RECORD_LEN
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.records.is_empty()
+	}
+
+	fn record(&self, i: usize) -> Option<Glyph<'a>> {
+		let record = &self.records[i * RECORD_LEN..(i + 1) * RECORD_LEN];
+		let ch = core::char::from_u32(u32::from_le_bytes(record[0..4].try_into().ok()?))?;
+		let offset = u32::from_le_bytes(record[4..8].try_into().ok()?) as usize;
+		let (width, height) = (record[8], record[9]);
+		let len = width as usize * height as usize;
+		Some(Glyph {
+			ch,
+			width,
+			height,
+			x_offset: record[10] as i8,
+			y_offset: record[11] as i8,
+			advance: record[12],
+			coverage: self.bitmaps.get(offset..offset.checked_add(len)?)?,
+		})
+	}
+
+	/// Looks up the glyph for a character.
+	pub fn glyph(&self, ch: char) -> Option<Glyph<'a>> {
+		let (mut low, mut high) = (0, self.len());
+		while low < high {
+			let mid = (low + high) / 2;
+			let glyph = self.record(mid)?;
+			match glyph.ch.cmp(&ch) {
+				core::cmp::Ordering::Equal => return Some(glyph),
+				core::cmp::Ordering::Less => low = mid + 1,
+				core::cmp::Ordering::Greater => high = mid,
+			}
+		}
+		None
+	}
+
+	/// Iterates over every glyph, in order of character.
+	pub fn glyphs(&self) -> impl Iterator<Item = Glyph<'a>> + '_ {
+		(0..self.len()).filter_map(move |i| self.record(i))
+	}
+
+	/// Returns the width of the widest line of `text`, in pixels. Characters
+	/// missing from the font are skipped.
+	pub fn measure(&self, text: &str) -> u32 {
+		text.lines()
+			.map(|line| {
+				line.chars()
+					.filter_map(|ch| self.glyph(ch))
+					.map(|glyph| u32::from(glyph.advance))
+					.sum::<u32>()
+			})
+			.max()
+			.unwrap_or(0)
+	}
+}
@@ -0,0 +1,435 @@
+//! # Drawing shapes
+//! A small software rasterizer for the framebuffer of a
+//! [`Screen`][crate::video::Screen], for programs that don't use SDL:
+//!
+//! ```
+//! use ndless::gfx::{Canvas, Color};
+//! use ndless::video::Screen;
+//!
+//! let mut screen = Screen::new().unwrap();
+//! let mut canvas = Canvas::new(&mut screen);
+//! canvas.clear(Color::WHITE);
+//! canvas.line(0, 0, 319, 239, Color::BLACK);
+//! canvas.fill_circle(160, 120, 40, Color::rgb(255, 0, 0));
+//! canvas.fill_triangle((10, 200), (60, 150), (110, 200), Color::rgb(0, 128, 255));
+//! drop(canvas);
+//! screen.present();
+//! ```
+//!
+//! Colors are converted to the screen's [mode][crate::video::Mode], so the
+//! same code draws in color on CX models and in gray on classic ones.
+//! Everything is clipped to the canvas's [clip rectangle][Canvas::set_clip],
+//! which is the whole screen by default, so shapes can be partly or entirely
+//! off screen.
+//!
+//! Text can be drawn with a [`Font`], and images with a [`Sprite`].
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::swap;
+
+use crate::video::{Framebuffer, Screen, HEIGHT, WIDTH};
+
+pub mod font;
+pub mod sprite;
+
+pub use font::Font;
+pub use sprite::Sprite;
+
+/// A color, converted to the format of the framebuffer when drawn.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Color {
+	pub r: u8,
+	pub g: u8,
+	pub b: u8,
+}
+
+impl Color {
+	pub const BLACK: Color = Color::rgb(0, 0, 0);
+	pub const WHITE: Color = Color::rgb(255, 255, 255);
+
+	pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+		Self { r, g, b }
+	}
+
+	/// The color as 5 bits of red, 6 of green, and 5 of blue.
+	pub fn to_rgb565(self) -> u16 {
+		(u16::from(self.r) >> 3) << 11 | (u16::from(self.g) >> 2) << 5 | u16::from(self.b) >> 3
+	}
+
+	pub fn from_rgb565(value: u16) -> Self {
+		let (r, g, b) = (value >> 11, value >> 5 & 0x3F, value & 0x1F);
+		Self::rgb(
+			(r << 3 | r >> 2) as u8,
+			(g << 2 | g >> 4) as u8,
+			(b << 3 | b >> 2) as u8,
+		)
+	}
+
+	/// A gray from a 4 bit level, from 0 for black to 15 for white.
+	pub fn from_gray4(level: u8) -> Self {
+		let value = (level & 0xF) * 17;
+		Self::rgb(value, value, value)
+	}
+
+	/// Mixes `other` into this color. `alpha` goes from 0, which keeps this
+	/// color, to 255, which gives `other`.
+	pub fn blend(self, other: Color, alpha: u8) -> Self {
+		let mix = |from: u8, to: u8| {
+			let alpha = u32::from(alpha);
+			((u32::from(from) * (255 - alpha) + u32::from(to) * alpha + 127) / 255) as u8
+		};
+		Self::rgb(
+			mix(self.r, other.r),
+			mix(self.g, other.g),
+			mix(self.b, other.b),
+		)
+	}
+
+	/// The color as a 4 bit gray level, from 0 for black to 15 for white.
+	pub fn to_gray4(self) -> u8 {
+		let luma = (u32::from(self.r) * 77 + u32::from(self.g) * 150 + u32::from(self.b) * 29) >> 8;
+		(luma >> 4) as u8
+	}
+}
+
+/// A rectangle, used for clipping.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Rect {
+	pub x: i32,
+	pub y: i32,
+	pub w: u32,
+	pub h: u32,
+}
+
+impl Rect {
+	pub fn new(x: i32, y: i32, w: u32, h: u32) -> Self {
+		Self { x, y, w, h }
+	}
+
+	fn right(&self) -> i32 {
+		self.x + self.w as i32
+	}
+
+	fn bottom(&self) -> i32 {
+		self.y + self.h as i32
+	}
+}
+
+/// Draws on a framebuffer. See the [module documentation][self].
+pub struct Canvas<'a> {
+	framebuffer: Framebuffer<'a>,
+	clip: Rect,
+}
+
+impl<'a> Canvas<'a> {
+	/// Draws on `screen`'s framebuffer. Drop the canvas before calling
+	/// [`Screen::present`].
+	pub fn new(screen: &'a mut Screen) -> Self {
+		Self::from_framebuffer(screen.framebuffer())
+	}
+
+	/// Draws on a framebuffer of [`WIDTH`] by [`HEIGHT`] pixels, such as one
+	/// kept in memory to draw off screen.
+	pub fn from_framebuffer(framebuffer: Framebuffer<'a>) -> Self {
+		Self {
+			framebuffer,
+			clip: Rect::new(0, 0, WIDTH as u32, HEIGHT as u32),
+		}
+	}
+
+	pub fn clip(&self) -> Rect {
+		self.clip
+	}
+
+	/// Only draws inside `clip`, which is cut to fit the screen.
+	pub fn set_clip(&mut self, clip: Rect) {
+		let x = clip.x.max(0).min(WIDTH as i32);
+		let y = clip.y.max(0).min(HEIGHT as i32);
+		let right = clip.right().max(x).min(WIDTH as i32);
+		let bottom = clip.bottom().max(y).min(HEIGHT as i32);
+		self.clip = Rect::new(x, y, (right - x) as u32, (bottom - y) as u32);
+	}
+
+	/// Draws on the whole screen again.
+	pub fn reset_clip(&mut self) {
+		self.clip = Rect::new(0, 0, WIDTH as u32, HEIGHT as u32);
+	}
+
+	/// Fills the clip rectangle with `color`.
+	pub fn clear(&mut self, color: Color) {
+		let clip = self.clip;
+		self.fill_rect(clip.x, clip.y, clip.w, clip.h, color);
+	}
+
+	/// Sets a single pixel, if it's inside the clip rectangle.
+	pub fn pixel(&mut self, x: i32, y: i32, color: Color) {
+		if x >= self.clip.x && x < self.clip.right() && y >= self.clip.y && y < self.clip.bottom() {
+			self.span(y, x, x, color);
+		}
+	}
+
+	/// Returns the color of a pixel, or `None` if it is outside the screen.
+	pub fn get_pixel(&self, x: i32, y: i32) -> Option<Color> {
+		if x < 0 || y < 0 || x >= WIDTH as i32 || y >= HEIGHT as i32 {
+			return None;
+		}
+		let index = y as usize * WIDTH + x as usize;
+		Some(match &self.framebuffer {
+			Framebuffer::Rgb565(pixels) => Color::from_rgb565(pixels[index]),
+			Framebuffer::Gray4(pixels) => {
+				let pair = pixels[index / 2];
+				Color::from_gray4(if index % 2 == 0 { pair >> 4 } else { pair })
+			}
+		})
+	}
+
+	/// Mixes `color` into a pixel, if it's inside the clip rectangle. `alpha`
+	/// goes from 0, which leaves the pixel as it is, to 255, which replaces
+	/// it.
+	pub fn blend_pixel(&mut self, x: i32, y: i32, color: Color, alpha: u8) {
+		match alpha {
+			0 => {}
+			255 => self.pixel(x, y, color),
+			_ => {
+				if let Some(under) = self.get_pixel(x, y) {
+					self.pixel(x, y, under.blend(color, alpha));
+				}
+			}
+		}
+	}
+
+	/// Fills the pixels from `x0` to `x1` inclusive on row `y`, which must
+	/// already be clipped.
+	fn span(&mut self, y: i32, x0: i32, x1: i32, color: Color) {
+		let row = y as usize * WIDTH;
+		match &mut self.framebuffer {
+			Framebuffer::Rgb565(pixels) => {
+				let value = color.to_rgb565();
+				for pixel in &mut pixels[row + x0 as usize..=row + x1 as usize] {
+					*pixel = value;
+				}
+			}
+			Framebuffer::Gray4(pixels) => {
+				let value = color.to_gray4();
+				for x in x0 as usize..=x1 as usize {
+					let pair = &mut pixels[(row + x) / 2];
+					*pair = if x % 2 == 0 {
+						*pair & 0x0F | value << 4
+					} else {
+						*pair & 0xF0 | value
+					};
+				}
+			}
+		}
+	}
+
+	/// Fills a horizontal line from `x0` to `x1` inclusive, clipped.
+	pub fn hline(&mut self, x0: i32, x1: i32, y: i32, color: Color) {
+		let (x0, x1) = if x0 <= x1 { (x0, x1) } else { (x1, x0) };
+		if y < self.clip.y || y >= self.clip.bottom() {
+			return;
+		}
+		let x0 = x0.max(self.clip.x);
+		let x1 = x1.min(self.clip.right() - 1);
+		if x0 <= x1 {
+			self.span(y, x0, x1, color);
+		}
+	}
+
+	/// Fills a vertical line from `y0` to `y1` inclusive, clipped.
+	pub fn vline(&mut self, x: i32, y0: i32, y1: i32, color: Color) {
+		let (y0, y1) = if y0 <= y1 { (y0, y1) } else { (y1, y0) };
+		for y in y0.max(self.clip.y)..=y1.min(self.clip.bottom() - 1) {
+			self.pixel(x, y, color);
+		}
+	}
+
+	/// Draws a line between two points, both included, with Bresenham's
+	/// algorithm.
+	pub fn line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Color) {
+		if y0 == y1 {
+			self.hline(x0, x1, y0, color);
+			return;
+		}
+		if x0 == x1 {
+			self.vline(x0, y0, y1, color);
+			return;
+		}
+		let dx = (x1 - x0).abs();
+		let dy = -(y1 - y0).abs();
+		let sx = if x0 < x1 { 1 } else { -1 };
+		let sy = if y0 < y1 { 1 } else { -1 };
+		let (mut x, mut y) = (x0, y0);
+		let mut err = dx + dy;
+		loop {
+			self.pixel(x, y, color);
+			if x == x1 && y == y1 {
+				break;
+			}
+			let e2 = 2 * err;
+			if e2 >= dy {
+				err += dy;
+				x += sx;
+			}
+			if e2 <= dx {
+				err += dx;
+				y += sy;
+			}
+		}
+	}
+
+	/// Draws the outline of a rectangle, one pixel thick.
+	pub fn rect(&mut self, x: i32, y: i32, w: u32, h: u32, color: Color) {
+		if w == 0 || h == 0 {
+			return;
+		}
+		let right = x + w as i32 - 1;
+		let bottom = y + h as i32 - 1;
+		self.hline(x, right, y, color);
+		self.hline(x, right, bottom, color);
+		self.vline(x, y, bottom, color);
+		self.vline(right, y, bottom, color);
+	}
+
+	pub fn fill_rect(&mut self, x: i32, y: i32, w: u32, h: u32, color: Color) {
+		if w == 0 {
+			return;
+		}
+		for row in y..y + h as i32 {
+			self.hline(x, x + w as i32 - 1, row, color);
+		}
+	}
+
+	/// Draws the outline of a circle centered on `(cx, cy)`.
+	pub fn circle(&mut self, cx: i32, cy: i32, radius: u32, color: Color) {
+		self.ellipse(cx, cy, radius, radius, color);
+	}
+
+	pub fn fill_circle(&mut self, cx: i32, cy: i32, radius: u32, color: Color) {
+		self.fill_ellipse(cx, cy, radius, radius, color);
+	}
+
+	/// Calls `plot` with the offsets of the points on the boundary of an
+	/// ellipse's top right quarter, using the midpoint algorithm.
+	fn ellipse_points(rx: u32, ry: u32, mut plot: impl FnMut(i32, i32)) {
+		if ry == 0 {
+			for x in 0..=rx as i32 {
+				plot(x, 0);
+			}
+			return;
+		}
+		let (rx, ry) = (i64::from(rx), i64::from(ry));
+		let (rx2, ry2) = (rx * rx, ry * ry);
+		let (mut x, mut y) = (0, ry);
+		let (mut px, mut py) = (0, 2 * rx2 * y);
+		// Region 1, where the slope is less than 1
+		let mut p = ry2 - rx2 * ry + rx2 / 4;
+		while px < py {
+			plot(x as i32, y as i32);
+			x += 1;
+			px += 2 * ry2;
+			if p < 0 {
+				p += ry2 + px;
+			} else {
+				y -= 1;
+				py -= 2 * rx2;
+				p += ry2 + px - py;
+			}
+		}
+		// Region 2
+		let mut p = ry2 * (2 * x + 1) * (2 * x + 1) / 4 + rx2 * (y - 1) * (y - 1) - rx2 * ry2;
+		while y >= 0 {
+			plot(x as i32, y as i32);
+			y -= 1;
+			py -= 2 * rx2;
+			if p > 0 {
+				p += rx2 - py;
+			} else {
+				x += 1;
+				px += 2 * ry2;
+				p += rx2 - py + px;
+			}
+		}
+	}
+
+	/// Draws the outline of an ellipse centered on `(cx, cy)`, with radii `rx`
+	/// and `ry`.
+	pub fn ellipse(&mut self, cx: i32, cy: i32, rx: u32, ry: u32, color: Color) {
+		Self::ellipse_points(rx, ry, |x, y| {
+			self.pixel(cx + x, cy + y, color);
+			self.pixel(cx - x, cy + y, color);
+			self.pixel(cx + x, cy - y, color);
+			self.pixel(cx - x, cy - y, color);
+		});
+	}
+
+	pub fn fill_ellipse(&mut self, cx: i32, cy: i32, rx: u32, ry: u32, color: Color) {
+		// The widest point on each row, so rows are only filled once
+		let mut widths: Vec<i32> = vec![-1; ry as usize + 1];
+		Self::ellipse_points(rx, ry, |x, y| {
+			let width = &mut widths[y as usize];
+			*width = (*width).max(x);
+		});
+		for (y, &x) in widths.iter().enumerate() {
+			let y = y as i32;
+			self.hline(cx - x, cx + x, cy + y, color);
+			if y != 0 {
+				self.hline(cx - x, cx + x, cy - y, color);
+			}
+		}
+	}
+
+	/// Draws the outline of a triangle.
+	pub fn triangle(&mut self, a: (i32, i32), b: (i32, i32), c: (i32, i32), color: Color) {
+		self.polygon(&[a, b, c], color);
+	}
+
+	pub fn fill_triangle(&mut self, a: (i32, i32), b: (i32, i32), c: (i32, i32), color: Color) {
+		self.fill_polygon(&[a, b, c], color);
+	}
+
+	/// Draws lines between each point and the next, and from the last point
+	/// back to the first.
+	pub fn polygon(&mut self, points: &[(i32, i32)], color: Color) {
+		for (i, &(x0, y0)) in points.iter().enumerate() {
+			let (x1, y1) = points[(i + 1) % points.len()];
+			self.line(x0, y0, x1, y1, color);
+		}
+	}
+
+	/// Fills a polygon, which may be concave or cross itself, using the
+	/// even-odd rule.
+	pub fn fill_polygon(&mut self, points: &[(i32, i32)], color: Color) {
+		if points.len() < 3 {
+			self.polygon(points, color);
+			return;
+		}
+		let top = points.iter().map(|&(_, y)| y).min().unwrap_or(0);
+		let bottom = points.iter().map(|&(_, y)| y).max().unwrap_or(0);
+		let mut crossings = Vec::new();
+		for y in top.max(self.clip.y)..=bottom.min(self.clip.bottom() - 1) {
+			crossings.clear();
+			for (i, &(mut x0, mut y0)) in points.iter().enumerate() {
+				let (mut x1, mut y1) = points[(i + 1) % points.len()];
+				if y0 > y1 {
+					swap(&mut x0, &mut x1);
+					swap(&mut y0, &mut y1);
+				}
+				// Each edge covers its top row but not its bottom one, so
+				// shared vertices aren't counted twice. Rows are sampled at
+				// their center.
+				if y >= y0 && y < y1 {
+					let x = x0 + ((y - y0) * 2 + 1) * (x1 - x0) / ((y1 - y0) * 2);
+					crossings.push(x);
+				}
+			}
+			crossings.sort_unstable();
+			for pair in crossings.chunks_exact(2) {
+				self.hline(pair[0], pair[1], y, color);
+			}
+		}
+		// The bottom edges aren't covered by the rows above
+		self.polygon(points, color);
+	}
+}
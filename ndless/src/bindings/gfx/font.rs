@@ -0,0 +1,297 @@
+//! Text on a [`Canvas`]
+//!
+//! [`Font::builtin`] is a 6x8 monospace font covering printable ASCII, which
+//! is always available. Any [`BitmapFont`], such as one made with
+//! `ndless-fontgen`, can be used too, and loaded from a file at run time:
+//!
+//! ```
+//! use ndless::gfx::{Canvas, Color, Font};
+//!
+//! let small = Font::builtin();
+//! canvas.draw_text(&small, 2, 2, Color::BLACK, "Score: 100\nLives: 3");
+//!
+//! let big = Font::load("/documents/game/title.nfnt.tns")?;
+//! canvas.draw_text_wrapped(&big, 10, 40, 300, Color::BLACK, long_description);
+//! ```
+
+use alloc::boxed::Box;
+
+use crate::font::BitmapFont;
+use crate::fs;
+use crate::gfx::{Canvas, Color};
+use crate::io;
+use crate::path::Path;
+
+/// The size of a character of the built-in font, including spacing.
+const BUILTIN_WIDTH: u8 = 6;
+const BUILTIN_HEIGHT: u8 = 8;
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+enum Inner<'a> {
+	Builtin,
+	Bitmap(BitmapFont<'a>),
+}
+
+/// A font that can be drawn on a [`Canvas`]. See the
+/// [module documentation][self].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Font<'a>(Inner<'a>);
+
+impl Font<'static> {
+	/// The built-in 6x8 font. Characters outside of printable ASCII are drawn
+	/// as `?`.
+	pub fn builtin() -> Self {
+		Font(Inner::Builtin)
+	}
+
+	/// Reads a [`BitmapFont`] from a file. Fonts are meant to be loaded once
+	/// and kept until the program exits, so the file's contents are never
+	/// freed.
+	pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+		let data: &'static [u8] = Box::leak(fs::read(path)?.into_boxed_slice());
+		BitmapFont::parse(data)
+			.map(|font| Font(Inner::Bitmap(font)))
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+	}
+}
+
+impl<'a> Font<'a> {
+	pub fn from_bitmap(font: BitmapFont<'a>) -> Self {
+		Font(Inner::Bitmap(font))
+	}
+
+	/// The distance between the tops of consecutive lines, in pixels.
+	pub fn line_height(&self) -> u32 {
+		match self.0 {
+			Inner::Builtin => u32::from(BUILTIN_HEIGHT),
+			Inner::Bitmap(font) => u32::from(font.line_height()),
+		}
+	}
+
+	/// How far the pen moves after `ch`.
+	fn advance(&self, ch: char) -> u32 {
+		match self.0 {
+			Inner::Builtin => u32::from(BUILTIN_WIDTH),
+			Inner::Bitmap(font) => font.glyph(ch).map_or(0, |glyph| u32::from(glyph.advance)),
+		}
+	}
+
+	/// Returns the width of the widest line of `text`, in pixels.
+	pub fn measure(&self, text: &str) -> u32 {
+		text.lines()
+			.map(|line| line.chars().map(|ch| self.advance(ch)).sum())
+			.max()
+			.unwrap_or(0)
+	}
+
+	/// Draws `ch` with the top left of its cell at `(x, y)`, returning how far
+	/// to move the pen.
+	fn draw_char(&self, canvas: &mut Canvas<'_>, x: i32, y: i32, color: Color, ch: char) -> i32 {
+		match self.0 {
+			Inner::Builtin => {
+				let index = match ch {
+					' '..='~' => ch as usize - ' ' as usize,
+					_ => '?' as usize - ' ' as usize,
+				};
+				for (row, bits) in BUILTIN[index].iter().enumerate() {
+					for col in 0..BUILTIN_WIDTH {
+						if bits & (0x80 >> col) != 0 {
+							canvas.pixel(x + i32::from(col), y + row as i32, color);
+						}
+					}
+				}
+				i32::from(BUILTIN_WIDTH)
+			}
+			Inner::Bitmap(font) => {
+				let glyph = match font.glyph(ch) {
+					Some(glyph) => glyph,
+					None => return 0,
+				};
+				let left = x + i32::from(glyph.x_offset);
+				let top = y + i32::from(glyph.y_offset);
+				for row in 0..glyph.height {
+					for col in 0..glyph.width {
+						let alpha = glyph.coverage_at(col, row);
+						if alpha != 0 {
+							canvas.blend_pixel(
+								left + i32::from(col),
+								top + i32::from(row),
+								color,
+								alpha,
+							);
+						}
+					}
+				}
+				i32::from(glyph.advance)
+			}
+		}
+	}
+}
+
+impl Canvas<'_> {
+	/// Draws `text` with its top left corner at `(x, y)`. Each `\n` starts a
+	/// new line below, at `x`. Returns the position after the last character,
+	/// so more text can be drawn after it.
+	pub fn draw_text(
+		&mut self,
+		font: &Font<'_>,
+		x: i32,
+		y: i32,
+		color: Color,
+		text: &str,
+	) -> (i32, i32) {
+		let (mut pen_x, mut pen_y) = (x, y);
+		for ch in text.chars() {
+			if ch == '\n' {
+				pen_x = x;
+				pen_y += font.line_height() as i32;
+			} else {
+				pen_x += font.draw_char(self, pen_x, pen_y, color, ch);
+			}
+		}
+		(pen_x, pen_y)
+	}
+
+	/// Draws `text` like [`draw_text`][Canvas::draw_text], but starts a new
+	/// line before any word that would go past `width` pixels. Words wider than
+	/// `width` are broken between characters. Returns the `y` coordinate below
+	/// the last line.
+	pub fn draw_text_wrapped(
+		&mut self,
+		font: &Font<'_>,
+		x: i32,
+		y: i32,
+		width: u32,
+		color: Color,
+		text: &str,
+	) -> i32 {
+		let line_height = font.line_height() as i32;
+		let space = font.advance(' ');
+		let mut pen_y = y;
+		for paragraph in text.split('\n') {
+			let mut used = 0;
+			for word in paragraph.split(' ') {
+				let word_width = font.measure(word);
+				let gap = if used == 0 { 0 } else { space };
+				if used > 0 && used + gap + word_width > width {
+					pen_y += line_height;
+					used = 0;
+				} else {
+					used += gap;
+				}
+				for ch in word.chars() {
+					let advance = font.advance(ch);
+					if used > 0 && used + advance > width && word_width > width {
+						pen_y += line_height;
+						used = 0;
+					}
+					font.draw_char(self, x + used as i32, pen_y, color, ch);
+					used += advance;
+				}
+			}
+			pen_y += line_height;
+		}
+		pen_y
+	}
+}
+
+/// Printable ASCII, from `' '` to `'~'`. Each glyph is 5x7 pixels in a 6x8
+/// cell, one byte per row, with the leftmost pixel in the highest bit.
+#[rustfmt::skip]
+const BUILTIN: [[u8; 8]; 95] = [
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // space
+	[0x20, 0x20, 0x20, 0x20, 0x20, 0x00, 0x20, 0x00], // !
+	[0x50, 0x50, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00], // "
+	[0x50, 0x50, 0xF8, 0x50, 0xF8, 0x50, 0x50, 0x00], // #
+	[0x20, 0x78, 0xA0, 0x70, 0x28, 0xF0, 0x20, 0x00], // $
+	[0xC0, 0xC8, 0x10, 0x20, 0x40, 0x98, 0x18, 0x00], // %
+	[0x60, 0x90, 0xA0, 0x40, 0xA8, 0x90, 0x68, 0x00], // &
+	[0x20, 0x20, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00], // '
+	[0x10, 0x20, 0x40, 0x40, 0x40, 0x20, 0x10, 0x00], // (
+	[0x40, 0x20, 0x10, 0x10, 0x10, 0x20, 0x40, 0x00], // )
+	[0x00, 0x20, 0xA8, 0x70, 0xA8, 0x20, 0x00, 0x00], // *
+	[0x00, 0x20, 0x20, 0xF8, 0x20, 0x20, 0x00, 0x00], // +
+	[0x00, 0x00, 0x00, 0x00, 0x60, 0x20, 0x40, 0x00], // ,
+	[0x00, 0x00, 0x00, 0xF8, 0x00, 0x00, 0x00, 0x00], // -
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x60, 0x60, 0x00], // .
+	[0x00, 0x08, 0x10, 0x20, 0x40, 0x80, 0x00, 0x00], // /
+	[0x70, 0x88, 0x98, 0xA8, 0xC8, 0x88, 0x70, 0x00], // 0
+	[0x20, 0x60, 0x20, 0x20, 0x20, 0x20, 0x70, 0x00], // 1
+	[0x70, 0x88, 0x08, 0x10, 0x20, 0x40, 0xF8, 0x00], // 2
+	[0xF8, 0x10, 0x20, 0x10, 0x08, 0x88, 0x70, 0x00], // 3
+	[0x10, 0x30, 0x50, 0x90, 0xF8, 0x10, 0x10, 0x00], // 4
+	[0xF8, 0x80, 0xF0, 0x08, 0x08, 0x88, 0x70, 0x00], // 5
+	[0x30, 0x40, 0x80, 0xF0, 0x88, 0x88, 0x70, 0x00], // 6
+	[0xF8, 0x08, 0x10, 0x20, 0x40, 0x40, 0x40, 0x00], // 7
+	[0x70, 0x88, 0x88, 0x70, 0x88, 0x88, 0x70, 0x00], // 8
+	[0x70, 0x88, 0x88, 0x78, 0x08, 0x10, 0x60, 0x00], // 9
+	[0x00, 0x60, 0x60, 0x00, 0x60, 0x60, 0x00, 0x00], // :
+	[0x00, 0x60, 0x60, 0x00, 0x60, 0x20, 0x40, 0x00], // ;
+	[0x10, 0x20, 0x40, 0x80, 0x40, 0x20, 0x10, 0x00], // <
+	[0x00, 0x00, 0xF8, 0x00, 0xF8, 0x00, 0x00, 0x00], // =
+	[0x40, 0x20, 0x10, 0x08, 0x10, 0x20, 0x40, 0x00], // >
+	[0x70, 0x88, 0x08, 0x10, 0x20, 0x00, 0x20, 0x00], // ?
+	[0x70, 0x88, 0x08, 0x68, 0xA8, 0xA8, 0x70, 0x00], // @
+	[0x70, 0x88, 0x88, 0x88, 0xF8, 0x88, 0x88, 0x00], // A
+	[0xF0, 0x88, 0x88, 0xF0, 0x88, 0x88, 0xF0, 0x00], // B
+	[0x70, 0x88, 0x80, 0x80, 0x80, 0x88, 0x70, 0x00], // C
+	[0xE0, 0x90, 0x88, 0x88, 0x88, 0x90, 0xE0, 0x00], // D
+	[0xF8, 0x80, 0x80, 0xF0, 0x80, 0x80, 0xF8, 0x00], // E
+	[0xF8, 0x80, 0x80, 0xF0, 0x80, 0x80, 0x80, 0x00], // F
+	[0x70, 0x88, 0x80, 0xB8, 0x88, 0x88, 0x78, 0x00], // G
+	[0x88, 0x88, 0x88, 0xF8, 0x88, 0x88, 0x88, 0x00], // H
+	[0x70, 0x20, 0x20, 0x20, 0x20, 0x20, 0x70, 0x00], // I
+	[0x38, 0x10, 0x10, 0x10, 0x10, 0x90, 0x60, 0x00], // J
+	[0x88, 0x90, 0xA0, 0xC0, 0xA0, 0x90, 0x88, 0x00], // K
+	[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0xF8, 0x00], // L
+	[0x88, 0xD8, 0xA8, 0xA8, 0x88, 0x88, 0x88, 0x00], // M
+	[0x88, 0x88, 0xC8, 0xA8, 0x98, 0x88, 0x88, 0x00], // N
+	[0x70, 0x88, 0x88, 0x88, 0x88, 0x88, 0x70, 0x00], // O
+	[0xF0, 0x88, 0x88, 0xF0, 0x80, 0x80, 0x80, 0x00], // P
+	[0x70, 0x88, 0x88, 0x88, 0xA8, 0x90, 0x68, 0x00], // Q
+	[0xF0, 0x88, 0x88, 0xF0, 0xA0, 0x90, 0x88, 0x00], // R
+	[0x78, 0x80, 0x80, 0x70, 0x08, 0x08, 0xF0, 0x00], // S
+	[0xF8, 0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x00], // T
+	[0x88, 0x88, 0x88, 0x88, 0x88, 0x88, 0x70, 0x00], // U
+	[0x88, 0x88, 0x88, 0x88, 0x88, 0x50, 0x20, 0x00], // V
+	[0x88, 0x88, 0x88, 0xA8, 0xA8, 0xA8, 0x50, 0x00], // W
+	[0x88, 0x88, 0x50, 0x20, 0x50, 0x88, 0x88, 0x00], // X
+	[0x88, 0x88, 0x88, 0x50, 0x20, 0x20, 0x20, 0x00], // Y
+	[0xF8, 0x08, 0x10, 0x20, 0x40, 0x80, 0xF8, 0x00], // Z
+	[0x70, 0x40, 0x40, 0x40, 0x40, 0x40, 0x70, 0x00], // [
+	[0x00, 0x80, 0x40, 0x20, 0x10, 0x08, 0x00, 0x00], // \\
+	[0x70, 0x10, 0x10, 0x10, 0x10, 0x10, 0x70, 0x00], // ]
+	[0x20, 0x50, 0x88, 0x00, 0x00, 0x00, 0x00, 0x00], // ^
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF8, 0x00], // _
+	[0x40, 0x20, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00], // `
+	[0x00, 0x00, 0x70, 0x08, 0x78, 0x88, 0x78, 0x00], // a
+	[0x80, 0x80, 0xB0, 0xC8, 0x88, 0x88, 0xF0, 0x00], // b
+	[0x00, 0x00, 0x70, 0x80, 0x80, 0x88, 0x70, 0x00], // c
+	[0x08, 0x08, 0x68, 0x98, 0x88, 0x88, 0x78, 0x00], // d
+	[0x00, 0x00, 0x70, 0x88, 0xF8, 0x80, 0x70, 0x00], // e
+	[0x30, 0x48, 0x40, 0xE0, 0x40, 0x40, 0x40, 0x00], // f
+	[0x00, 0x78, 0x88, 0x88, 0x78, 0x08, 0x70, 0x00], // g
+	[0x80, 0x80, 0xB0, 0xC8, 0x88, 0x88, 0x88, 0x00], // h
+	[0x20, 0x00, 0x60, 0x20, 0x20, 0x20, 0x70, 0x00], // i
+	[0x10, 0x00, 0x30, 0x10, 0x10, 0x90, 0x60, 0x00], // j
+	[0x80, 0x80, 0x90, 0xA0, 0xC0, 0xA0, 0x90, 0x00], // k
+	[0x60, 0x20, 0x20, 0x20, 0x20, 0x20, 0x70, 0x00], // l
+	[0x00, 0x00, 0xD0, 0xA8, 0xA8, 0x88, 0x88, 0x00], // m
+	[0x00, 0x00, 0xB0, 0xC8, 0x88, 0x88, 0x88, 0x00], // n
+	[0x00, 0x00, 0x70, 0x88, 0x88, 0x88, 0x70, 0x00], // o
+	[0x00, 0x00, 0xF0, 0x88, 0xF0, 0x80, 0x80, 0x00], // p
+	[0x00, 0x00, 0x68, 0x98, 0x78, 0x08, 0x08, 0x00], // q
+	[0x00, 0x00, 0xB0, 0xC8, 0x80, 0x80, 0x80, 0x00], // r
+	[0x00, 0x00, 0x70, 0x80, 0x70, 0x08, 0xF0, 0x00], // s
+	[0x40, 0x40, 0xE0, 0x40, 0x40, 0x48, 0x30, 0x00], // t
+	[0x00, 0x00, 0x88, 0x88, 0x88, 0x98, 0x68, 0x00], // u
+	[0x00, 0x00, 0x88, 0x88, 0x88, 0x50, 0x20, 0x00], // v
+	[0x00, 0x00, 0x88, 0x88, 0xA8, 0xA8, 0x50, 0x00], // w
+	[0x00, 0x00, 0x88, 0x50, 0x20, 0x50, 0x88, 0x00], // x
+	[0x00, 0x00, 0x88, 0x88, 0x78, 0x08, 0x70, 0x00], // y
+	[0x00, 0x00, 0xF8, 0x10, 0x20, 0x40, 0xF8, 0x00], // z
+	[0x10, 0x20, 0x20, 0x40, 0x20, 0x20, 0x10, 0x00], // {
+	[0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x00], // |
+	[0x40, 0x20, 0x20, 0x10, 0x20, 0x20, 0x40, 0x00], // }
+	[0x00, 0x00, 0x40, 0xA8, 0x10, 0x00, 0x00, 0x00], // ~
+];
@@ -0,0 +1,280 @@
+//! Sprites
+//!
+//! A [`Sprite`] is an image in RGB565, with some pixels optionally left
+//! transparent, either by a color key or by a 1 bit mask. Sprites borrow
+//! their pixels, so they can be used straight from `include_bytes!` data or
+//! a decoded image:
+//!
+//! ```
+//! use ndless::gfx::sprite::{Blit, Flip, Sprite, SpriteSheet};
+//!
+//! let player = Sprite::new(16, 16, &PLAYER_PIXELS).color_key(0xF81F);
+//! canvas.blit(&player, x, y);
+//! canvas.blit_ex(&player, player.bounds(), x, y, Flip::HORIZONTAL);
+//!
+//! // Draw a row of tiles from a sheet of 8x8 tiles
+//! let tiles = SpriteSheet::new(Sprite::new(128, 64, &TILE_PIXELS), 8, 8);
+//! let blits: Vec<Blit> = (0..10)
+//!     .map(|i| Blit::new(tiles.tile(i), i as i32 * 8, 0))
+//!     .collect();
+//! canvas.blit_batch(tiles.sprite(), &blits);
+//! ```
+//!
+//! On classic models, colors are converted to gray as they're drawn.
+
+use crate::gfx::{Canvas, Color, Rect};
+use crate::video::{Framebuffer, HEIGHT, WIDTH};
+
+/// Which pixels of a [`Sprite`] aren't drawn.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Transparency<'a> {
+	/// Every pixel is drawn.
+	Opaque,
+	/// Pixels of this RGB565 color aren't drawn.
+	ColorKey(u16),
+	/// One bit per pixel, row by row, with each row starting on a new byte
+	/// and the leftmost pixel in the highest bit. Pixels whose bit is 0
+	/// aren't drawn.
+	Mask(&'a [u8]),
+}
+
+/// Mirrors a sprite as it's drawn.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, Default)]
+pub struct Flip {
+	pub horizontal: bool,
+	pub vertical: bool,
+}
+
+impl Flip {
+	pub const NONE: Flip = Flip {
+		horizontal: false,
+		vertical: false,
+	};
+	pub const HORIZONTAL: Flip = Flip {
+		horizontal: true,
+		vertical: false,
+	};
+	pub const VERTICAL: Flip = Flip {
+		horizontal: false,
+		vertical: true,
+	};
+	pub const BOTH: Flip = Flip {
+		horizontal: true,
+		vertical: true,
+	};
+}
+
+/// An RGB565 image. See the [module documentation][self].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Sprite<'a> {
+	width: u32,
+	height: u32,
+	pixels: &'a [u16],
+	transparency: Transparency<'a>,
+}
+
+impl<'a> Sprite<'a> {
+	/// Creates an opaque sprite. `pixels` holds `width * height` pixels, row
+	/// by row.
+	///
+	/// # Panics
+	/// Panics if `pixels` has the wrong length.
+	pub fn new(width: u32, height: u32, pixels: &'a [u16]) -> Self {
+		assert_eq!(
+			pixels.len(),
+			width as usize * height as usize,
+			"sprite pixels don't match its size"
+		);
+		Self {
+			width,
+			height,
+			pixels,
+			transparency: Transparency::Opaque,
+		}
+	}
+
+	/// Doesn't draw pixels of the RGB565 color `key`.
+	pub fn color_key(mut self, key: u16) -> Self {
+		self.transparency = Transparency::ColorKey(key);
+		self
+	}
+
+	/// Only draws pixels whose bit is set in `mask`. See
+	/// [`Transparency::Mask`] for its layout.
+	///
+	/// # Panics
+	/// Panics if `mask` is too short for the sprite.
+	pub fn mask(mut self, mask: &'a [u8]) -> Self {
+		assert!(
+			mask.len() >= self.mask_stride() * self.height as usize,
+			"sprite mask is too short"
+		);
+		self.transparency = Transparency::Mask(mask);
+		self
+	}
+
+	pub fn width(&self) -> u32 {
+		self.width
+	}
+
+	pub fn height(&self) -> u32 {
+		self.height
+	}
+
+	/// A rectangle covering the whole sprite, to use as a source rectangle.
+	pub fn bounds(&self) -> Rect {
+		Rect::new(0, 0, self.width, self.height)
+	}
+
+	fn mask_stride(&self) -> usize {
+		(self.width as usize + 7) / 8
+	}
+
+	/// Returns the pixel at `(x, y)`, or `None` if it's transparent.
+	fn pixel(&self, x: usize, y: usize) -> Option<u16> {
+		let value = self.pixels[y * self.width as usize + x];
+		match self.transparency {
+			Transparency::Opaque => Some(value),
+			Transparency::ColorKey(key) if key == value => None,
+			Transparency::ColorKey(_) => Some(value),
+			Transparency::Mask(mask) => {
+				let byte = mask[y * self.mask_stride() + x / 8];
+				if byte & (0x80 >> (x % 8)) != 0 {
+					Some(value)
+				} else {
+					None
+				}
+			}
+		}
+	}
+}
+
+/// A sprite divided into equal tiles, numbered row by row from the top left.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct SpriteSheet<'a> {
+	sprite: Sprite<'a>,
+	tile_width: u32,
+	tile_height: u32,
+}
+
+impl<'a> SpriteSheet<'a> {
+	pub fn new(sprite: Sprite<'a>, tile_width: u32, tile_height: u32) -> Self {
+		Self {
+			sprite,
+			tile_width: tile_width.max(1),
+			tile_height: tile_height.max(1),
+		}
+	}
+
+	pub fn sprite(&self) -> &Sprite<'a> {
+		&self.sprite
+	}
+
+	/// The number of tiles in each row.
+	pub fn columns(&self) -> u32 {
+		self.sprite.width / self.tile_width
+	}
+
+	/// The source rectangle of tile number `index`.
+	pub fn tile(&self, index: u32) -> Rect {
+		let columns = self.columns().max(1);
+		Rect::new(
+			(index % columns * self.tile_width) as i32,
+			(index / columns * self.tile_height) as i32,
+			self.tile_width,
+			self.tile_height,
+		)
+	}
+}
+
+/// One part of a [`Canvas::blit_batch`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Blit {
+	/// The part of the sprite to draw
+	pub source: Rect,
+	pub x: i32,
+	pub y: i32,
+	pub flip: Flip,
+}
+
+impl Blit {
+	pub fn new(source: Rect, x: i32, y: i32) -> Self {
+		Self {
+			source,
+			x,
+			y,
+			flip: Flip::NONE,
+		}
+	}
+
+	pub fn flip(mut self, flip: Flip) -> Self {
+		self.flip = flip;
+		self
+	}
+}
+
+impl Canvas<'_> {
+	/// Draws all of `sprite` with its top left corner at `(x, y)`.
+	pub fn blit(&mut self, sprite: &Sprite<'_>, x: i32, y: i32) {
+		self.blit_ex(sprite, sprite.bounds(), x, y, Flip::NONE);
+	}
+
+	/// Draws the part of `sprite` in `source` with its top left corner at
+	/// `(x, y)`, mirrored by `flip`. `source` is cut to fit the sprite.
+	pub fn blit_ex(&mut self, sprite: &Sprite<'_>, source: Rect, x: i32, y: i32, flip: Flip) {
+		// Cut the source to the sprite, moving the destination to match
+		let left = source.x.max(0);
+		let top = source.y.max(0);
+		let right = (source.x + source.w as i32).min(sprite.width as i32);
+		let bottom = (source.y + source.h as i32).min(sprite.height as i32);
+		if left >= right || top >= bottom {
+			return;
+		}
+		let (w, h) = (right - left, bottom - top);
+		let x = x + if flip.horizontal {
+			source.x + source.w as i32 - right
+		} else {
+			left - source.x
+		};
+		let y = y + if flip.vertical {
+			source.y + source.h as i32 - bottom
+		} else {
+			top - source.y
+		};
+
+		let clip = self.clip;
+		let dest_left = x.max(clip.x);
+		let dest_right = (x + w).min(clip.x + clip.w as i32);
+		let dest_top = y.max(clip.y);
+		let dest_bottom = (y + h).min(clip.y + clip.h as i32);
+		for dest_y in dest_top..dest_bottom {
+			let row = dest_y - y;
+			let src_y = if flip.vertical { h - 1 - row } else { row } + top;
+			for dest_x in dest_left..dest_right {
+				let col = dest_x - x;
+				let src_x = if flip.horizontal { w - 1 - col } else { col } + left;
+				if let Some(value) = sprite.pixel(src_x as usize, src_y as usize) {
+					self.put_rgb565(dest_x, dest_y, value);
+				}
+			}
+		}
+	}
+
+	/// Draws several parts of one sprite, such as tiles from a
+	/// [`SpriteSheet`].
+	pub fn blit_batch(&mut self, sprite: &Sprite<'_>, blits: &[Blit]) {
+		for blit in blits {
+			self.blit_ex(sprite, blit.source, blit.x, blit.y, blit.flip);
+		}
+	}
+
+	/// Sets a pixel, which must be on screen, to an RGB565 value.
+	fn put_rgb565(&mut self, x: i32, y: i32, value: u16) {
+		debug_assert!(x >= 0 && y >= 0 && (x as usize) < WIDTH && (y as usize) < HEIGHT);
+		if let Framebuffer::Rgb565(pixels) = &mut self.framebuffer {
+			pixels[y as usize * WIDTH + x as usize] = value;
+		} else {
+			self.pixel(x, y, Color::from_rgb565(value));
+		}
+	}
+}
@@ -0,0 +1,136 @@
+//! # embedded-hal
+//! Implementations of the [`embedded-hal`](https://docs.rs/embedded-hal)
+//! traits, so drivers written for microcontrollers can be used on the
+//! calculator. Enable the `hal` feature to use them:
+//!
+//! ```
+//! use ndless::hal::Delay;
+//! use ndless::serial::Uart;
+//!
+//! let uart = Uart::new(9600).expect("the serial port is already in use");
+//! // Any driver that takes embedded-hal serial and delay traits
+//! let mut sensor = Sensor::new(uart, Delay);
+//! ```
+//!
+//! | Trait                                  | Implemented by |
+//! |----------------------------------------|----------------|
+//! | `blocking::delay::{DelayUs, DelayMs}`  | [`Delay`] |
+//! | `serial::{Read, Write}`                | [`Uart`] |
+//! | `digital::v2::InputPin`                | [`gpio::InputPin`] |
+//! | `digital::v2::{OutputPin, StatefulOutputPin, ToggleableOutputPin}` | [`gpio::OutputPin`] |
+
+use core::convert::Infallible;
+
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use embedded_hal::digital::v2;
+use embedded_hal::serial;
+
+use crate::hw::gpio;
+use crate::serial::Uart;
+use crate::time::{delay_us, sleep_ms};
+
+/// Waits by busy-looping for short delays, and sleeping for long ones.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, Default)]
+pub struct Delay;
+
+impl DelayUs<u32> for Delay {
+	fn delay_us(&mut self, us: u32) {
+		delay_us(us);
+	}
+}
+
+impl DelayUs<u16> for Delay {
+	fn delay_us(&mut self, us: u16) {
+		delay_us(us.into());
+	}
+}
+
+impl DelayUs<u8> for Delay {
+	fn delay_us(&mut self, us: u8) {
+		delay_us(us.into());
+	}
+}
+
+impl DelayMs<u32> for Delay {
+	fn delay_ms(&mut self, ms: u32) {
+		sleep_ms(ms);
+	}
+}
+
+impl DelayMs<u16> for Delay {
+	fn delay_ms(&mut self, ms: u16) {
+		sleep_ms(ms.into());
+	}
+}
+
+impl DelayMs<u8> for Delay {
+	fn delay_ms(&mut self, ms: u8) {
+		sleep_ms(ms.into());
+	}
+}
+
+impl serial::Read<u8> for Uart {
+	type Error = Infallible;
+
+	fn read(&mut self) -> nb::Result<u8, Infallible> {
+		self.try_read_byte().ok_or(nb::Error::WouldBlock)
+	}
+}
+
+impl serial::Write<u8> for Uart {
+	type Error = Infallible;
+
+	fn write(&mut self, word: u8) -> nb::Result<(), Infallible> {
+		self.write_byte(word);
+		Ok(())
+	}
+
+	fn flush(&mut self) -> nb::Result<(), Infallible> {
+		Ok(())
+	}
+}
+
+impl v2::InputPin for gpio::InputPin {
+	type Error = Infallible;
+
+	fn is_high(&self) -> Result<bool, Infallible> {
+		Ok(gpio::InputPin::is_high(self))
+	}
+
+	fn is_low(&self) -> Result<bool, Infallible> {
+		Ok(gpio::InputPin::is_low(self))
+	}
+}
+
+impl v2::OutputPin for gpio::OutputPin {
+	type Error = Infallible;
+
+	fn set_low(&mut self) -> Result<(), Infallible> {
+		gpio::OutputPin::set_low(self);
+		Ok(())
+	}
+
+	fn set_high(&mut self) -> Result<(), Infallible> {
+		gpio::OutputPin::set_high(self);
+		Ok(())
+	}
+}
+
+impl v2::StatefulOutputPin for gpio::OutputPin {
+	fn is_set_high(&self) -> Result<bool, Infallible> {
+		Ok(gpio::OutputPin::is_set_high(self))
+	}
+
+	fn is_set_low(&self) -> Result<bool, Infallible> {
+		Ok(!gpio::OutputPin::is_set_high(self))
+	}
+}
+
+impl v2::ToggleableOutputPin for gpio::OutputPin {
+	type Error = Infallible;
+
+	fn toggle(&mut self) -> Result<(), Infallible> {
+		gpio::OutputPin::toggle(self);
+		Ok(())
+	}
+}
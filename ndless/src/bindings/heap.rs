@@ -0,0 +1,427 @@
+//! # Heap
+//! Tracks how much memory the program has allocated, so memory use can be
+//! tuned to fit the calculator's limited RAM:
+//!
+//! ```
+//! use ndless::heap;
+//!
+//! load_level();
+//! let stats = heap::stats();
+//! println!("{} bytes in use, at most {}", stats.current, stats.peak);
+//! ```
+//!
+//! Statistics are recorded by the global allocator in `ndless-handler`. With
+//! a different allocator, they stay at zero unless it calls the hidden
+//! `__record_*` functions too.
+//!
+//! ## Fallible allocation
+//! Running out of memory normally aborts the program. The `try_*` functions
+//! return an error instead, so the program can free something and carry on:
+//!
+//! ```
+//! use ndless::heap;
+//!
+//! if heap::try_reserve(&mut samples, 44_100).is_err() {
+//!     thumbnail_cache.clear();
+//!     heap::try_reserve(&mut samples, 44_100).map_err(|_| "not enough memory to record")?;
+//! }
+//! let mut pixels = heap::try_vec::<u16>(320 * 240)?;
+//! ```
+//!
+//! `Vec::try_reserve` and `Box::try_new` aren't stable in the toolchain this
+//! crate supports, so these work on the standard collections from outside.
+//!
+//! ## Arenas
+//! An arena is one big block of memory that allocations are carved from in
+//! order, so allocating is a few instructions, and everything in it is freed
+//! at once. Games that build temporary lists every frame can use an
+//! [`ArenaScope`] instead of going through `malloc` and `free` each time:
+//!
+//! ```
+//! use ndless::heap::{self, ArenaScope};
+//!
+//! heap::init_arena(256 * 1024)?;
+//! loop {
+//!     // Safety: nothing allocated while drawing is kept after the frame
+//!     let frame = unsafe { ArenaScope::enter() };
+//!     let visible: Vec<_> = sprites.iter().filter(|s| s.on_screen()).collect();
+//!     draw(&visible);
+//!     drop(visible);
+//!     drop(frame);
+//! }
+//! ```
+//!
+//! The arena can also replace `malloc` for the whole program, with
+//! [`use_arena_globally`], `#[entry(arena = SIZE)]`, or the `arena-allocator`
+//! feature, which uses an arena of [`DEFAULT_ARENA_SIZE`]. Memory freed in
+//! the arena is only reused if it was the latest allocation, or when a scope
+//! ends, so this suits programs that allocate up front. Once the arena is
+//! full, allocations fall back to `malloc`.
+//!
+//! Arenas need the allocator from `ndless-handler`.
+//!
+//! This is `heap` rather than `alloc`, as `ndless::alloc` is the `alloc`
+//! crate.
+
+use alloc::alloc::{alloc, realloc};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::fmt;
+use core::mem;
+use core::ptr::{self, NonNull};
+
+use crate::error::Error;
+
+/// An error returned when there isn't enough memory for an allocation.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "memory allocation failed")
+	}
+}
+
+impl Error for AllocError {}
+
+/// A snapshot of heap usage, returned by [`stats`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, Default)]
+pub struct Stats {
+	/// Bytes currently allocated
+	pub current: usize,
+	/// The most bytes allocated at once, since the program started or
+	/// [`reset_peak`] was called
+	pub peak: usize,
+	/// How many allocations have been made
+	pub allocations: usize,
+	/// How many allocations have been freed
+	pub deallocations: usize,
+	/// How many allocations have been resized
+	pub reallocations: usize,
+	/// How many allocations have failed, including ones that were handled,
+	/// such as by `Vec::try_reserve`
+	pub failures: usize,
+}
+
+impl Stats {
+	/// The number of allocations that haven't been freed.
+	pub fn live(&self) -> usize {
+		self.allocations - self.deallocations
+	}
+}
+
+static mut STATS: Stats = Stats {
+	current: 0,
+	peak: 0,
+	allocations: 0,
+	deallocations: 0,
+	reallocations: 0,
+	failures: 0,
+};
+static mut ALLOC_ERROR_HOOK: Option<fn(Layout)> = None;
+static mut ALLOC_ERROR_DIALOG: bool = false;
+
+/// Returns the heap usage so far.
+pub fn stats() -> Stats {
+	unsafe { STATS }
+}
+
+/// Sets the peak to the current usage, to measure the peak of one part of the
+/// program.
+pub fn reset_peak() {
+	unsafe { STATS.peak = STATS.current }
+}
+
+/// Runs `hook` when an allocation fails and the program is about to abort,
+/// such as to save the user's work or show how much memory was in use. It
+/// can't allocate.
+pub fn on_alloc_error(hook: fn(Layout)) {
+	unsafe { ALLOC_ERROR_HOOK = Some(hook) }
+}
+
+/// Removes the hook set by [`on_alloc_error`].
+pub fn remove_alloc_error_hook() {
+	unsafe { ALLOC_ERROR_HOOK = None }
+}
+
+/// Sets whether a message box saying how much memory was asked for is shown
+/// when an allocation fails, after the hook set by [`on_alloc_error`]. Showing
+/// it needs a little memory, so it may not appear if the heap is completely
+/// full.
+pub fn set_alloc_error_dialog(enabled: bool) {
+	unsafe { ALLOC_ERROR_DIALOG = enabled }
+}
+
+fn add_current(size: usize) {
+	unsafe {
+		STATS.current += size;
+		STATS.peak = STATS.peak.max(STATS.current);
+	}
+}
+
+#[doc(hidden)]
+pub fn __record_alloc(size: usize) {
+	unsafe { STATS.allocations += 1 };
+	add_current(size);
+}
+
+#[doc(hidden)]
+pub fn __record_dealloc(size: usize) {
+	unsafe {
+		STATS.deallocations += 1;
+		STATS.current = STATS.current.saturating_sub(size);
+	}
+}
+
+#[doc(hidden)]
+pub fn __record_realloc(old_size: usize, new_size: usize) {
+	unsafe {
+		STATS.reallocations += 1;
+		STATS.current = STATS.current.saturating_sub(old_size);
+	}
+	add_current(new_size);
+}
+
+#[doc(hidden)]
+pub fn __record_failure() {
+	unsafe { STATS.failures += 1 }
+}
+
+/// Called by the allocation error handler before aborting.
+#[doc(hidden)]
+pub fn __alloc_error(layout: Layout) {
+	if let Some(hook) = unsafe { ALLOC_ERROR_HOOK.take() } {
+		hook(layout);
+	}
+	if unsafe { ALLOC_ERROR_DIALOG } {
+		// A Screen might have the LCD, which the message box needs
+		crate::video::restore();
+		crate::msg::msg(
+			"Out of memory",
+			&format!("Could not allocate {} bytes.", layout.size()),
+		);
+	}
+}
+
+/// Allocates memory for `layout`, which must not have a size of 0. It must
+/// be freed with [`alloc::alloc::dealloc`] using the same layout.
+pub fn try_alloc(layout: Layout) -> Result<NonNull<u8>, AllocError> {
+	assert_ne!(layout.size(), 0, "cannot allocate 0 bytes");
+	NonNull::new(unsafe { alloc(layout) }).ok_or(AllocError)
+}
+
+/// Moves `value` to the heap, or gives it back if there isn't enough memory.
+pub fn try_box<T>(value: T) -> Result<Box<T>, T> {
+	let layout = Layout::new::<T>();
+	let ptr = if layout.size() == 0 {
+		NonNull::dangling()
+	} else {
+		match try_alloc(layout) {
+			Ok(ptr) => ptr.cast(),
+			Err(AllocError) => return Err(value),
+		}
+	};
+	unsafe {
+		ptr::write(ptr.as_ptr(), value);
+		Ok(Box::from_raw(ptr.as_ptr()))
+	}
+}
+
+/// Creates an empty `Vec` with space for at least `capacity` elements.
+pub fn try_vec<T>(capacity: usize) -> Result<Vec<T>, AllocError> {
+	let mut vec = Vec::new();
+	try_reserve_exact(&mut vec, capacity)?;
+	Ok(vec)
+}
+
+/// Makes space for at least `additional` more elements in `vec`, like
+/// [`Vec::reserve`]. On failure, `vec` is unchanged.
+pub fn try_reserve<T>(vec: &mut Vec<T>, additional: usize) -> Result<(), AllocError> {
+	let needed = vec.len().checked_add(additional).ok_or(AllocError)?;
+	if needed <= vec.capacity() {
+		return Ok(());
+	}
+	// Double like `Vec` does, so pushing in a loop doesn't copy every time
+	grow(vec, needed.max(vec.capacity().saturating_mul(2))).or_else(|_| grow(vec, needed))
+}
+
+/// Makes space for exactly `additional` more elements in `vec`, like
+/// [`Vec::reserve_exact`]. On failure, `vec` is unchanged.
+pub fn try_reserve_exact<T>(vec: &mut Vec<T>, additional: usize) -> Result<(), AllocError> {
+	let needed = vec.len().checked_add(additional).ok_or(AllocError)?;
+	if needed <= vec.capacity() {
+		return Ok(());
+	}
+	grow(vec, needed)
+}
+
+/// Appends `value` to `vec`, or gives it back if there isn't enough memory.
+pub fn try_push<T>(vec: &mut Vec<T>, value: T) -> Result<(), T> {
+	if try_reserve(vec, 1).is_err() {
+		return Err(value);
+	}
+	vec.push(value);
+	Ok(())
+}
+
+/// Resizes the buffer of `vec` to `capacity`, which is more than it has.
+fn grow<T>(vec: &mut Vec<T>, capacity: usize) -> Result<(), AllocError> {
+	if mem::size_of::<T>() == 0 {
+		// Zero-sized types never need memory
+		return Ok(());
+	}
+	let new_layout = Layout::array::<T>(capacity).map_err(|_| AllocError)?;
+	let ptr = if vec.capacity() == 0 {
+		unsafe { alloc(new_layout) }
+	} else {
+		let old_layout = Layout::array::<T>(vec.capacity()).map_err(|_| AllocError)?;
+		unsafe { realloc(vec.as_mut_ptr() as *mut u8, old_layout, new_layout.size()) }
+	};
+	// If this fails, the old buffer is untouched
+	let ptr = NonNull::new(ptr).ok_or(AllocError)?.cast::<T>();
+	let len = vec.len();
+	// The old buffer was moved by `realloc`, so the old `Vec` must not free it
+	mem::forget(mem::replace(vec, unsafe {
+		Vec::from_raw_parts(ptr.as_ptr(), len, capacity)
+	}));
+	Ok(())
+}
+
+/// The size of the arena created by the `arena-allocator` feature.
+pub const DEFAULT_ARENA_SIZE: usize = 4 * 1024 * 1024;
+
+/// The arena's memory, from `start` up to `end`. Allocations are made from
+/// `top` upwards.
+struct Arena {
+	start: usize,
+	end: usize,
+	top: usize,
+}
+
+static mut ARENA: Arena = Arena {
+	start: 0,
+	end: 0,
+	top: 0,
+};
+static mut ARENA_SCOPES: usize = 0;
+static mut ARENA_GLOBAL: bool = false;
+
+/// Creates the arena, taking `size` bytes from the heap. The arena lasts
+/// until the program exits, and can only be created once: later calls do
+/// nothing.
+pub fn init_arena(size: usize) -> Result<(), AllocError> {
+	unsafe {
+		if ARENA.start != 0 {
+			return Ok(());
+		}
+		let start = ndless_sys::malloc(size) as usize;
+		if start == 0 {
+			return Err(AllocError);
+		}
+		ARENA = Arena {
+			start,
+			end: start + size,
+			top: start,
+		};
+	}
+	Ok(())
+}
+
+/// Makes every allocation come from the arena while it has space, rather than
+/// only inside an [`ArenaScope`]. Does nothing until [`init_arena`] is called.
+pub fn use_arena_globally(enabled: bool) {
+	unsafe { ARENA_GLOBAL = enabled }
+}
+
+/// The number of bytes used in the arena.
+pub fn arena_used() -> usize {
+	unsafe { ARENA.top - ARENA.start }
+}
+
+/// The size of the arena, or 0 if it hasn't been created.
+pub fn arena_capacity() -> usize {
+	unsafe { ARENA.end - ARENA.start }
+}
+
+/// While this exists, allocations come from the arena. When it is dropped,
+/// everything allocated since it was entered is freed at once. See the
+/// [module documentation][self].
+#[derive(Debug)]
+pub struct ArenaScope {
+	mark: usize,
+}
+
+impl ArenaScope {
+	/// Starts allocating from the arena, which must have been created with
+	/// [`init_arena`]. Scopes can be nested, and must be dropped in the
+	/// reverse order they were entered.
+	///
+	/// # Safety
+	/// Nothing allocated while the scope exists may be used after it is
+	/// dropped, including freeing it: collections created inside the scope
+	/// must be dropped or [forgotten][mem::forget] before the scope.
+	pub unsafe fn enter() -> Self {
+		ARENA_SCOPES += 1;
+		Self { mark: ARENA.top }
+	}
+}
+
+impl Drop for ArenaScope {
+	fn drop(&mut self) {
+		unsafe {
+			ARENA_SCOPES -= 1;
+			ARENA.top = self.mark;
+		}
+	}
+}
+
+/// Allocates from the arena, if it's in use and has space.
+#[doc(hidden)]
+pub fn __arena_alloc(layout: Layout) -> Option<*mut u8> {
+	unsafe {
+		if ARENA.start == 0 || (!ARENA_GLOBAL && ARENA_SCOPES == 0) {
+			return None;
+		}
+		let start = ARENA.top.checked_add(layout.align() - 1)? & !(layout.align() - 1);
+		let end = start.checked_add(layout.size())?;
+		if end > ARENA.end {
+			return None;
+		}
+		ARENA.top = end;
+		Some(start as *mut u8)
+	}
+}
+
+/// Returns whether `ptr` is in the arena, and so must not be freed with
+/// `free`.
+#[doc(hidden)]
+pub fn __arena_contains(ptr: *mut u8) -> bool {
+	let addr = ptr as usize;
+	unsafe { addr >= ARENA.start && addr < ARENA.end }
+}
+
+/// Frees `ptr` if it is in the arena, returning whether it was. Only the
+/// latest allocation is given back to the arena.
+#[doc(hidden)]
+pub fn __arena_dealloc(ptr: *mut u8, layout: Layout) -> bool {
+	if !__arena_contains(ptr) {
+		return false;
+	}
+	let addr = ptr as usize;
+	unsafe {
+		if addr + layout.size() == ARENA.top {
+			ARENA.top = addr;
+		}
+	}
+	true
+}
+
+/// Called by `#[entry(arena = ...)]` and the `arena-allocator` feature.
+#[doc(hidden)]
+pub fn __init_global_arena(size: usize) {
+	if init_arena(size).is_ok() {
+		use_arena_globally(true);
+	}
+}
@@ -1,6 +1,20 @@
 //! # Tools to interact with the hardware
 //! This module contains functions to gather information about the calculator.
 
+pub mod adc;
+pub mod backlight;
+pub mod battery;
+pub mod dma;
+pub mod gpio;
+pub(crate) mod irq;
+pub mod keypad;
+pub mod led;
+pub mod power;
+
+use core::fmt;
+
+use crate::os::os_index;
+
 /// Returned by [`hw_type`]
 #[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
 pub enum Type {
@@ -43,6 +57,159 @@ pub fn has_touchpad() -> bool {
 	unsafe { ndless_sys::_is_touchpad() > 0 }
 }
 
+/// A TI-Nspire model, returned by [`model`]
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Model {
+	/// Classic TI-Nspire with the original keypad, CAS or not
+	Clickpad,
+	/// Classic TI-Nspire with a touchpad keypad, CAS or not
+	Touchpad,
+	/// TI-Nspire CX, or CM
+	Cx,
+	/// TI-Nspire CX CAS, or CM CAS
+	CxCas,
+	/// TI-Nspire CX II, or CX II-T
+	CxII,
+	/// TI-Nspire CX II CAS
+	CxIICas,
+}
+
+impl Model {
+	pub fn has_color_screen(self) -> bool {
+		!matches!(self, Model::Clickpad | Model::Touchpad)
+	}
+
+	pub fn is_cas(self) -> bool {
+		matches!(self, Model::CxCas | Model::CxIICas)
+	}
+}
+
+/// Returns the model of the calculator.
+///
+/// CAS models are told apart by the OS they run, so on an OS released after
+/// this crate, a CAS model is reported as the model without CAS. See
+/// [`os_version`].
+pub fn model() -> Model {
+	let cas = is_cas();
+	match unsafe { ndless_sys::hwtype() } {
+		0 if has_touchpad() => Model::Touchpad,
+		0 => Model::Clickpad,
+		1 if cas => Model::CxCas,
+		1 => Model::Cx,
+		_ if cas => Model::CxIICas,
+		_ => Model::CxII,
+	}
+}
+
+/// TRUE if the device has a screen in color. The same as [`has_colors`].
+pub fn has_color_screen() -> bool {
+	has_colors()
+}
+
+/// TRUE if the calculator runs a CAS OS. FALSE if the OS is unknown, see
+/// [`os_version`].
+pub fn is_cas() -> bool {
+	os_info().map_or(false, |(_, cas)| cas)
+}
+
+/// An OS version, such as 4.5.0
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct OsVersion {
+	pub major: u8,
+	pub minor: u8,
+	pub patch: u8,
+}
+
+impl OsVersion {
+	pub const fn new(major: u8, minor: u8, patch: u8) -> Self {
+		Self {
+			major,
+			minor,
+			patch,
+		}
+	}
+}
+
+impl fmt::Display for OsVersion {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+	}
+}
+
+/// The OS versions Ndless supports, in the order of its OS index, which
+/// [`ndless_sys::nl_osvalue`] picks values by, and whether each one is CAS
+pub(crate) const OS_VERSIONS: [(OsVersion, bool); 32] = [
+	(OsVersion::new(3, 1, 0), false),
+	(OsVersion::new(3, 1, 0), true),
+	(OsVersion::new(3, 1, 0), false), // CX
+	(OsVersion::new(3, 1, 0), true),  // CX
+	(OsVersion::new(3, 1, 0), false), // CM
+	(OsVersion::new(3, 1, 0), true),  // CM
+	(OsVersion::new(3, 6, 0), false),
+	(OsVersion::new(3, 6, 0), true),
+	(OsVersion::new(3, 6, 0), false), // CX
+	(OsVersion::new(3, 6, 0), true),  // CX
+	(OsVersion::new(3, 9, 0), false),
+	(OsVersion::new(3, 9, 0), true),
+	(OsVersion::new(3, 9, 0), false), // CX
+	(OsVersion::new(3, 9, 0), true),  // CX
+	(OsVersion::new(3, 9, 1), false),
+	(OsVersion::new(3, 9, 1), true),
+	(OsVersion::new(3, 9, 1), false), // CX
+	(OsVersion::new(3, 9, 1), true),  // CX
+	(OsVersion::new(4, 0, 0), false),
+	(OsVersion::new(4, 0, 0), true),
+	(OsVersion::new(4, 0, 3), false),
+	(OsVersion::new(4, 0, 3), true),
+	(OsVersion::new(4, 2, 0), false),
+	(OsVersion::new(4, 2, 0), true),
+	(OsVersion::new(4, 3, 0), false),
+	(OsVersion::new(4, 3, 0), true),
+	(OsVersion::new(4, 4, 0), false),
+	(OsVersion::new(4, 4, 0), true),
+	(OsVersion::new(4, 5, 0), false),
+	(OsVersion::new(4, 5, 0), true),
+	(OsVersion::new(4, 5, 1), false),
+	(OsVersion::new(4, 5, 1), true),
+];
+
+fn os_info() -> Option<(OsVersion, bool)> {
+	OS_VERSIONS.get(os_index()?).copied()
+}
+
+/// Returns the version of the OS, or `None` if it's newer than the ones this
+/// crate knows about.
+pub fn os_version() -> Option<OsVersion> {
+	os_info().map(|(version, _)| version)
+}
+
+/// A hardware revision, returned by [`hw_revision`]
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Revision {
+	/// Any classic TI-Nspire
+	Classic,
+	/// A CX or CM from before HW-W, with a landscape LCD
+	Cx,
+	/// A CX from HW-W on, with a portrait LCD
+	CxHwW,
+	/// Any CX II
+	CxII,
+}
+
+/// Returns the hardware revision, for the changes that matter to programs.
+pub fn hw_revision() -> Revision {
+	match unsafe { ndless_sys::hwtype() } {
+		0 => Revision::Classic,
+		1 => match screen::lcd_type() {
+			screen::Screen::Screen240x320x565 | screen::Screen::Screen240x320x555 => {
+				Revision::CxHwW
+			}
+			_ => Revision::Cx,
+		},
+		_ => Revision::CxII,
+	}
+}
+
 /// flush the data cache and invalidate the instruction and data caches of the
 /// processor. Should be called before loading code dynamically, after a code
 /// patch or with self-modifying code.
@@ -50,6 +217,13 @@ pub fn clear_cache() {
 	unsafe { ndless_sys::clear_cache() }
 }
 
+/// TRUE if a USB cable supplying power is plugged in, such as from a computer
+/// or a charger. Read from the B-session valid bit of the USB controller.
+pub fn usb_connected() -> bool {
+	let otgsc = 0xB000_01A4 as *const u32;
+	unsafe { core::ptr::read_volatile(otgsc) & (1 << 11) != 0 }
+}
+
 pub mod screen {
 	/// Returned by [`lcd_type`]
 	#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
@@ -0,0 +1,42 @@
+//! ADC
+//!
+//! Measures voltages with the calculator's analog to digital converter:
+//!
+//! ```
+//! use ndless::hw::adc;
+//!
+//! let millivolts = adc::read(DOCK_CHANNEL).expect("no such channel");
+//! ```
+//!
+//! Channel [`BATTERY`] measures the battery through a divider, which
+//! [`battery::voltage`][crate::hw::battery::voltage] takes into account. The
+//! others measure whatever they are wired to, which depends on the model, so
+//! check the wiring before relying on one.
+
+use core::ptr::read_volatile;
+
+/// The number of channels.
+pub const CHANNELS: usize = 7;
+/// The channel that measures the battery.
+pub const BATTERY: usize = 3;
+
+/// The reference voltage, in millivolts, which reads as [`MAX`]
+pub const REFERENCE: u32 = 3300;
+/// The highest reading. Readings are 10 bits.
+pub const MAX: u32 = 0x3FF;
+
+/// Returns the last reading of `channel`, from 0 to [`MAX`], or `None` if
+/// there is no such channel.
+pub fn read_raw(channel: usize) -> Option<u32> {
+	if channel >= CHANNELS {
+		return None;
+	}
+	let value = (0xC400_0110 + channel * 0x20) as *const u32;
+	Some(unsafe { read_volatile(value) } & MAX)
+}
+
+/// Returns the voltage on `channel` in millivolts, or `None` if there is no
+/// such channel.
+pub fn read(channel: usize) -> Option<u32> {
+	read_raw(channel).map(|raw| raw * REFERENCE / MAX)
+}
@@ -0,0 +1,88 @@
+//! Backlight
+//!
+//! Brightness goes from 0, the dimmest the screen can be while still readable,
+//! to [`MAX`], the brightest:
+//!
+//! ```
+//! use ndless::hw::backlight;
+//! use ndless::time::Duration;
+//!
+//! let brightness = backlight::get();
+//! // Dim the screen while paused
+//! backlight::fade_to(backlight::MAX / 4, Duration::from_millis(300));
+//! // ...
+//! backlight::set(brightness);
+//! ```
+//!
+//! The OS doesn't restore the brightness when a program exits, so programs
+//! that change it should put it back. On classic models, which have no
+//! backlight, this sets the contrast of the LCD instead.
+
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::hw::{hw_revision, Revision};
+use crate::thread;
+use crate::time::Duration;
+use crate::timer::ticks;
+
+/// How long [`fade_to`] waits between changes to the brightness
+const FADE_STEP: Duration = Duration::from_millis(10);
+
+/// The brightest setting.
+pub const MAX: u8 = 255;
+
+/// The register that controls the brightness, and its values for the dimmest
+/// and brightest settings
+fn register() -> (*mut u32, u32, u32) {
+	match hw_revision() {
+		// LCD contrast
+		Revision::Classic => (0x900F_0020 as *mut u32, 0x60, 0xC0),
+		// The duty cycle of the backlight's PWM, where lower is brighter
+		Revision::Cx | Revision::CxHwW => (0x9013_0018 as *mut u32, 0xE0, 0x00),
+		// The CX II's PWM counts the other way, with a longer period
+		Revision::CxII => (0x9013_0018 as *mut u32, 0x20, 0x1FF),
+	}
+}
+
+/// Returns the current brightness.
+pub fn get() -> u8 {
+	let (register, dim, bright) = register();
+	let value = unsafe { read_volatile(register) };
+	let (value, dim, bright) = (value as i32, dim as i32, bright as i32);
+	let level = (value - dim) * MAX as i32 / (bright - dim);
+	level.max(0).min(MAX as i32) as u8
+}
+
+/// Sets the brightness right away.
+pub fn set(level: u8) {
+	let (register, dim, bright) = register();
+	let (dim, bright) = (dim as i32, bright as i32);
+	let value = dim + (bright - dim) * level as i32 / MAX as i32;
+	unsafe { write_volatile(register, value as u32) }
+}
+
+/// Changes the brightness gradually over `duration`, sleeping between steps.
+/// Returns once the brightness is `level`.
+pub fn fade_to(level: u8, duration: Duration) {
+	let from = get();
+	let start = ticks();
+	let length = duration
+		.as_secs()
+		.saturating_mul(1_000_000)
+		.saturating_add(u64::from(duration.subsec_micros()));
+	loop {
+		let elapsed = ticks() - start;
+		if elapsed >= length {
+			break;
+		}
+		// Less than the difference, as elapsed < length
+		let change = (u64::from(from.max(level) - from.min(level)) * elapsed / length) as u8;
+		set(if level > from {
+			from + change
+		} else {
+			from - change
+		});
+		thread::sleep(FADE_STEP.min(Duration::from_micros(length - elapsed)));
+	}
+	set(level);
+}
@@ -0,0 +1,273 @@
+//! Battery voltage and charging
+//!
+//! The voltage is read from the calculator's ADC. TI-Nspire CX models use a
+//! rechargeable lithium-ion battery, while classic models use four AAA
+//! batteries, so the voltage ranges are different on each.
+//!
+//! A program can be told when the battery changes, such as to redraw a
+//! battery icon:
+//!
+//! ```
+//! use ndless::hw::battery;
+//!
+//! battery::on_change(|status| draw_battery_icon(status.percent, status.charging));
+//! loop {
+//!     battery::poll();
+//!     // ...
+//! }
+//! ```
+//!
+//! The battery is also checked by [`EventLoop`][crate::event::EventLoop], so
+//! programs built around it don't need to call [`poll`].
+//!
+//! ## Running out
+//! Use a [`BatteryMonitor`] to be notified when the charge gets low, then call
+//! [`prepare_shutdown`] to save everything and warn the user:
+//!
+//! ```
+//! use ndless::hw::battery::{self, BatteryEvent, BatteryMonitor};
+//!
+//! ndless::process::at_exit(|| save_game());
+//! let mut monitor = BatteryMonitor::new();
+//! loop {
+//!     match monitor.poll() {
+//!         Some(BatteryEvent::Critical) => battery::prepare_shutdown(),
+//!         Some(BatteryEvent::Low) => ndless::ui::toast("Battery low"),
+//!         _ => {}
+//!     }
+//!     // ...
+//! }
+//! ```
+
+use alloc::boxed::Box;
+
+use crate::hw::{adc, clear_cache, is_classic, usb_connected};
+use crate::io::{stdout, Write};
+use crate::msg::msg;
+use crate::ndless::refresh_documents;
+use crate::process;
+use crate::time::Duration;
+use crate::timer::{get_ticks, has_time_passed, TICKS_PER_SECOND};
+
+/// The voltage range, in millivolts, from empty to full.
+fn range() -> (u32, u32) {
+	if is_classic() {
+		(4400, 6000)
+	} else {
+		(3400, 4200)
+	}
+}
+
+/// Returns the battery voltage in millivolts.
+pub fn voltage() -> u32 {
+	// The ADC is behind a divider that halves the battery voltage on CX models,
+	// and thirds it on classic ones.
+	let divider = if is_classic() { 3 } else { 2 };
+	adc::read(adc::BATTERY).unwrap_or(0) * divider
+}
+
+/// Returns an estimate of the remaining charge, from 0 to 100.
+pub fn percent() -> u8 {
+	let (empty, full) = range();
+	let voltage = voltage().max(empty).min(full);
+	((voltage - empty) * 100 / (full - empty)) as u8
+}
+
+/// TRUE if a charger or a computer is supplying power over USB.
+pub fn charger_connected() -> bool {
+	usb_connected()
+}
+
+/// TRUE if the battery is being charged. Classic models can't charge their
+/// batteries, so this is always FALSE on them.
+pub fn is_charging() -> bool {
+	!is_classic() && charger_connected() && percent() < 100
+}
+
+/// The state of the battery, returned by [`status`]
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Status {
+	/// An estimate of the remaining charge, from 0 to 100
+	pub percent: u8,
+	/// The voltage in millivolts
+	pub voltage: u32,
+	pub charger_connected: bool,
+	pub charging: bool,
+}
+
+pub fn status() -> Status {
+	Status {
+		percent: percent(),
+		voltage: voltage(),
+		charger_connected: charger_connected(),
+		charging: is_charging(),
+	}
+}
+
+static mut HANDLER: Option<Box<dyn FnMut(Status)>> = None;
+/// The status last passed to the handler, and when the battery is next read
+static mut LAST: Option<(Status, u32)> = None;
+
+/// Calls `handler` from [`poll`] when the charge changes by 5% or more, or a
+/// charger is connected or removed, replacing any handler set before. It is
+/// also called once on the next [`poll`], with the current status.
+pub fn on_change(handler: impl FnMut(Status) + 'static) {
+	unsafe {
+		HANDLER = Some(Box::new(handler));
+		LAST = None;
+	}
+}
+
+/// Removes the handler set with [`on_change`].
+pub fn remove_on_change_handler() {
+	unsafe {
+		HANDLER = None;
+		LAST = None;
+	}
+}
+
+/// Checks the battery, calling the handler if it changed. The ADC is only read
+/// once per second, so this is cheap to call every frame.
+pub fn poll() {
+	if unsafe { HANDLER.is_none() } {
+		return;
+	}
+	let last = unsafe { LAST };
+	if let Some((_, next)) = last {
+		if !has_time_passed(next) {
+			return;
+		}
+	}
+	let status = status();
+	let changed = last.map_or(true, |(last, _)| {
+		last.charger_connected != status.charger_connected
+			|| last.charging != status.charging
+			|| (last.percent as i32 - status.percent as i32).abs() >= 5
+	});
+	let reported = match last {
+		Some((last, _)) if !changed => last,
+		_ => status,
+	};
+	unsafe { LAST = Some((reported, get_ticks().wrapping_add(TICKS_PER_SECOND))) }
+	if changed {
+		// Taken out while running, in case the handler replaces or removes
+		// itself
+		if let Some(mut handler) = unsafe { HANDLER.take() } {
+			handler(status);
+			unsafe {
+				if HANDLER.is_none() && LAST.is_some() {
+					HANDLER = Some(handler);
+				}
+			}
+		}
+	}
+}
+
+/// Sent by [`BatteryMonitor::poll`] when the charge crosses a threshold.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum BatteryEvent {
+	/// The charge dropped below the low threshold.
+	Low,
+	/// The charge dropped below the critical threshold. The program should
+	/// save and exit soon.
+	Critical,
+	/// The charge went back above the low threshold, such as when the
+	/// calculator was plugged in.
+	Recovered,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+enum Level {
+	Normal,
+	Low,
+	Critical,
+}
+
+/// Checks the battery periodically, reporting when the charge crosses the low
+/// or critical thresholds.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct BatteryMonitor {
+	low: u8,
+	critical: u8,
+	interval: u32,
+	next_check: u32,
+	level: Level,
+}
+
+impl Default for BatteryMonitor {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl BatteryMonitor {
+	/// Creates a monitor with a low threshold of 15%, and a critical threshold
+	/// of 5%, that checks every 5 seconds.
+	pub fn new() -> Self {
+		Self {
+			low: 15,
+			critical: 5,
+			interval: Duration::from_secs(5).as_ticks(),
+			next_check: get_ticks(),
+			level: Level::Normal,
+		}
+	}
+
+	/// Sets the thresholds, as percentages of a full charge.
+	pub fn thresholds(mut self, low: u8, critical: u8) -> Self {
+		self.low = low;
+		self.critical = critical.min(low);
+		self
+	}
+
+	/// Sets how often the battery is checked.
+	pub fn interval(mut self, interval: Duration) -> Self {
+		self.interval = interval.as_ticks();
+		self
+	}
+
+	/// Checks the battery if the interval has passed, returning an event if
+	/// a threshold was crossed. Each event is only returned once per crossing.
+	pub fn poll(&mut self) -> Option<BatteryEvent> {
+		if !has_time_passed(self.next_check) {
+			return None;
+		}
+		self.next_check = get_ticks().wrapping_add(self.interval);
+		let percent = percent();
+		let level = if percent <= self.critical {
+			Level::Critical
+		} else if percent <= self.low {
+			Level::Low
+		} else {
+			Level::Normal
+		};
+		let event = match (self.level, level) {
+			(Level::Normal, Level::Normal)
+			| (Level::Low, Level::Low)
+			| (Level::Critical, Level::Critical) => None,
+			(_, Level::Critical) => Some(BatteryEvent::Critical),
+			(Level::Normal, Level::Low) => Some(BatteryEvent::Low),
+			// Don't report a drop from critical to low as a new event
+			(Level::Critical, Level::Low) => None,
+			(_, Level::Normal) => Some(BatteryEvent::Recovered),
+		};
+		self.level = level;
+		event
+	}
+}
+
+/// Gets ready for the calculator to turn off: runs the hooks registered with
+/// [`process::at_exit`], flushes output, updates the document browser, and
+/// warns the user that the battery is about to run out.
+///
+/// The program keeps running afterwards. It is up to the program to exit.
+pub fn prepare_shutdown() {
+	process::run_exit_hooks();
+	let _ = stdout().flush();
+	clear_cache();
+	refresh_documents();
+	msg(
+		"Battery low",
+		"The battery is almost empty. Your work has been saved. Please charge or replace the batteries.",
+	);
+}
@@ -0,0 +1,165 @@
+//! DMA
+//!
+//! Copies and fills large buffers, such as whole framebuffers, faster than a
+//! loop on the CPU:
+//!
+//! ```
+//! use ndless::hw::dma;
+//!
+//! // Clear the background, then draw the saved one over it
+//! dma::fill(back_buffer, 0u16);
+//! dma::copy(&background, back_buffer);
+//! ```
+//!
+//! CX models have a DMA controller, which copies while the CPU waits for it.
+//! Other models, and buffers the controller can't handle, are copied with an
+//! unrolled loop of ARM multiple-register loads and stores instead, so these
+//! functions can be used on any calculator.
+
+use core::mem::size_of;
+use core::ptr::{self, read_volatile, write_volatile};
+
+use crate::hw::{clear_cache, hw_revision, Revision};
+
+/// The DMA controller, an ARM PL080
+const BASE: usize = 0xBC00_0000;
+const ENABLED_CHANNELS: *const u32 = (BASE + 0x01C) as *const u32;
+const CONFIGURATION: *mut u32 = (BASE + 0x030) as *mut u32;
+/// Channel 0's registers
+const SOURCE: *mut u32 = (BASE + 0x100) as *mut u32;
+const DESTINATION: *mut u32 = (BASE + 0x104) as *mut u32;
+const LINKED_LIST: *mut u32 = (BASE + 0x108) as *mut u32;
+const CHANNEL_CONTROL: *mut u32 = (BASE + 0x10C) as *mut u32;
+const CHANNEL_CONFIGURATION: *mut u32 = (BASE + 0x110) as *mut u32;
+
+/// The most words one transfer can move
+const MAX_TRANSFER: usize = 0xFFF;
+/// Bursts of 4 words, 32 bit wide on both sides
+const CONTROL_WORDS: u32 = 1 << 12 | 1 << 15 | 2 << 18 | 2 << 21;
+const CONTROL_SOURCE_INCREMENT: u32 = 1 << 26;
+const CONTROL_DESTINATION_INCREMENT: u32 = 1 << 27;
+/// Buffers smaller than this, in bytes, are quicker to copy with the CPU than
+/// to set up the controller for
+const MIN_DMA: usize = 256;
+
+/// Returns true if the calculator has a DMA controller.
+pub fn has_dma() -> bool {
+	matches!(hw_revision(), Revision::Cx | Revision::CxHwW)
+}
+
+/// Copies `src` into `dst`, like
+/// [`copy_from_slice`](https://doc.rust-lang.org/std/primitive.slice.html#method.copy_from_slice).
+///
+/// # Panics
+/// Panics if the slices have different lengths.
+pub fn copy<T: Copy>(src: &[T], dst: &mut [T]) {
+	assert_eq!(
+		src.len(),
+		dst.len(),
+		"DMA copy between slices of different lengths"
+	);
+	let bytes = src.len() * size_of::<T>();
+	let src = src.as_ptr() as *const u8;
+	let dst = dst.as_mut_ptr() as *mut u8;
+	if words_aligned(src, dst, bytes) {
+		let words = bytes / 4;
+		if bytes >= MIN_DMA && transfer(src as u32, dst as u32, words, true) {
+			return;
+		}
+		copy_words(src as *const u32, dst as *mut u32, words);
+	} else {
+		unsafe { ptr::copy_nonoverlapping(src, dst, bytes) }
+	}
+}
+
+/// Sets every element of `dst` to `value`. Only elements of 1, 2 or 4 bytes
+/// can be filled by the DMA controller.
+pub fn fill<T: Copy>(dst: &mut [T], value: T) {
+	let bytes = dst.len() * size_of::<T>();
+	let repeat = match size_of::<T>() {
+		1 => 0x0101_0101,
+		2 => 0x0001_0001,
+		4 => 1,
+		_ => {
+			dst.iter_mut().for_each(|element| *element = value);
+			return;
+		}
+	};
+	let mut pattern = 0u32;
+	unsafe {
+		let value = &value as *const T as *const u8;
+		ptr::copy_nonoverlapping(value, &mut pattern as *mut u32 as *mut u8, size_of::<T>());
+	}
+	let pattern = pattern * repeat;
+	let ptr = dst.as_mut_ptr() as *mut u8;
+	if bytes < MIN_DMA
+		|| !words_aligned(ptr, ptr, bytes)
+		|| !transfer(&pattern as *const u32 as u32, ptr as u32, bytes / 4, false)
+	{
+		dst.iter_mut().for_each(|element| *element = value);
+	}
+}
+
+fn words_aligned(src: *const u8, dst: *const u8, bytes: usize) -> bool {
+	(src as usize | dst as usize | bytes) & 3 == 0
+}
+
+/// Moves `words` words with channel 0 of the DMA controller, waiting for it
+/// to finish. Returns false if the controller can't be used, without moving
+/// anything.
+fn transfer(src: u32, dst: u32, words: usize, increment_source: bool) -> bool {
+	if !has_dma() || unsafe { read_volatile(ENABLED_CHANNELS) } & 1 != 0 {
+		return false;
+	}
+	// The controller reads and writes memory directly, so the cache must be
+	// written back first, and must not keep the old contents afterwards
+	clear_cache();
+	let mut control = CONTROL_WORDS | CONTROL_DESTINATION_INCREMENT;
+	if increment_source {
+		control |= CONTROL_SOURCE_INCREMENT;
+	}
+	let (mut src, mut dst, mut remaining) = (src, dst, words);
+	unsafe {
+		write_volatile(CONFIGURATION, 1);
+		while remaining > 0 {
+			let count = remaining.min(MAX_TRANSFER);
+			write_volatile(SOURCE, src);
+			write_volatile(DESTINATION, dst);
+			write_volatile(LINKED_LIST, 0);
+			write_volatile(CHANNEL_CONTROL, control | count as u32);
+			// Memory to memory, started right away
+			write_volatile(CHANNEL_CONFIGURATION, 1);
+			while read_volatile(CHANNEL_CONFIGURATION) & 1 != 0 {}
+			if increment_source {
+				src += count as u32 * 4;
+			}
+			dst += count as u32 * 4;
+			remaining -= count;
+		}
+	}
+	clear_cache();
+	true
+}
+
+/// Copies words 16 bytes at a time with `ldm` and `stm`, and the rest one at
+/// a time.
+fn copy_words(mut src: *const u32, mut dst: *mut u32, words: usize) {
+	let mut blocks = words / 4;
+	if blocks > 0 {
+		unsafe {
+			llvm_asm!("
+				1:
+				ldmia $0!, {r3, r4, r5, r12}
+				stmia $1!, {r3, r4, r5, r12}
+				subs $2, $2, #1
+				bne 1b
+				"
+				: "+r"(src), "+r"(dst), "+r"(blocks)
+				:
+				: "r3", "r4", "r5", "r12", "cc", "memory"
+				: "volatile"
+			)
+		}
+	}
+	unsafe { ptr::copy_nonoverlapping(src, dst, words % 4) }
+}
@@ -0,0 +1,333 @@
+//! GPIO
+//!
+//! Reads and drives the calculator's general purpose I/O lines, such as the
+//! ones wired to the dock connector:
+//!
+//! ```
+//! use ndless::hw::gpio::{InputPin, OutputPin};
+//!
+//! let mut led = OutputPin::new(LED_LINE).expect("the line is already in use");
+//! let button = InputPin::new(BUTTON_LINE).expect("the line is already in use");
+//! loop {
+//!     led.set(button.is_high());
+//! }
+//! ```
+//!
+//! Inputs can be pulled up or down, so a button only needs to connect the line
+//! to ground or to power, and can call a handler when the line changes:
+//!
+//! ```
+//! use ndless::hw::gpio::{Edge, InputPin, Pull};
+//!
+//! static mut PRESSES: u32 = 0;
+//!
+//! fn on_press(_line: u32) {
+//!     unsafe { PRESSES += 1 }
+//! }
+//!
+//! let mut button = InputPin::new(BUTTON_LINE).unwrap();
+//! button.set_pull(Pull::Up);
+//! // The button connects the line to ground when pressed
+//! button.on_edge(Edge::Falling, on_press);
+//! ```
+//!
+//! Handlers run in an interrupt, through [`interrupt`][crate::interrupt], so
+//! they should be short, and only share data with the rest of the program
+//! inside [`interrupt::free`][crate::interrupt::free].
+//!
+//! Lines are numbered from 0 to [`LINES`] - 1, in ports of 8. Which lines
+//! reach the dock connector depends on the model, and others are used by the
+//! calculator itself, so check the wiring before driving one: the OS doesn't
+//! expect it to change. Each pin puts its line back as it found it when
+//! dropped, and [`restore`] puts back every line still held when the program
+//! exits, including through [`process::exit`][crate::process::exit] or
+//! [`abort`][crate::process::abort].
+
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::interrupt::{self, Irq, Registration};
+
+const BASE: usize = 0x9000_0000;
+/// Each port of 8 lines has its own block of registers
+const PORT_SIZE: usize = 0x40;
+/// A set bit means the line's interrupt was raised. Writing it clears it.
+const INT_STATUS: usize = 0x00;
+const INT_ENABLE: usize = 0x04;
+const INT_RISING: usize = 0x08;
+const INT_FALLING: usize = 0x0C;
+/// A set bit makes the line an input
+const DIRECTION: usize = 0x10;
+const OUTPUT: usize = 0x14;
+const INPUT: usize = 0x18;
+const PULL_UP: usize = 0x1C;
+const PULL_DOWN: usize = 0x20;
+
+/// The number of lines.
+pub const LINES: u32 = 64;
+
+/// The lines held by a pin
+static mut TAKEN: u64 = 0;
+/// The handlers of lines with edge interrupts
+static mut HANDLERS: [Option<fn(u32)>; LINES as usize] = [None; LINES as usize];
+/// Held while any line has a handler
+static mut REGISTRATION: Option<Registration> = None;
+/// The state of each held line when it was taken
+static mut SAVED: [Option<Saved>; LINES as usize] = [None; LINES as usize];
+
+fn register(line: u32, offset: usize) -> *mut u32 {
+	(BASE + (line / 8) as usize * PORT_SIZE + offset) as *mut u32
+}
+
+fn bit(line: u32) -> u32 {
+	1 << (line % 8)
+}
+
+fn read_bit(line: u32, offset: usize) -> bool {
+	unsafe { read_volatile(register(line, offset)) & bit(line) != 0 }
+}
+
+fn write_bit(line: u32, offset: usize, value: bool) {
+	let register = register(line, offset);
+	unsafe {
+		let old = read_volatile(register);
+		let new = if value {
+			old | bit(line)
+		} else {
+			old & !bit(line)
+		};
+		write_volatile(register, new);
+	}
+}
+
+/// A pull resistor on an input.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Pull {
+	/// The line floats when nothing drives it.
+	None,
+	/// The line reads high when nothing drives it.
+	Up,
+	/// The line reads low when nothing drives it.
+	Down,
+}
+
+/// The changes that call an edge handler.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Edge {
+	/// Low to high
+	Rising,
+	/// High to low
+	Falling,
+	Both,
+}
+
+fn on_interrupt() -> bool {
+	let mut handled = false;
+	for port in 0..LINES / 8 {
+		let line = port * 8;
+		let pending = unsafe {
+			let pending = read_volatile(register(line, INT_STATUS))
+				& read_volatile(register(line, INT_ENABLE))
+				& 0xFF;
+			write_volatile(register(line, INT_STATUS), pending);
+			pending
+		};
+		for bit in 0..8 {
+			if pending & 1 << bit == 0 {
+				continue;
+			}
+			if let Some(handler) = unsafe { HANDLERS[(line + bit) as usize] } {
+				handler(line + bit);
+				handled = true;
+			}
+		}
+	}
+	handled
+}
+
+/// The state of a line when it was taken, put back when it's released
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+struct Saved {
+	input: bool,
+	high: bool,
+	pull_up: bool,
+	pull_down: bool,
+	int_enable: bool,
+	int_rising: bool,
+	int_falling: bool,
+}
+
+/// A line held by a pin, released when dropped
+#[derive(Eq, PartialEq, Debug)]
+struct Line {
+	line: u32,
+}
+
+impl Line {
+	fn take(line: u32) -> Option<Self> {
+		if line >= LINES {
+			return None;
+		}
+		unsafe {
+			if TAKEN & 1 << line != 0 {
+				return None;
+			}
+			TAKEN |= 1 << line;
+			SAVED[line as usize] = Some(Saved {
+				input: read_bit(line, DIRECTION),
+				high: read_bit(line, OUTPUT),
+				pull_up: read_bit(line, PULL_UP),
+				pull_down: read_bit(line, PULL_DOWN),
+				int_enable: read_bit(line, INT_ENABLE),
+				int_rising: read_bit(line, INT_RISING),
+				int_falling: read_bit(line, INT_FALLING),
+			});
+		}
+		Some(Self { line })
+	}
+}
+
+impl Drop for Line {
+	fn drop(&mut self) {
+		release(self.line);
+	}
+}
+
+/// Puts a held line back as it was when it was taken
+fn release(line: u32) {
+	remove_edge_handler(line);
+	if let Some(saved) = unsafe { SAVED[line as usize].take() } {
+		write_bit(line, OUTPUT, saved.high);
+		write_bit(line, DIRECTION, saved.input);
+		write_bit(line, PULL_UP, saved.pull_up);
+		write_bit(line, PULL_DOWN, saved.pull_down);
+		write_bit(line, INT_RISING, saved.int_rising);
+		write_bit(line, INT_FALLING, saved.int_falling);
+		write_bit(line, INT_ENABLE, saved.int_enable);
+	}
+	unsafe { TAKEN &= !(1 << line) }
+}
+
+/// Puts every line still held by a pin back as it was, and removes their edge
+/// handlers. Called by [`process::exit`][crate::process::exit] and
+/// [`abort`][crate::process::abort], which don't run destructors.
+pub fn restore() {
+	for line in 0..LINES {
+		if unsafe { TAKEN } & 1 << line != 0 {
+			release(line);
+		}
+	}
+}
+
+/// A line read as an input.
+#[derive(Debug)]
+pub struct InputPin {
+	line: Line,
+}
+
+impl InputPin {
+	/// Makes `line` an input. Returns `None` if it's out of range, or already
+	/// held by another pin.
+	pub fn new(line: u32) -> Option<Self> {
+		let line = Line::take(line)?;
+		write_bit(line.line, DIRECTION, true);
+		Some(Self { line })
+	}
+
+	pub fn line(&self) -> u32 {
+		self.line.line
+	}
+
+	pub fn is_high(&self) -> bool {
+		read_bit(self.line.line, INPUT)
+	}
+
+	pub fn is_low(&self) -> bool {
+		!self.is_high()
+	}
+
+	pub fn set_pull(&mut self, pull: Pull) {
+		let line = self.line.line;
+		write_bit(line, PULL_UP, pull == Pull::Up);
+		write_bit(line, PULL_DOWN, pull == Pull::Down);
+	}
+
+	/// Calls `handler` with the line's number whenever it changes in the way
+	/// given by `edge`, replacing any handler set before. Returns false if the
+	/// GPIO interrupt can't be registered.
+	pub fn on_edge(&mut self, edge: Edge, handler: fn(u32)) -> bool {
+		let line = self.line.line;
+		interrupt::free(|| unsafe {
+			if REGISTRATION.is_none() {
+				REGISTRATION = interrupt::register(Irq::GPIO, on_interrupt);
+				if REGISTRATION.is_none() {
+					return false;
+				}
+			}
+			HANDLERS[line as usize] = Some(handler);
+			write_bit(line, INT_RISING, edge != Edge::Falling);
+			write_bit(line, INT_FALLING, edge != Edge::Rising);
+			write_volatile(register(line, INT_STATUS), bit(line));
+			write_bit(line, INT_ENABLE, true);
+			true
+		})
+	}
+
+	/// Removes the handler set with [`on_edge`][InputPin::on_edge].
+	pub fn remove_edge_handler(&mut self) {
+		remove_edge_handler(self.line.line);
+	}
+}
+
+fn remove_edge_handler(line: u32) {
+	interrupt::free(|| unsafe {
+		if HANDLERS[line as usize].take().is_none() {
+			return;
+		}
+		write_bit(line, INT_ENABLE, false);
+		if HANDLERS.iter().all(Option::is_none) {
+			REGISTRATION = None;
+		}
+	})
+}
+
+/// A line driven as an output.
+#[derive(Debug)]
+pub struct OutputPin {
+	line: Line,
+}
+
+impl OutputPin {
+	/// Makes `line` an output, starting low. Returns `None` if it's out of
+	/// range, or already held by another pin.
+	pub fn new(line: u32) -> Option<Self> {
+		let line = Line::take(line)?;
+		write_bit(line.line, OUTPUT, false);
+		write_bit(line.line, DIRECTION, false);
+		Some(Self { line })
+	}
+
+	pub fn line(&self) -> u32 {
+		self.line.line
+	}
+
+	pub fn set(&mut self, high: bool) {
+		write_bit(self.line.line, OUTPUT, high);
+	}
+
+	pub fn set_high(&mut self) {
+		self.set(true);
+	}
+
+	pub fn set_low(&mut self) {
+		self.set(false);
+	}
+
+	/// Returns true if the line is being driven high.
+	pub fn is_set_high(&self) -> bool {
+		read_bit(self.line.line, OUTPUT)
+	}
+
+	pub fn toggle(&mut self) {
+		self.set(!self.is_set_high());
+	}
+}
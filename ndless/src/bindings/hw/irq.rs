@@ -0,0 +1,127 @@
+//! Hooks into the IRQ vector
+//!
+//! The OS handles interrupts itself. Handlers registered here run first, and
+//! the OS's handler is called for any interrupt that they don't handle.
+
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::hw::has_colors;
+
+/// The address that the IRQ exception vector jumps to is stored here.
+const IRQ_VECTOR: *mut u32 = 0x38 as *mut u32;
+
+global_asm!(
+	"
+	.text
+	.arm
+	.global __ndless_irq_entry
+__ndless_irq_entry:
+	sub lr, lr, #4
+	stmfd sp!, {r0-r12, lr}
+	mov r0, sp
+	bl __ndless_irq_dispatch
+	cmp r0, #0
+	ldmfd sp!, {r0-r12, lr}
+	movsne pc, lr
+	add lr, lr, #4
+	ldr pc, __ndless_irq_orig
+	.global __ndless_irq_orig
+__ndless_irq_orig:
+	.word 0
+"
+);
+
+extern "C" {
+	fn __ndless_irq_entry();
+	/// The OS's handler, stored next to the entry point so that it can be
+	/// loaded relative to the program counter
+	static mut __ndless_irq_orig: u32;
+}
+
+/// The registers of the interrupted code, saved on the IRQ stack.
+#[repr(C)]
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub(crate) struct Frame {
+	pub r: [u32; 13],
+	/// Where the interrupted code will resume. Changing this makes the
+	/// interrupted code continue somewhere else.
+	pub pc: u32,
+}
+
+/// Returns true if the interrupt was handled.
+pub(crate) type Handler = fn(&mut Frame) -> bool;
+
+static mut HANDLERS: [Option<Handler>; 4] = [None; 4];
+
+#[no_mangle]
+extern "C" fn __ndless_irq_dispatch(frame: &mut Frame) -> u32 {
+	let mut handled = false;
+	for handler in unsafe { HANDLERS.iter() }.flatten() {
+		handled |= handler(frame);
+	}
+	handled as u32
+}
+
+/// Registers a handler, hooking the IRQ vector if this is the first one.
+/// Returns false if there is no room for more handlers.
+pub(crate) fn register(handler: Handler) -> bool {
+	unsafe {
+		let slot = match HANDLERS.iter_mut().find(|slot| slot.is_none()) {
+			Some(slot) => slot,
+			None => return false,
+		};
+		*slot = Some(handler);
+		if read_volatile(IRQ_VECTOR) != __ndless_irq_entry as usize as u32 {
+			__ndless_irq_orig = read_volatile(IRQ_VECTOR);
+			write_volatile(IRQ_VECTOR, __ndless_irq_entry as usize as u32);
+		}
+	}
+	true
+}
+
+/// Unregisters a handler, restoring the OS's IRQ vector if it was the last
+/// one. This must happen before the program exits.
+pub(crate) fn unregister(handler: Handler) {
+	unsafe {
+		for slot in HANDLERS.iter_mut() {
+			if *slot == Some(handler) {
+				*slot = None;
+			}
+		}
+		if HANDLERS.iter().all(Option::is_none)
+			&& read_volatile(IRQ_VECTOR) == __ndless_irq_entry as usize as u32
+		{
+			write_volatile(IRQ_VECTOR, __ndless_irq_orig);
+		}
+	}
+}
+
+/// Unmasks an interrupt line in the interrupt controller.
+pub(crate) fn enable(line: u32) {
+	let enable = if has_colors() {
+		0xDC00_0010
+	} else {
+		0xDC00_0008
+	} as *mut u32;
+	unsafe { write_volatile(enable, 1 << line) }
+}
+
+/// Returns true if an interrupt line is unmasked in the interrupt controller.
+pub(crate) fn is_enabled(line: u32) -> bool {
+	let enabled = if has_colors() {
+		0xDC00_0010
+	} else {
+		0xDC00_0008
+	} as *const u32;
+	unsafe { read_volatile(enabled) & (1 << line) != 0 }
+}
+
+/// Masks an interrupt line in the interrupt controller.
+pub(crate) fn disable(line: u32) {
+	let disable = if has_colors() {
+		0xDC00_0014
+	} else {
+		0xDC00_000C
+	} as *mut u32;
+	unsafe { write_volatile(disable, 1 << line) }
+}
@@ -0,0 +1,240 @@
+//! Keypad controller
+//!
+//! Direct access to the keypad matrix, for programs that need more than
+//! [`input`][crate::input] gives, such as reading many keys at exactly the
+//! same moment, or timing presses more precisely than once per frame.
+//!
+//! The controller scans the keys as a grid of [`ROWS`] rows of up to
+//! [`COLUMNS`] columns, and keeps the result of the last scan in its data
+//! registers. [`rows`] reads them all at once, with a set bit for each key
+//! that is down, on every model:
+//!
+//! ```
+//! use ndless::hw::keypad;
+//! use ndless::input::Key;
+//!
+//! let (row, col) = keypad::position(Key::Enter).unwrap();
+//! let matrix = keypad::rows();
+//! let down: u32 = matrix.iter().map(|row| row.count_ones()).sum();
+//! if matrix[row] & (1 << col) != 0 {
+//!     // Enter is down, along with `down - 1` other keys
+//! }
+//! ```
+//!
+//! Changing how the controller scans goes through a [`Controller`], which
+//! puts the OS's settings back when dropped, so that the keypad keeps working
+//! after the program exits. [`restore`] does the same when the program exits,
+//! including through [`process::exit`][crate::process::exit],
+//! [`abort`][crate::process::abort] or a panic.
+
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::hw::{has_touchpad, is_classic};
+use crate::input::Key;
+
+const BASE: usize = 0x900E_0000;
+const CONTROL: *mut u32 = BASE as *mut u32;
+const INT_STATUS: *mut u32 = (BASE + 0x08) as *mut u32;
+const INT_MASK: *mut u32 = (BASE + 0x0C) as *mut u32;
+const DATA: *const u16 = (BASE + 0x10) as *const u16;
+
+/// The number of rows in the matrix.
+pub const ROWS: usize = 8;
+/// The number of columns in the matrix. Not every row uses all of them.
+pub const COLUMNS: usize = 11;
+
+/// Interrupt sent when a scan finishes.
+pub const INT_SCAN_DONE: u32 = 1 << 0;
+/// Interrupt sent when a scan differs from the one before.
+pub const INT_DATA_CHANGED: u32 = 1 << 1;
+/// Interrupt sent when any key is pressed.
+pub const INT_KEY_PRESSED: u32 = 1 << 2;
+
+/// The OS's control and interrupt mask registers, saved before a
+/// [`Controller`] first changes them
+static mut SAVED: Option<(u32, u32)> = None;
+
+fn save() {
+	unsafe {
+		if SAVED.is_none() {
+			SAVED = Some((read_volatile(CONTROL), read_volatile(INT_MASK)));
+		}
+	}
+}
+
+/// Puts back the controller settings the OS had, if a [`Controller`] changed
+/// them. This is done when the `Controller` is dropped and when the program
+/// exits, so it rarely needs to be called directly.
+pub fn restore() {
+	if let Some((control, mask)) = unsafe { SAVED.take() } {
+		unsafe {
+			write_volatile(CONTROL, control);
+			write_volatile(INT_MASK, mask);
+		}
+	}
+}
+
+/// Set in rows read from a classic keypad, where a key reads as 0 when it is
+/// down
+fn inverted() -> bool {
+	is_classic()
+}
+
+/// Returns a row from the last scan, with bit `n` set if the key in column
+/// `n` is down. Panics if `row` isn't less than [`ROWS`].
+pub fn row(row: usize) -> u16 {
+	assert!(row < ROWS, "keypad row {} out of range", row);
+	decode(unsafe { read_volatile(DATA.add(row)) })
+}
+
+/// Reads the data registers as they are, without asking the OS which model
+/// this is, so that it can be called from an interrupt handler. Each row must
+/// be passed through [`decode`] before use.
+pub(crate) fn raw_rows() -> [u16; ROWS] {
+	let mut rows = [0; ROWS];
+	for (index, value) in rows.iter_mut().enumerate() {
+		*value = unsafe { read_volatile(DATA.add(index)) };
+	}
+	rows
+}
+
+/// Turns a data register's value into a row like [`row`] returns.
+pub(crate) fn decode(value: u16) -> u16 {
+	let mask = (1 << COLUMNS) - 1;
+	if inverted() {
+		!value & mask
+	} else {
+		value & mask
+	}
+}
+
+/// Returns every row from the last scan. See [`row`].
+pub fn rows() -> [u16; ROWS] {
+	let mut rows = [0; ROWS];
+	for (index, value) in rows.iter_mut().enumerate() {
+		*value = row(index);
+	}
+	rows
+}
+
+/// Returns the row and column of `key` in the matrix of the attached keypad,
+/// or `None` if it isn't part of the matrix, like the touchpad's arrows.
+pub fn position(key: Key) -> Option<(usize, usize)> {
+	let raw = key.raw()?;
+	if raw.tpad_arrow != 0 && has_touchpad() {
+		return None;
+	}
+	let (offset, mask) = if has_touchpad() {
+		(raw.tpad_row, raw.tpad_col)
+	} else {
+		(raw.row, raw.col)
+	};
+	// Rows are 16 bit registers, starting at the first data register
+	let row = (offset as usize).checked_sub(0x10)? / 2;
+	if row >= ROWS || mask == 0 {
+		return None;
+	}
+	Some((row, mask.trailing_zeros() as usize))
+}
+
+/// Returns true if the key at `row` and `col` was down in the last scan.
+pub fn is_down(row: usize, col: usize) -> bool {
+	col < COLUMNS && self::row(row) & (1 << col) != 0
+}
+
+/// The interrupts that are pending, as `INT_*` flags.
+pub fn interrupt_status() -> u32 {
+	unsafe { read_volatile(INT_STATUS) & 0b111 }
+}
+
+/// The interrupts that are enabled, as `INT_*` flags.
+pub fn interrupt_mask() -> u32 {
+	unsafe { read_volatile(INT_MASK) & 0b111 }
+}
+
+/// How the controller scans the keypad.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum ScanMode {
+	/// Not scanning. The data registers keep their last values.
+	Idle,
+	/// Scan once, then go idle.
+	Single,
+	/// Scan over and over. This is what the OS uses.
+	Continuous,
+}
+
+/// Changes the controller's settings, and restores the OS's when dropped.
+/// See the [module documentation][self].
+#[must_use = "the settings are restored when the controller is dropped"]
+#[derive(Debug)]
+pub struct Controller {
+	_private: (),
+}
+
+impl Controller {
+	pub fn new() -> Self {
+		Self { _private: () }
+	}
+
+	pub fn scan_mode(&self) -> ScanMode {
+		match unsafe { read_volatile(CONTROL) } & 0b11 {
+			0 => ScanMode::Idle,
+			1 => ScanMode::Single,
+			_ => ScanMode::Continuous,
+		}
+	}
+
+	pub fn set_scan_mode(&mut self, mode: ScanMode) {
+		let bits = match mode {
+			ScanMode::Idle => 0,
+			ScanMode::Single => 1,
+			ScanMode::Continuous => 2,
+		};
+		save();
+		unsafe { write_volatile(CONTROL, read_volatile(CONTROL) & !0b11 | bits) }
+	}
+
+	/// Sets the delay between scanning each row, and between each scan, in
+	/// controller clock cycles. Shorter delays notice presses sooner, but can
+	/// make keys in neighbouring rows read as pressed. The row delay is 14
+	/// bits, and is cut to fit.
+	pub fn set_delays(&mut self, row_delay: u16, scan_delay: u16) {
+		let mode = unsafe { read_volatile(CONTROL) } & 0b11;
+		let value = u32::from(scan_delay) << 16 | u32::from(row_delay & 0x3FFF) << 2 | mode;
+		save();
+		unsafe { write_volatile(CONTROL, value) }
+	}
+
+	/// Enables the interrupts in `mask`, as `INT_*` flags, and disables the
+	/// others.
+	pub fn set_interrupt_mask(&mut self, mask: u32) {
+		save();
+		unsafe { write_volatile(INT_MASK, mask & 0b111) }
+	}
+
+	/// Clears the pending interrupts in `mask`.
+	pub fn acknowledge(&mut self, mask: u32) {
+		unsafe { write_volatile(INT_STATUS, mask & 0b111) }
+	}
+
+	/// Starts a scan, and waits for it to finish. Afterwards, the controller
+	/// is left idle.
+	pub fn scan_now(&mut self) {
+		self.acknowledge(INT_SCAN_DONE);
+		self.set_scan_mode(ScanMode::Single);
+		while interrupt_status() & INT_SCAN_DONE == 0 {}
+		self.acknowledge(INT_SCAN_DONE);
+	}
+}
+
+impl Default for Controller {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Drop for Controller {
+	fn drop(&mut self) {
+		restore();
+	}
+}
@@ -0,0 +1,114 @@
+//! Status LED
+//!
+//! The CX II has an LED above the screen, which the OS uses to show that the
+//! battery is charging. Programs can use it to show activity without drawing
+//! anything:
+//!
+//! ```
+//! use ndless::hw::led::{self, Color};
+//! use ndless::time::Duration;
+//!
+//! // Blink green while sending
+//! led::blink_pattern(&[Color::Green, Color::Off], Duration::from_millis(250));
+//! send_file();
+//! led::restore();
+//! ```
+//!
+//! Patterns run from [`cooperative::dispatch`][crate::timer::cooperative::dispatch], so they keep
+//! going while the program sleeps or calls it. On other models, these
+//! functions do nothing.
+//!
+//! The LED is given back to the OS with [`restore`] when the program exits,
+//! including through [`process::exit`][crate::process::exit] or
+//! [`abort`][crate::process::abort].
+
+use alloc::vec::Vec;
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::hw::{hw_revision, Revision};
+use crate::time::Duration;
+use crate::timer::cooperative::{every, IntervalHandle};
+
+const CONTROL: *mut u32 = 0x9011_0B00 as *mut u32;
+const GREEN: u32 = 1;
+const RED: u32 = 1 << 1;
+
+/// The control register before it was first changed
+static mut SAVED: Option<u32> = None;
+static mut PATTERN: Option<IntervalHandle> = None;
+
+/// A color the LED can show.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Color {
+	Off,
+	Green,
+	Red,
+	/// Green and red together
+	Yellow,
+}
+
+impl Color {
+	fn bits(self) -> u32 {
+		match self {
+			Color::Off => 0,
+			Color::Green => GREEN,
+			Color::Red => RED,
+			Color::Yellow => GREEN | RED,
+		}
+	}
+}
+
+/// Returns true if the calculator has an LED.
+pub fn is_supported() -> bool {
+	hw_revision() == Revision::CxII
+}
+
+fn write(color: Color) {
+	unsafe {
+		let value = read_volatile(CONTROL);
+		if SAVED.is_none() {
+			SAVED = Some(value);
+		}
+		write_volatile(CONTROL, value & !(GREEN | RED) | color.bits());
+	}
+}
+
+/// Shows `color`, stopping any pattern.
+pub fn set_color(color: Color) {
+	if !is_supported() {
+		return;
+	}
+	unsafe { PATTERN = None }
+	write(color);
+}
+
+/// Shows each color in `colors` for `step`, over and over, replacing any
+/// pattern already running.
+pub fn blink_pattern(colors: &[Color], step: Duration) {
+	if !is_supported() {
+		return;
+	}
+	let colors: Vec<Color> = colors.to_vec();
+	let mut index = 0;
+	if let Some(&first) = colors.first() {
+		write(first);
+	}
+	let handle = every(step, move || {
+		if colors.is_empty() {
+			return;
+		}
+		index = (index + 1) % colors.len();
+		write(colors[index]);
+	});
+	unsafe { PATTERN = Some(handle) }
+}
+
+/// Stops any pattern, and gives the LED back to the OS.
+pub fn restore() {
+	unsafe {
+		PATTERN = None;
+		if let Some(value) = SAVED.take() {
+			write_volatile(CONTROL, value);
+		}
+	}
+}
@@ -0,0 +1,193 @@
+//! CPU clock and turning the screen off
+//!
+//! Programs start with the CPU at full speed: 132MHz on CX models, and 396MHz
+//! on the CX II. A program that spends most of its time waiting, such as on a
+//! menu, can slow the CPU down and [`idle`] between frames to save battery:
+//!
+//! ```
+//! use ndless::hw::power::{self, Speed};
+//!
+//! power::set_cpu_speed(Speed::Quarter);
+//! while !start_pressed() {
+//!     draw_menu();
+//!     power::idle();
+//! }
+//! power::set_cpu_speed(Speed::Full);
+//! ```
+//!
+//! The OS doesn't put the speed back when a program exits, so
+//! [`restore_cpu_speed`] is called when the program exits, including through
+//! [`process::exit`][crate::process::exit], [`abort`][crate::process::abort]
+//! or a panic.
+//!
+//! ## Turning the screen off
+//! Batch jobs that don't need to show anything can save battery by turning
+//! off the LCD and dimming the backlight while they run:
+//!
+//! ```
+//! use ndless::hw::power;
+//!
+//! power::screen_off();
+//! for file in files {
+//!     convert(file);
+//!     ndless::timer::cooperative::dispatch();
+//! }
+//! power::screen_on();
+//! ```
+//!
+//! The screen comes back on when any key is pressed, from a
+//! [`cooperative::every`][crate::timer::cooperative::every] callback, and when the program
+//! exits, including through [`process::exit`][crate::process::exit] or a
+//! panic.
+
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::hw::backlight;
+use crate::input::any_key_pressed;
+use crate::time::{calibrate, cpu_mhz, delay_us, Duration};
+use crate::timer::cooperative::{every, IntervalHandle};
+use crate::timer::{get_ticks, has_time_passed, is_running, TICKS_PER_MILLISECOND};
+use crate::video::LCD_CONTROL;
+
+pub use super::idle;
+
+/// Clock control. Bits 12-14 hold the CPU's divisor of the base clock, minus
+/// one.
+const CLOCK_CONTROL: *mut u32 = 0x900B_0000 as *mut u32;
+/// Writing 4 applies a change to [`CLOCK_CONTROL`]
+const CLOCK_APPLY: *mut u32 = 0x900B_000C as *mut u32;
+const DIVISOR_SHIFT: u32 = 12;
+const DIVISOR_MASK: u32 = 0b111 << DIVISOR_SHIFT;
+
+/// The clock control value the OS set, saved before it's first changed
+static mut OS_CLOCK: Option<u32> = None;
+
+/// A CPU speed, as a fraction of the speed programs start at.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Speed {
+	Full,
+	Half,
+	Quarter,
+}
+
+impl Speed {
+	fn divisor(self) -> u32 {
+		match self {
+			Speed::Full => 1,
+			Speed::Half => 2,
+			Speed::Quarter => 4,
+		}
+	}
+}
+
+/// Returns true if the CPU speed can be changed. The CX II's clocks work
+/// differently, and aren't supported yet.
+pub fn can_set_cpu_speed() -> bool {
+	unsafe { ndless_sys::hwtype() < 2 }
+}
+
+/// Changes the CPU speed. Returns false, leaving the speed unchanged, if it
+/// can't be changed on this calculator.
+///
+/// Only the CPU is slowed down, so timers and the screen run as before.
+/// Busy-wait delays are calibrated again for the new speed.
+pub fn set_cpu_speed(speed: Speed) -> bool {
+	if !can_set_cpu_speed() {
+		return false;
+	}
+	let os = unsafe {
+		if OS_CLOCK.is_none() {
+			OS_CLOCK = Some(read_volatile(CLOCK_CONTROL));
+		}
+		OS_CLOCK.unwrap()
+	};
+	let divisor = ((os & DIVISOR_MASK) >> DIVISOR_SHIFT) + 1;
+	let divisor = (divisor * speed.divisor()).min(8);
+	write_clock(os & !DIVISOR_MASK | (divisor - 1) << DIVISOR_SHIFT);
+	true
+}
+
+/// Puts the CPU back to the speed the OS set, if [`set_cpu_speed`] changed it.
+pub fn restore_cpu_speed() {
+	if let Some(os) = unsafe { OS_CLOCK.take() } {
+		write_clock(os);
+	}
+}
+
+fn write_clock(value: u32) {
+	unsafe {
+		write_volatile(CLOCK_CONTROL, value);
+		write_volatile(CLOCK_APPLY, 4);
+	}
+	calibrate();
+}
+
+/// Returns the speed the CPU runs at, in MHz. This is measured against the
+/// 32768Hz timer, so it is approximate.
+pub fn current_speed() -> u32 {
+	cpu_mhz()
+}
+
+/// Bits of the LCD controller's control register: the controller itself, and
+/// the power to the panel
+const LCD_ENABLE: u32 = 1 << 0;
+const LCD_POWER: u32 = 1 << 11;
+/// How long the panel needs between the controller and power being switched
+const LCD_POWER_DELAY: u32 = 20;
+/// How often [`screen_off`] checks the keypad
+const WAKE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The LCD control register and brightness saved by [`screen_off`], and the
+/// callback that turns the screen back on when a key is pressed
+static mut SCREEN_OFF: Option<(u32, u8, IntervalHandle)> = None;
+
+fn lcd_delay() {
+	if !is_running() {
+		return delay_us(LCD_POWER_DELAY * 1000);
+	}
+	let until = get_ticks().wrapping_add(LCD_POWER_DELAY * TICKS_PER_MILLISECOND);
+	while !has_time_passed(until) {}
+}
+
+/// Turns off the LCD and dims the backlight as far as it goes, leaving the CPU
+/// running. Does nothing if the screen is already off. See the
+/// [module documentation][self].
+///
+/// The key that turns the screen back on is still seen by the program.
+pub fn screen_off() {
+	if is_screen_off() {
+		return;
+	}
+	let control = unsafe { read_volatile(LCD_CONTROL) };
+	let brightness = backlight::get();
+	let wake = every(WAKE_INTERVAL, || {
+		if any_key_pressed() {
+			screen_on();
+		}
+	});
+	unsafe { SCREEN_OFF = Some((control, brightness, wake)) }
+	backlight::set(0);
+	// The panel is powered off before the controller, and on after it
+	unsafe { write_volatile(LCD_CONTROL, control & !LCD_POWER) }
+	lcd_delay();
+	unsafe { write_volatile(LCD_CONTROL, control & !(LCD_POWER | LCD_ENABLE)) }
+}
+
+/// Turns the screen back on after [`screen_off`], with the brightness it had.
+/// Does nothing if the screen isn't off.
+pub fn screen_on() {
+	// Dropping the callback from inside it is fine, see cooperative::every
+	let (control, brightness, _wake) = match unsafe { SCREEN_OFF.take() } {
+		Some(saved) => saved,
+		None => return,
+	};
+	unsafe { write_volatile(LCD_CONTROL, control & !LCD_POWER) }
+	lcd_delay();
+	unsafe { write_volatile(LCD_CONTROL, control) }
+	backlight::set(brightness);
+}
+
+/// Returns true if [`screen_off`] turned the screen off.
+pub fn is_screen_off() -> bool {
+	unsafe { SCREEN_OFF.is_some() }
+}
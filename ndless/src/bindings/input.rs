@@ -7,6 +7,29 @@ use alloc::vec::Vec;
 
 use crate::alloc::borrow::Borrow;
 use crate::bindings::input::raw_keys::*;
+use crate::time::record_idle;
+use crate::timer::cooperative::{dispatch, next_due};
+use crate::timer::{configure_sleep, disable_sleep, get_ticks};
+
+pub mod button;
+pub mod events;
+pub mod idle;
+pub mod keymap;
+pub mod layout;
+pub mod line;
+pub mod on_key;
+pub mod record;
+pub mod shortcuts;
+
+pub use button::Button;
+pub use events::{poll_events, KeyEvent, KeyState};
+pub use idle::{idle_time, on_idle};
+pub use keymap::KeyMap;
+pub use layout::{keypad, Keypad};
+pub use line::{get_char, read_line};
+pub use on_key::on_key_handler;
+pub use record::{Player, Recorder};
+pub use shortcuts::Shortcuts;
 
 /// Keys available in the Nspire keypad, including the touchpad.
 #[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
@@ -129,6 +152,31 @@ pub enum Key {
 }
 
 impl Key {
+	/// Returns every key, on every keypad.
+	pub fn all() -> impl Iterator<Item = Key> + 'static {
+		KEY_MAPPING.iter().map(|(_, key)| *key)
+	}
+
+	/// Looks up a key by the name its `Debug` implementation prints, such as
+	/// `"Esc"`. Used to store keys in text files.
+	pub(crate) fn named(name: &str) -> Option<Key> {
+		Key::all().find(|key| alloc::format!("{:?}", key) == name)
+	}
+
+	/// The key's entry in the table used by `isKeyPressed`
+	pub(crate) fn raw(self) -> Option<ndless_sys::t_key> {
+		KEY_MAPPING
+			.iter()
+			.find(|(_, key)| *key == self)
+			.map(|(raw_key, _)| *raw_key)
+	}
+
+	/// Returns true if the key exists on the attached keypad. See
+	/// [`layout`] for more about each keypad.
+	pub fn is_available(self) -> bool {
+		layout::current().has_key(self)
+	}
+
 	fn from_arrow(arrow: u8) -> Option<Self> {
 		match u32::from(arrow) {
 			ndless_sys::tpad_arrow_TPAD_ARROW_CLICK => Some(Key::Click),
@@ -451,6 +499,16 @@ pub fn is_key_pressed(key: impl Borrow<Key>) -> bool {
 		)
 }
 
+/// Returns true if `key` is pressed. The same as [`is_key_pressed`].
+pub fn is_pressed(key: Key) -> bool {
+	is_key_pressed(key)
+}
+
+/// Returns the keys that are pressed. The same as [`iter_keys`].
+pub fn keys_pressed() -> impl Iterator<Item = Key> + 'static {
+	iter_keys()
+}
+
 /// Returns true if any buttons are currently pressed, including pushing the
 /// touchpad.
 pub fn any_key_pressed() -> bool {
@@ -462,14 +520,46 @@ pub fn key_on_pressed() -> bool {
 	unsafe { ndless_sys::on_key_pressed() > 0 }
 }
 
-/// Suspends the program until [`any_key_pressed`] returns true.
-pub fn wait_key_pressed() {
-	unsafe { ndless_sys::wait_key_pressed() }
+/// How often the keypad is checked while waiting, in case its interrupt isn't
+/// enabled. About 10ms.
+const SCAN_INTERVAL: u32 = 328;
+
+/// Idles the CPU until the next interrupt, such as from the keypad, or until
+/// [`SCAN_INTERVAL`] passes. Timer callbacks are run while waiting.
+pub(crate) fn idle_until_input() {
+	configure_sleep(
+		next_due()
+			.map_or(SCAN_INTERVAL, |due| due.min(SCAN_INTERVAL))
+			.max(1),
+	);
+	let idle_start = get_ticks();
+	crate::hw::idle();
+	record_idle(get_ticks().wrapping_sub(idle_start));
+	dispatch();
+	disable_sleep();
+}
+
+/// Suspends the program until a key is pressed, and returns it. If several
+/// keys are pressed at once, one of them is returned.
+///
+/// The CPU is idled between checks, rather than busy-waiting, to save
+/// battery. Callbacks registered with [`cooperative::every`][crate::timer::cooperative::every]
+/// are run while waiting.
+pub fn wait_key_pressed() -> Key {
+	loop {
+		if let Some(key) = iter_keys().next() {
+			return key;
+		}
+		idle_until_input();
+	}
 }
 
-/// Suspends the program until [`any_key_pressed`]  returns false.
+/// Suspends the program until [`any_key_pressed`] returns false. Like
+/// [`wait_key_pressed`], the CPU is idled while waiting.
 pub fn wait_no_key_pressed() {
-	unsafe { ndless_sys::wait_no_key_pressed() }
+	while any_key_pressed() {
+		idle_until_input();
+	}
 }
 
 pub mod touchpad {
@@ -0,0 +1,114 @@
+//! Buttons
+//!
+//! A [`Button`] follows one key from frame to frame, so a game can tell when
+//! it was first pressed or released, and how long it has been held, without
+//! keeping track itself:
+//!
+//! ```
+//! use ndless::input::{Button, Key};
+//! use ndless::time::Duration;
+//!
+//! let mut jump = Button::new(Key::Up);
+//! loop {
+//!     jump.update();
+//!     if jump.just_pressed() {
+//!         start_jump();
+//!     } else if jump.is_pressed() && jump.held_for() < Duration::from_millis(300) {
+//!         // Holding the key jumps higher
+//!         extend_jump();
+//!     }
+//!     // ...
+//! }
+//! ```
+//!
+//! Call [`Button::update`] exactly once per frame. The other methods only
+//! report what was seen then, so they give the same answer for the whole
+//! frame, however many times they are called.
+
+use crate::input::{is_pressed, Key};
+use crate::time::Duration;
+use crate::timer::{get_ticks, Ticks};
+
+/// The state of a key, updated once per frame. See the
+/// [module documentation][self].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Button {
+	key: Key,
+	/// Ticks after a change during which the key is not read again
+	debounce: u32,
+	pressed: bool,
+	was_pressed: bool,
+	/// When `pressed` last changed
+	changed_at: u32,
+	updated_at: u32,
+}
+
+impl Button {
+	/// Follows `key`, with a debounce time of 5ms.
+	pub fn new(key: Key) -> Self {
+		Self {
+			key,
+			debounce: Duration::from_millis(5).as_ticks(),
+			pressed: false,
+			was_pressed: false,
+			changed_at: get_ticks(),
+			updated_at: get_ticks(),
+		}
+	}
+
+	/// Sets how long to ignore the key for after it is pressed or released, so
+	/// contacts that bounce aren't reported as several presses. Changes are
+	/// still reported as soon as they're seen.
+	pub fn debounce(mut self, debounce: Duration) -> Self {
+		self.debounce = debounce.as_ticks();
+		self
+	}
+
+	pub fn key(&self) -> Key {
+		self.key
+	}
+
+	/// Reads the key. Call this once per frame, before the other methods.
+	pub fn update(&mut self) {
+		let now = get_ticks();
+		self.was_pressed = self.pressed;
+		self.updated_at = now;
+		if now.wrapping_sub(self.changed_at) >= self.debounce
+			&& is_pressed(self.key) != self.pressed
+		{
+			self.pressed = !self.pressed;
+			self.changed_at = now;
+		}
+	}
+
+	/// Returns true if the key is held down.
+	pub fn is_pressed(&self) -> bool {
+		self.pressed
+	}
+
+	/// Returns true if the key went down in the last [`update`][Button::update].
+	pub fn just_pressed(&self) -> bool {
+		self.pressed && !self.was_pressed
+	}
+
+	/// Returns true if the key came up in the last [`update`][Button::update].
+	pub fn just_released(&self) -> bool {
+		!self.pressed && self.was_pressed
+	}
+
+	/// Returns how long the key has been held, as of the last
+	/// [`update`][Button::update], or zero if it isn't pressed.
+	pub fn held_for(&self) -> Duration {
+		if self.pressed {
+			self.state_for()
+		} else {
+			Duration::from_secs(0)
+		}
+	}
+
+	/// Returns how long the key has been in its current state, pressed or not,
+	/// as of the last [`update`][Button::update].
+	pub fn state_for(&self) -> Duration {
+		Duration::from_ticks(self.updated_at.wrapping_sub(self.changed_at))
+	}
+}
@@ -0,0 +1,234 @@
+//! Key events
+//!
+//! Turns the keypad's state into a queue of presses, releases, and key
+//! repeats, so that a key held across several frames is only handled once:
+//!
+//! ```
+//! use ndless::input::events::{self, KeyState};
+//! use ndless::input::Key;
+//!
+//! loop {
+//!     for event in events::poll_events() {
+//!         match (event.key, event.state) {
+//!             (Key::Esc, KeyState::Pressed) => return,
+//!             (key, KeyState::Pressed) | (key, KeyState::Repeat) => editor.type_key(key),
+//!             _ => {}
+//!         }
+//!     }
+//!     // ...
+//! }
+//! ```
+//!
+//! The keypad is scanned whenever [`poll_events`] is called. Short taps
+//! between two calls can be missed if frames are slow, so the keypad can also
+//! be read from its interrupt, using [`scan_on_interrupt`]. Each change is
+//! then kept until the next [`poll_events`], even if the program is busy.
+
+use alloc::vec::Vec;
+
+use crate::hw::keypad::{self, Controller, INT_DATA_CHANGED, ROWS};
+use crate::input::{iter_keys, on_key, record, Key};
+use crate::interrupt::{self, Irq, Registration};
+use crate::time::Duration;
+use crate::timer::{get_ticks, has_time_passed, Ticks};
+
+/// Events older than this are dropped if nothing reads them.
+const MAX_QUEUED: usize = 64;
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum KeyState {
+	Pressed,
+	Released,
+	/// The key has been held down long enough to repeat.
+	Repeat,
+}
+
+/// Returned by [`poll_events`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct KeyEvent {
+	pub key: Key,
+	pub state: KeyState,
+}
+
+struct Held {
+	key: Key,
+	next_repeat: u32,
+}
+
+/// Delay before the first repeat, and between the ones after it, in ticks.
+/// `None` disables repeats.
+static mut REPEAT: Option<(u32, u32)> = Some((13107, 2621));
+static mut HELD: Vec<Held> = Vec::new();
+static mut QUEUE: Vec<KeyEvent> = Vec::new();
+
+/// The number of keypad changes kept between two scans. The oldest are
+/// dropped when it is full.
+const MAX_SNAPSHOTS: usize = 16;
+
+/// Data registers read by the keypad interrupt, which [`scan`] turns into
+/// events. A fixed buffer, as the interrupt handler can't allocate.
+static mut SNAPSHOTS: [[u16; ROWS]; MAX_SNAPSHOTS] = [[0; ROWS]; MAX_SNAPSHOTS];
+static mut FIRST_SNAPSHOT: usize = 0;
+static mut SNAPSHOT_COUNT: usize = 0;
+/// Set while [`scan_on_interrupt`] is enabled
+static mut CONTROLLER: Option<Controller> = None;
+
+fn push(event: KeyEvent) {
+	record::on_event(event);
+	unsafe {
+		if QUEUE.len() >= MAX_QUEUED {
+			QUEUE.remove(0);
+		}
+		QUEUE.push(event);
+	}
+}
+
+/// Sets how long a key must be held before it repeats, and how often it
+/// repeats after that. Defaults to 400ms and 80ms.
+pub fn set_repeat(delay: Duration, rate: Duration) {
+	unsafe { REPEAT = Some((delay.as_ticks(), rate.as_ticks().max(1))) }
+}
+
+/// Stops keys from repeating.
+pub fn disable_repeat() {
+	unsafe { REPEAT = None }
+}
+
+/// Checks the keypad, and queues events for anything that changed since the
+/// last scan. The ON key is checked too, for its [handler][on_key]. While a
+/// [`Player`][record::Player] is playing input back, its events are queued
+/// instead.
+pub fn scan() {
+	on_key::poll();
+	if record::play(push) {
+		return;
+	}
+	for rows in take_snapshots() {
+		// Keys outside the matrix, like the touchpad's arrows, keep their
+		// state until the live scan below
+		let pressed = Key::all()
+			.filter(|&key| match keypad::position(key) {
+				Some((row, col)) => keypad::decode(rows[row]) & (1 << col) != 0,
+				None => unsafe { HELD.iter() }.any(|held| held.key == key),
+			})
+			.collect();
+		update(pressed);
+	}
+	update(iter_keys().collect());
+}
+
+/// Queues events for the difference between `pressed` and the keys that were
+/// held.
+fn update(pressed: Vec<Key>) {
+	let now = get_ticks();
+	let held = unsafe { &mut HELD };
+	let repeat = unsafe { REPEAT };
+
+	held.retain(|held| {
+		let still_held = pressed.contains(&held.key);
+		if !still_held {
+			push(KeyEvent {
+				key: held.key,
+				state: KeyState::Released,
+			});
+		}
+		still_held
+	});
+	for held in held.iter_mut() {
+		if let Some((_, rate)) = repeat {
+			if has_time_passed(held.next_repeat) {
+				push(KeyEvent {
+					key: held.key,
+					state: KeyState::Repeat,
+				});
+				held.next_repeat = now.wrapping_add(rate);
+			}
+		}
+	}
+	for key in pressed {
+		if !held.iter().any(|held| held.key == key) {
+			push(KeyEvent {
+				key,
+				state: KeyState::Pressed,
+			});
+			held.push(Held {
+				key,
+				next_repeat: now.wrapping_add(repeat.map_or(0, |(delay, _)| delay)),
+			});
+		}
+	}
+}
+
+fn take_snapshots() -> Vec<[u16; ROWS]> {
+	interrupt::free(|| unsafe {
+		let snapshots = (0..SNAPSHOT_COUNT)
+			.map(|index| SNAPSHOTS[(FIRST_SNAPSHOT + index) % MAX_SNAPSHOTS])
+			.collect();
+		FIRST_SNAPSHOT = 0;
+		SNAPSHOT_COUNT = 0;
+		snapshots
+	})
+}
+
+fn on_interrupt() -> bool {
+	let controller = match unsafe { CONTROLLER.as_mut() } {
+		Some(controller) => controller,
+		None => return false,
+	};
+	if keypad::interrupt_status() & INT_DATA_CHANGED == 0 {
+		return false;
+	}
+	controller.acknowledge(INT_DATA_CHANGED);
+	unsafe {
+		if SNAPSHOT_COUNT == MAX_SNAPSHOTS {
+			FIRST_SNAPSHOT = (FIRST_SNAPSHOT + 1) % MAX_SNAPSHOTS;
+			SNAPSHOT_COUNT -= 1;
+		}
+		SNAPSHOTS[(FIRST_SNAPSHOT + SNAPSHOT_COUNT) % MAX_SNAPSHOTS] = keypad::raw_rows();
+		SNAPSHOT_COUNT += 1;
+	}
+	true
+}
+
+/// Keeps the keypad interrupt enabled. Returned by [`scan_on_interrupt`].
+#[must_use = "the interrupt is disabled when the handle is dropped"]
+#[derive(Debug)]
+pub struct InterruptScan {
+	_registration: Registration,
+}
+
+impl Drop for InterruptScan {
+	fn drop(&mut self) {
+		// Puts back the OS's interrupt mask
+		interrupt::free(|| unsafe { CONTROLLER = None });
+	}
+}
+
+/// Reads the keypad each time it changes, from its interrupt, so that short
+/// presses aren't missed while the program is busy. The changes are turned
+/// into events on the next [`scan`] or [`poll_events`]. Scanning this way
+/// stops when the handle is dropped.
+///
+/// Returns `None` if the keypad interrupt already has a handler.
+pub fn scan_on_interrupt() -> Option<InterruptScan> {
+	let registration = interrupt::register(Irq::KEYPAD, on_interrupt)?;
+	let mut controller = Controller::new();
+	controller.acknowledge(INT_DATA_CHANGED);
+	controller.set_interrupt_mask(keypad::interrupt_mask() | INT_DATA_CHANGED);
+	interrupt::free(|| unsafe { CONTROLLER = Some(controller) });
+	Some(InterruptScan {
+		_registration: registration,
+	})
+}
+
+/// Scans the keypad, then returns the events queued since the last call.
+pub fn poll_events() -> impl Iterator<Item = KeyEvent> {
+	scan();
+	unsafe { core::mem::take(&mut QUEUE) }.into_iter()
+}
+
+/// Discards queued events. Keys that are held are not reported as pressed
+/// again.
+pub fn clear_events() {
+	unsafe { QUEUE.clear() }
+}
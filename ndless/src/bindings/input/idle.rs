@@ -0,0 +1,135 @@
+//! Idle detection
+//!
+//! Keeps track of how long it has been since the user last pressed a key or
+//! touched the touchpad. This can be used for dimming the screen, attract
+//! modes, or auto-saving.
+//!
+//! Input is only noticed when it is checked, so call [`poll`] once per frame:
+//!
+//! ```
+//! use ndless::input::idle::{self, IdleEvent};
+//! use ndless::time::Duration;
+//!
+//! let _attract = idle::on_idle(Duration::from_secs(30), |event| match event {
+//!     IdleEvent::Idle => start_attract_mode(),
+//!     IdleEvent::Resumed => stop_attract_mode(),
+//! });
+//! loop {
+//!     idle::poll();
+//!     // ...
+//! }
+//! ```
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::hw::has_touchpad;
+use crate::input::any_key_pressed;
+use crate::input::touchpad::touchpad_scan;
+use crate::time::Duration;
+use crate::timer::{get_ticks, Ticks};
+
+/// Sent to callbacks registered with [`on_idle`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum IdleEvent {
+	/// There has been no input for the registered duration.
+	Idle,
+	/// There was input after an [`Idle`][IdleEvent::Idle] event.
+	Resumed,
+}
+
+struct Handler {
+	id: usize,
+	ticks: u32,
+	idle: bool,
+	callback: Box<dyn FnMut(IdleEvent)>,
+}
+
+static mut LAST_INPUT: u32 = 0;
+static mut NEXT_ID: usize = 0;
+static mut HANDLERS: Vec<Handler> = Vec::new();
+/// Handles dropped while [`poll`] is running callbacks
+static mut DROPPED: Option<Vec<usize>> = None;
+
+/// Unregisters its callback when dropped. Returned by [`on_idle`].
+#[must_use = "the callback is unregistered when the handle is dropped"]
+#[derive(Debug)]
+pub struct IdleHandle {
+	id: usize,
+}
+
+impl Drop for IdleHandle {
+	fn drop(&mut self) {
+		let id = self.id;
+		unsafe {
+			HANDLERS.retain(|handler| handler.id != id);
+			if let Some(dropped) = &mut DROPPED {
+				dropped.push(id);
+			}
+		}
+	}
+}
+
+fn has_input() -> bool {
+	any_key_pressed() || (has_touchpad() && touchpad_scan().map_or(false, |report| report.contact))
+}
+
+/// Records that input happened now. This is done automatically by [`poll`],
+/// but may be called if the program gets input some other way.
+pub fn reset() {
+	unsafe { LAST_INPUT = get_ticks() }
+}
+
+/// Returns the time since the last input was noticed, checking for input first.
+pub fn idle_time() -> Duration {
+	if has_input() {
+		reset();
+	}
+	Duration::from_ticks(get_ticks().wrapping_sub(unsafe { LAST_INPUT }))
+}
+
+/// Registers `callback` to be called with [`IdleEvent::Idle`] once there has
+/// been no input for `after`, and with [`IdleEvent::Resumed`] when input
+/// happens again. Callbacks are only called from [`poll`].
+pub fn on_idle(after: Duration, callback: impl FnMut(IdleEvent) + 'static) -> IdleHandle {
+	unsafe {
+		let id = NEXT_ID;
+		NEXT_ID += 1;
+		HANDLERS.push(Handler {
+			id,
+			ticks: after.as_ticks(),
+			idle: false,
+			callback: Box::new(callback),
+		});
+		IdleHandle { id }
+	}
+}
+
+/// Checks for input, and calls any callbacks whose state has changed.
+pub fn poll() {
+	let idle_ticks = idle_time().as_ticks();
+	// Callbacks are taken out while running so they can register or drop
+	// other handlers.
+	let mut handlers = unsafe {
+		DROPPED = Some(Vec::new());
+		core::mem::take(&mut HANDLERS)
+	};
+	for handler in handlers.iter_mut() {
+		let idle = idle_ticks >= handler.ticks;
+		if idle != handler.idle {
+			handler.idle = idle;
+			(handler.callback)(if idle {
+				IdleEvent::Idle
+			} else {
+				IdleEvent::Resumed
+			});
+		}
+	}
+	unsafe {
+		if let Some(dropped) = DROPPED.take() {
+			handlers.retain(|handler| !dropped.contains(&handler.id));
+		}
+		handlers.append(&mut HANDLERS);
+		HANDLERS = handlers;
+	}
+}
@@ -0,0 +1,247 @@
+//! Remappable controls
+//!
+//! A [`KeyMap`] binds logical actions to chords, which the user can change
+//! while the program runs. Each action has a name, used to save the mapping
+//! to a settings file:
+//!
+//! ```
+//! use ndless::input::keymap::KeyMap;
+//! use ndless::input::shortcuts::Chord;
+//! use ndless::input::Key;
+//!
+//! #[derive(Eq, PartialEq, Clone, Copy)]
+//! enum Action { Left, Right, Jump }
+//!
+//! let mut keys = KeyMap::new()
+//!     .action(Action::Left, "left", Chord::new(Key::Left))
+//!     .action(Action::Right, "right", Chord::new(Key::Right))
+//!     .action(Action::Jump, "jump", Chord::new(Key::Up));
+//! let _ = keys.load("/documents/game/keys.tns");
+//!
+//! // In the options menu
+//! if keys.capture(Action::Jump).is_some() {
+//!     keys.save("/documents/game/keys.tns")?;
+//! }
+//!
+//! // In the game loop
+//! if keys.is_held(Action::Left) {
+//!     x -= 1;
+//! }
+//! for action in keys.poll() {
+//!     // ...
+//! }
+//! ```
+//!
+//! The settings file has one `name<TAB>chord` line for each binding, such as
+//! `jump	Ctrl+Up`, so it can also be edited by hand.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::autosave::write_atomic;
+use crate::fs;
+use crate::input::events::{clear_events, poll_events, scan, KeyEvent};
+use crate::input::shortcuts::{Chord, ModifierTracker, Modifiers};
+use crate::input::{is_pressed, Key};
+use crate::io;
+use crate::path::Path;
+use crate::thread::sleep;
+use crate::time::Duration;
+
+struct Binding<A> {
+	action: A,
+	name: &'static str,
+	default: Vec<Chord>,
+	chords: Vec<Chord>,
+}
+
+/// Maps chords to actions that the user can rebind. See the
+/// [module documentation][self].
+pub struct KeyMap<A> {
+	bindings: Vec<Binding<A>>,
+	modifiers: ModifierTracker,
+}
+
+impl<A: Copy + Eq> Default for KeyMap<A> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Parses a chord in the format used by its [`Display`][core::fmt::Display]
+/// implementation, such as `Ctrl+Shift+A`.
+fn parse_chord(text: &str) -> Option<Chord> {
+	let mut modifiers = Modifiers::default();
+	let mut rest = text.trim();
+	loop {
+		if let Some(after) = rest.strip_prefix("Ctrl+") {
+			modifiers.ctrl = true;
+			rest = after;
+		} else if let Some(after) = rest.strip_prefix("Shift+") {
+			modifiers.shift = true;
+			rest = after;
+		} else {
+			break;
+		}
+	}
+	Some(Chord {
+		key: Key::named(rest)?,
+		modifiers,
+	})
+}
+
+impl<A: Copy + Eq> KeyMap<A> {
+	pub fn new() -> Self {
+		Self {
+			bindings: Vec::new(),
+			modifiers: ModifierTracker::new(),
+		}
+	}
+
+	/// Adds an action, bound to `chord` by default. `name` identifies it in
+	/// the settings file, so it shouldn't change between versions.
+	pub fn action(mut self, action: A, name: &'static str, chord: Chord) -> Self {
+		match self.binding_mut(action) {
+			Some(binding) => {
+				binding.default.push(chord);
+				binding.chords.push(chord);
+			}
+			None => self.bindings.push(Binding {
+				action,
+				name,
+				default: vec![chord],
+				chords: vec![chord],
+			}),
+		}
+		self
+	}
+
+	fn binding_mut(&mut self, action: A) -> Option<&mut Binding<A>> {
+		self.bindings
+			.iter_mut()
+			.find(|binding| binding.action == action)
+	}
+
+	/// The chords that trigger `action`.
+	pub fn chords(&self, action: A) -> &[Chord] {
+		self.bindings
+			.iter()
+			.find(|binding| binding.action == action)
+			.map_or(&[], |binding| &binding.chords)
+	}
+
+	/// Binds `action` to `chord` only. Any other action bound to `chord` loses
+	/// it, so a chord never does two things.
+	pub fn bind(&mut self, action: A, chord: Chord) {
+		for binding in self.bindings.iter_mut() {
+			binding.chords.retain(|other| *other != chord);
+		}
+		if let Some(binding) = self.binding_mut(action) {
+			binding.chords = vec![chord];
+		}
+	}
+
+	/// Puts every action back to its default chords.
+	pub fn reset(&mut self) {
+		for binding in self.bindings.iter_mut() {
+			binding.chords = binding.default.clone();
+		}
+	}
+
+	/// Returns true if a key bound to `action` is held down, ignoring
+	/// modifiers. Useful for movement in games.
+	pub fn is_held(&self, action: A) -> bool {
+		self.chords(action)
+			.iter()
+			.any(|chord| is_pressed(chord.key))
+	}
+
+	/// Updates the modifier state with an event, returning the action of the
+	/// chord it completes, if any.
+	pub fn handle(&mut self, event: KeyEvent) -> Option<A> {
+		let chord = self.modifiers.handle(event)?;
+		self.bindings
+			.iter()
+			.find(|binding| binding.chords.contains(&chord))
+			.map(|binding| binding.action)
+	}
+
+	/// Reads events with [`poll_events`], returning the actions of the chords
+	/// that were pressed.
+	pub fn poll(&mut self) -> Vec<A> {
+		poll_events()
+			.filter_map(|event| self.handle(event))
+			.collect()
+	}
+
+	/// Waits for the user to press a chord, and binds `action` to it. This is
+	/// the "press a key" step of a controls menu, so the program should show a
+	/// prompt first.
+	///
+	/// Returns the new chord, or `None` if escape is pressed, which leaves the
+	/// binding unchanged.
+	pub fn capture(&mut self, action: A) -> Option<Chord> {
+		let mut modifiers = ModifierTracker::new();
+		// Ignore keys that are already held, such as the one that opened the menu
+		scan();
+		clear_events();
+		loop {
+			for event in poll_events() {
+				match modifiers.handle(event) {
+					Some(chord) if chord.key == Key::Esc => return None,
+					Some(chord) => {
+						self.bind(action, chord);
+						return Some(chord);
+					}
+					None => {}
+				}
+			}
+			sleep(Duration::from_millis(10));
+		}
+	}
+
+	/// Reads a mapping saved with [`save`][KeyMap::save]. Actions that aren't in
+	/// the file keep their current chords, and unknown names are ignored.
+	pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+		let contents = fs::read_to_string(path)?;
+		let mut loaded: Vec<&str> = Vec::new();
+		for line in contents.lines() {
+			let mut parts = line.splitn(2, '\t');
+			let (name, chord) = match (parts.next(), parts.next().and_then(parse_chord)) {
+				(Some(name), Some(chord)) => (name, chord),
+				_ => continue,
+			};
+			if let Some(binding) = self
+				.bindings
+				.iter_mut()
+				.find(|binding| binding.name == name)
+			{
+				// The first line for an action replaces its chords, and later
+				// ones add to them
+				if !loaded.contains(&binding.name) {
+					binding.chords.clear();
+					loaded.push(binding.name);
+				}
+				binding.chords.push(chord);
+			}
+		}
+		Ok(())
+	}
+
+	/// Writes the mapping to `path`, creating its folder if needed.
+	pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		let path = path.as_ref();
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+		let mut contents = String::new();
+		for binding in &self.bindings {
+			for chord in &binding.chords {
+				contents.push_str(&format!("{}\t{}\n", binding.name, chord));
+			}
+		}
+		write_atomic(path, contents)
+	}
+}
@@ -0,0 +1,303 @@
+//! Physical keypad layout
+//!
+//! Describes the keypad of the calculator the program is running on, so that
+//! programs can draw accurate help screens ("press [trig]") or remapping UIs
+//! that look like the real keypad.
+//!
+//! Keys are arranged in rows from top to bottom, and each row lists its keys
+//! from left to right. Rows are not all the same length, and the arrows of the
+//! touchpad or clickpad are not included in them: see [`Layout::arrows`].
+//!
+//! ```
+//! use ndless::input::layout;
+//!
+//! let layout = layout::current();
+//! for row in layout.rows() {
+//!     for key in row.iter() {
+//!         print!("[{}] ", key.legend);
+//!     }
+//!     println!();
+//! }
+//! ```
+
+use crate::hw::has_touchpad;
+use crate::input::Key;
+use crate::input::Key::*;
+
+/// The type of keypad a calculator has.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Keypad {
+	/// The original keypad of the classic TI-Nspire, with a 4-way clickpad
+	Clickpad,
+	/// The keypad of the TI-Nspire Touchpad and every CX model
+	Touchpad,
+}
+
+/// A single key on the keypad.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct KeyInfo {
+	pub key: Key,
+	/// The main label printed on the key
+	pub legend: &'static str,
+	/// The row the key is in, starting at 0 for the top row
+	pub row: u8,
+	/// The position of the key in its row, starting at 0 for the leftmost key
+	pub col: u8,
+}
+
+/// The arrangement of keys on a keypad. Get the one for the current calculator
+/// with [`current`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Layout {
+	keypad: Keypad,
+	rows: &'static [&'static [Key]],
+	arrows: &'static [Key],
+}
+
+impl Layout {
+	/// Returns the layout of a specific type of keypad.
+	pub fn of(keypad: Keypad) -> Self {
+		match keypad {
+			Keypad::Clickpad => Self {
+				keypad,
+				rows: CLICKPAD_ROWS,
+				arrows: CLICKPAD_ARROWS,
+			},
+			Keypad::Touchpad => Self {
+				keypad,
+				rows: TOUCHPAD_ROWS,
+				arrows: TOUCHPAD_ARROWS,
+			},
+		}
+	}
+
+	pub fn keypad(&self) -> Keypad {
+		self.keypad
+	}
+
+	/// The number of rows of keys, not counting the arrows.
+	pub fn row_count(&self) -> usize {
+		self.rows.len()
+	}
+
+	/// The number of keys in the longest row.
+	pub fn max_columns(&self) -> usize {
+		self.rows.iter().map(|row| row.len()).max().unwrap_or(0)
+	}
+
+	/// Iterates over each row, from top to bottom.
+	pub fn rows(&self) -> impl Iterator<Item = Row> + '_ {
+		self.rows.iter().enumerate().map(|(row, keys)| Row {
+			row: row as u8,
+			keys,
+		})
+	}
+
+	/// Iterates over every key in every row.
+	pub fn keys(&self) -> impl Iterator<Item = KeyInfo> + '_ {
+		self.rows().flat_map(|row| row.iter())
+	}
+
+	/// The directions that can be pressed on the touchpad or clickpad,
+	/// including [`Key::Click`].
+	pub fn arrows(&self) -> &'static [Key] {
+		self.arrows
+	}
+
+	/// Returns the position of a key, or `None` if it is not on this keypad or
+	/// is one of the [`arrows`][Layout::arrows].
+	pub fn find(&self, key: Key) -> Option<KeyInfo> {
+		self.keys().find(|info| info.key == key)
+	}
+
+	/// Returns true if the key physically exists on this keypad.
+	pub fn has_key(&self, key: Key) -> bool {
+		self.arrows.contains(&key) || self.find(key).is_some()
+	}
+}
+
+/// A row of keys in a [`Layout`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Row {
+	row: u8,
+	keys: &'static [Key],
+}
+
+impl Row {
+	pub fn index(&self) -> usize {
+		self.row as usize
+	}
+
+	pub fn len(&self) -> usize {
+		self.keys.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.keys.is_empty()
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = KeyInfo> {
+		let row = self.row;
+		self.keys
+			.iter()
+			.enumerate()
+			.map(move |(col, &key)| KeyInfo {
+				key,
+				legend: legend(key),
+				row,
+				col: col as u8,
+			})
+	}
+}
+
+/// Returns the keypad of the calculator the program is running on.
+pub fn keypad() -> Keypad {
+	if has_touchpad() {
+		Keypad::Touchpad
+	} else {
+		Keypad::Clickpad
+	}
+}
+
+/// Returns the layout of the calculator the program is running on.
+pub fn current() -> Layout {
+	Layout::of(keypad())
+}
+
+/// Returns the main label printed on a key, such as `"trig"` for
+/// [`Key::Trig`].
+pub fn legend(key: Key) -> &'static str {
+	match key {
+		Key0 => "0",
+		Key1 => "1",
+		Key2 => "2",
+		Key3 => "3",
+		Key4 => "4",
+		Key5 => "5",
+		Key6 => "6",
+		Key7 => "7",
+		Key8 => "8",
+		Key9 => "9",
+		A => "A",
+		B => "B",
+		C => "C",
+		D => "D",
+		E => "E",
+		F => "F",
+		G => "G",
+		H => "H",
+		I => "I",
+		J => "J",
+		K => "K",
+		L => "L",
+		M => "M",
+		N => "N",
+		O => "O",
+		P => "P",
+		Q => "Q",
+		R => "R",
+		S => "S",
+		T => "T",
+		U => "U",
+		V => "V",
+		W => "W",
+		X => "X",
+		Y => "Y",
+		Z => "Z",
+		Up => "▲",
+		UpRight => "◥",
+		Right => "▶",
+		RightDown => "◢",
+		Down => "▼",
+		DownLeft => "◣",
+		Left => "◀",
+		LeftUp => "◤",
+		Click => "click",
+		Catalog => "catalog",
+		Comma => ",",
+		Ctrl => "ctrl",
+		Del => "del",
+		Divide => "÷",
+		Doc => "doc",
+		EE => "EE",
+		Enter => "enter",
+		Equals => "=",
+		Esc => "esc",
+		Exponent => "^",
+		Flag => "flag",
+		Template => "template",
+		On => "on",
+		LeftParenthesis => "(",
+		Menu => "menu",
+		Minus => "−",
+		Multiply => "×",
+		Negative => "(−)",
+		Period => ".",
+		Pi => "π",
+		Plus => "+",
+		QuestionExclamation => "?!▶",
+		Return => "return",
+		RightParenthesis => ")",
+		Scratchpad => "pad",
+		Shift => "shift",
+		Space => "space",
+		Squared => "x²",
+		Tab => "tab",
+		TenExp => "10ˣ",
+		Trig => "trig",
+		Var => "var",
+		EExp => "eˣ",
+		Apostrophe => "'",
+		Bar => "|",
+		Colon => ":",
+		Cos => "cos",
+		GreaterThan => ">",
+		II => "ii",
+		LessThan => "<",
+		Question => "?",
+		Quote => "\"",
+		Sin => "sin",
+		Tan => "tan",
+		Theta => "θ",
+	}
+}
+
+const TOUCHPAD_ARROWS: &[Key] = &[
+	Up, UpRight, Right, RightDown, Down, DownLeft, Left, LeftUp, Click,
+];
+
+const CLICKPAD_ARROWS: &[Key] = &[Up, Right, Down, Left, Click];
+
+const TOUCHPAD_ROWS: &[&[Key]] = &[
+	&[Esc, On],
+	&[Scratchpad, Doc],
+	&[Tab, Menu],
+	&[Ctrl, Shift, Var, Del],
+	&[Equals, Trig, Template, Catalog, Divide],
+	&[Exponent, Squared, Key7, Key8, Key9, Multiply],
+	&[EExp, TenExp, Key4, Key5, Key6, Minus],
+	&[LeftParenthesis, RightParenthesis, Key1, Key2, Key3, Plus],
+	&[Key0, Period, Negative, Enter],
+	&[A, B, C, D, E, F, G],
+	&[H, I, J, K, L, M, N],
+	&[O, P, Q, R, S, T, U],
+	&[V, W, X, Y, Z, EE, Pi],
+	&[Flag, QuestionExclamation, Comma, Space, Return],
+];
+
+const CLICKPAD_ROWS: &[&[Key]] = &[
+	&[Esc, On, Menu],
+	&[Ctrl, Shift, Var, Del],
+	&[Tab, Equals, Bar, Catalog],
+	&[Exponent, Squared, Key7, Key8, Key9, Multiply],
+	&[EExp, TenExp, Key4, Key5, Key6, Minus],
+	&[LeftParenthesis, RightParenthesis, Key1, Key2, Key3, Plus],
+	&[Sin, Cos, Tan, Divide],
+	&[Key0, Period, Negative, Enter],
+	&[Quote, Apostrophe, Colon, Theta, II],
+	&[A, B, C, D, E, F, G],
+	&[H, I, J, K, L, M, N],
+	&[O, P, Q, R, S, T, U],
+	&[V, W, X, Y, Z, EE, Pi],
+	&[LessThan, GreaterThan, Question, Flag, Comma, Space, Return],
+];
@@ -0,0 +1,293 @@
+//! Line editing
+//!
+//! Edits a single line of text with the keypad: typing, moving the cursor,
+//! and deleting, with shift and ctrl working like they do in the OS.
+//!
+//! [`read_line`] runs an editor until enter or escape is pressed. Drawing is
+//! left to a [`LineView`], so the line can be shown with any graphics library.
+//! A [`Console`][crate::out::Console] is one, which draws the line on the
+//! cursor's row:
+//!
+//! ```
+//! use ndless::input::line::read_line;
+//! use ndless::out::Console;
+//!
+//! let mut console = Console::new().unwrap();
+//! if let Some(name) = read_line("Name: ", &mut console) {
+//!     // ...
+//! }
+//! ```
+//!
+//! `ndless-sdl` has one for SDL surfaces, `SurfaceLineView`.
+//!
+//! Use a [`LineEditor`] directly to edit text as part of a bigger screen.
+//! Programs that only need characters one at a time, like a terminal, can use
+//! [`get_char`] instead.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::input::events::{clear_events, poll_events, scan, KeyEvent};
+use crate::input::shortcuts::{Chord, ModifierTracker, Modifiers};
+use crate::input::Key;
+use crate::thread::sleep;
+use crate::time::Duration;
+
+/// Draws a line being edited.
+pub trait LineView {
+	/// Draws `prompt`, followed by `text`, with the cursor before the
+	/// character at index `cursor` (counted in characters, not bytes).
+	/// `modifiers` are the modifiers that will apply to the next key, which
+	/// can be shown as an indicator.
+	fn draw_line(&mut self, prompt: &str, text: &str, cursor: usize, modifiers: Modifiers);
+}
+
+/// Returned by [`LineEditor::handle`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Edit {
+	/// The text, cursor, or modifiers changed, so the line should be redrawn.
+	Changed,
+	Unchanged,
+	/// Enter was pressed.
+	Done,
+	/// Escape was pressed.
+	Cancelled,
+}
+
+/// Returns the character typed by a chord, if any. Letters are lowercase
+/// unless shift is active.
+pub fn char_for(chord: Chord) -> Option<char> {
+	let Modifiers { ctrl, shift } = chord.modifiers;
+	let letter = |lower: char| {
+		Some(if shift {
+			lower.to_ascii_uppercase()
+		} else {
+			lower
+		})
+	};
+	Some(match chord.key {
+		Key::Key0 => '0',
+		Key::Key1 => '1',
+		Key::Key2 => '2',
+		Key::Key3 => '3',
+		Key::Key4 => '4',
+		Key::Key5 => '5',
+		Key::Key6 => '6',
+		Key::Key7 => '7',
+		Key::Key8 => '8',
+		Key::Key9 => '9',
+		Key::A => return letter('a'),
+		Key::B => return letter('b'),
+		Key::C => return letter('c'),
+		Key::D => return letter('d'),
+		Key::E => return letter('e'),
+		Key::F => return letter('f'),
+		Key::G => return letter('g'),
+		Key::H => return letter('h'),
+		Key::I => return letter('i'),
+		Key::J => return letter('j'),
+		Key::K => return letter('k'),
+		Key::L => return letter('l'),
+		Key::M => return letter('m'),
+		Key::N => return letter('n'),
+		Key::O => return letter('o'),
+		Key::P => return letter('p'),
+		Key::Q => return letter('q'),
+		Key::R => return letter('r'),
+		Key::S => return letter('s'),
+		Key::T => return letter('t'),
+		Key::U => return letter('u'),
+		Key::V => return letter('v'),
+		Key::W => return letter('w'),
+		Key::X => return letter('x'),
+		Key::Y => return letter('y'),
+		Key::Z => return letter('z'),
+		Key::Space => ' ',
+		Key::Period => '.',
+		Key::Comma => ',',
+		Key::Plus => '+',
+		Key::Minus | Key::Negative => '-',
+		Key::Multiply if ctrl => '"',
+		Key::Multiply => '*',
+		Key::Divide => '/',
+		Key::Equals => '=',
+		Key::Exponent => '^',
+		Key::LeftParenthesis if ctrl => '[',
+		Key::LeftParenthesis if shift => '{',
+		Key::LeftParenthesis => '(',
+		Key::RightParenthesis if ctrl => ']',
+		Key::RightParenthesis if shift => '}',
+		Key::RightParenthesis => ')',
+		Key::QuestionExclamation if shift => '!',
+		Key::QuestionExclamation => '?',
+		Key::Question => '?',
+		Key::Apostrophe => '\'',
+		Key::Quote => '"',
+		Key::Colon => ':',
+		Key::LessThan => '<',
+		Key::GreaterThan => '>',
+		Key::Bar => '|',
+		Key::Theta => 'θ',
+		Key::Pi => 'π',
+		Key::EE => 'E',
+		Key::Squared => '²',
+		_ => return None,
+	})
+}
+
+static mut MODIFIERS: ModifierTracker = ModifierTracker::new();
+static mut PENDING: Vec<char> = Vec::new();
+
+/// Returns the next character typed, or `None` if nothing has been typed since
+/// the last call. Doesn't wait.
+///
+/// Characters come from [`char_for`], so shift and ctrl work like they do in
+/// the OS. Enter and return give `'\n'`, tab gives `'\t'`, del gives
+/// backspace (`'\u{8}'`), and escape gives `'\u{1b}'`. Other keys are
+/// ignored. Keys only found on one [keypad][crate::input::layout] are handled
+/// on that keypad, so this works the same on every model.
+///
+/// This reads events with [`poll_events`], so it shouldn't be mixed with other
+/// users of the event queue.
+pub fn get_char() -> Option<char> {
+	let modifiers = unsafe { &mut MODIFIERS };
+	let pending = unsafe { &mut PENDING };
+	for event in poll_events() {
+		let chord = match modifiers.handle(event) {
+			Some(chord) => chord,
+			None => continue,
+		};
+		let ch = match chord.key {
+			Key::Enter | Key::Return => Some('\n'),
+			Key::Tab => Some('\t'),
+			Key::Del => Some('\u{8}'),
+			Key::Esc => Some('\u{1b}'),
+			_ => char_for(chord),
+		};
+		pending.extend(ch);
+	}
+	if pending.is_empty() {
+		None
+	} else {
+		Some(pending.remove(0))
+	}
+}
+
+/// The state of a line being edited.
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct LineEditor {
+	text: Vec<char>,
+	cursor: usize,
+	max_len: Option<usize>,
+	modifiers: ModifierTracker,
+}
+
+impl Default for LineEditor {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl LineEditor {
+	pub fn new() -> Self {
+		Self::with_text("")
+	}
+
+	/// Starts with `text`, with the cursor at the end.
+	pub fn with_text(text: &str) -> Self {
+		let text: Vec<char> = text.chars().collect();
+		Self {
+			cursor: text.len(),
+			text,
+			max_len: None,
+			modifiers: ModifierTracker::new(),
+		}
+	}
+
+	/// Limits the number of characters that can be typed.
+	pub fn max_len(mut self, max_len: usize) -> Self {
+		self.max_len = Some(max_len);
+		self
+	}
+
+	pub fn text(&self) -> String {
+		self.text.iter().collect()
+	}
+
+	/// The cursor's position, in characters.
+	pub fn cursor(&self) -> usize {
+		self.cursor
+	}
+
+	pub fn modifiers(&self) -> Modifiers {
+		self.modifiers.modifiers()
+	}
+
+	/// Updates the line with a key event.
+	pub fn handle(&mut self, event: KeyEvent) -> Edit {
+		let before = self.modifiers();
+		let chord = match self.modifiers.handle(event) {
+			Some(chord) => chord,
+			None if self.modifiers() != before => return Edit::Changed,
+			None => return Edit::Unchanged,
+		};
+		let Modifiers { ctrl, shift } = chord.modifiers;
+		match chord.key {
+			Key::Enter | Key::Return => return Edit::Done,
+			Key::Esc => return Edit::Cancelled,
+			Key::Left | Key::LeftUp | Key::DownLeft if ctrl => self.cursor = 0,
+			Key::Left | Key::LeftUp | Key::DownLeft => self.cursor = self.cursor.saturating_sub(1),
+			Key::Right | Key::UpRight | Key::RightDown if ctrl => self.cursor = self.text.len(),
+			Key::Right | Key::UpRight | Key::RightDown => {
+				self.cursor = (self.cursor + 1).min(self.text.len())
+			}
+			Key::Up => self.cursor = 0,
+			Key::Down => self.cursor = self.text.len(),
+			Key::Del if ctrl || shift => {
+				if self.cursor < self.text.len() {
+					self.text.remove(self.cursor);
+				}
+			}
+			Key::Del => {
+				if self.cursor > 0 {
+					self.cursor -= 1;
+					self.text.remove(self.cursor);
+				}
+			}
+			_ => match char_for(chord) {
+				Some(ch) if self.max_len.map_or(true, |max| self.text.len() < max) => {
+					self.text.insert(self.cursor, ch);
+					self.cursor += 1;
+				}
+				_ => return Edit::Unchanged,
+			},
+		}
+		Edit::Changed
+	}
+}
+
+/// Lets the user type a line of text after `prompt`, drawn with `view`.
+/// Returns the text when enter is pressed, or `None` if escape is pressed.
+pub fn read_line(prompt: &str, view: &mut impl LineView) -> Option<String> {
+	let mut editor = LineEditor::new();
+	// Ignore keys that are already held, such as enter from a previous prompt
+	scan();
+	clear_events();
+	view.draw_line(prompt, "", 0, editor.modifiers());
+	loop {
+		let mut changed = false;
+		for event in poll_events() {
+			match editor.handle(event) {
+				Edit::Changed => changed = true,
+				Edit::Unchanged => {}
+				Edit::Done => return Some(editor.text()),
+				Edit::Cancelled => return None,
+			}
+		}
+		if changed {
+			let text = editor.text();
+			view.draw_line(prompt, &text, editor.cursor(), editor.modifiers());
+		}
+		sleep(Duration::from_millis(10));
+	}
+}
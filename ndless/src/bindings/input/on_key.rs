@@ -0,0 +1,79 @@
+//! The ON key
+//!
+//! Ndless can't stop the OS from reacting to the ON key while it has
+//! control, such as during a [message box][crate::msg], but while the program
+//! runs, ON is just another key. This module lets a program choose what
+//! pressing it does:
+//!
+//! ```
+//! use ndless::input::on_key;
+//!
+//! on_key::on_key_handler(|| paused = true);
+//! loop {
+//!     on_key::poll();
+//!     // ...
+//! }
+//! ```
+//!
+//! [`exit_on_key`] installs a handler that quits cleanly, running the
+//! callbacks registered with [`process::at_exit`] first.
+//!
+//! The key is checked by [`poll`], and whenever the event queue is scanned, so
+//! programs that already use [`poll_events`][crate::input::poll_events] don't
+//! need to call [`poll`].
+
+use alloc::boxed::Box;
+
+use crate::input::key_on_pressed;
+use crate::process;
+
+static mut HANDLER: Option<Box<dyn FnMut()>> = None;
+static mut WAS_PRESSED: bool = false;
+/// Changed whenever the handler is set or removed
+static mut GENERATION: usize = 0;
+
+/// Calls `handler` each time the ON key is pressed, replacing any handler set
+/// before.
+pub fn on_key_handler(handler: impl FnMut() + 'static) {
+	unsafe {
+		HANDLER = Some(Box::new(handler));
+		GENERATION += 1;
+	}
+}
+
+/// Removes the handler, so pressing ON does nothing.
+pub fn remove_on_key_handler() {
+	unsafe {
+		HANDLER = None;
+		GENERATION += 1;
+	}
+}
+
+/// Sets a handler that exits with [`process::exit`] when ON is pressed, which
+/// runs the hooks registered with [`process::at_exit`].
+///
+/// Like [`process::exit`], this doesn't run destructors, so anything that
+/// must be saved should be saved by an exit hook.
+pub fn exit_on_key() {
+	on_key_handler(|| process::exit(0));
+}
+
+/// Checks the ON key, calling the handler if it was pressed since the last
+/// check.
+pub fn poll() {
+	let pressed = key_on_pressed();
+	let was_pressed = unsafe { core::mem::replace(&mut WAS_PRESSED, pressed) };
+	if !pressed || was_pressed {
+		return;
+	}
+	// Taken out while running, in case the handler replaces or removes itself
+	let generation = unsafe { GENERATION };
+	if let Some(mut handler) = unsafe { HANDLER.take() } {
+		handler();
+		unsafe {
+			if GENERATION == generation {
+				HANDLER = Some(handler);
+			}
+		}
+	}
+}
@@ -0,0 +1,238 @@
+//! Recording and playing back input
+//!
+//! A [`Recorder`] logs every [key event][crate::input::events] with the time
+//! it happened, and can save the log to a file. A [`Player`] feeds a saved
+//! log back into the event queue with the same timing, in place of the
+//! keypad. This makes bugs reported by users reproducible, and can run demos
+//! or automated tests of a whole program:
+//!
+//! ```
+//! use ndless::input::record::{Player, Recorder};
+//!
+//! let recorder = Recorder::start();
+//! run_game();
+//! recorder.save("/documents/game/bug.tns")?;
+//!
+//! // Later, to see the bug again
+//! let player = Player::load("/documents/game/bug.tns")?;
+//! player.start();
+//! run_game();
+//! ```
+//!
+//! Only events from [`poll_events`][crate::input::events::poll_events] and
+//! the APIs built on it are recorded and played back. Functions that read the
+//! keypad directly, like [`is_pressed`][crate::input::is_pressed], still see
+//! the real keypad during playback.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::autosave::write_atomic;
+use crate::fs;
+use crate::input::events::{KeyEvent, KeyState};
+use crate::input::Key;
+use crate::io;
+use crate::path::Path;
+use crate::timer::get_ticks;
+
+/// An event, with the number of ticks since recording started
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+struct Timed {
+	at: u32,
+	event: KeyEvent,
+}
+
+struct Recording {
+	/// Which [`Recorder`] this belongs to
+	generation: u32,
+	start: u32,
+	events: Vec<Timed>,
+}
+
+struct Playback {
+	start: u32,
+	events: Vec<Timed>,
+	next: usize,
+	held: Vec<Key>,
+}
+
+static mut RECORDING: Option<Recording> = None;
+/// The generation of the next [`Recorder`]
+static mut NEXT_GENERATION: u32 = 0;
+static mut PLAYBACK: Option<Playback> = None;
+
+/// Called by the event queue for every event
+pub(crate) fn on_event(event: KeyEvent) {
+	if let Some(recording) = unsafe { RECORDING.as_mut() } {
+		recording.events.push(Timed {
+			at: get_ticks().wrapping_sub(recording.start),
+			event,
+		});
+	}
+}
+
+/// Called by the event queue instead of scanning the keypad. Returns false if
+/// nothing is being played back, so the keypad should be scanned.
+pub(crate) fn play(mut push: impl FnMut(KeyEvent)) -> bool {
+	let playback = match unsafe { PLAYBACK.as_mut() } {
+		Some(playback) => playback,
+		None => return false,
+	};
+	let now = get_ticks().wrapping_sub(playback.start);
+	while let Some(timed) = playback.events.get(playback.next) {
+		if timed.at > now {
+			return true;
+		}
+		match timed.event.state {
+			KeyState::Pressed => playback.held.push(timed.event.key),
+			KeyState::Released => playback.held.retain(|key| *key != timed.event.key),
+			KeyState::Repeat => {}
+		}
+		push(timed.event);
+		playback.next += 1;
+	}
+	// Release keys that were still held when recording stopped
+	for key in playback.held.drain(..) {
+		push(KeyEvent {
+			key,
+			state: KeyState::Released,
+		});
+	}
+	unsafe { PLAYBACK = None }
+	true
+}
+
+fn state_name(state: KeyState) -> &'static str {
+	match state {
+		KeyState::Pressed => "press",
+		KeyState::Released => "release",
+		KeyState::Repeat => "repeat",
+	}
+}
+
+/// Records key events until it is saved or dropped. See the
+/// [module documentation][self].
+pub struct Recorder {
+	generation: u32,
+}
+
+impl Recorder {
+	/// Starts recording. Only one recording can run at a time, so this replaces
+	/// any recording that is already running, and the [`Recorder`] for that
+	/// one records nothing more.
+	pub fn start() -> Self {
+		let generation = unsafe {
+			let generation = NEXT_GENERATION;
+			NEXT_GENERATION = NEXT_GENERATION.wrapping_add(1);
+			RECORDING = Some(Recording {
+				generation,
+				start: get_ticks(),
+				events: Vec::new(),
+			});
+			generation
+		};
+		Recorder { generation }
+	}
+
+	/// The recording, if a newer one hasn't replaced it
+	fn recording(&self) -> Option<&'static mut Recording> {
+		unsafe { RECORDING.as_mut() }.filter(|recording| recording.generation == self.generation)
+	}
+
+	/// The number of events recorded so far.
+	pub fn len(&self) -> usize {
+		self.recording()
+			.map_or(0, |recording| recording.events.len())
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Stops recording, and writes the events to `path`, one per line.
+	pub fn save(self, path: impl AsRef<Path>) -> io::Result<()> {
+		let events = match self.recording() {
+			Some(recording) => core::mem::take(&mut recording.events),
+			None => Vec::new(),
+		};
+		let mut contents = String::new();
+		for timed in events {
+			contents.push_str(&format!(
+				"{}\t{:?}\t{}\n",
+				timed.at,
+				timed.event.key,
+				state_name(timed.event.state)
+			));
+		}
+		write_atomic(path, contents)
+	}
+}
+
+impl Drop for Recorder {
+	fn drop(&mut self) {
+		if self.recording().is_some() {
+			unsafe { RECORDING = None }
+		}
+	}
+}
+
+/// Plays back events saved by a [`Recorder`]. See the
+/// [module documentation][self].
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct Player {
+	events: Vec<Timed>,
+}
+
+impl Player {
+	/// Reads events saved with [`Recorder::save`]. Lines that can't be read
+	/// are skipped.
+	pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+		let contents = fs::read_to_string(path)?;
+		let events = contents
+			.lines()
+			.filter_map(|line| {
+				let mut parts = line.split('\t');
+				let at = parts.next()?.parse().ok()?;
+				let key = Key::named(parts.next()?)?;
+				let state = match parts.next()? {
+					"press" => KeyState::Pressed,
+					"release" => KeyState::Released,
+					"repeat" => KeyState::Repeat,
+					_ => return None,
+				};
+				Some(Timed {
+					at,
+					event: KeyEvent { key, state },
+				})
+			})
+			.collect();
+		Ok(Player { events })
+	}
+
+	/// Starts playing the events back, from now. The keypad is ignored by the
+	/// event queue until every event has been played, or [`stop`] is called.
+	pub fn start(self) {
+		unsafe {
+			PLAYBACK = Some(Playback {
+				start: get_ticks(),
+				events: self.events,
+				next: 0,
+				held: Vec::new(),
+			})
+		}
+	}
+}
+
+/// Returns true while a [`Player`] is playing events back.
+pub fn is_playing() -> bool {
+	unsafe { PLAYBACK.is_some() }
+}
+
+/// Stops playback early, so the keypad is used again. Keys that the playback
+/// was holding are released on the next scan.
+pub fn stop() {
+	if let Some(playback) = unsafe { PLAYBACK.as_mut() } {
+		playback.next = playback.events.len();
+	}
+}
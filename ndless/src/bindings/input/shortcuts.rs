@@ -0,0 +1,240 @@
+//! Keyboard shortcuts
+//!
+//! On the Nspire, ctrl and shift are usually pressed and released before the
+//! key they modify, rather than held. [`Shortcuts`] handles both ways, and
+//! turns matching chords into actions:
+//!
+//! ```
+//! use ndless::input::shortcuts::{Chord, Shortcuts};
+//! use ndless::input::Key;
+//!
+//! #[derive(Clone)]
+//! enum Action { Quit, Save }
+//!
+//! let mut shortcuts = Shortcuts::new()
+//!     .on(Chord::new(Key::Esc).ctrl(), Action::Quit)
+//!     .on(Chord::new(Key::S).ctrl(), Action::Save);
+//! loop {
+//!     for action in shortcuts.poll() {
+//!         match action {
+//!             Action::Quit => return,
+//!             Action::Save => save(),
+//!         }
+//!     }
+//!     // ...
+//! }
+//! ```
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::input::events::{poll_events, KeyEvent, KeyState};
+use crate::input::Key;
+
+/// Which modifiers are active.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, Default)]
+pub struct Modifiers {
+	pub ctrl: bool,
+	pub shift: bool,
+}
+
+/// A key, with the modifiers that must be active when it's pressed.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Chord {
+	pub key: Key,
+	pub modifiers: Modifiers,
+}
+
+impl Chord {
+	pub fn new(key: Key) -> Self {
+		Self {
+			key,
+			modifiers: Modifiers::default(),
+		}
+	}
+
+	pub fn ctrl(mut self) -> Self {
+		self.modifiers.ctrl = true;
+		self
+	}
+
+	pub fn shift(mut self) -> Self {
+		self.modifiers.shift = true;
+		self
+	}
+}
+
+impl fmt::Display for Chord {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.modifiers.ctrl {
+			f.write_str("Ctrl+")?;
+		}
+		if self.modifiers.shift {
+			f.write_str("Shift+")?;
+		}
+		write!(f, "{:?}", self.key)
+	}
+}
+
+enum Binding<A> {
+	Action(A),
+	Callback(Box<dyn FnMut()>),
+}
+
+/// State of one modifier key
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+enum Modifier {
+	Up,
+	/// Held, and no other key has been pressed yet
+	Down,
+	/// Held while another key was pressed
+	Used,
+	/// Pressed and released on its own, so it applies to the next key
+	Sticky,
+}
+
+impl Modifier {
+	fn is_active(self) -> bool {
+		self != Modifier::Up
+	}
+
+	fn update(&mut self, state: KeyState) {
+		*self = match (state, *self) {
+			(KeyState::Pressed, Modifier::Sticky) => Modifier::Up,
+			(KeyState::Pressed, _) => Modifier::Down,
+			(KeyState::Released, Modifier::Down) => Modifier::Sticky,
+			(KeyState::Released, Modifier::Used) => Modifier::Up,
+			(_, state) => state,
+		};
+	}
+
+	/// Called when another key is pressed
+	fn consume(&mut self) {
+		*self = match *self {
+			Modifier::Down | Modifier::Used => Modifier::Used,
+			Modifier::Sticky | Modifier::Up => Modifier::Up,
+		}
+	}
+}
+
+/// Turns key events into chords, tracking held and sticky modifiers.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub(crate) struct ModifierTracker {
+	ctrl: Modifier,
+	shift: Modifier,
+}
+
+impl ModifierTracker {
+	pub(crate) const fn new() -> Self {
+		Self {
+			ctrl: Modifier::Up,
+			shift: Modifier::Up,
+		}
+	}
+
+	pub(crate) fn modifiers(&self) -> Modifiers {
+		Modifiers {
+			ctrl: self.ctrl.is_active(),
+			shift: self.shift.is_active(),
+		}
+	}
+
+	/// Returns the chord completed by a press or repeat of a key other than a
+	/// modifier.
+	pub(crate) fn handle(&mut self, event: KeyEvent) -> Option<Chord> {
+		match event.key {
+			Key::Ctrl => {
+				self.ctrl.update(event.state);
+				return None;
+			}
+			Key::Shift => {
+				self.shift.update(event.state);
+				return None;
+			}
+			_ => {}
+		}
+		if event.state == KeyState::Released {
+			return None;
+		}
+		let chord = Chord {
+			key: event.key,
+			modifiers: self.modifiers(),
+		};
+		if event.state == KeyState::Pressed {
+			self.ctrl.consume();
+			self.shift.consume();
+		}
+		Some(chord)
+	}
+}
+
+/// Maps chords to actions or callbacks. See the [module documentation][self].
+pub struct Shortcuts<A = ()> {
+	bindings: Vec<(Chord, Binding<A>)>,
+	modifiers: ModifierTracker,
+}
+
+impl<A: Clone> Default for Shortcuts<A> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<A: Clone> Shortcuts<A> {
+	pub fn new() -> Self {
+		Self {
+			bindings: Vec::new(),
+			modifiers: ModifierTracker::new(),
+		}
+	}
+
+	/// Returns `action` from [`handle`][Shortcuts::handle] and
+	/// [`poll`][Shortcuts::poll] when `chord` is pressed.
+	pub fn on(mut self, chord: Chord, action: A) -> Self {
+		self.bindings.push((chord, Binding::Action(action)));
+		self
+	}
+
+	/// Runs `callback` when `chord` is pressed.
+	pub fn on_callback(mut self, chord: Chord, callback: impl FnMut() + 'static) -> Self {
+		self.bindings
+			.push((chord, Binding::Callback(Box::new(callback))));
+		self
+	}
+
+	/// Removes every binding for `chord`.
+	pub fn remove(&mut self, chord: Chord) {
+		self.bindings.retain(|(other, _)| *other != chord);
+	}
+
+	/// Returns the modifiers that will apply to the next key, such as for
+	/// showing an indicator.
+	pub fn modifiers(&self) -> Modifiers {
+		self.modifiers.modifiers()
+	}
+
+	/// Updates the modifier state with an event, returning the action of the
+	/// chord it completes, if any. Callbacks are run here.
+	pub fn handle(&mut self, event: KeyEvent) -> Option<A> {
+		let chord = self.modifiers.handle(event)?;
+		let mut action = None;
+		for (bound, binding) in self.bindings.iter_mut() {
+			if *bound == chord {
+				match binding {
+					Binding::Action(bound) => action = action.or_else(|| Some(bound.clone())),
+					Binding::Callback(callback) => callback(),
+				}
+			}
+		}
+		action
+	}
+
+	/// Reads events with [`poll_events`], returning the actions of the chords
+	/// that were pressed.
+	pub fn poll(&mut self) -> Vec<A> {
+		poll_events()
+			.filter_map(|event| self.handle(event))
+			.collect()
+	}
+}
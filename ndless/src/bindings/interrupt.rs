@@ -0,0 +1,174 @@
+//! # Interrupts
+//! Runs code when a device raises an interrupt, such as a timer expiring or a
+//! key being pressed, without patching the exception vectors by hand:
+//!
+//! ```
+//! use ndless::interrupt::{self, Irq};
+//!
+//! static mut TICKS: u32 = 0;
+//!
+//! fn on_timer() -> bool {
+//!     if !timer_raised_interrupt() {
+//!         // Let the OS handle it
+//!         return false;
+//!     }
+//!     clear_timer_interrupt();
+//!     unsafe { TICKS += 1 }
+//!     true
+//! }
+//!
+//! let _registration = interrupt::register(Irq::SECOND_TIMER, on_timer)
+//!     .expect("the timer interrupt is already in use");
+//! // Read the count without the handler changing it halfway through
+//! let ticks = interrupt::free(|| unsafe { TICKS });
+//! ```
+//!
+//! Handlers run with interrupts disabled, and must clear the device's
+//! interrupt before returning true, or they will be called again straight
+//! away. A handler that returns false passes the interrupt on to the OS, which
+//! uses several of these lines itself.
+//!
+//! A handler stays registered until its [`Registration`] is dropped. Programs
+//! that exit early with [`process::exit`][crate::process::exit] or
+//! [`abort`][crate::process::abort], including when they panic, don't run
+//! destructors, so these call [`restore`], which removes every handler.
+
+use crate::hw::irq::{self, Frame};
+
+/// An interrupt line of the interrupt controller.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Irq(pub u32);
+
+impl Irq {
+	pub const UART: Irq = Irq(1);
+	pub const WATCHDOG: Irq = Irq(3);
+	pub const RTC: Irq = Irq(4);
+	pub const GPIO: Irq = Irq(7);
+	pub const USB: Irq = Irq(8);
+	pub const ADC: Irq = Irq(11);
+	pub const KEYPAD: Irq = Irq(16);
+	pub const FAST_TIMER: Irq = Irq(17);
+	pub const FIRST_TIMER: Irq = Irq(18);
+	pub const SECOND_TIMER: Irq = Irq(19);
+	pub const LCD: Irq = Irq(21);
+}
+
+/// The number of interrupt lines.
+pub const LINES: usize = 32;
+
+/// Returns true if the interrupt was handled. See the
+/// [module documentation][self].
+pub type Handler = fn() -> bool;
+
+/// The interrupt controller's status of the lines that are raised and enabled
+const IRQ_STATUS: *const u32 = 0xDC00_0000 as *const u32;
+
+static mut HANDLERS: [Option<Handler>; LINES] = [None; LINES];
+/// The lines that were enabled before a handler was registered on them
+static mut WERE_ENABLED: u32 = 0;
+
+fn dispatch(_frame: &mut Frame) -> bool {
+	let status = unsafe { core::ptr::read_volatile(IRQ_STATUS) };
+	let mut handled = false;
+	for (line, handler) in unsafe { HANDLERS.iter() }.enumerate() {
+		if let Some(handler) = handler {
+			if status & (1 << line) != 0 {
+				handled |= handler();
+			}
+		}
+	}
+	handled
+}
+
+/// Runs `f` with interrupts disabled, so that no handler runs in the middle
+/// of it. Interrupts are enabled again afterwards, unless they were already
+/// disabled.
+pub fn free<R>(f: impl FnOnce() -> R) -> R {
+	let cpsr: u32;
+	unsafe {
+		llvm_asm!("
+			mrs $0, cpsr
+			orr r1, $0, #0x80
+			msr cpsr_c, r1
+			"
+			: "=r"(cpsr)
+			:
+			: "r1"
+			: "volatile"
+		)
+	}
+	let result = f();
+	unsafe { llvm_asm!("msr cpsr_c, $0" : : "r"(cpsr) : : "volatile") }
+	result
+}
+
+/// Keeps a handler registered. The handler is removed, and the line masked
+/// again if it was masked before, when this is dropped.
+#[must_use = "the handler is removed when the registration is dropped"]
+#[derive(Debug)]
+pub struct Registration {
+	irq: Irq,
+}
+
+impl Registration {
+	pub fn irq(&self) -> Irq {
+		self.irq
+	}
+}
+
+impl Drop for Registration {
+	fn drop(&mut self) {
+		unregister(self.irq);
+	}
+}
+
+/// Calls `handler` whenever `irq` is raised, and unmasks it in the interrupt
+/// controller.
+///
+/// Returns `None` if the line is out of range or already has a handler, or if
+/// the IRQ vector can't be hooked.
+pub fn register(irq: Irq, handler: Handler) -> Option<Registration> {
+	let line = irq.0 as usize;
+	if line >= LINES {
+		return None;
+	}
+	free(|| unsafe {
+		if HANDLERS[line].is_some() {
+			return None;
+		}
+		if HANDLERS.iter().all(Option::is_none) && !irq::register(dispatch) {
+			return None;
+		}
+		HANDLERS[line] = Some(handler);
+		if irq::is_enabled(irq.0) {
+			WERE_ENABLED |= 1 << line;
+		} else {
+			WERE_ENABLED &= !(1 << line);
+		}
+		irq::enable(irq.0);
+		Some(Registration { irq })
+	})
+}
+
+fn unregister(irq: Irq) {
+	let line = irq.0 as usize;
+	free(|| unsafe {
+		if HANDLERS[line].take().is_none() {
+			return;
+		}
+		if WERE_ENABLED & (1 << line) == 0 {
+			irq::disable(irq.0);
+		}
+		if HANDLERS.iter().all(Option::is_none) {
+			irq::unregister(dispatch);
+		}
+	})
+}
+
+/// Removes every handler registered with [`register`]. This is done when the
+/// program exits, so it rarely needs to be called directly.
+pub fn restore() {
+	for line in 0..LINES {
+		unregister(Irq(line as u32));
+	}
+}
@@ -0,0 +1,340 @@
+//! # Linking calculators
+//! A [`Peer`] turns a connection that can lose or garble bytes into a
+//! reliable byte stream, for multiplayer games or sending files between two
+//! programs:
+//!
+//! ```
+//! use ndless::io::{Read, Write};
+//! use ndless::link::Peer;
+//! use ndless::serial::Uart;
+//!
+//! let mut uart = Uart::new(115_200).expect("the serial port is already in use");
+//! uart.set_nonblocking(true);
+//! uart.enable_rx_interrupt();
+//! let mut peer = Peer::new(uart);
+//! peer.write_all(&my_move)?;
+//! let mut their_move = [0; 4];
+//! peer.read_exact(&mut their_move)?;
+//! ```
+//!
+//! Data is sent in frames of up to [`MAX_PAYLOAD`] bytes, each checked with a
+//! CRC. Every frame is acknowledged by the other side, and sent again if the
+//! acknowledgement doesn't arrive in time, so bytes arrive once, in order, or
+//! the write fails with [`TimedOut`][io::ErrorKind::TimedOut]. Only the
+//! frame that's expected next is acknowledged, so if one side restarts and the
+//! two get out of step, writes time out instead of their data being lost.
+//!
+//! Any connection that implements [`Read`][io::Read] and [`Write`][io::Write]
+//! can be used, such as a [`serial::Uart`][crate::serial::Uart] over the dock
+//! connector, or a [`usb::CdcAcm`][crate::usb::CdcAcm] to a computer running
+//! the same protocol. Reads from it must not wait: they should fail with
+//! [`WouldBlock`][io::ErrorKind::WouldBlock] when nothing has arrived.
+//!
+//! Two calculators connected directly with a mini USB cable aren't supported
+//! yet, as that needs one of them to act as a USB host.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::io;
+use crate::time::Duration;
+use crate::timer::{get_ticks, has_time_passed, Ticks};
+
+/// The most bytes sent in one frame.
+pub const MAX_PAYLOAD: usize = 128;
+
+/// Starts every frame
+const FLAG: u8 = 0x7E;
+/// Comes before a [`FLAG`] or [`ESCAPE`] byte in a frame, which is then sent
+/// XORed with 0x20
+const ESCAPE: u8 = 0x7D;
+
+const KIND_DATA: u8 = 0;
+const KIND_ACK: u8 = 1;
+
+/// CRC-16/CCITT-FALSE
+fn crc16(data: &[u8]) -> u16 {
+	let mut crc = 0xFFFF_u16;
+	for &byte in data {
+		crc ^= u16::from(byte) << 8;
+		for _ in 0..8 {
+			crc = if crc & 0x8000 != 0 {
+				crc << 1 ^ 0x1021
+			} else {
+				crc << 1
+			};
+		}
+	}
+	crc
+}
+
+/// A reliable byte stream over `T`. See the [module documentation][self].
+#[derive(Debug)]
+pub struct Peer<T> {
+	transport: T,
+	/// The sequence number of the next frame to send
+	send_seq: u8,
+	/// The sequence number of the next frame expected
+	receive_seq: u8,
+	/// Bytes received from frames that haven't been read yet
+	received: VecDeque<u8>,
+	/// The unescaped bytes of the frame being received, or `None` before the
+	/// first [`FLAG`]
+	frame: Option<Vec<u8>>,
+	escaped: bool,
+	/// The last acknowledged sequence number, if not taken yet
+	acked: Option<u8>,
+	timeout: u32,
+	retries: u32,
+	read_timeout: Option<u32>,
+}
+
+impl<T: io::Read + io::Write> Peer<T> {
+	/// Runs the protocol over `transport`, waiting 100ms for each
+	/// acknowledgement, and sending each frame up to 10 times.
+	pub fn new(transport: T) -> Self {
+		Self {
+			transport,
+			send_seq: 0,
+			receive_seq: 0,
+			received: VecDeque::new(),
+			frame: None,
+			escaped: false,
+			acked: None,
+			timeout: Duration::from_millis(100).as_ticks(),
+			retries: 10,
+			read_timeout: None,
+		}
+	}
+
+	/// Sets how long to wait for an acknowledgement before sending a frame
+	/// again, and how many times to send it before giving up.
+	pub fn retransmit(mut self, timeout: Duration, retries: u32) -> Self {
+		self.timeout = timeout.as_ticks().max(1);
+		self.retries = retries.max(1);
+		self
+	}
+
+	/// Makes reads fail with [`TimedOut`][io::ErrorKind::TimedOut] if nothing
+	/// arrives within `timeout`. With `None`, the default, reads wait forever.
+	pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+		self.read_timeout = timeout.map(|timeout| timeout.as_ticks());
+	}
+
+	pub fn get_ref(&self) -> &T {
+		&self.transport
+	}
+
+	pub fn get_mut(&mut self) -> &mut T {
+		&mut self.transport
+	}
+
+	pub fn into_inner(self) -> T {
+		self.transport
+	}
+
+	/// The number of bytes that can be read without waiting.
+	pub fn available(&self) -> usize {
+		self.received.len()
+	}
+
+	fn send_frame(&mut self, kind: u8, seq: u8, payload: &[u8]) -> io::Result<()> {
+		let mut body = Vec::with_capacity(payload.len() + 5);
+		body.extend_from_slice(&[kind, seq, payload.len() as u8]);
+		body.extend_from_slice(payload);
+		body.extend_from_slice(&crc16(&body).to_le_bytes());
+		let mut frame = Vec::with_capacity(body.len() * 2 + 1);
+		frame.push(FLAG);
+		for byte in body {
+			if byte == FLAG || byte == ESCAPE {
+				frame.extend_from_slice(&[ESCAPE, byte ^ 0x20]);
+			} else {
+				frame.push(byte);
+			}
+		}
+		self.transport.write_all(&frame)?;
+		self.transport.flush()
+	}
+
+	/// Handles a complete frame, without its flag.
+	fn handle_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+		if frame.len() < 5 {
+			return Ok(());
+		}
+		let (body, crc) = frame.split_at(frame.len() - 2);
+		let (kind, seq, len) = (body[0], body[1], body[2] as usize);
+		if len != body.len() - 3 || crc16(body).to_le_bytes() != [crc[0], crc[1]] {
+			// Garbled. The sender will try again.
+			return Ok(());
+		}
+		match kind {
+			KIND_DATA => {
+				if seq == self.receive_seq {
+					self.received.extend(&body[3..]);
+					self.receive_seq = self.receive_seq.wrapping_add(1);
+					self.send_frame(KIND_ACK, seq, &[])
+				} else if seq == self.receive_seq.wrapping_sub(1) {
+					// A duplicate, acknowledged again in case the first
+					// acknowledgement was lost
+					self.send_frame(KIND_ACK, seq, &[])
+				} else {
+					// Out of step. Acknowledging it would lose its data.
+					Ok(())
+				}
+			}
+			KIND_ACK => {
+				self.acked = Some(seq);
+				Ok(())
+			}
+			_ => Ok(()),
+		}
+	}
+
+	/// Reads whatever has arrived, acknowledging frames and collecting their
+	/// data. Reading and writing do this while they wait, but a program that
+	/// does neither for a while should call this, so the other side doesn't
+	/// give up on its frames.
+	pub fn poll(&mut self) -> io::Result<()> {
+		let mut buf = [0; 64];
+		loop {
+			let len = match self.transport.read(&mut buf) {
+				Ok(0) => return Ok(()),
+				Ok(len) => len,
+				Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+				Err(err) => return Err(err),
+			};
+			for &byte in &buf[..len] {
+				if byte == FLAG {
+					if let Some(frame) = self.frame.replace(Vec::new()) {
+						self.handle_frame(&frame)?;
+					}
+					self.escaped = false;
+					continue;
+				}
+				let frame = match &mut self.frame {
+					Some(frame) => frame,
+					None => continue,
+				};
+				if byte == ESCAPE {
+					self.escaped = true;
+					continue;
+				}
+				frame.push(if self.escaped { byte ^ 0x20 } else { byte });
+				self.escaped = false;
+				// The length is known once the header is in
+				if frame.len() >= 3 && frame.len() == frame[2] as usize + 5 {
+					let frame = self.frame.take().unwrap_or_default();
+					self.handle_frame(&frame)?;
+				} else if frame.len() > MAX_PAYLOAD + 5 {
+					self.frame = None;
+				}
+			}
+		}
+	}
+}
+
+impl<T: io::Read + io::Write> io::Read for Peer<T> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		let deadline = self
+			.read_timeout
+			.map(|timeout| get_ticks().wrapping_add(timeout));
+		while self.received.is_empty() {
+			self.poll()?;
+			if deadline.map_or(false, has_time_passed) {
+				return Err(io::ErrorKind::TimedOut.into());
+			}
+		}
+		let len = buf.len().min(self.received.len());
+		for (byte, received) in buf.iter_mut().zip(self.received.drain(..len)) {
+			*byte = received;
+		}
+		Ok(len)
+	}
+}
+
+impl<T: io::Read + io::Write> io::Write for Peer<T> {
+	/// Sends up to [`MAX_PAYLOAD`] bytes in one frame, and waits for it to be
+	/// acknowledged.
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		let payload = &buf[..buf.len().min(MAX_PAYLOAD)];
+		let seq = self.send_seq;
+		for _ in 0..self.retries {
+			self.acked = None;
+			self.send_frame(KIND_DATA, seq, payload)?;
+			let deadline = get_ticks().wrapping_add(self.timeout);
+			while !has_time_passed(deadline) {
+				self.poll()?;
+				if self.acked == Some(seq) {
+					self.send_seq = seq.wrapping_add(1);
+					return Ok(payload.len());
+				}
+			}
+		}
+		Err(io::ErrorKind::TimedOut.into())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.transport.flush()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::vec;
+	use alloc::vec::Vec;
+
+	use super::*;
+
+	/// Records what is written, and never has anything to read
+	struct Recorder(Vec<u8>);
+
+	impl io::Read for Recorder {
+		fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+			Err(io::ErrorKind::WouldBlock.into())
+		}
+	}
+
+	impl io::Write for Recorder {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			self.0.extend_from_slice(buf);
+			Ok(buf.len())
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+
+	fn data_frame(seq: u8, payload: &[u8]) -> Vec<u8> {
+		let mut frame = vec![KIND_DATA, seq, payload.len() as u8];
+		frame.extend_from_slice(payload);
+		let crc = crc16(&frame);
+		frame.extend_from_slice(&crc.to_le_bytes());
+		frame
+	}
+
+	#[test]
+	fn out_of_step_frames_are_not_acknowledged() {
+		let mut peer = Peer::new(Recorder(Vec::new()));
+		peer.handle_frame(&data_frame(5, b"lost")).unwrap();
+		assert_eq!(peer.available(), 0);
+		assert!(peer.get_ref().0.is_empty());
+
+		peer.handle_frame(&data_frame(0, b"hi")).unwrap();
+		assert_eq!(peer.available(), 2);
+		let acks = peer.get_ref().0.len();
+		assert!(acks > 0);
+
+		// A duplicate is acknowledged again, without its data
+		peer.handle_frame(&data_frame(0, b"hi")).unwrap();
+		assert_eq!(peer.available(), 2);
+		assert_eq!(peer.get_ref().0.len(), acks * 2);
+	}
+}
@@ -4,6 +4,15 @@ use core::intrinsics;
 
 use ndless_sys as cmath;
 
+pub mod fast;
+pub mod fixed;
+pub mod vector;
+
+mod bigint;
+
+pub use bigint::{BigInt, ParseBigIntError};
+pub use vector::{Mat4, Scalar, Vec2, Vec3};
+
 /// Copy and pasted from Rust std
 pub trait Float: Sized {
 	/// Returns the largest integer less than or equal to a number.
@@ -206,6 +215,36 @@ pub trait Float: Sized {
 	/// ```
 	fn mod_euc(self, rhs: Self) -> Self;
 
+	/// Calculates Euclidean division. The same as
+	/// [`div_euc`][Float::div_euc], under the name std uses.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// let a: f64 = 7.0;
+	/// let b = 4.0;
+	/// assert_eq!(a.div_euclid(b), 1.0); // 7.0 > 4.0 * 1.0
+	/// assert_eq!((-a).div_euclid(b), -2.0); // -7.0 >= 4.0 * -2.0
+	/// ```
+	fn div_euclid(self, rhs: Self) -> Self {
+		self.div_euc(rhs)
+	}
+
+	/// Calculates the Euclidean remainder, which is never negative. The same
+	/// as [`mod_euc`][Float::mod_euc], under the name std uses.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// let a: f64 = 7.0;
+	/// let b = 4.0;
+	/// assert_eq!(a.rem_euclid(b), 3.0);
+	/// assert_eq!((-a).rem_euclid(b), 1.0);
+	/// ```
+	fn rem_euclid(self, rhs: Self) -> Self {
+		self.mod_euc(rhs)
+	}
+
 	/// Raises a number to an integer power.
 	///
 	/// Using this function is generally faster than using `powf`
@@ -843,17 +882,20 @@ impl Float for f64 {
 	}
 	#[inline]
 	fn asinh(self) -> f64 {
-		if self == core::f64::NEG_INFINITY {
-			core::f64::NEG_INFINITY
-		} else {
-			(self + ((self * self) + 1.0).sqrt()).ln()
-		}
+		// Written like this to keep precision for large and negative numbers,
+		// as in std
+		let ax = self.abs();
+		let ix = 1.0 / ax;
+		(ax + (ax / (Float::hypot(1.0, ix) + ix)))
+			.ln_1p()
+			.copysign(self)
 	}
 	#[inline]
 	fn acosh(self) -> f64 {
-		match self {
-			x if x < 1.0 => core::f64::NAN,
-			x => (x + ((x * x) - 1.0).sqrt()).ln(),
+		if self < 1.0 {
+			core::f64::NAN
+		} else {
+			(self + ((self - 1.0).sqrt() * (self + 1.0).sqrt())).ln()
 		}
 	}
 	#[inline]
@@ -1027,17 +1069,20 @@ impl Float for f32 {
 	}
 	#[inline]
 	fn asinh(self) -> f32 {
-		if self == core::f32::NEG_INFINITY {
-			core::f32::NEG_INFINITY
-		} else {
-			(self + ((self * self) + 1.0).sqrt()).ln()
-		}
+		// Written like this to keep precision for large and negative numbers,
+		// as in std
+		let ax = self.abs();
+		let ix = 1.0 / ax;
+		(ax + (ax / (Float::hypot(1.0, ix) + ix)))
+			.ln_1p()
+			.copysign(self)
 	}
 	#[inline]
 	fn acosh(self) -> f32 {
-		match self {
-			x if x < 1.0 => core::f32::NAN,
-			x => (x + ((x * x) - 1.0).sqrt()).ln(),
+		if self < 1.0 {
+			core::f32::NAN
+		} else {
+			(self + ((self - 1.0).sqrt() * (self + 1.0).sqrt())).ln()
 		}
 	}
 	#[inline]
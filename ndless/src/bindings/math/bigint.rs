@@ -0,0 +1,572 @@
+//! Big integers
+//!
+//! Integers that grow to fit whatever they hold, for number theory tools
+//! such as primality tests or RSA demonstrations:
+//!
+//! ```
+//! use ndless::math::BigInt;
+//!
+//! let p: BigInt = "170141183460469231731687303715884105727".parse().unwrap();
+//! let two = BigInt::from(2);
+//! // Fermat test
+//! let one = two.modpow(&(&p - &BigInt::one()), &p).unwrap();
+//! assert_eq!(one, BigInt::one());
+//! println!("{}", two.pow(100));
+//! ```
+//!
+//! Operators are implemented for both `BigInt` and `&BigInt`, so values that
+//! are used again don't need to be cloned. Like the built in integers,
+//! division rounds towards zero, and panics when dividing by zero.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::fmt::{self, Write};
+use core::ops::{
+	Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign,
+};
+use core::str::FromStr;
+
+use crate::error::Error;
+
+/// The largest power of 10 that fits in a limb
+const DECIMAL_BASE: u32 = 1_000_000_000;
+const DECIMAL_DIGITS: usize = 9;
+
+/// An error returned when parsing a [`BigInt`] from a string.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum ParseBigIntError {
+	/// The string has no digits.
+	Empty,
+	/// The string contains something other than an optional sign followed by
+	/// decimal digits.
+	InvalidDigit,
+}
+
+impl fmt::Display for ParseBigIntError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ParseBigIntError::Empty => write!(f, "cannot parse integer from empty string"),
+			ParseBigIntError::InvalidDigit => write!(f, "invalid digit found in string"),
+		}
+	}
+}
+
+impl Error for ParseBigIntError {}
+
+/// An integer of any size. See the [module documentation][self].
+#[derive(Eq, PartialEq, Clone, Hash, Default)]
+pub struct BigInt {
+	/// Never set for zero
+	negative: bool,
+	/// The magnitude in base 2^32, least significant limb first, without
+	/// trailing zero limbs
+	mag: Vec<u32>,
+}
+
+fn trim(mut mag: Vec<u32>) -> Vec<u32> {
+	while mag.last() == Some(&0) {
+		mag.pop();
+	}
+	mag
+}
+
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+	a.len()
+		.cmp(&b.len())
+		.then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+	let (a, b) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+	let mut out = Vec::with_capacity(a.len() + 1);
+	let mut carry = 0;
+	for (i, &x) in a.iter().enumerate() {
+		let sum = u64::from(x) + u64::from(b.get(i).copied().unwrap_or(0)) + carry;
+		out.push(sum as u32);
+		carry = sum >> 32;
+	}
+	out.push(carry as u32);
+	trim(out)
+}
+
+/// Returns `a - b`, where `a >= b`
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+	let mut out = Vec::with_capacity(a.len());
+	let mut borrow = 0;
+	for (i, &x) in a.iter().enumerate() {
+		let diff = i64::from(x) - i64::from(b.get(i).copied().unwrap_or(0)) - borrow;
+		out.push(diff as u32);
+		borrow = if diff < 0 { 1 } else { 0 };
+	}
+	trim(out)
+}
+
+fn mul_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+	if a.is_empty() || b.is_empty() {
+		return Vec::new();
+	}
+	let mut out = vec![0; a.len() + b.len()];
+	for (i, &x) in a.iter().enumerate() {
+		let mut carry = 0;
+		for (j, &y) in b.iter().enumerate() {
+			let product = u64::from(x) * u64::from(y) + u64::from(out[i + j]) + carry;
+			out[i + j] = product as u32;
+			carry = product >> 32;
+		}
+		out[i + b.len()] = carry as u32;
+	}
+	trim(out)
+}
+
+/// Sets `mag` to `mag * factor + addend`
+fn mul_add_small(mag: &mut Vec<u32>, factor: u32, addend: u32) {
+	let mut carry = u64::from(addend);
+	for limb in mag.iter_mut() {
+		let product = u64::from(*limb) * u64::from(factor) + carry;
+		*limb = product as u32;
+		carry = product >> 32;
+	}
+	if carry != 0 {
+		mag.push(carry as u32);
+	}
+}
+
+/// Divides `mag` by `divisor` in place, and returns the remainder
+fn div_small(mag: &mut Vec<u32>, divisor: u32) -> u32 {
+	let mut rem = 0_u64;
+	for limb in mag.iter_mut().rev() {
+		let num = rem << 32 | u64::from(*limb);
+		*limb = (num / u64::from(divisor)) as u32;
+		rem = num % u64::from(divisor);
+	}
+	while mag.last() == Some(&0) {
+		mag.pop();
+	}
+	rem as u32
+}
+
+/// Shifts left by `shift` bits, less than 32, always adding a limb
+fn shl_bits(a: &[u32], shift: u32) -> Vec<u32> {
+	let mut out = Vec::with_capacity(a.len() + 1);
+	let mut carry = 0;
+	for &x in a {
+		out.push(x << shift | carry);
+		carry = if shift == 0 { 0 } else { x >> (32 - shift) };
+	}
+	out.push(carry);
+	out
+}
+
+fn shr_bits(a: &[u32], shift: u32) -> Vec<u32> {
+	if shift == 0 {
+		return trim(a.to_vec());
+	}
+	let out = (0..a.len())
+		.map(|i| a[i] >> shift | a.get(i + 1).map_or(0, |&next| next << (32 - shift)))
+		.collect();
+	trim(out)
+}
+
+/// Returns the quotient and remainder of `u / v`, where `v` isn't zero
+fn div_rem_mag(u: &[u32], v: &[u32]) -> (Vec<u32>, Vec<u32>) {
+	if cmp_mag(u, v) == Ordering::Less {
+		return (Vec::new(), u.to_vec());
+	}
+	if v.len() == 1 {
+		let mut q = u.to_vec();
+		let r = div_small(&mut q, v[0]);
+		return (q, trim(vec![r]));
+	}
+	// Knuth's algorithm D, as in Hacker's Delight. Normalizing so the top bit
+	// of the divisor is set makes each estimated quotient limb at most 2 too
+	// large.
+	let shift = v[v.len() - 1].leading_zeros();
+	let mut v = shl_bits(v, shift);
+	v.pop();
+	let mut u = shl_bits(u, shift);
+	let n = v.len();
+	let m = u.len() - n - 1;
+	let mut q = vec![0; m + 1];
+	let base = 1_u64 << 32;
+	for j in (0..=m).rev() {
+		let num = u64::from(u[j + n]) << 32 | u64::from(u[j + n - 1]);
+		let mut qhat = num / u64::from(v[n - 1]);
+		let mut rhat = num % u64::from(v[n - 1]);
+		let next = u64::from(u[j + n - 2]);
+		while qhat >= base || qhat * u64::from(v[n - 2]) > (rhat << 32 | next) {
+			qhat -= 1;
+			rhat += u64::from(v[n - 1]);
+			if rhat >= base {
+				break;
+			}
+		}
+		// Subtract qhat * v from the current window of u
+		let mut borrow = 0_i64;
+		for (i, &limb) in v.iter().enumerate() {
+			let product = qhat * u64::from(limb);
+			let t = i64::from(u[i + j]) - borrow - (product & 0xFFFF_FFFF) as i64;
+			u[i + j] = t as u32;
+			borrow = (product >> 32) as i64 - (t >> 32);
+		}
+		let t = i64::from(u[j + n]) - borrow;
+		u[j + n] = t as u32;
+		if t < 0 {
+			// qhat was one too large: add v back
+			qhat -= 1;
+			let mut carry = 0_u64;
+			for (i, &limb) in v.iter().enumerate() {
+				let sum = u64::from(u[i + j]) + u64::from(limb) + carry;
+				u[i + j] = sum as u32;
+				carry = sum >> 32;
+			}
+			u[j + n] = u[j + n].wrapping_add(carry as u32);
+		}
+		q[j] = qhat as u32;
+	}
+	(trim(q), shr_bits(&u[..n], shift))
+}
+
+impl BigInt {
+	fn from_parts(negative: bool, mag: Vec<u32>) -> Self {
+		let mag = trim(mag);
+		Self {
+			negative: negative && !mag.is_empty(),
+			mag,
+		}
+	}
+
+	pub fn zero() -> Self {
+		Self::default()
+	}
+
+	pub fn one() -> Self {
+		Self::from(1_u32)
+	}
+
+	pub fn is_zero(&self) -> bool {
+		self.mag.is_empty()
+	}
+
+	pub fn is_negative(&self) -> bool {
+		self.negative
+	}
+
+	pub fn is_even(&self) -> bool {
+		self.mag.first().map_or(true, |limb| limb & 1 == 0)
+	}
+
+	pub fn abs(&self) -> Self {
+		Self::from_parts(false, self.mag.clone())
+	}
+
+	/// Returns -1, 0 or 1 depending on the sign.
+	pub fn signum(&self) -> i32 {
+		match (self.negative, self.is_zero()) {
+			(true, _) => -1,
+			(false, true) => 0,
+			(false, false) => 1,
+		}
+	}
+
+	/// The number of bits needed to hold the magnitude, which is 0 for zero.
+	pub fn bits(&self) -> u32 {
+		match self.mag.last() {
+			Some(top) => self.mag.len() as u32 * 32 - top.leading_zeros(),
+			None => 0,
+		}
+	}
+
+	/// Returns whether bit `bit` of the magnitude is set.
+	pub fn bit(&self, bit: u32) -> bool {
+		self.mag
+			.get((bit / 32) as usize)
+			.map_or(false, |limb| limb >> (bit % 32) & 1 != 0)
+	}
+
+	/// Returns the value as an `i64`, or `None` if it doesn't fit.
+	pub fn to_i64(&self) -> Option<i64> {
+		if self.mag.len() > 2 {
+			return None;
+		}
+		let mag = self
+			.mag
+			.iter()
+			.rev()
+			.fold(0, |acc, &limb| acc << 32 | u64::from(limb));
+		match (self.negative, i64::try_from(mag)) {
+			(false, Ok(value)) => Some(value),
+			(true, Ok(value)) => Some(-value),
+			(true, Err(_)) if mag == 1 << 63 => Some(i64::MIN),
+			_ => None,
+		}
+	}
+
+	/// Returns the quotient and remainder, rounding towards zero like `/` and
+	/// `%`, or `None` if `divisor` is zero.
+	pub fn div_rem(&self, divisor: &BigInt) -> Option<(BigInt, BigInt)> {
+		if divisor.is_zero() {
+			return None;
+		}
+		let (q, r) = div_rem_mag(&self.mag, &divisor.mag);
+		Some((
+			Self::from_parts(self.negative != divisor.negative, q),
+			Self::from_parts(self.negative, r),
+		))
+	}
+
+	/// Returns the remainder from 0 up to `|divisor|`, or `None` if `divisor`
+	/// is zero.
+	pub fn rem_euclid(&self, divisor: &BigInt) -> Option<BigInt> {
+		let (_, r) = self.div_rem(divisor)?;
+		Some(if r.negative { r + divisor.abs() } else { r })
+	}
+
+	pub fn pow(&self, mut exp: u32) -> BigInt {
+		let mut base = self.clone();
+		let mut result = BigInt::one();
+		while exp > 0 {
+			if exp & 1 != 0 {
+				result = &result * &base;
+			}
+			exp >>= 1;
+			if exp > 0 {
+				base = &base * &base;
+			}
+		}
+		result
+	}
+
+	/// Returns `self^exp mod modulus`, from 0 up to `|modulus|`. Returns `None`
+	/// if `exp` is negative or `modulus` is zero.
+	pub fn modpow(&self, exp: &BigInt, modulus: &BigInt) -> Option<BigInt> {
+		if exp.negative || modulus.is_zero() {
+			return None;
+		}
+		let modulus = modulus.abs();
+		let base = self.rem_euclid(&modulus)?;
+		let mut result = BigInt::one().rem_euclid(&modulus)?;
+		for bit in (0..exp.bits()).rev() {
+			result = (&result * &result).rem_euclid(&modulus)?;
+			if exp.bit(bit) {
+				result = (&result * &base).rem_euclid(&modulus)?;
+			}
+		}
+		Some(result)
+	}
+
+	/// Returns the greatest common divisor, which is never negative.
+	pub fn gcd(&self, other: &BigInt) -> BigInt {
+		let mut a = self.abs();
+		let mut b = other.abs();
+		while !b.is_zero() {
+			let r = div_rem_mag(&a.mag, &b.mag).1;
+			a = b;
+			b = Self::from_parts(false, r);
+		}
+		a
+	}
+
+	fn add_signed(&self, rhs: &BigInt, rhs_negative: bool) -> BigInt {
+		if self.negative == rhs_negative {
+			return Self::from_parts(self.negative, add_mag(&self.mag, &rhs.mag));
+		}
+		match cmp_mag(&self.mag, &rhs.mag) {
+			Ordering::Less => Self::from_parts(rhs_negative, sub_mag(&rhs.mag, &self.mag)),
+			_ => Self::from_parts(self.negative, sub_mag(&self.mag, &rhs.mag)),
+		}
+	}
+}
+
+macro_rules! from_unsigned {
+	($($ty:ty),*) => {$(
+		impl From<$ty> for BigInt {
+			fn from(value: $ty) -> Self {
+				let value = value as u64;
+				Self::from_parts(false, vec![value as u32, (value >> 32) as u32])
+			}
+		}
+	)*};
+}
+
+macro_rules! from_signed {
+	($($ty:ty),*) => {$(
+		impl From<$ty> for BigInt {
+			fn from(value: $ty) -> Self {
+				let mag = (value as i64).wrapping_abs() as u64;
+				Self::from_parts(value < 0, vec![mag as u32, (mag >> 32) as u32])
+			}
+		}
+	)*};
+}
+
+from_unsigned!(u8, u16, u32, u64, usize);
+from_signed!(i8, i16, i32, i64, isize);
+
+impl FromStr for BigInt {
+	type Err = ParseBigIntError;
+
+	/// Parses a decimal integer, with an optional `+` or `-` sign.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (negative, digits) = match s.as_bytes().first() {
+			Some(b'-') => (true, &s[1..]),
+			Some(b'+') => (false, &s[1..]),
+			_ => (false, s),
+		};
+		if digits.is_empty() {
+			return Err(ParseBigIntError::Empty);
+		}
+		if !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+			return Err(ParseBigIntError::InvalidDigit);
+		}
+		let mut mag = Vec::new();
+		// The first chunk is short, so the rest are a full limb of digits
+		let first = (digits.len() - 1) % DECIMAL_DIGITS + 1;
+		let mut chunk_start = 0;
+		let mut chunk_end = first;
+		while chunk_start < digits.len() {
+			let chunk = &digits.as_bytes()[chunk_start..chunk_end];
+			let value = chunk
+				.iter()
+				.fold(0, |acc, digit| acc * 10 + u32::from(digit - b'0'));
+			mul_add_small(&mut mag, 10_u32.pow(chunk.len() as u32), value);
+			chunk_start = chunk_end;
+			chunk_end += DECIMAL_DIGITS;
+		}
+		Ok(Self::from_parts(negative, mag))
+	}
+}
+
+impl fmt::Display for BigInt {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut chunks = Vec::new();
+		let mut mag = self.mag.clone();
+		while !mag.is_empty() {
+			chunks.push(div_small(&mut mag, DECIMAL_BASE));
+		}
+		let mut digits = String::new();
+		let mut chunks = chunks.iter().rev();
+		let _ = write!(digits, "{}", chunks.next().unwrap_or(&0));
+		for chunk in chunks {
+			let _ = write!(digits, "{:09}", chunk);
+		}
+		f.pad_integral(!self.negative, "", &digits)
+	}
+}
+
+impl fmt::Debug for BigInt {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Display::fmt(self, f)
+	}
+}
+
+impl Ord for BigInt {
+	fn cmp(&self, other: &Self) -> Ordering {
+		match (self.negative, other.negative) {
+			(false, true) => Ordering::Greater,
+			(true, false) => Ordering::Less,
+			(false, false) => cmp_mag(&self.mag, &other.mag),
+			(true, true) => cmp_mag(&other.mag, &self.mag),
+		}
+	}
+}
+
+impl PartialOrd for BigInt {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Neg for BigInt {
+	type Output = BigInt;
+
+	fn neg(self) -> BigInt {
+		Self::from_parts(!self.negative, self.mag)
+	}
+}
+
+impl Neg for &BigInt {
+	type Output = BigInt;
+
+	fn neg(self) -> BigInt {
+		BigInt::from_parts(!self.negative, self.mag.clone())
+	}
+}
+
+fn add(a: &BigInt, b: &BigInt) -> BigInt {
+	a.add_signed(b, b.negative)
+}
+
+fn sub(a: &BigInt, b: &BigInt) -> BigInt {
+	a.add_signed(b, !b.negative)
+}
+
+fn mul(a: &BigInt, b: &BigInt) -> BigInt {
+	BigInt::from_parts(a.negative != b.negative, mul_mag(&a.mag, &b.mag))
+}
+
+fn div(a: &BigInt, b: &BigInt) -> BigInt {
+	a.div_rem(b).expect("attempt to divide by zero").0
+}
+
+fn rem(a: &BigInt, b: &BigInt) -> BigInt {
+	a.div_rem(b)
+		.expect("attempt to calculate the remainder with a divisor of zero")
+		.1
+}
+
+macro_rules! binop {
+	($trait:ident, $method:ident, $assign_trait:ident, $assign_method:ident, $op:ident) => {
+		impl $trait<&BigInt> for &BigInt {
+			type Output = BigInt;
+
+			fn $method(self, rhs: &BigInt) -> BigInt {
+				$op(self, rhs)
+			}
+		}
+
+		impl $trait<BigInt> for &BigInt {
+			type Output = BigInt;
+
+			fn $method(self, rhs: BigInt) -> BigInt {
+				$op(self, &rhs)
+			}
+		}
+
+		impl $trait<&BigInt> for BigInt {
+			type Output = BigInt;
+
+			fn $method(self, rhs: &BigInt) -> BigInt {
+				$op(&self, rhs)
+			}
+		}
+
+		impl $trait<BigInt> for BigInt {
+			type Output = BigInt;
+
+			fn $method(self, rhs: BigInt) -> BigInt {
+				$op(&self, &rhs)
+			}
+		}
+
+		impl $assign_trait<&BigInt> for BigInt {
+			fn $assign_method(&mut self, rhs: &BigInt) {
+				*self = $op(self, rhs);
+			}
+		}
+
+		impl $assign_trait<BigInt> for BigInt {
+			fn $assign_method(&mut self, rhs: BigInt) {
+				*self = $op(self, &rhs);
+			}
+		}
+	};
+}
+
+binop!(Add, add, AddAssign, add_assign, add);
+binop!(Sub, sub, SubAssign, sub_assign, sub);
+binop!(Mul, mul, MulAssign, mul_assign, mul);
+binop!(Div, div, DivAssign, div_assign, div);
+binop!(Rem, rem, RemAssign, rem_assign, rem);
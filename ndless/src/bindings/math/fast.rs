@@ -0,0 +1,169 @@
+//! Fast kernels
+//!
+//! Loops over pixels or samples that are too slow when written the simple
+//! way, using ARM instructions the compiler doesn't pick on its own: storing
+//! four registers at once, and the DSP instructions of the ARM926EJ-S.
+//!
+//! ```
+//! use ndless::math::fast;
+//!
+//! // Clear an RGB565 framebuffer to blue
+//! fast::fill16(pixels, 0x001F);
+//! // Mix a sound effect into the music, without wrapping around on overflow
+//! fast::mix_i16(&mut music, &effect);
+//! // Apply an FIR filter
+//! let sample = fast::dot_i16(&history, &coefficients) >> 15;
+//! ```
+//!
+//! Each function has a plain Rust version, used when not compiling for ARM,
+//! and for the unaligned ends of slices.
+
+/// Sets every element of `dst` to `value`, four at a time.
+pub fn fill32(dst: &mut [u32], value: u32) {
+	let blocks = dst.len() / 4;
+	let (head, tail) = dst.split_at_mut(blocks * 4);
+	#[cfg(target_arch = "arm")]
+	{
+		if blocks > 0 {
+			let mut ptr = head.as_mut_ptr();
+			let mut count = blocks;
+			unsafe {
+				llvm_asm!("
+					mov r3, $2
+					mov r4, $2
+					mov r5, $2
+					mov r12, $2
+					1:
+					stmia $0!, {r3, r4, r5, r12}
+					subs $1, $1, #1
+					bne 1b
+					"
+					: "+r"(ptr), "+r"(count)
+					: "r"(value)
+					: "r3", "r4", "r5", "r12", "cc", "memory"
+					: "volatile"
+				)
+			}
+		}
+	}
+	#[cfg(not(target_arch = "arm"))]
+	head.iter_mut().for_each(|element| *element = value);
+	tail.iter_mut().for_each(|element| *element = value);
+}
+
+/// Sets every element of `dst` to `value`, such as filling RGB565 pixels.
+pub fn fill16(dst: &mut [u16], value: u16) {
+	// Reinterpreting the aligned middle as words is sound: any bits are a
+	// valid u32
+	let (head, middle, tail) = unsafe { dst.align_to_mut::<u32>() };
+	head.iter_mut().for_each(|element| *element = value);
+	fill32(middle, u32::from(value) * 0x0001_0001);
+	tail.iter_mut().for_each(|element| *element = value);
+}
+
+/// Adds `a` and `b`, saturating at `i32::MIN` and `i32::MAX`
+#[inline(always)]
+fn qadd(a: i32, b: i32) -> i32 {
+	#[cfg(target_arch = "arm")]
+	{
+		let result: i32;
+		unsafe { llvm_asm!("qadd $0, $1, $2" : "=r"(result) : "r"(a), "r"(b)) }
+		result
+	}
+	#[cfg(not(target_arch = "arm"))]
+	a.saturating_add(b)
+}
+
+/// Adds each sample of `src` to the one in `dst`, saturating instead of
+/// wrapping around, which mixes two sounds without loud clicks when they
+/// overflow. Stops at the end of the shorter slice.
+pub fn mix_i16(dst: &mut [i16], src: &[i16]) {
+	for (dst, &src) in dst.iter_mut().zip(src) {
+		// In the top half of a word, the 32 bit saturation is 16 bit saturation
+		*dst = (qadd(i32::from(*dst) << 16, i32::from(src) << 16) >> 16) as i16;
+	}
+}
+
+/// Adds each byte of `src` to the one in `dst`, saturating at 255, such as
+/// for additive blending of color channels. Stops at the end of the shorter
+/// slice.
+pub fn add_saturating_u8(dst: &mut [u8], src: &[u8]) {
+	let len = dst.len().min(src.len());
+	let (dst, src) = (&mut dst[..len], &src[..len]);
+	let mut dst_words = dst.chunks_exact_mut(4);
+	let mut src_words = src.chunks_exact(4);
+	for (dst, src) in (&mut dst_words).zip(&mut src_words) {
+		let a = u32::from_ne_bytes([dst[0], dst[1], dst[2], dst[3]]);
+		let b = u32::from_ne_bytes([src[0], src[1], src[2], src[3]]);
+		// Four bytes at once: add the low 7 bits, then work out the top bit
+		// and whether each byte carried out of it
+		let low = (a & 0x7F7F_7F7F) + (b & 0x7F7F_7F7F);
+		let sum = low ^ ((a ^ b) & 0x8080_8080);
+		let carry = ((a & b) | ((a | b) & !sum)) & 0x8080_8080;
+		let result = sum | (carry >> 7) * 0xFF;
+		dst.copy_from_slice(&result.to_ne_bytes());
+	}
+	let dst = dst_words.into_remainder();
+	for (dst, &src) in dst.iter_mut().zip(src_words.remainder()) {
+		*dst = dst.saturating_add(src);
+	}
+}
+
+/// Multiplies the bottom halves of `a` and `b` and the top halves, as signed
+/// 16 bit numbers, and adds both to `acc`
+#[inline(always)]
+fn mac_pair(acc: i32, a: u32, b: u32) -> i32 {
+	#[cfg(target_arch = "arm")]
+	{
+		let mut acc = acc;
+		unsafe {
+			llvm_asm!("
+				smlabb $0, $1, $2, $0
+				smlatt $0, $1, $2, $0
+				"
+				: "+r"(acc)
+				: "r"(a), "r"(b)
+			)
+		}
+		acc
+	}
+	#[cfg(not(target_arch = "arm"))]
+	{
+		let bottom = i32::from(a as i16) * i32::from(b as i16);
+		let top = i32::from((a >> 16) as i16) * i32::from((b >> 16) as i16);
+		acc.wrapping_add(bottom).wrapping_add(top)
+	}
+}
+
+/// Returns the sum of `a[i] * b[i]`, such as for filters or mixing with
+/// volumes. Stops at the end of the shorter slice. The sum wraps around if it
+/// doesn't fit in an `i32`.
+pub fn dot_i16(a: &[i16], b: &[i16]) -> i32 {
+	let len = a.len().min(b.len());
+	let (a, b) = (&a[..len], &b[..len]);
+	let pack = |pair: &[i16]| u32::from(pair[0] as u16) | u32::from(pair[1] as u16) << 16;
+	let mut a_pairs = a.chunks_exact(2);
+	let mut b_pairs = b.chunks_exact(2);
+	let mut acc = 0;
+	for (a, b) in (&mut a_pairs).zip(&mut b_pairs) {
+		acc = mac_pair(acc, pack(a), pack(b));
+	}
+	match (a_pairs.remainder(), b_pairs.remainder()) {
+		(&[a], &[b]) => acc.wrapping_add(i32::from(a) * i32::from(b)),
+		_ => acc,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn dot_i16_stops_at_the_shorter_slice() {
+		assert_eq!(dot_i16(&[1, 2, 3, 4, 5], &[10, 20, 30]), 140);
+		assert_eq!(dot_i16(&[1, 2, 3, 4], &[10, 20, 30]), 140);
+		assert_eq!(dot_i16(&[10, 20, 30], &[1, 2, 3, 4, 5]), 140);
+		assert_eq!(dot_i16(&[1, -2], &[3, 4, 5]), -5);
+		assert_eq!(dot_i16(&[], &[1]), 0);
+	}
+}
@@ -0,0 +1,433 @@
+//! Fixed-point numbers
+//!
+//! The calculator's CPU has no floating point unit, so every `f32` operation
+//! is a call into a software library. Fixed-point numbers are plain integers
+//! with an implied binary point, so adding them is one instruction, and
+//! multiplying a few:
+//!
+//! ```
+//! use ndless::math::fixed::Fx16_16;
+//!
+//! let gravity = Fx16_16::from_f32(9.81);
+//! let dt = Fx16_16::ONE / 60;
+//! velocity += gravity * dt;
+//! let angle = Fx16_16::atan2(velocity.y, velocity.x);
+//! let (sin, cos) = (angle.sin(), angle.cos());
+//! ```
+//!
+//! [`Fx16_16`] has 16 bits on each side of the point, for values up to
+//! ±32768 with a precision of about 0.000015, which suits positions and
+//! velocities. [`Fx8_24`] trades range for precision, for values up to ±128.
+//!
+//! Overflow wraps around, like integers in release builds. Trigonometry uses
+//! lookup tables, and is accurate to about 0.0001.
+
+use core::fmt;
+use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, Sub, SubAssign};
+
+/// sin(i * π / 512) in 16.16, for a quarter of a turn
+#[rustfmt::skip]
+const SIN: [i32; 257] = [
+	0, 402, 804, 1206, 1608, 2010, 2412, 2814,
+	3216, 3617, 4019, 4420, 4821, 5222, 5623, 6023,
+	6424, 6824, 7224, 7623, 8022, 8421, 8820, 9218,
+	9616, 10014, 10411, 10808, 11204, 11600, 11996, 12391,
+	12785, 13180, 13573, 13966, 14359, 14751, 15143, 15534,
+	15924, 16314, 16703, 17091, 17479, 17867, 18253, 18639,
+	19024, 19409, 19792, 20175, 20557, 20939, 21320, 21699,
+	22078, 22457, 22834, 23210, 23586, 23961, 24335, 24708,
+	25080, 25451, 25821, 26190, 26558, 26925, 27291, 27656,
+	28020, 28383, 28745, 29106, 29466, 29824, 30182, 30538,
+	30893, 31248, 31600, 31952, 32303, 32652, 33000, 33347,
+	33692, 34037, 34380, 34721, 35062, 35401, 35738, 36075,
+	36410, 36744, 37076, 37407, 37736, 38064, 38391, 38716,
+	39040, 39362, 39683, 40002, 40320, 40636, 40951, 41264,
+	41576, 41886, 42194, 42501, 42806, 43110, 43412, 43713,
+	44011, 44308, 44604, 44898, 45190, 45480, 45769, 46056,
+	46341, 46624, 46906, 47186, 47464, 47741, 48015, 48288,
+	48559, 48828, 49095, 49361, 49624, 49886, 50146, 50404,
+	50660, 50914, 51166, 51417, 51665, 51911, 52156, 52398,
+	52639, 52878, 53114, 53349, 53581, 53812, 54040, 54267,
+	54491, 54714, 54934, 55152, 55368, 55582, 55794, 56004,
+	56212, 56418, 56621, 56823, 57022, 57219, 57414, 57607,
+	57798, 57986, 58172, 58356, 58538, 58718, 58896, 59071,
+	59244, 59415, 59583, 59750, 59914, 60075, 60235, 60392,
+	60547, 60700, 60851, 60999, 61145, 61288, 61429, 61568,
+	61705, 61839, 61971, 62101, 62228, 62353, 62476, 62596,
+	62714, 62830, 62943, 63054, 63162, 63268, 63372, 63473,
+	63572, 63668, 63763, 63854, 63944, 64031, 64115, 64197,
+	64277, 64354, 64429, 64501, 64571, 64639, 64704, 64766,
+	64827, 64884, 64940, 64993, 65043, 65091, 65137, 65180,
+	65220, 65259, 65294, 65328, 65358, 65387, 65413, 65436,
+	65457, 65476, 65492, 65505, 65516, 65525, 65531, 65535,
+	65536,
+];
+
+/// atan(i / 256) in 16.16
+#[rustfmt::skip]
+const ATAN: [i32; 257] = [
+	0, 256, 512, 768, 1024, 1280, 1536, 1792,
+	2047, 2303, 2559, 2814, 3070, 3325, 3580, 3836,
+	4091, 4346, 4600, 4855, 5110, 5364, 5618, 5872,
+	6126, 6380, 6633, 6887, 7140, 7392, 7645, 7898,
+	8150, 8402, 8653, 8905, 9156, 9407, 9657, 9908,
+	10158, 10408, 10657, 10906, 11155, 11403, 11652, 11899,
+	12147, 12394, 12641, 12887, 13133, 13379, 13624, 13869,
+	14114, 14358, 14601, 14845, 15088, 15330, 15572, 15814,
+	16055, 16296, 16536, 16776, 17015, 17254, 17492, 17730,
+	17968, 18205, 18441, 18677, 18913, 19148, 19382, 19616,
+	19850, 20083, 20315, 20547, 20779, 21009, 21240, 21469,
+	21699, 21927, 22156, 22383, 22610, 22836, 23062, 23288,
+	23512, 23737, 23960, 24183, 24406, 24627, 24849, 25069,
+	25289, 25509, 25727, 25946, 26163, 26380, 26597, 26813,
+	27028, 27242, 27456, 27670, 27882, 28094, 28306, 28517,
+	28727, 28936, 29145, 29354, 29561, 29768, 29975, 30180,
+	30386, 30590, 30794, 30997, 31200, 31402, 31603, 31803,
+	32003, 32203, 32401, 32600, 32797, 32994, 33190, 33385,
+	33580, 33774, 33968, 34160, 34353, 34544, 34735, 34925,
+	35115, 35304, 35492, 35680, 35867, 36053, 36239, 36424,
+	36608, 36792, 36975, 37158, 37340, 37521, 37701, 37881,
+	38060, 38239, 38417, 38594, 38771, 38947, 39123, 39297,
+	39472, 39645, 39818, 39990, 40162, 40333, 40503, 40673,
+	40842, 41010, 41178, 41346, 41512, 41678, 41844, 42008,
+	42172, 42336, 42499, 42661, 42823, 42984, 43145, 43304,
+	43464, 43622, 43780, 43938, 44095, 44251, 44407, 44562,
+	44716, 44870, 45024, 45176, 45328, 45480, 45631, 45781,
+	45931, 46080, 46229, 46377, 46525, 46672, 46818, 46964,
+	47109, 47254, 47398, 47542, 47685, 47827, 47969, 48111,
+	48251, 48392, 48531, 48671, 48809, 48947, 49085, 49222,
+	49359, 49495, 49630, 49765, 49899, 50033, 50167, 50299,
+	50432, 50563, 50695, 50826, 50956, 51086, 51215, 51344,
+	51472,
+];
+
+/// π in 16.16
+const PI_16: i32 = 205_887;
+/// 1024 / 2π in 16.16, to turn radians into steps of the sine table
+const STEPS_PER_RADIAN_16: i64 = 10_680_707;
+
+/// The sine of step `step` of 1024 in a turn, in 16.16
+fn sin_step(step: i64) -> i32 {
+	let step = (step & 1023) as usize;
+	match step >> 8 {
+		0 => SIN[step],
+		1 => SIN[512 - step],
+		2 => -SIN[step - 512],
+		_ => -SIN[1024 - step],
+	}
+}
+
+/// The sine of `radians`, both in 16.16
+fn sin_16(radians: i32) -> i32 {
+	let steps = i64::from(radians) * STEPS_PER_RADIAN_16 >> 16;
+	let (step, frac) = (steps >> 16, steps & 0xFFFF);
+	let (a, b) = (sin_step(step), sin_step(step + 1));
+	a + ((i64::from(b - a) * frac) >> 16) as i32
+}
+
+/// The arctangent of `ratio`, which is from 0 to 1, both in 16.16
+fn atan_16(ratio: i32) -> i32 {
+	let ratio = ratio.max(0).min(1 << 16);
+	let (index, frac) = ((ratio >> 8) as usize, ratio & 0xFF);
+	let a = ATAN[index];
+	let b = ATAN[(index + 1).min(256)];
+	a + ((b - a) * frac >> 8)
+}
+
+/// The angle of `(x, y)` from the x axis, from -π to π, all in 16.16
+fn atan2_16(y: i64, x: i64) -> i32 {
+	if x == 0 && y == 0 {
+		return 0;
+	}
+	let (ax, ay) = (x.abs(), y.abs());
+	let angle = if ax >= ay {
+		atan_16(((ay << 16) / ax) as i32)
+	} else {
+		PI_16 / 2 - atan_16(((ax << 16) / ay) as i32)
+	};
+	let angle = if x < 0 { PI_16 - angle } else { angle };
+	if y < 0 {
+		-angle
+	} else {
+		angle
+	}
+}
+
+fn isqrt(value: u64) -> u64 {
+	let mut result = 0;
+	let mut bit = 1 << 62;
+	let mut value = value;
+	while bit > value {
+		bit >>= 2;
+	}
+	while bit != 0 {
+		if value >= result + bit {
+			value -= result + bit;
+			result = (result >> 1) + bit;
+		} else {
+			result >>= 1;
+		}
+		bit >>= 2;
+	}
+	result
+}
+
+macro_rules! fixed {
+	($name:ident, $frac:expr, $doc:expr) => {
+		#[doc = $doc]
+		#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Copy, Hash, Default)]
+		pub struct $name(i32);
+
+		impl $name {
+			/// The number of fractional bits.
+			pub const FRAC_BITS: u32 = $frac;
+			pub const ZERO: $name = $name(0);
+			pub const ONE: $name = $name(1 << $frac);
+			pub const HALF: $name = $name(1 << ($frac - 1));
+			pub const MIN: $name = $name(i32::MIN);
+			pub const MAX: $name = $name(i32::MAX);
+			/// The smallest step between two values.
+			pub const EPSILON: $name = $name(1);
+			pub const PI: $name = $name::from_16_16(PI_16);
+			pub const FRAC_PI_2: $name = $name::from_16_16(PI_16 / 2);
+			pub const TAU: $name = $name::from_16_16(PI_16 * 2);
+
+			/// Creates a number from its raw bits, which are the value times
+			/// 2 to the power of [`FRAC_BITS`][Self::FRAC_BITS].
+			pub const fn from_bits(bits: i32) -> Self {
+				$name(bits)
+			}
+
+			pub const fn to_bits(self) -> i32 {
+				self.0
+			}
+
+			pub const fn from_int(value: i32) -> Self {
+				$name(value << $frac)
+			}
+
+			pub fn from_f32(value: f32) -> Self {
+				let scaled = value * (1u32 << $frac) as f32;
+				$name(if scaled >= 0.0 {
+					scaled + 0.5
+				} else {
+					scaled - 0.5
+				} as i32)
+			}
+
+			pub fn to_f32(self) -> f32 {
+				self.0 as f32 / (1u32 << $frac) as f32
+			}
+
+			const fn from_16_16(bits: i32) -> Self {
+				if $frac >= 16 {
+					$name(bits << ($frac - 16))
+				} else {
+					$name(bits >> (16 - $frac))
+				}
+			}
+
+			fn to_16_16(self) -> i32 {
+				if $frac >= 16 {
+					self.0 >> ($frac - 16)
+				} else {
+					self.0 << (16 - $frac)
+				}
+			}
+
+			/// Rounds towards negative infinity, to an integer.
+			pub const fn to_int(self) -> i32 {
+				self.0 >> $frac
+			}
+
+			/// Rounds to the nearest integer, with halves rounded up.
+			pub const fn round(self) -> i32 {
+				self.0.wrapping_add(1 << ($frac - 1)) >> $frac
+			}
+
+			pub const fn floor(self) -> Self {
+				$name(self.0 & !((1 << $frac) - 1))
+			}
+
+			/// The part after the point, which is never negative.
+			pub const fn fract(self) -> Self {
+				$name(self.0 & ((1 << $frac) - 1))
+			}
+
+			pub const fn abs(self) -> Self {
+				$name(self.0.wrapping_abs())
+			}
+
+			pub fn min(self, other: Self) -> Self {
+				core::cmp::min(self, other)
+			}
+
+			pub fn max(self, other: Self) -> Self {
+				core::cmp::max(self, other)
+			}
+
+			pub fn clamp(self, min: Self, max: Self) -> Self {
+				self.max(min).min(max)
+			}
+
+			/// Multiplies, saturating at [`MIN`][Self::MIN] and
+			/// [`MAX`][Self::MAX] instead of wrapping.
+			pub fn saturating_mul(self, other: Self) -> Self {
+				let product = (i64::from(self.0) * i64::from(other.0)) >> $frac;
+				$name(product.max(i64::from(i32::MIN)).min(i64::from(i32::MAX)) as i32)
+			}
+
+			/// The square root, or zero for negative numbers.
+			pub fn sqrt(self) -> Self {
+				if self.0 <= 0 {
+					return Self::ZERO;
+				}
+				$name(isqrt((self.0 as u64) << $frac) as i32)
+			}
+
+			/// The sine of an angle in radians.
+			pub fn sin(self) -> Self {
+				Self::from_16_16(sin_16(self.to_16_16()))
+			}
+
+			/// The cosine of an angle in radians.
+			pub fn cos(self) -> Self {
+				Self::from_16_16(sin_16(self.to_16_16().wrapping_add(PI_16 / 2)))
+			}
+
+			/// The angle of the point `(x, y)` from the x axis, in radians from
+			/// -π to π.
+			pub fn atan2(y: Self, x: Self) -> Self {
+				Self::from_16_16(atan2_16(i64::from(y.0), i64::from(x.0)))
+			}
+		}
+
+		impl From<$name> for f32 {
+			fn from(value: $name) -> f32 {
+				value.to_f32()
+			}
+		}
+
+		impl fmt::Display for $name {
+			fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+				fmt::Display::fmt(&self.to_f32(), f)
+			}
+		}
+
+		impl Add for $name {
+			type Output = Self;
+
+			fn add(self, other: Self) -> Self {
+				$name(self.0.wrapping_add(other.0))
+			}
+		}
+
+		impl Sub for $name {
+			type Output = Self;
+
+			fn sub(self, other: Self) -> Self {
+				$name(self.0.wrapping_sub(other.0))
+			}
+		}
+
+		impl Mul for $name {
+			type Output = Self;
+
+			fn mul(self, other: Self) -> Self {
+				$name(((i64::from(self.0) * i64::from(other.0)) >> $frac) as i32)
+			}
+		}
+
+		/// # Panics
+		/// Panics if `other` is zero.
+		impl Div for $name {
+			type Output = Self;
+
+			fn div(self, other: Self) -> Self {
+				$name(((i64::from(self.0) << $frac) / i64::from(other.0)) as i32)
+			}
+		}
+
+		impl Rem for $name {
+			type Output = Self;
+
+			fn rem(self, other: Self) -> Self {
+				$name(self.0 % other.0)
+			}
+		}
+
+		impl Mul<i32> for $name {
+			type Output = Self;
+
+			fn mul(self, other: i32) -> Self {
+				$name(self.0.wrapping_mul(other))
+			}
+		}
+
+		impl Div<i32> for $name {
+			type Output = Self;
+
+			fn div(self, other: i32) -> Self {
+				$name(self.0 / other)
+			}
+		}
+
+		impl Neg for $name {
+			type Output = Self;
+
+			fn neg(self) -> Self {
+				$name(self.0.wrapping_neg())
+			}
+		}
+
+		impl AddAssign for $name {
+			fn add_assign(&mut self, other: Self) {
+				*self = *self + other;
+			}
+		}
+
+		impl SubAssign for $name {
+			fn sub_assign(&mut self, other: Self) {
+				*self = *self - other;
+			}
+		}
+
+		impl MulAssign for $name {
+			fn mul_assign(&mut self, other: Self) {
+				*self = *self * other;
+			}
+		}
+
+		impl DivAssign for $name {
+			fn div_assign(&mut self, other: Self) {
+				*self = *self / other;
+			}
+		}
+	};
+}
+
+fixed!(
+	Fx16_16,
+	16,
+	"A fixed-point number with 16 integer bits and 16 fractional bits. See the \
+	 [module documentation][self]."
+);
+fixed!(
+	Fx8_24,
+	24,
+	"A fixed-point number with 8 integer bits and 24 fractional bits. See the \
+	 [module documentation][self]."
+);
+
+impl From<Fx16_16> for Fx8_24 {
+	/// Wraps around if the value is outside ±128.
+	fn from(value: Fx16_16) -> Self {
+		Fx8_24::from_bits(value.to_bits() << 8)
+	}
+}
+
+impl From<Fx8_24> for Fx16_16 {
+	fn from(value: Fx8_24) -> Self {
+		Fx16_16::from_bits(value.to_bits() >> 8)
+	}
+}
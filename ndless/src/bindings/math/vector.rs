@@ -0,0 +1,518 @@
+//! Vectors and matrices
+//!
+//! Small types for 2D and 3D graphics, such as spinning wireframe models:
+//!
+//! ```
+//! use ndless::math::fixed::Fx16_16;
+//! use ndless::math::{Mat4, Vec3};
+//!
+//! let f = Fx16_16::from_f32;
+//! let projection = Mat4::perspective(f(1.0), f(320.0 / 240.0), f(0.1), f(100.0));
+//! let camera = Mat4::look_at(
+//!     Vec3::new(f(0.0), f(0.0), f(5.0)),
+//!     Vec3::zero(),
+//!     Vec3::new(f(0.0), f(1.0), f(0.0)),
+//! );
+//! let transform = projection * camera * Mat4::rotation_y(angle);
+//! for &(a, b) in &edges {
+//!     if let (Some(a), Some(b)) = (
+//!         transform.transform_point(vertices[a]),
+//!         transform.transform_point(vertices[b]),
+//!     ) {
+//!         let (a, b) = (a.to_screen(320, 240), b.to_screen(320, 240));
+//!         screen.draw_line(a, b, color);
+//!     }
+//! }
+//! ```
+//!
+//! Every type works with any [`Scalar`]: `f32`, [`Fx16_16`] or [`Fx8_24`],
+//! defaulting to `f32`. There is no `f64` version, and the `f32` one uses
+//! [`Float`] methods for `f32` throughout, so nothing is quietly promoted to
+//! the much slower `f64`. As the CPU has no floating point unit, [`Fx16_16`]
+//! is usually fastest, as long as values stay in its range. Squared lengths
+//! are the first to overflow, as the sum of the squared components has to
+//! stay below the largest value the type holds. Before calling `length`,
+//! `length_squared`, `normalize` or `dot` on a vector, keep its components
+//! below:
+//!
+//! | Type           | [`Vec2`] | [`Vec3`] |
+//! |----------------|----------|----------|
+//! | [`Fx16_16`]    | 128      | 104      |
+//! | [`Fx8_24`]     | 8        | 6.5      |
+//!
+//! These are √(32768 / 2), √(32768 / 3), √(128 / 2) and √(128 / 3), rounded
+//! down.
+//!
+//! Matrices follow the OpenGL conventions: they multiply column vectors on
+//! their right, the camera looks down -Z, and projections map what is visible
+//! to -1 to 1 on each axis.
+
+use core::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+use super::fixed::{Fx16_16, Fx8_24};
+use super::Float;
+
+/// A number type that vectors and matrices can hold.
+pub trait Scalar:
+	Copy
+	+ PartialOrd
+	+ Add<Output = Self>
+	+ Sub<Output = Self>
+	+ Mul<Output = Self>
+	+ Div<Output = Self>
+	+ Neg<Output = Self>
+{
+	const ZERO: Self;
+	const ONE: Self;
+
+	fn from_f32(value: f32) -> Self;
+	fn from_i32(value: i32) -> Self;
+	/// Rounds to the nearest integer.
+	fn to_i32(self) -> i32;
+	fn sqrt(self) -> Self;
+	fn sin(self) -> Self;
+	fn cos(self) -> Self;
+}
+
+impl Scalar for f32 {
+	const ZERO: Self = 0.0;
+	const ONE: Self = 1.0;
+
+	fn from_f32(value: f32) -> Self {
+		value
+	}
+
+	fn from_i32(value: i32) -> Self {
+		value as f32
+	}
+
+	fn to_i32(self) -> i32 {
+		Float::round(self) as i32
+	}
+
+	fn sqrt(self) -> Self {
+		Float::sqrt(self)
+	}
+
+	fn sin(self) -> Self {
+		Float::sin(self)
+	}
+
+	fn cos(self) -> Self {
+		Float::cos(self)
+	}
+}
+
+macro_rules! fixed_scalar {
+	($($ty:ident),*) => {$(
+		impl Scalar for $ty {
+			const ZERO: Self = $ty::ZERO;
+			const ONE: Self = $ty::ONE;
+
+			fn from_f32(value: f32) -> Self {
+				$ty::from_f32(value)
+			}
+
+			fn from_i32(value: i32) -> Self {
+				$ty::from_int(value)
+			}
+
+			fn to_i32(self) -> i32 {
+				self.round()
+			}
+
+			fn sqrt(self) -> Self {
+				$ty::sqrt(self)
+			}
+
+			fn sin(self) -> Self {
+				$ty::sin(self)
+			}
+
+			fn cos(self) -> Self {
+				$ty::cos(self)
+			}
+		}
+	)*};
+}
+
+fixed_scalar!(Fx16_16, Fx8_24);
+
+/// A 2D vector.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, Default)]
+pub struct Vec2<T = f32> {
+	pub x: T,
+	pub y: T,
+}
+
+/// A 3D vector, or a point in 3D space.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, Default)]
+pub struct Vec3<T = f32> {
+	pub x: T,
+	pub y: T,
+	pub z: T,
+}
+
+impl<T> Vec2<T> {
+	pub const fn new(x: T, y: T) -> Self {
+		Self { x, y }
+	}
+}
+
+impl<T> Vec3<T> {
+	pub const fn new(x: T, y: T, z: T) -> Self {
+		Self { x, y, z }
+	}
+}
+
+impl<T: Scalar> Vec2<T> {
+	pub fn zero() -> Self {
+		Self::new(T::ZERO, T::ZERO)
+	}
+
+	pub fn dot(self, other: Self) -> T {
+		self.x * other.x + self.y * other.y
+	}
+
+	/// The z component of the 3D cross product, which is positive when
+	/// `other` is counterclockwise from `self`.
+	pub fn perp_dot(self, other: Self) -> T {
+		self.x * other.y - self.y * other.x
+	}
+
+	pub fn length_squared(self) -> T {
+		self.dot(self)
+	}
+
+	pub fn length(self) -> T {
+		self.length_squared().sqrt()
+	}
+
+	/// Returns a vector in the same direction with a length of 1, or zero if
+	/// `self` is zero.
+	pub fn normalize(self) -> Self {
+		let length = self.length();
+		if length == T::ZERO {
+			self
+		} else {
+			self / length
+		}
+	}
+
+	/// Rotates counterclockwise by `angle` radians.
+	pub fn rotate(self, angle: T) -> Self {
+		let (sin, cos) = (angle.sin(), angle.cos());
+		Self::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+	}
+
+	pub fn extend(self, z: T) -> Vec3<T> {
+		Vec3::new(self.x, self.y, z)
+	}
+}
+
+impl<T: Scalar> Vec3<T> {
+	pub fn zero() -> Self {
+		Self::new(T::ZERO, T::ZERO, T::ZERO)
+	}
+
+	pub fn dot(self, other: Self) -> T {
+		self.x * other.x + self.y * other.y + self.z * other.z
+	}
+
+	pub fn cross(self, other: Self) -> Self {
+		Self::new(
+			self.y * other.z - self.z * other.y,
+			self.z * other.x - self.x * other.z,
+			self.x * other.y - self.y * other.x,
+		)
+	}
+
+	pub fn length_squared(self) -> T {
+		self.dot(self)
+	}
+
+	pub fn length(self) -> T {
+		self.length_squared().sqrt()
+	}
+
+	/// Returns a vector in the same direction with a length of 1, or zero if
+	/// `self` is zero.
+	pub fn normalize(self) -> Self {
+		let length = self.length();
+		if length == T::ZERO {
+			self
+		} else {
+			self / length
+		}
+	}
+
+	pub fn truncate(self) -> Vec2<T> {
+		Vec2::new(self.x, self.y)
+	}
+
+	/// Converts a point from -1 to 1, as returned by
+	/// [`Mat4::transform_point`] with a projection, to pixel coordinates on a
+	/// screen of `width` by `height`. +Y goes up the screen.
+	pub fn to_screen(self, width: i32, height: i32) -> (i32, i32) {
+		let half_width = T::from_i32(width) / T::from_i32(2);
+		let half_height = T::from_i32(height) / T::from_i32(2);
+		(
+			((self.x + T::ONE) * half_width).to_i32(),
+			((T::ONE - self.y) * half_height).to_i32(),
+		)
+	}
+}
+
+macro_rules! vector_ops {
+	($name:ident, $($field:ident),*) => {
+		impl<T: Scalar> Add for $name<T> {
+			type Output = Self;
+
+			fn add(self, rhs: Self) -> Self {
+				Self { $($field: self.$field + rhs.$field),* }
+			}
+		}
+
+		impl<T: Scalar> Sub for $name<T> {
+			type Output = Self;
+
+			fn sub(self, rhs: Self) -> Self {
+				Self { $($field: self.$field - rhs.$field),* }
+			}
+		}
+
+		impl<T: Scalar> Mul<T> for $name<T> {
+			type Output = Self;
+
+			fn mul(self, rhs: T) -> Self {
+				Self { $($field: self.$field * rhs),* }
+			}
+		}
+
+		impl<T: Scalar> Div<T> for $name<T> {
+			type Output = Self;
+
+			fn div(self, rhs: T) -> Self {
+				Self { $($field: self.$field / rhs),* }
+			}
+		}
+
+		impl<T: Scalar> Neg for $name<T> {
+			type Output = Self;
+
+			fn neg(self) -> Self {
+				Self { $($field: -self.$field),* }
+			}
+		}
+
+		impl<T: Scalar> AddAssign for $name<T> {
+			fn add_assign(&mut self, rhs: Self) {
+				*self = *self + rhs;
+			}
+		}
+
+		impl<T: Scalar> SubAssign for $name<T> {
+			fn sub_assign(&mut self, rhs: Self) {
+				*self = *self - rhs;
+			}
+		}
+	};
+}
+
+vector_ops!(Vec2, x, y);
+vector_ops!(Vec3, x, y, z);
+
+/// A 4x4 matrix, for transforming 3D points. See the
+/// [module documentation][self].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Mat4<T = f32> {
+	/// The elements, as `rows[row][column]`
+	pub rows: [[T; 4]; 4],
+}
+
+impl<T: Scalar> Default for Mat4<T> {
+	fn default() -> Self {
+		Self::identity()
+	}
+}
+
+impl<T: Scalar> Mat4<T> {
+	pub fn identity() -> Self {
+		Self::scaling(Vec3::new(T::ONE, T::ONE, T::ONE))
+	}
+
+	pub fn translation(offset: Vec3<T>) -> Self {
+		let (o, l) = (T::ZERO, T::ONE);
+		Self {
+			rows: [
+				[l, o, o, offset.x],
+				[o, l, o, offset.y],
+				[o, o, l, offset.z],
+				[o, o, o, l],
+			],
+		}
+	}
+
+	pub fn scaling(scale: Vec3<T>) -> Self {
+		let (o, l) = (T::ZERO, T::ONE);
+		Self {
+			rows: [
+				[scale.x, o, o, o],
+				[o, scale.y, o, o],
+				[o, o, scale.z, o],
+				[o, o, o, l],
+			],
+		}
+	}
+
+	/// Rotates counterclockwise by `angle` radians, looking from +X towards
+	/// the origin.
+	pub fn rotation_x(angle: T) -> Self {
+		let (o, l) = (T::ZERO, T::ONE);
+		let (sin, cos) = (angle.sin(), angle.cos());
+		Self {
+			rows: [
+				[l, o, o, o],
+				[o, cos, -sin, o],
+				[o, sin, cos, o],
+				[o, o, o, l],
+			],
+		}
+	}
+
+	/// Rotates counterclockwise by `angle` radians, looking from +Y towards
+	/// the origin.
+	pub fn rotation_y(angle: T) -> Self {
+		let (o, l) = (T::ZERO, T::ONE);
+		let (sin, cos) = (angle.sin(), angle.cos());
+		Self {
+			rows: [
+				[cos, o, sin, o],
+				[o, l, o, o],
+				[-sin, o, cos, o],
+				[o, o, o, l],
+			],
+		}
+	}
+
+	/// Rotates counterclockwise by `angle` radians, looking from +Z towards
+	/// the origin.
+	pub fn rotation_z(angle: T) -> Self {
+		let (o, l) = (T::ZERO, T::ONE);
+		let (sin, cos) = (angle.sin(), angle.cos());
+		Self {
+			rows: [
+				[cos, -sin, o, o],
+				[sin, cos, o, o],
+				[o, o, l, o],
+				[o, o, o, l],
+			],
+		}
+	}
+
+	/// A perspective projection with a vertical field of view of `fov_y`
+	/// radians, where `aspect` is the width divided by the height. Only points
+	/// between `near` and `far` in front of the camera are visible.
+	pub fn perspective(fov_y: T, aspect: T, near: T, far: T) -> Self {
+		let (o, l) = (T::ZERO, T::ONE);
+		let two = l + l;
+		let half = fov_y / two;
+		let f = half.cos() / half.sin();
+		let depth = near - far;
+		Self {
+			rows: [
+				[f / aspect, o, o, o],
+				[o, f, o, o],
+				[o, o, (far + near) / depth, two * far * near / depth],
+				[o, o, -l, o],
+			],
+		}
+	}
+
+	/// An orthographic projection, where the box from `left`, `bottom` and
+	/// `-near` to `right`, `top` and `-far` is visible. Unlike
+	/// [`perspective`][Self::perspective], distant objects aren't smaller.
+	pub fn orthographic(left: T, right: T, bottom: T, top: T, near: T, far: T) -> Self {
+		let (o, l) = (T::ZERO, T::ONE);
+		let two = l + l;
+		let (width, height, depth) = (right - left, top - bottom, far - near);
+		Self {
+			rows: [
+				[two / width, o, o, -(right + left) / width],
+				[o, two / height, o, -(top + bottom) / height],
+				[o, o, -two / depth, -(far + near) / depth],
+				[o, o, o, l],
+			],
+		}
+	}
+
+	/// Moves the world so a camera at `eye` looks at `target`, with `up`
+	/// towards the top of the screen.
+	pub fn look_at(eye: Vec3<T>, target: Vec3<T>, up: Vec3<T>) -> Self {
+		let (o, l) = (T::ZERO, T::ONE);
+		let forward = (target - eye).normalize();
+		let side = forward.cross(up).normalize();
+		let up = side.cross(forward);
+		Self {
+			rows: [
+				[side.x, side.y, side.z, -side.dot(eye)],
+				[up.x, up.y, up.z, -up.dot(eye)],
+				[-forward.x, -forward.y, -forward.z, forward.dot(eye)],
+				[o, o, o, l],
+			],
+		}
+	}
+
+	pub fn transpose(self) -> Self {
+		let mut rows = self.rows;
+		for (i, row) in rows.iter_mut().enumerate() {
+			for (j, element) in row.iter_mut().enumerate() {
+				*element = self.rows[j][i];
+			}
+		}
+		Self { rows }
+	}
+
+	/// Transforms a point, including translation and the perspective divide.
+	/// Returns `None` if the point ends up at or behind the camera of a
+	/// perspective projection, where it can't be drawn.
+	pub fn transform_point(&self, point: Vec3<T>) -> Option<Vec3<T>> {
+		let r = &self.rows;
+		let apply = |row: &[T; 4]| row[0] * point.x + row[1] * point.y + row[2] * point.z + row[3];
+		let w = apply(&r[3]);
+		if w <= T::ZERO {
+			return None;
+		}
+		let transformed = Vec3::new(apply(&r[0]), apply(&r[1]), apply(&r[2]));
+		Some(if w == T::ONE {
+			transformed
+		} else {
+			transformed / w
+		})
+	}
+
+	/// Transforms a direction, ignoring translation.
+	pub fn transform_vector(&self, vector: Vec3<T>) -> Vec3<T> {
+		let apply = |row: &[T; 4]| row[0] * vector.x + row[1] * vector.y + row[2] * vector.z;
+		Vec3::new(
+			apply(&self.rows[0]),
+			apply(&self.rows[1]),
+			apply(&self.rows[2]),
+		)
+	}
+}
+
+impl<T: Scalar> Mul for Mat4<T> {
+	type Output = Self;
+
+	/// Combines two transforms, applying `rhs` first.
+	fn mul(self, rhs: Self) -> Self {
+		let mut rows = [[T::ZERO; 4]; 4];
+		for (i, row) in rows.iter_mut().enumerate() {
+			for (j, element) in row.iter_mut().enumerate() {
+				*element = (0..4).fold(T::ZERO, |sum, k| sum + self.rows[i][k] * rhs.rows[k][j]);
+			}
+		}
+		Self { rows }
+	}
+}
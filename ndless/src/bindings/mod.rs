@@ -1,10 +1,37 @@
+pub mod app;
+pub mod audio;
+pub mod autosave;
+pub mod collections;
+pub mod consent;
+pub mod crash;
 pub mod env;
+pub mod event;
+pub mod font;
+pub mod gfx;
+#[cfg(feature = "hal")]
+pub mod hal;
+pub mod heap;
 pub mod hw;
 pub mod input;
+pub mod interrupt;
+pub mod link;
 pub mod math;
 pub mod msg;
 pub mod ndless;
+pub mod os;
 pub mod out;
+pub mod panic;
 pub mod process;
+pub mod rand;
+pub mod replay;
+pub mod serial;
+pub mod telemetry;
 pub mod thread;
 pub mod timer;
+pub mod ui;
+pub mod usb;
+#[cfg(feature = "serde")]
+pub mod versioned;
+pub mod vfs;
+pub mod video;
+pub mod watchdog;
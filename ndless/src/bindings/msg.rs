@@ -1,8 +1,16 @@
 //! # Message boxes
 //! This module contains functions to display message boxes to the user.
+//!
+//! [`println!`] output can't be seen once the program exits, so errors are
+//! better reported in a message box. [`error_dialog!`][crate::error_dialog]
+//! formats a message and shows it, and
+//! [`msg_assert!`][crate::msg_assert] shows one and exits if a condition
+//! doesn't hold. Messages too long for one box are split into pages by
+//! [`msg_paged`].
 
 use alloc::string::String;
-use core::mem::transmute;
+use alloc::vec::Vec;
+use core::fmt;
 use core::slice;
 
 use cstr_core::CStr;
@@ -21,55 +29,117 @@ pub enum Button {
 
 /// Creates a dialog box with a single button, labeled "OK"
 pub fn msg(title: &str, msg: &str) {
+	show_msgbox(title, msg, &[]);
+}
+
+/// Shows a dialog box with up to three buttons, or just "OK" if `buttons` is
+/// empty. Returns the button pressed, or `None` if the box was closed without
+/// pressing one.
+pub(crate) fn show_msgbox(title: &str, msg: &str, buttons: &[&str]) -> Option<Button> {
 	let title = cstr!(title);
 	let msg = cstr!(msg);
-	unsafe {
-		_show_msgbox(title.as_ptr(), msg.as_ptr(), 0);
+	let buttons = buttons.iter().map(|&b| cstr!(b)).collect::<Vec<_>>();
+	let pressed = unsafe {
+		match &buttons[..] {
+			[] => _show_msgbox(title.as_ptr(), msg.as_ptr(), 0),
+			[one, two] => _show_msgbox(title.as_ptr(), msg.as_ptr(), 2, one.as_ptr(), two.as_ptr()),
+			[one, two, three] => _show_msgbox(
+				title.as_ptr(),
+				msg.as_ptr(),
+				3,
+				one.as_ptr(),
+				two.as_ptr(),
+				three.as_ptr(),
+			),
+			_ => panic!("a message box has 0, 2 or 3 buttons"),
+		}
+	};
+	match pressed {
+		1 => Some(Button::One),
+		2 => Some(Button::Two),
+		3 => Some(Button::Three),
+		_ => None,
 	}
 }
 
-/// Creates a dialog box with two buttons
-pub fn msg_2b(title: &str, msg: &str, btn1: &str, btn2: &str) -> Button {
-	let title = cstr!(title);
-	let msg = cstr!(msg);
-	let btn1 = cstr!(btn1);
-	let btn2 = cstr!(btn2);
-	unsafe {
-		transmute(_show_msgbox(
-			title.as_ptr(),
-			msg.as_ptr(),
-			2,
-			btn1.as_ptr(),
-			btn2.as_ptr(),
-		))
+/// The most text shown in a single message box
+const PAGE_LEN: usize = 400;
+
+/// Creates a dialog box with a single button, splitting `msg` over several
+/// boxes if it is too long for one. Each page but the last has a "Close"
+/// button to skip the rest.
+pub fn msg_paged(title: &str, msg: &str) {
+	let pages = paginate(msg, PAGE_LEN);
+	if pages.len() <= 1 {
+		return self::msg(title, msg);
+	}
+	for (i, page) in pages.iter().enumerate() {
+		let title = format!("{} ({}/{})", title, i + 1, pages.len());
+		if i + 1 == pages.len() {
+			self::msg(&title, page);
+		} else if msg_2b(&title, page, "Next", "Close") == Button::Two {
+			break;
+		}
 	}
 }
 
-/// Creates a dialog box with three buttons
-pub fn msg_3b(title: &str, msg: &str, btn1: &str, btn2: &str, btn3: &str) -> Button {
-	let title = cstr!(title);
-	let msg = cstr!(msg);
-	let btn1 = cstr!(btn1);
-	let btn2 = cstr!(btn2);
-	let btn3 = cstr!(btn3);
-	unsafe {
-		transmute(_show_msgbox(
-			title.as_ptr(),
-			msg.as_ptr(),
-			3,
-			btn1.as_ptr(),
-			btn2.as_ptr(),
-			btn3.as_ptr(),
-		))
+/// Splits `text` into pages of at most `len` bytes, breaking after a newline
+/// or space where possible.
+fn paginate(mut text: &str, len: usize) -> Vec<&str> {
+	let mut pages = Vec::new();
+	while text.len() > len {
+		let mut end = len;
+		while !text.is_char_boundary(end) {
+			end -= 1;
+		}
+		let end = text[..end]
+			.rfind('\n')
+			.or_else(|| text[..end].rfind(' '))
+			.map_or(end, |i| i + 1);
+		pages.push(text[..end].trim_end());
+		text = &text[end..];
 	}
+	pages.push(text);
+	pages
+}
+
+#[doc(hidden)]
+pub fn __error_dialog(args: fmt::Arguments) {
+	msg_paged("Error", &format!("{}", args));
+}
+
+#[doc(hidden)]
+pub fn __assert_failed(cond: &str, args: Option<fmt::Arguments>, file: &str, line: u32) -> ! {
+	let msg = match args {
+		Some(args) => format!("{}\n\n{} at {}:{}", args, cond, file, line),
+		None => format!("{}\n\nat {}:{}", cond, file, line),
+	};
+	// A Screen might have the LCD, which the message box needs
+	crate::video::restore();
+	msg_paged("Assertion failed", &msg);
+	// The same exit code as a Rust panic
+	crate::process::exit(101)
+}
+
+/// Creates a dialog box with two buttons. Closing it without pressing
+/// either counts as pressing the second.
+pub fn msg_2b(title: &str, msg: &str, btn1: &str, btn2: &str) -> Button {
+	show_msgbox(title, msg, &[btn1, btn2]).unwrap_or(Button::Two)
+}
+
+/// Creates a dialog box with three buttons. Closing it without pressing any
+/// counts as pressing the third.
+pub fn msg_3b(title: &str, msg: &str, btn1: &str, btn2: &str, btn3: &str) -> Button {
+	show_msgbox(title, msg, &[btn1, btn2, btn3]).unwrap_or(Button::Three)
 }
 
-/// Creates a dialog box with a numerical input
+/// Creates a dialog box with a numerical input, starting at 0, or the end of
+/// `range` nearest to it
 pub fn msg_numeric(title: &str, subtitle: &str, msg: &str, range: (i32, i32)) -> Option<i32> {
 	let title = cstr!(title);
 	let subtitle = cstr!(subtitle);
 	let msg = cstr!(msg);
-	let mut num = 0i32;
+	let mut num = 0i32.max(range.0).min(range.1);
 	match unsafe {
 		ndless_sys::show_1numeric_input(
 			title.as_ptr(),
@@ -128,7 +198,8 @@ pub fn msg_input(title: &str, msg: &str, default: &str) -> Option<String> {
 	let ret = match unsafe {
 		ndless_sys::show_msg_user_input(title.as_ptr(), msg.as_ptr(), default.as_ptr(), &mut ptr)
 	} {
-		-1 => None,
+		_ if ptr.is_null() => return None,
+		len if len < 0 => None,
 		len => unsafe {
 			Some(
 				CStr::from_bytes_with_nul_unchecked(slice::from_raw_parts(
@@ -0,0 +1,261 @@
+//! # OS and Ndless version checks
+//! Programs that use features only some calculators have can check for them
+//! at runtime, and turn the feature off instead of crashing:
+//!
+//! ```
+//! use ndless::os::{self, OsVersion, Requirement};
+//!
+//! // Exits with a message on an OS older than 4.0
+//! os::requires(OsVersion::new(4, 0, 0));
+//!
+//! let fancy_ui = Requirement::new()
+//!     .os(OsVersion::new(4, 5, 0))
+//!     .syscall(NEW_DIALOG_SYSCALL)
+//!     .is_met();
+//! ```
+//!
+//! Calling a syscall that the installed Ndless doesn't provide shows an error
+//! and exits, so check [`has_syscall`] first for any that were added to Ndless
+//! after the oldest version the program supports.
+//!
+//! An OS released after this crate is reported as unknown by
+//! [`os_version`]. It's assumed to be newer than every known version, so
+//! it meets every OS requirement.
+//!
+//! ## Values that depend on the OS
+//! Addresses inside the OS, and other values that change with each version,
+//! go in an [`OsValue`] table, which has an entry for each OS that Ndless
+//! supports:
+//!
+//! ```
+//! use ndless::os::{self, OsValue};
+//!
+//! static STATUS_BAR: OsValue<u32> = OsValue::new([
+//!     // 3.1.0, 3.1.0 CAS, 3.1.0 CX, ...
+//!     None, None, None, /* ... */
+//! ]);
+//! // Or computed from the version
+//! let max_name = OsValue::from_fn(|version, _cas| {
+//!     Some(if version.major >= 4 { 255 } else { 63 })
+//! });
+//!
+//! if let Some(address) = os::value(&STATUS_BAR) {
+//!     // ...
+//! }
+//! ```
+//!
+//! [`os::value`][value] returns `None` on an OS that isn't in the table, or
+//! whose entry is `None`, instead of the 0 that
+//! [`nl_osvalue`][ndless_sys::nl_osvalue] returns.
+
+use alloc::format;
+use core::fmt;
+
+use crate::error::Error;
+use crate::hw::OS_VERSIONS;
+use crate::msg;
+use crate::process;
+
+pub use crate::hw::{os_version, OsVersion};
+
+/// The number of OS versions that Ndless supports, and entries in an
+/// [`OsValue`]
+pub const OS_COUNT: usize = OS_VERSIONS.len();
+
+/// The position of the running OS in [`OsValue`] tables, or `None` if it's
+/// newer than any this crate knows.
+pub fn os_index() -> Option<usize> {
+	// Indices are offset by one, since nl_osvalue gives 0 for unknown OSes
+	let mut indices = [0; OS_COUNT];
+	for (index, value) in indices.iter_mut().enumerate() {
+		*value = index as u32 + 1;
+	}
+	let index = unsafe { ndless_sys::nl_osvalue(indices.as_ptr(), indices.len() as u32) };
+	(index as usize).checked_sub(1)
+}
+
+/// A value for each OS that Ndless supports, in the order of
+/// [`os_versions`]. See the [module documentation][self].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct OsValue<T> {
+	values: [Option<T>; OS_COUNT],
+}
+
+impl<T> OsValue<T> {
+	pub const fn new(values: [Option<T>; OS_COUNT]) -> Self {
+		Self { values }
+	}
+}
+
+impl<T: Copy> OsValue<T> {
+	/// Builds a table by calling `f` with each OS version, and whether it's
+	/// CAS.
+	pub fn from_fn(mut f: impl FnMut(OsVersion, bool) -> Option<T>) -> Self {
+		let mut values = [None; OS_COUNT];
+		for (value, &(version, cas)) in values.iter_mut().zip(OS_VERSIONS.iter()) {
+			*value = f(version, cas);
+		}
+		Self { values }
+	}
+
+	/// The value for the running OS.
+	pub fn get(&self) -> Option<T> {
+		self.values[os_index()?]
+	}
+}
+
+/// Returns the value in `table` for the running OS, or `None` if there isn't
+/// one. The same as [`OsValue::get`].
+pub fn value<T: Copy>(table: &OsValue<T>) -> Option<T> {
+	table.get()
+}
+
+/// The OS versions that Ndless supports, and whether each one is CAS, in the
+/// order of [`OsValue`] tables. Before 4.0, the CX and CM have entries of
+/// their own.
+pub fn os_versions() -> &'static [(OsVersion, bool); OS_COUNT] {
+	&OS_VERSIONS
+}
+
+/// The revision of Ndless that's installed, such as 2014.
+pub fn ndless_rev() -> u32 {
+	unsafe { ndless_sys::nl_ndless_rev() }
+}
+
+/// TRUE if the installed Ndless provides the syscall numbered `id`.
+pub fn has_syscall(id: u32) -> bool {
+	unsafe { ndless_sys::_nl_hassyscall(id as i32) > 0 }
+}
+
+/// TRUE if the OS is `min` or newer, or is newer than any this crate knows.
+pub fn is_at_least(min: OsVersion) -> bool {
+	os_version().map_or(true, |version| version >= min)
+}
+
+/// Returned by [`Requirement::check`] for the first requirement that isn't
+/// met.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Unsupported {
+	/// The OS is older than `required`.
+	Os {
+		required: OsVersion,
+		found: OsVersion,
+	},
+	/// Ndless is older than revision `required`.
+	Ndless { required: u32, found: u32 },
+	/// Ndless doesn't provide the syscall with this number.
+	Syscall(u32),
+}
+
+impl fmt::Display for Unsupported {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Unsupported::Os { required, found } => write!(
+				f,
+				"this program requires OS {} or newer, but {} is installed",
+				required, found
+			),
+			Unsupported::Ndless { required, found } => write!(
+				f,
+				"this program requires Ndless revision {} or newer, but {} is installed",
+				required, found
+			),
+			Unsupported::Syscall(id) => write!(
+				f,
+				"this program requires syscall {}, which the installed Ndless doesn't provide",
+				id
+			),
+		}
+	}
+}
+
+impl Error for Unsupported {}
+
+/// A set of things a feature needs, checked all at once. See the
+/// [module documentation][self].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, Default)]
+pub struct Requirement {
+	os: Option<OsVersion>,
+	ndless: Option<u32>,
+	syscalls: [Option<u32>; 8],
+}
+
+impl Requirement {
+	/// A requirement that is always met, until more are added.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Requires the OS to be `min` or newer.
+	pub fn os(mut self, min: OsVersion) -> Self {
+		self.os = Some(self.os.map_or(min, |os| os.max(min)));
+		self
+	}
+
+	/// Requires Ndless to be revision `min` or newer.
+	pub fn ndless_rev(mut self, min: u32) -> Self {
+		self.ndless = Some(self.ndless.map_or(min, |rev| rev.max(min)));
+		self
+	}
+
+	/// Requires Ndless to provide the syscall numbered `id`.
+	///
+	/// # Panics
+	///
+	/// Panics if more than 8 syscalls are required.
+	pub fn syscall(mut self, id: u32) -> Self {
+		let slot = self
+			.syscalls
+			.iter_mut()
+			.find(|slot| slot.is_none())
+			.expect("too many syscalls in one requirement");
+		*slot = Some(id);
+		self
+	}
+
+	/// Returns the first requirement that isn't met, if any.
+	pub fn check(&self) -> Result<(), Unsupported> {
+		if let (Some(required), Some(found)) = (self.os, os_version()) {
+			if found < required {
+				return Err(Unsupported::Os { required, found });
+			}
+		}
+		if let Some(required) = self.ndless {
+			let found = ndless_rev();
+			if found < required {
+				return Err(Unsupported::Ndless { required, found });
+			}
+		}
+		match self.syscalls.iter().flatten().find(|&&id| !has_syscall(id)) {
+			Some(&id) => Err(Unsupported::Syscall(id)),
+			None => Ok(()),
+		}
+	}
+
+	/// TRUE if every requirement is met.
+	pub fn is_met(&self) -> bool {
+		self.check().is_ok()
+	}
+
+	/// Runs `f` if every requirement is met, and returns its result.
+	pub fn run<T>(&self, f: impl FnOnce() -> T) -> Option<T> {
+		if self.is_met() {
+			Some(f())
+		} else {
+			None
+		}
+	}
+
+	/// Shows a message box and exits if a requirement isn't met.
+	pub fn require(&self) {
+		if let Err(e) = self.check() {
+			msg::msg("Unsupported", &format!("{}", e));
+			process::exit(1);
+		}
+	}
+}
+
+/// Shows a message box and exits if the OS is older than `min`.
+pub fn requires(min: OsVersion) {
+	Requirement::new().os(min).require()
+}
@@ -1,12 +1,61 @@
-//! # Serial output
+//! # Serial and screen output
 //! This module contains tools to output to the serial port. Also, take a look
 //! at [io::stdout](crate::io::stdout).
+//!
+//! ## Console
+//! [`Console`] shows text on the screen in a grid of characters, for
+//! TUI-style programs that don't need SDL:
+//!
+//! ```
+//! use core::fmt::Write;
+//! use ndless::gfx::Color;
+//! use ndless::out::Console;
+//!
+//! let mut console = Console::new().unwrap();
+//! // Keep the bottom row as a status line
+//! let (_, rows) = console.size();
+//! console.set_scroll_region(0, rows - 2);
+//! console.goto(0, rows - 1);
+//! console.set_colors(Color::BLACK, Color::WHITE);
+//! write!(console, "Press esc to quit");
+//! console.clear_line();
+//! console.set_colors(Color::WHITE, Color::BLACK);
+//! console.goto(0, 0);
+//! writeln!(console, "Hello!");
+//! ```
+//!
+//! The built-in font gives 53 columns and 30 rows. Text past the bottom of the
+//! scrolling region, which is the whole screen by default, scrolls it up.
+//!
+//! ## ANSI escape sequences
+//! Text written to a [`Console`] can use the escape sequences of a terminal,
+//! so libraries that print colored output work unchanged:
+//!
+//! - `ESC [ n m`: colors, with `0` to reset, `1` for bright colors, `7` to
+//!   swap the colors, `30`–`37`, `90`–`97` and `39` for the foreground,
+//!   `40`–`47`, `100`–`107` and `49` for the background, and `38;5;n`,
+//!   `38;2;r;g;b`, `48;5;n` and `48;2;r;g;b` for any color
+//! - `ESC [ n A`, `B`, `C` and `D`: move the cursor up, down, right and left
+//! - `ESC [ n E` and `F`: move to the start of the next or previous line
+//! - `ESC [ n G` and `d`: move to a column or row
+//! - `ESC [ row ; col H`: move the cursor, counting from 1
+//! - `ESC [ n J`: clear after the cursor, `1` before it, or `2` everything
+//! - `ESC [ n K`: the same, within the cursor's row
+//! - `ESC [ top ; bottom r`: set the scrolling region
+//! - `ESC [ s` and `u`: save and restore the cursor
+//!
+//! Anything else, such as `ESC [?25l` to hide the cursor, is ignored.
 
 use core::fmt::Arguments;
 
 use crate::io;
 use crate::io::Write;
 
+pub use console::Console;
+
+mod ansi;
+mod console;
+
 pub fn print_fmt(fmt: Arguments) -> core::fmt::Result {
 	io::stdout().write_fmt(fmt).map_err(|_| core::fmt::Error)
 }
@@ -0,0 +1,218 @@
+use crate::gfx::Color;
+
+/// The most parameters kept for one sequence. Any more are dropped.
+const MAX_PARAMS: usize = 16;
+
+/// The standard 16 terminal colors, with the bright ones last
+const PALETTE: [Color; 16] = [
+	Color::rgb(0, 0, 0),
+	Color::rgb(170, 0, 0),
+	Color::rgb(0, 170, 0),
+	Color::rgb(170, 85, 0),
+	Color::rgb(0, 0, 170),
+	Color::rgb(170, 0, 170),
+	Color::rgb(0, 170, 170),
+	Color::rgb(170, 170, 170),
+	Color::rgb(85, 85, 85),
+	Color::rgb(255, 85, 85),
+	Color::rgb(85, 255, 85),
+	Color::rgb(255, 255, 85),
+	Color::rgb(85, 85, 255),
+	Color::rgb(255, 85, 255),
+	Color::rgb(85, 255, 255),
+	Color::rgb(255, 255, 255),
+];
+
+/// A color from the 256-color palette used by `38;5;n`
+fn indexed(n: u16) -> Color {
+	match n {
+		0..=15 => PALETTE[n as usize],
+		16..=231 => {
+			let n = n - 16;
+			let level = |v: u16| if v == 0 { 0 } else { (55 + v * 40) as u8 };
+			Color::rgb(level(n / 36), level(n / 6 % 6), level(n % 6))
+		}
+		_ => {
+			let gray = (8 + (n.min(255) - 232) * 10) as u8;
+			Color::rgb(gray, gray, gray)
+		}
+	}
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+enum State {
+	Text,
+	/// After `ESC`
+	Escape,
+	/// After `ESC [`
+	Csi,
+}
+
+/// Something for the console to do
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub(super) enum Action {
+	Print(char),
+	/// A finished `ESC [` sequence, with the character that ended it
+	Csi(Params, char),
+	/// Part of a sequence, or one that isn't supported
+	None,
+}
+
+/// The numbers in a control sequence. Missing ones read as 0.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub(super) struct Params {
+	values: [u16; MAX_PARAMS],
+	len: usize,
+}
+
+impl Params {
+	const EMPTY: Params = Params {
+		values: [0; MAX_PARAMS],
+		len: 0,
+	};
+
+	pub(super) fn get(&self, index: usize) -> u16 {
+		if index < self.len {
+			self.values[index]
+		} else {
+			0
+		}
+	}
+
+	/// Like [`get`][Params::get], but 0 reads as 1, as for cursor movement
+	pub(super) fn count(&self, index: usize) -> u16 {
+		self.get(index).max(1)
+	}
+
+	pub(super) fn len(&self) -> usize {
+		self.len
+	}
+}
+
+/// Splits text into characters and control sequences
+pub(super) struct Parser {
+	state: State,
+	params: Params,
+	/// A sequence starting with `?` or another private marker, which is read
+	/// but ignored
+	private: bool,
+}
+
+impl Parser {
+	pub(super) const fn new() -> Self {
+		Parser {
+			state: State::Text,
+			params: Params::EMPTY,
+			private: false,
+		}
+	}
+
+	pub(super) fn feed(&mut self, ch: char) -> Action {
+		match self.state {
+			State::Text if ch == '\x1b' => {
+				self.state = State::Escape;
+				Action::None
+			}
+			State::Text => Action::Print(ch),
+			State::Escape if ch == '[' => {
+				self.state = State::Csi;
+				self.params = Params {
+					len: 1,
+					..Params::EMPTY
+				};
+				self.private = false;
+				Action::None
+			}
+			State::Escape => {
+				// Other escapes, such as `ESC c`, are two characters long
+				self.state = State::Text;
+				Action::None
+			}
+			State::Csi => match ch {
+				'0'..='9' => {
+					let digit = ch as u16 - '0' as u16;
+					if let Some(value) = self.params.values.get_mut(self.params.len - 1) {
+						*value = value.saturating_mul(10).saturating_add(digit);
+					}
+					Action::None
+				}
+				';' | ':' => {
+					if self.params.len < MAX_PARAMS {
+						self.params.len += 1;
+					}
+					Action::None
+				}
+				'<'..='?' => {
+					self.private = true;
+					Action::None
+				}
+				'\x40'..='\x7e' => {
+					self.state = State::Text;
+					if self.private {
+						Action::None
+					} else {
+						Action::Csi(self.params, ch)
+					}
+				}
+				// Intermediate bytes
+				'\x20'..='\x2f' => Action::None,
+				_ => {
+					self.state = State::Text;
+					Action::None
+				}
+			},
+		}
+	}
+}
+
+/// Applies a Select Graphic Rendition (`m`) sequence to a pair of colors.
+/// `default` are the colors that `0`, `39` and `49` go back to.
+pub(super) fn apply_sgr(
+	params: &Params,
+	colors: &mut (Color, Color),
+	bold: &mut bool,
+	default: (Color, Color),
+) {
+	let mut i = 0;
+	while i < params.len().max(1) {
+		match params.get(i) {
+			0 => {
+				*colors = default;
+				*bold = false;
+			}
+			1 => *bold = true,
+			22 => *bold = false,
+			7 => *colors = (colors.1, colors.0),
+			n @ 30..=37 => colors.0 = PALETTE[(n - 30) as usize + if *bold { 8 } else { 0 }],
+			n @ 90..=97 => colors.0 = PALETTE[(n - 90) as usize + 8],
+			n @ 40..=47 => colors.1 = PALETTE[(n - 40) as usize],
+			n @ 100..=107 => colors.1 = PALETTE[(n - 100) as usize + 8],
+			39 => colors.0 = default.0,
+			49 => colors.1 = default.1,
+			n @ 38 | n @ 48 => {
+				let color = match params.get(i + 1) {
+					5 => {
+						i += 2;
+						indexed(params.get(i))
+					}
+					2 => {
+						i += 4;
+						Color::rgb(
+							params.get(i - 2) as u8,
+							params.get(i - 1) as u8,
+							params.get(i) as u8,
+						)
+					}
+					_ => break,
+				};
+				if n == 38 {
+					colors.0 = color;
+				} else {
+					colors.1 = color;
+				}
+			}
+			_ => {}
+		}
+		i += 1;
+	}
+}
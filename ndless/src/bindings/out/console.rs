@@ -0,0 +1,373 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use crate::gfx::{Canvas, Color, Font};
+use crate::input::line::LineView;
+use crate::input::shortcuts::Modifiers;
+use crate::io;
+use crate::video::{Screen, HEIGHT, WIDTH};
+
+use super::ansi::{self, Action, Params, Parser};
+
+/// The number of spaces a tab moves to the next multiple of
+const TAB_WIDTH: u32 = 8;
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+struct Cell {
+	ch: char,
+	fg: Color,
+	bg: Color,
+}
+
+/// A text console on the screen. See the [module documentation][self].
+pub struct Console {
+	screen: Screen,
+	font: Font<'static>,
+	cols: u32,
+	rows: u32,
+	cells: Vec<Cell>,
+	x: u32,
+	y: u32,
+	fg: Color,
+	bg: Color,
+	/// The colors that `ESC [0m` goes back to
+	default: (Color, Color),
+	bold: bool,
+	/// The rows that scroll when text goes past the bottom
+	scroll: Range<u32>,
+	/// Where `ESC [s` saved the cursor
+	saved: (u32, u32),
+	parser: Parser,
+}
+
+impl Console {
+	/// Takes over the LCD, and clears it to black. Returns `None` if a
+	/// [`Screen`] already has it.
+	pub fn new() -> Option<Self> {
+		let font = Font::builtin();
+		let cols = WIDTH as u32 / font.measure("M");
+		let rows = HEIGHT as u32 / font.line_height();
+		let blank = Cell {
+			ch: ' ',
+			fg: Color::WHITE,
+			bg: Color::BLACK,
+		};
+		let mut console = Console {
+			screen: Screen::new()?,
+			font,
+			cols,
+			rows,
+			cells: vec![blank; (cols * rows) as usize],
+			x: 0,
+			y: 0,
+			fg: blank.fg,
+			bg: blank.bg,
+			default: (blank.fg, blank.bg),
+			bold: false,
+			scroll: 0..rows,
+			saved: (0, 0),
+			parser: Parser::new(),
+		};
+		console.clear();
+		Some(console)
+	}
+
+	/// The number of columns and rows of characters.
+	pub fn size(&self) -> (u32, u32) {
+		(self.cols, self.rows)
+	}
+
+	/// Moves the cursor to column `x` and row `y`, counting from 0 at the top
+	/// left. Positions off the screen are moved to the nearest edge.
+	pub fn goto(&mut self, x: u32, y: u32) {
+		self.x = x.min(self.cols - 1);
+		self.y = y.min(self.rows - 1);
+	}
+
+	/// The column and row of the cursor.
+	pub fn cursor(&self) -> (u32, u32) {
+		(self.x, self.y)
+	}
+
+	/// Sets the colors of text written from now on. Classic models show them
+	/// in gray. These are also the colors that `ESC [0m` goes back to.
+	pub fn set_colors(&mut self, fg: Color, bg: Color) {
+		self.fg = fg;
+		self.bg = bg;
+		self.default = (fg, bg);
+	}
+
+	pub fn set_foreground(&mut self, fg: Color) {
+		self.fg = fg;
+		self.default.0 = fg;
+	}
+
+	pub fn set_background(&mut self, bg: Color) {
+		self.bg = bg;
+		self.default.1 = bg;
+	}
+
+	/// The foreground and background colors.
+	pub fn colors(&self) -> (Color, Color) {
+		(self.fg, self.bg)
+	}
+
+	/// Makes only rows `top` to `bottom`, inclusive, scroll when text goes
+	/// past the bottom, such as to keep a status line in place. The cursor
+	/// moves to the top of the region.
+	pub fn set_scroll_region(&mut self, top: u32, bottom: u32) {
+		let bottom = bottom.min(self.rows - 1);
+		let top = top.min(bottom);
+		self.scroll = top..bottom + 1;
+		self.goto(0, top);
+	}
+
+	/// Lets the whole screen scroll again.
+	pub fn reset_scroll_region(&mut self) {
+		self.scroll = 0..self.rows;
+	}
+
+	fn blank(&self) -> Cell {
+		Cell {
+			ch: ' ',
+			fg: self.fg,
+			bg: self.bg,
+		}
+	}
+
+	/// Fills the screen with the background color, and moves the cursor to
+	/// the top left.
+	pub fn clear(&mut self) {
+		self.clear_cells(0..self.cells.len());
+		self.x = 0;
+		self.y = 0;
+		self.present();
+	}
+
+	/// Clears from the cursor to the end of its row.
+	pub fn clear_line(&mut self) {
+		let end = ((self.y + 1) * self.cols) as usize;
+		self.clear_cells(self.index()..end);
+	}
+
+	/// The index of the cell under the cursor, or the one after the end of
+	/// the row if the cursor is past it
+	fn index(&self) -> usize {
+		(self.y * self.cols + self.x.min(self.cols)) as usize
+	}
+
+	fn clear_cells(&mut self, cells: Range<usize>) {
+		if cells.is_empty() {
+			return;
+		}
+		let blank = self.blank();
+		let cols = self.cols as usize;
+		let rows = (cells.start / cols) as u32..((cells.end - 1) / cols + 1) as u32;
+		self.cells[cells].iter_mut().for_each(|cell| *cell = blank);
+		self.redraw(rows);
+	}
+
+	fn draw_cell(&mut self, x: u32, y: u32) {
+		let cell = self.cells[(y * self.cols + x) as usize];
+		let width = self.font.measure("M");
+		let height = self.font.line_height();
+		let (px, py) = ((x * width) as i32, (y * height) as i32);
+		let mut canvas = Canvas::new(&mut self.screen);
+		canvas.fill_rect(px, py, width, height, cell.bg);
+		if cell.ch != ' ' {
+			let mut buf = [0; 4];
+			canvas.draw_text(&self.font, px, py, cell.fg, cell.ch.encode_utf8(&mut buf));
+		}
+	}
+
+	fn redraw(&mut self, rows: Range<u32>) {
+		for y in rows {
+			for x in 0..self.cols {
+				self.draw_cell(x, y);
+			}
+		}
+	}
+
+	/// Moves the scroll region up a row, if the cursor is at its bottom, or
+	/// moves the cursor down.
+	fn line_feed(&mut self) {
+		if self.y + 1 != self.scroll.end {
+			self.y = (self.y + 1).min(self.rows - 1);
+			return;
+		}
+		let cols = self.cols as usize;
+		let start = self.scroll.start as usize * cols;
+		let end = self.scroll.end as usize * cols;
+		self.cells.copy_within(start + cols..end, start);
+		let blank = self.blank();
+		self.cells[end - cols..end]
+			.iter_mut()
+			.for_each(|cell| *cell = blank);
+		self.redraw(self.scroll.clone());
+	}
+
+	/// Writes a character at the cursor, and moves the cursor. `\n`, `\r`,
+	/// `\t` and backspace (`\x08`) move the cursor as on a terminal, and
+	/// ANSI escape sequences are interpreted. See the
+	/// [module documentation][super].
+	pub fn put_char(&mut self, ch: char) {
+		match self.parser.feed(ch) {
+			Action::Print(ch) => self.print(ch),
+			Action::Csi(params, action) => self.control(&params, action),
+			Action::None => {}
+		}
+	}
+
+	fn control(&mut self, params: &Params, action: char) {
+		let (cols, rows) = (self.cols, self.rows);
+		let n = u32::from(params.count(0));
+		match action {
+			'A' => self.y = self.y.saturating_sub(n),
+			'B' => self.y = (self.y + n).min(rows - 1),
+			'C' => self.x = (self.x + n).min(cols - 1),
+			'D' => self.x = self.x.min(cols - 1).saturating_sub(n),
+			'E' => self.goto(0, self.y + n),
+			'F' => self.goto(0, self.y.saturating_sub(n)),
+			'G' => self.x = (n - 1).min(cols - 1),
+			'd' => self.y = (n - 1).min(rows - 1),
+			'H' | 'f' => self.goto(
+				u32::from(params.count(1)) - 1,
+				u32::from(params.count(0)) - 1,
+			),
+			'J' => {
+				let cells = match params.get(0) {
+					0 => self.index()..self.cells.len(),
+					1 => 0..(self.index() + 1).min(self.cells.len()),
+					_ => 0..self.cells.len(),
+				};
+				self.clear_cells(cells);
+			}
+			'K' => {
+				let start = (self.y * cols) as usize;
+				let end = start + cols as usize;
+				let cells = match params.get(0) {
+					0 => self.index()..end,
+					1 => start..(self.index() + 1).min(end),
+					_ => start..end,
+				};
+				self.clear_cells(cells);
+			}
+			'm' => {
+				let mut colors = (self.fg, self.bg);
+				ansi::apply_sgr(params, &mut colors, &mut self.bold, self.default);
+				self.fg = colors.0;
+				self.bg = colors.1;
+			}
+			'r' => {
+				let bottom = match params.get(1) {
+					0 => rows,
+					bottom => u32::from(bottom),
+				};
+				self.set_scroll_region(u32::from(params.count(0)) - 1, bottom - 1);
+				self.goto(0, 0);
+			}
+			's' => self.saved = (self.x, self.y),
+			'u' => self.goto(self.saved.0, self.saved.1),
+			_ => {}
+		}
+	}
+
+	fn print(&mut self, ch: char) {
+		match ch {
+			'\n' => {
+				self.x = 0;
+				self.line_feed();
+			}
+			'\r' => self.x = 0,
+			'\t' => {
+				let next = (self.x / TAB_WIDTH + 1) * TAB_WIDTH;
+				self.x = next.min(self.cols - 1);
+			}
+			'\x08' => self.x = self.x.min(self.cols - 1).saturating_sub(1),
+			// Such as a bell
+			ch if ch.is_control() => {}
+			ch => {
+				if self.x >= self.cols {
+					self.x = 0;
+					self.line_feed();
+				}
+				let index = (self.y * self.cols + self.x) as usize;
+				self.cells[index] = Cell {
+					ch,
+					fg: self.fg,
+					bg: self.bg,
+				};
+				self.draw_cell(self.x, self.y);
+				// Left past the last column until the next character, so
+				// that filling a row doesn't scroll
+				self.x += 1;
+			}
+		}
+	}
+
+	/// Writes `text`, then shows it.
+	pub fn put_str(&mut self, text: &str) {
+		for ch in text.chars() {
+			self.put_char(ch);
+		}
+		self.present();
+	}
+
+	/// Shows everything written so far. [`put_str`][Console::put_str] and
+	/// writing with `write!` do this already.
+	pub fn present(&mut self) {
+		self.screen.present();
+	}
+}
+
+impl LineView for Console {
+	/// Draws the line on the cursor's row, scrolled sideways if it doesn't
+	/// fit, so that the cursor is always visible. The cursor is shown with the
+	/// colors swapped, and the console's cursor is left at the same place.
+	fn draw_line(&mut self, prompt: &str, text: &str, cursor: usize, _modifiers: Modifiers) {
+		let line: Vec<char> = prompt.chars().chain(text.chars()).collect();
+		let cursor = prompt.chars().count() + cursor;
+		let cols = self.cols as usize;
+		let start = (cursor + 1).saturating_sub(cols);
+		let y = self.y;
+		for x in 0..cols {
+			let ch = match line.get(start + x) {
+				Some(&ch) if !ch.is_control() => ch,
+				_ => ' ',
+			};
+			let (fg, bg) = if start + x == cursor {
+				(self.bg, self.fg)
+			} else {
+				(self.fg, self.bg)
+			};
+			self.cells[y as usize * cols + x] = Cell { ch, fg, bg };
+			self.draw_cell(x as u32, y);
+		}
+		self.x = (cursor - start) as u32;
+		self.present();
+	}
+}
+
+impl fmt::Write for Console {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.put_str(s);
+		Ok(())
+	}
+}
+
+impl io::Write for Console {
+	/// Invalid UTF-8 is shown as `�`. A character split between two
+	/// writes is shown that way too.
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.put_str(&String::from_utf8_lossy(buf));
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.present();
+		Ok(())
+	}
+}
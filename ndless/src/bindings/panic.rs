@@ -0,0 +1,384 @@
+//! # Panic screen
+//! When a program panics, the panic handler in `ndless-handler` calls
+//! [`show`], which takes over the LCD and shows:
+//!
+//! - the panic message and where it happened
+//! - the CPU's registers in the panic handler
+//! - addresses from the call stack, to look up on a computer
+//!
+//! It then waits for a key, so the message can't be missed, before the
+//! program exits.
+//!
+//! The screen is drawn straight into a [`Screen`]'s framebuffer, so it works
+//! whether or not the program used SDL. If the LCD can't be taken over, the
+//! message is shown in a message box instead.
+//!
+//! ## Surviving a panic
+//! [`catch`] runs a closure, and returns an error instead of exiting if it
+//! panics, so a program that runs scripts or plugins can report the problem
+//! and carry on:
+//!
+//! ```
+//! use ndless::panic;
+//!
+//! match panic::catch(|| plugin.run()) {
+//!     Ok(result) => show_result(result),
+//!     Err(panic) => msg("Plugin crashed", &panic.to_string()),
+//! }
+//! ```
+//!
+//! There is no unwinding on the calculator: the panic handler jumps straight
+//! back to `catch`, like `setjmp` and `longjmp`. Nothing owned by the code
+//! that panicked is dropped, so its heap memory is leaked, and anything it
+//! was in the middle of changing, such as a `static` or an open file, may be
+//! left half-done.
+//!
+//! ## Finding functions from addresses
+//! The program is loaded at a different address each time, so the screen
+//! also shows the address of [`show`] itself. Subtract it from each address,
+//! then add the address of `ndless::panic::show` in the ELF file (from
+//! `nm --demangle`), and look up the result with `addr2line -f -C -e`.
+//!
+//! The call stack is found by searching the stack for values that point just
+//! after a `bl` or `blx` instruction near the program's code, as there are no
+//! frame pointers or unwind tables to follow. Most of them are real return
+//! addresses, but some may be left over from earlier calls.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+use core::ptr::read_volatile;
+
+use crate::error::Error;
+use crate::gfx::{Canvas, Color, Font};
+use crate::input::any_key_pressed;
+use crate::msg::msg;
+use crate::video::{Screen, HEIGHT, WIDTH};
+
+/// How far from [`show`] a return address may be, as the program's code is
+/// smaller than this
+const CODE_RANGE: u32 = 4 * 1024 * 1024;
+/// How many words of the stack to search for return addresses
+const STACK_WORDS: usize = 1024;
+/// The most return addresses shown
+const MAX_FRAMES: usize = 16;
+
+const BACKGROUND: Color = Color::rgb(0, 0, 96);
+const HEADER: Color = Color::rgb(192, 0, 0);
+const TEXT: Color = Color::WHITE;
+const DIM: Color = Color::rgb(170, 170, 204);
+
+global_asm!(
+	"
+	.text
+	.arm
+	.global __ndless_catch
+@ r0: the jump buffer, r1: the function to call, r2: its argument
+__ndless_catch:
+	stmia r0, {r4-r11, sp, lr}
+	push {r0, lr}
+	mov r0, r2
+	blx r1
+	pop {r0, lr}
+	mov r0, #0
+	bx lr
+	.global __ndless_uncatch
+@ r0: the jump buffer. Returns from __ndless_catch again, with 1.
+__ndless_uncatch:
+	ldmia r0, {r4-r11, sp, lr}
+	mov r0, #1
+	bx lr
+"
+);
+
+/// r4 to r11, sp and lr: the registers a function must preserve, and where it
+/// returns to
+type JumpBuffer = [u32; 10];
+
+extern "C" {
+	/// Calls `f(data)`, and returns 0. If [`__ndless_uncatch`] is called with
+	/// the same buffer before `f` returns, returns 1 instead.
+	fn __ndless_catch(buf: *mut JumpBuffer, f: extern "C" fn(*mut u8), data: *mut u8) -> u32;
+	fn __ndless_uncatch(buf: *mut JumpBuffer) -> !;
+}
+
+/// The buffer of the innermost [`catch`]
+static mut CATCHING: Option<*mut JumpBuffer> = None;
+static mut CAUGHT: Option<Panic> = None;
+
+/// A panic stopped by [`catch`].
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct Panic {
+	pub message: String,
+	pub file: String,
+	pub line: u32,
+	pub column: u32,
+}
+
+impl fmt::Display for Panic {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{} at {}:{}:{}",
+			self.message, self.file, self.line, self.column
+		)
+	}
+}
+
+impl Error for Panic {}
+
+/// The CPU's registers at one point in time.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Registers {
+	/// r0 to r12
+	pub r: [u32; 13],
+	pub sp: u32,
+	pub lr: u32,
+	pub pc: u32,
+	pub cpsr: u32,
+}
+
+impl Registers {
+	/// Reads the registers at the point this is called.
+	#[inline(always)]
+	pub fn capture() -> Self {
+		let mut r = [0; 13];
+		let (sp, lr, pc, cpsr): (u32, u32, u32, u32);
+		unsafe {
+			llvm_asm!("stmia $0, {r0-r12}" : : "r"(r.as_mut_ptr()) : "memory" : "volatile");
+			llvm_asm!("
+				mov $0, sp
+				mov $1, lr
+				mov $2, pc
+				mrs $3, cpsr
+				"
+				: "=r"(sp), "=r"(lr), "=r"(pc), "=r"(cpsr)
+				:
+				:
+				: "volatile"
+			);
+		}
+		Self {
+			r,
+			sp,
+			lr,
+			pc,
+			cpsr,
+		}
+	}
+
+	pub(crate) fn lines(&self) -> Vec<String> {
+		let mut names = (0..13).map(|i| format!("r{}", i)).chain(
+			["sp", "lr", "pc", "cpsr"]
+				.iter()
+				.map(|name| String::from(*name)),
+		);
+		let values = self
+			.r
+			.iter()
+			.chain(&[self.sp, self.lr, self.pc, self.cpsr])
+			.copied()
+			.collect::<Vec<_>>();
+		values
+			.chunks(4)
+			.map(|chunk| {
+				let mut line = String::new();
+				for value in chunk {
+					let name = names.next().unwrap_or_default();
+					let _ = write!(line, "{:>4}={:08x} ", name, value);
+				}
+				line
+			})
+			.collect()
+	}
+}
+
+/// Returns whether `addr` looks like a return address: just after a `bl` or
+/// `blx` instruction, in ARM mode, near the program's code.
+fn is_return_address(addr: u32) -> bool {
+	let base = show as usize as u32;
+	if addr & 3 != 0
+		|| addr < 4
+		|| addr.wrapping_sub(base.wrapping_sub(CODE_RANGE)) > 2 * CODE_RANGE
+	{
+		return false;
+	}
+	let insn = unsafe { read_volatile((addr - 4) as *const u32) };
+	// bl <label>, with any condition
+	let bl = insn & 0x0F00_0000 == 0x0B00_0000 && insn >> 28 != 0xF;
+	// blx <label>
+	let blx_label = insn & 0xFE00_0000 == 0xFA00_0000;
+	// blx <register>, with any condition
+	let blx_reg = insn & 0x0FFF_FFF0 == 0x012F_FF30;
+	bl || blx_label || blx_reg
+}
+
+/// Returns addresses on the stack above `sp` that look like return
+/// addresses, innermost first. See the [module documentation][self].
+pub fn backtrace(sp: u32) -> Vec<u32> {
+	let stack = (sp & !3) as *const u32;
+	(0..STACK_WORDS)
+		.map(|i| unsafe { read_volatile(stack.add(i)) })
+		.filter(|&addr| is_return_address(addr))
+		.take(MAX_FRAMES)
+		.collect()
+}
+
+pub(crate) fn wait_for_key() {
+	// Keys held when the panic happened don't count
+	while any_key_pressed() {}
+	while !any_key_pressed() {}
+	while any_key_pressed() {}
+}
+
+/// Shows the panic screen for `info`, and waits for a key. See the
+/// [module documentation][self].
+pub fn show(info: &PanicInfo) {
+	let registers = Registers::capture();
+	let message = match info.message() {
+		Some(message) => format!("{}", message),
+		None => String::from("The program panicked."),
+	};
+	let location = info
+		.location()
+		.map(|loc| format!("at {}:{}:{}", loc.file(), loc.line(), loc.column()));
+	let frames = backtrace(registers.sp);
+
+	if !show_report(
+		"The program panicked",
+		&message,
+		location.as_deref(),
+		&registers,
+		&frames,
+	) {
+		msg(
+			"Error",
+			&format!("{}\n{}", message, location.unwrap_or_default()),
+		);
+		return;
+	}
+	wait_for_key();
+}
+
+/// Draws a report with a message, registers and call stack. Returns false if
+/// the LCD can't be taken over.
+pub(crate) fn show_report(
+	title: &str,
+	message: &str,
+	detail: Option<&str>,
+	registers: &Registers,
+	frames: &[u32],
+) -> bool {
+	let mut screen = match Screen::new() {
+		Some(screen) => screen,
+		None => return false,
+	};
+	{
+		let font = Font::builtin();
+		let line = font.line_height() as i32;
+		let width = WIDTH as u32 - 8;
+		let mut canvas = Canvas::new(&mut screen);
+		canvas.clear(BACKGROUND);
+		canvas.fill_rect(0, 0, WIDTH as u32, line as u32 + 4, HEADER);
+		canvas.draw_text(&font, 4, 2, TEXT, title);
+
+		let mut y = line + 8;
+		y = canvas.draw_text_wrapped(&font, 4, y, width, TEXT, message);
+		if let Some(detail) = detail {
+			y = canvas.draw_text_wrapped(&font, 4, y, width, DIM, detail);
+		}
+
+		y += line / 2;
+		canvas.draw_text(&font, 4, y, DIM, "Registers");
+		y += line;
+		for text in registers.lines() {
+			canvas.draw_text(&font, 4, y, TEXT, &text);
+			y += line;
+		}
+
+		y += line / 2;
+		let header = format!("Call stack (show = {:08x})", show as usize);
+		canvas.draw_text(&font, 4, y, DIM, &header);
+		y += line;
+		for chunk in frames.chunks(5) {
+			let mut text = String::new();
+			for addr in chunk {
+				let _ = write!(text, "{:08x}  ", addr);
+			}
+			canvas.draw_text(&font, 4, y, TEXT, &text);
+			y += line;
+		}
+
+		let footer_y = HEIGHT as i32 - line - 2;
+		canvas.draw_text(&font, 4, footer_y, DIM, "Press any key to exit");
+	}
+	screen.present();
+	true
+}
+
+struct Call<F, T> {
+	f: Option<F>,
+	result: Option<T>,
+}
+
+extern "C" fn call<F: FnOnce() -> T, T>(data: *mut u8) {
+	let call = unsafe { &mut *(data as *mut Call<F, T>) };
+	if let Some(f) = call.f.take() {
+		call.result = Some(f());
+	}
+}
+
+/// Runs `f`, returning the panic instead of exiting if it panics. Calls can
+/// be nested. See the [module documentation][self].
+pub fn catch<F: FnOnce() -> T, T>(f: F) -> Result<T, Panic> {
+	let mut buf: JumpBuffer = [0; 10];
+	let mut call = Call {
+		f: Some(f),
+		result: None,
+	};
+	let outer = unsafe { CATCHING.replace(&mut buf) };
+	let panicked = unsafe {
+		__ndless_catch(
+			&mut buf,
+			call::<F, T>,
+			&mut call as *mut Call<F, T> as *mut u8,
+		)
+	} != 0;
+	unsafe { CATCHING = outer };
+	match call.result {
+		Some(result) if !panicked => Ok(result),
+		_ => Err(unsafe { CAUGHT.take() }.unwrap_or_else(|| Panic {
+			message: String::from("unknown panic"),
+			file: String::new(),
+			line: 0,
+			column: 0,
+		})),
+	}
+}
+
+/// Called first by the panic handler. If inside [`catch`], jumps back to it,
+/// otherwise returns.
+#[doc(hidden)]
+pub fn __unwind_if_caught(info: &PanicInfo) {
+	let buf = match unsafe { CATCHING.take() } {
+		Some(buf) => buf,
+		None => return,
+	};
+	let (file, line, column) = info.location().map_or((String::new(), 0, 0), |loc| {
+		(String::from(loc.file()), loc.line(), loc.column())
+	});
+	let panic = Panic {
+		message: info
+			.message()
+			.map_or_else(|| String::from("panic"), |message| format!("{}", message)),
+		file,
+		line,
+		column,
+	};
+	unsafe {
+		CAUGHT = Some(panic);
+		__ndless_uncatch(buf);
+	}
+}
@@ -1,5 +1,163 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
 use core::fmt;
 
+use crate::hw::{gpio, keypad, led, power};
+use crate::{audio, crash, heap, interrupt, serial, timer, usb, video, watchdog};
+
+pub use command::{Child, Command, ExitStatus};
+
+mod command;
+
+static mut EXIT_HOOKS: Vec<Box<dyn FnOnce()>> = Vec::new();
+static mut STARTUP: Startup = Startup::DEFAULT;
+
+/// What happens to the LCD when the program exits.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Lcd {
+	/// Put it back in the mode the OS uses, with [`video::restore`]
+	Restore,
+	/// Leave it alone, as the program sets it up and puts it back itself. The
+	/// LCD is still pointed back at the OS's framebuffer, if
+	/// [`Buffering::Triple`][crate::video::Buffering::Triple] moved it.
+	Owned,
+}
+
+/// Whether the tick timer is set up when the program starts.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Timers {
+	/// Start the timer used by [`timer::get_ticks`] and
+	/// [`Instant`][crate::time::Instant], and put it back when the program
+	/// exits
+	Restore,
+	/// Don't touch any timers. Nothing that measures time works, and
+	/// [`time::delay_us`][crate::time::delay_us] assumes the fastest CPU
+	/// speed of any model, so its delays are longer than asked on the rest.
+	Off,
+}
+
+/// What happens when an allocation fails.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum OnAllocError {
+	/// Abort straight away
+	Abort,
+	/// Show how much memory was asked for in a message box, then abort. See
+	/// [`heap::set_alloc_error_dialog`][crate::heap::set_alloc_error_dialog].
+	Dialog,
+}
+
+/// What happens when the CPU raises an exception, such as a data abort.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum CrashHandler {
+	/// Show the [crash screen][crate::crash]
+	Screen,
+	/// Leave it to the OS, which reboots
+	Os,
+}
+
+/// What the runtime sets up when the program starts, and puts back when it
+/// exits. The `entry` macro takes these as options, as strings:
+///
+/// ```
+/// #[entry(lcd = "owned", timers = "off", alloc_error = "dialog", crash = "os")]
+/// fn main() {}
+/// ```
+///
+/// Anything left out keeps its value from [`Startup::DEFAULT`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Startup {
+	pub lcd: Lcd,
+	pub timers: Timers,
+	pub alloc_error: OnAllocError,
+	pub crash: CrashHandler,
+}
+
+impl Startup {
+	/// What programs get without any options: the LCD and timer are put back
+	/// on exit, crashes show the [crash screen][crate::crash], and allocation
+	/// failures aren't reported. Programs without `#[entry]` start with these
+	/// too.
+	pub const DEFAULT: Startup = Startup {
+		lcd: Lcd::Restore,
+		timers: Timers::Restore,
+		alloc_error: OnAllocError::Abort,
+		crash: CrashHandler::Screen,
+	};
+}
+
+impl Default for Startup {
+	fn default() -> Self {
+		Startup::DEFAULT
+	}
+}
+
+/// Returns how the runtime was set up when the program started.
+pub fn startup() -> Startup {
+	unsafe { STARTUP }
+}
+
+/// Called before `main`, after the arguments are stored.
+#[doc(hidden)]
+pub fn __startup(startup: Startup) {
+	unsafe { STARTUP = startup };
+	if startup.timers == Timers::Restore {
+		timer::__init();
+	}
+	heap::set_alloc_error_dialog(startup.alloc_error == OnAllocError::Dialog);
+	if startup.crash == CrashHandler::Screen {
+		crash::install();
+	}
+}
+
+/// Registers a callback to run when the program exits: when `main` returns,
+/// [`exit`] is called, or after the panic screen. Use this to put back
+/// anything the OS relies on, such as the LCD mode or interrupt handlers, and
+/// to flush files.
+///
+/// Hooks run in the reverse order they were registered. [`abort`] doesn't run
+/// them.
+pub fn at_exit(hook: impl FnOnce() + 'static) {
+	unsafe { EXIT_HOOKS.push(Box::new(hook)) }
+}
+
+/// Runs and removes every hook registered with [`at_exit`]. This is done
+/// automatically, but can be called before exiting some other way.
+pub fn run_exit_hooks() {
+	while let Some(hook) = unsafe { EXIT_HOOKS.pop() } {
+		hook();
+	}
+}
+
+/// Called after `main` returns.
+#[doc(hidden)]
+pub fn __cleanup() {
+	run_exit_hooks();
+	teardown();
+}
+
+/// Gives everything the program took over back to the OS, so that it keeps
+/// working after the program exits.
+fn teardown() {
+	// Stops the audio timer before its interrupt handler is removed
+	audio::stop();
+	serial::restore();
+	gpio::restore();
+	keypad::restore();
+	watchdog::restore();
+	interrupt::restore();
+	usb::restore();
+	power::screen_on();
+	power::restore_cpu_speed();
+	led::restore();
+	// Triple buffering points the LCD at pages on the heap, which are freed
+	video::restore_base();
+	if unsafe { STARTUP.lcd } == Lcd::Restore {
+		video::restore();
+	}
+	timer::restore();
+	crash::restore();
+}
+
 /// ## WARNING
 ///
 /// This **will** leak memory without careful planning, as it does not run any
@@ -16,7 +174,14 @@ use core::fmt;
 /// }
 /// ```
 /// to ensure that no memory leaks.
+///
+/// Interrupt handlers are removed, and the USB controller, LCD and timer are
+/// given back to the OS, first, with [`interrupt::restore`],
+/// [`usb::restore`], [`video::restore`] and [`timer::restore`]. This makes it
+/// safe to call from anywhere, even while a [`Screen`][video::Screen] is
+/// alive.
 pub fn abort() -> ! {
+	teardown();
 	unsafe { ndless_sys::abort() }
 }
 
@@ -46,10 +211,29 @@ pub fn abort() -> ! {
 /// }
 /// ```
 /// to ensure that no memory leaks.
+///
+/// Hooks registered with [`at_exit`] are run first, then everything is given
+/// back to the OS the same way as [`abort`] does.
 pub fn exit(code: i32) -> ! {
+	__cleanup();
 	unsafe { ndless_sys::exit(code) }
 }
 
+/// A status code to return from `main`, like `std::process::ExitCode`.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct ExitCode(i32);
+
+impl ExitCode {
+	pub const SUCCESS: ExitCode = ExitCode(0);
+	pub const FAILURE: ExitCode = ExitCode(1);
+}
+
+impl From<u8> for ExitCode {
+	fn from(code: u8) -> Self {
+		ExitCode(code.into())
+	}
+}
+
 /// A trait for implementing arbitrary return types in the `main` function.
 ///
 /// The c-main function only supports to return integers as return type.
@@ -78,12 +262,10 @@ impl Termination for i32 {
 	}
 }
 
-impl<E: fmt::Debug> Termination for Result<(), E> {
+impl Termination for ExitCode {
+	#[inline]
 	fn report(self) -> i32 {
-		match self {
-			Ok(()) => ().report(),
-			Err(err) => Err::<!, _>(err).report(),
-		}
+		self.0
 	}
 }
 
@@ -93,13 +275,25 @@ impl Termination for ! {
 	}
 }
 
-impl<E: fmt::Debug> Termination for Result<!, E> {
+/// Errors are shown in a message box, paginated if they're long, and 1 is
+/// returned. This lets `main` use `?`:
+///
+/// ```
+/// #[entry]
+/// fn main() -> Result<(), ndless::io::Error> {
+///     let level = ndless::fs::read_to_string("level.tns")?;
+///     play(&level);
+///     Ok(())
+/// }
+/// ```
+impl<T: Termination, E: fmt::Debug> Termination for Result<T, E> {
 	fn report(self) -> i32 {
-		let err = match self {
-			Err(err) => err,
-			_ => unreachable!(),
-		};
-		crate::msg::msg("Error", &alloc::format!("Error: {:?}", err));
-		1
+		match self {
+			Ok(value) => value.report(),
+			Err(err) => {
+				crate::msg::msg_paged("Error", &alloc::format!("Error: {:?}", err));
+				ExitCode::FAILURE.report()
+			}
+		}
 	}
 }
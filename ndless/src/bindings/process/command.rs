@@ -0,0 +1,142 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use cstr_core::CString;
+
+use crate::env;
+use crate::fs;
+use crate::io::{self, ErrorKind};
+use crate::path::{Path, PathBuf};
+
+/// Runs another program with `nl_exec`, like `std::process::Command`:
+///
+/// ```
+/// use ndless::process::Command;
+///
+/// let status = Command::new("convert.tns")
+///     .arg("/documents/photo.tns")
+///     .args(&["--gray", "scale=2"])
+///     .status()?;
+/// if !status.success() {
+///     msg("Error", "The conversion failed");
+/// }
+/// ```
+///
+/// Only one program runs at a time, so this program is paused until the other
+/// one exits. A relative path is found from the
+/// [current directory][env::current_dir], which starts out as the one this
+/// program is in.
+#[derive(Debug, Clone)]
+pub struct Command {
+	program: PathBuf,
+	args: Vec<CString>,
+}
+
+impl Command {
+	/// Prepares to run the program at `program`, with no arguments.
+	pub fn new<P: AsRef<Path>>(program: P) -> Self {
+		Command {
+			program: program.as_ref().to_path_buf(),
+			args: Vec::new(),
+		}
+	}
+
+	/// Adds an argument.
+	///
+	/// # Panics
+	///
+	/// Panics if `arg` contains a nul byte.
+	pub fn arg<S: AsRef<str>>(&mut self, arg: S) -> &mut Self {
+		self.args
+			.push(CString::new(arg.as_ref()).expect("argument contains a nul byte"));
+		self
+	}
+
+	/// Adds several arguments.
+	///
+	/// # Panics
+	///
+	/// Panics if any of them contain a nul byte.
+	pub fn args<I, S>(&mut self, args: I) -> &mut Self
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<str>,
+	{
+		for arg in args {
+			self.arg(arg);
+		}
+		self
+	}
+
+	/// Runs the program until it exits. Unlike `std`, the program has already
+	/// finished when this returns, so [`Child::wait`] returns straight away.
+	pub fn spawn(&mut self) -> io::Result<Child> {
+		self.status().map(|status| Child { status })
+	}
+
+	/// Runs the program until it exits, returning its exit status.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the program doesn't exist, or its path isn't valid
+	/// UTF-8.
+	pub fn status(&mut self) -> io::Result<ExitStatus> {
+		let path = if self.program.is_relative() {
+			env::current_dir()?.join(&self.program)
+		} else {
+			self.program.clone()
+		};
+		if !fs::metadata(&path)?.is_file() {
+			return Err(ErrorKind::NotFound.into());
+		}
+		let path = path
+			.to_str()
+			.and_then(|path| CString::new(path).ok())
+			.ok_or_else(|| io::Error::from(ErrorKind::InvalidInput))?;
+		let mut argv = self
+			.args
+			.iter()
+			.map(|arg| arg.as_ptr() as *mut cty::c_char)
+			.collect::<Vec<_>>();
+		let code = unsafe {
+			ndless_sys::nl_exec(path.as_ptr(), argv.len() as cty::c_int, argv.as_mut_ptr())
+		};
+		Ok(ExitStatus(code))
+	}
+}
+
+/// A program started by [`Command::spawn`], which has already exited.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Child {
+	status: ExitStatus,
+}
+
+impl Child {
+	/// Returns the program's exit status.
+	pub fn wait(&mut self) -> io::Result<ExitStatus> {
+		Ok(self.status)
+	}
+}
+
+/// How a program run by [`Command`] exited.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct ExitStatus(i32);
+
+impl ExitStatus {
+	/// Returns true if the program exited with 0.
+	pub fn success(&self) -> bool {
+		self.0 == 0
+	}
+
+	/// Returns the value the program returned from `main`, or passed to
+	/// [`exit`][crate::process::exit].
+	pub fn code(&self) -> Option<i32> {
+		Some(self.0)
+	}
+}
+
+impl fmt::Display for ExitStatus {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "exit code: {}", self.0)
+	}
+}
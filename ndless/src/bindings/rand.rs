@@ -0,0 +1,231 @@
+//! # Random numbers
+//! A small, fast generator for games and simulations, seeded from whatever
+//! the calculator has that varies between runs:
+//!
+//! ```
+//! use ndless::rand::Rng;
+//!
+//! let mut rng = Rng::new();
+//! let dice = rng.range(1..7);
+//! let mut deck: Vec<u8> = (0..52).collect();
+//! rng.shuffle(&mut deck);
+//! ```
+//!
+//! [`Rng`] is xoshiro128++, which is fast on a 32 bit CPU and passes
+//! statistical tests, but isn't suitable for cryptography. Use
+//! [`Rng::from_seed`] to get the same numbers every run, such as for
+//! [replays][crate::replay].
+//!
+//! With the `rand_core` feature, [`Rng`] implements
+//! [`RngCore`][rand_core::RngCore] and
+//! [`SeedableRng`][rand_core::SeedableRng], so it works with crates from the
+//! `rand` ecosystem.
+#![allow(clippy::unreadable_literal)]
+
+use core::ops::Range;
+use core::ptr::read_volatile;
+
+use crate::timer::{get_ticks, is_running};
+
+/// The real time clock, in seconds
+const RTC_VALUE: *const u32 = 0x90090000 as *const u32;
+
+fn splitmix64(state: &mut u64) -> u64 {
+	*state = state.wrapping_add(0x9E3779B97F4A7C15);
+	let mut z = *state;
+	z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+	z ^ (z >> 31)
+}
+
+/// Returns 64 bits that are likely to be different every time, collected
+/// from:
+///
+/// - the real time clock
+/// - the time since the program started, in 32768Hz ticks
+/// - how many loops the CPU gets through between ticks, which varies with
+///   cache and bus timing, unless the program started with
+///   [`Timers::Off`][crate::process::Timers::Off]
+/// - leftover data on the stack from the OS and earlier calls
+///
+/// This is slow, about a millisecond. Use it to seed a [`Rng`], not as one.
+pub fn entropy() -> u64 {
+	let mut state = 0;
+	let mut hash = 0;
+	let mut add = |value: u32| {
+		state ^= u64::from(value);
+		hash ^= splitmix64(&mut state);
+	};
+	add(unsafe { read_volatile(RTC_VALUE) });
+	add(get_ticks());
+	// The timer doesn't advance with Timers::Off
+	let rounds = if is_running() { 32 } else { 0 };
+	for _ in 0..rounds {
+		let start = get_ticks();
+		let mut loops = 0_u32;
+		while get_ticks() == start {
+			loops = loops.wrapping_add(1);
+		}
+		add(loops);
+	}
+	// Below the stack pointer is memory that was used by earlier calls, but
+	// doesn't belong to anything now. It is read as raw memory, like a
+	// hardware register.
+	let sp: *const u32;
+	unsafe { llvm_asm!("mov $0, sp" : "=r"(sp)) };
+	for offset in 64..320 {
+		add(unsafe { read_volatile(sp.wrapping_sub(offset)) });
+	}
+	hash
+}
+
+/// A xoshiro128++ random number generator. See the
+/// [module documentation][self].
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct Rng {
+	state: [u32; 4],
+}
+
+impl Default for Rng {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Rng {
+	/// Creates a generator seeded from [`entropy`].
+	pub fn new() -> Self {
+		Self::from_seed(entropy())
+	}
+
+	/// Creates a generator that returns the same numbers every time for the
+	/// same `seed`.
+	pub fn from_seed(seed: u64) -> Self {
+		let mut seed = seed;
+		let a = splitmix64(&mut seed);
+		let b = splitmix64(&mut seed);
+		Self {
+			state: [a as u32, (a >> 32) as u32, b as u32, (b >> 32) as u32],
+		}
+	}
+
+	pub fn next_u32(&mut self) -> u32 {
+		let s = &mut self.state;
+		let result = s[0].wrapping_add(s[3]).rotate_left(7).wrapping_add(s[0]);
+		let t = s[1] << 9;
+		s[2] ^= s[0];
+		s[3] ^= s[1];
+		s[1] ^= s[2];
+		s[0] ^= s[3];
+		s[2] ^= t;
+		s[3] = s[3].rotate_left(11);
+		result
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		u64::from(self.next_u32()) | u64::from(self.next_u32()) << 32
+	}
+
+	/// Returns a number from 0 up to, but not including, `n`, with every
+	/// number equally likely.
+	///
+	/// # Panics
+	/// If `n` is 0.
+	pub fn below(&mut self, n: u32) -> u32 {
+		assert!(n > 0, "cannot pick a number below 0");
+		// Lemire's method: multiply into the top 32 bits, and try again in the
+		// rare case that would favour some numbers
+		let threshold = n.wrapping_neg() % n;
+		loop {
+			let product = u64::from(self.next_u32()) * u64::from(n);
+			if product as u32 >= threshold {
+				return (product >> 32) as u32;
+			}
+		}
+	}
+
+	/// Returns a number in `range`, with every number equally likely.
+	///
+	/// # Panics
+	/// If `range` is empty.
+	pub fn range(&mut self, range: Range<i32>) -> i32 {
+		assert!(range.start < range.end, "cannot pick from an empty range");
+		let len = range.end.wrapping_sub(range.start) as u32;
+		range.start.wrapping_add(self.below(len) as i32)
+	}
+
+	/// Returns a number from 0 up to, but not including, 1.
+	pub fn f32(&mut self) -> f32 {
+		(self.next_u32() >> 8) as f32 * (1.0 / (1 << 24) as f32)
+	}
+
+	/// Returns `true` with probability `p`, from 0 to 1.
+	pub fn chance(&mut self, p: f32) -> bool {
+		self.f32() < p
+	}
+
+	/// Fills `dest` with random bytes.
+	pub fn fill_bytes(&mut self, dest: &mut [u8]) {
+		for chunk in dest.chunks_mut(4) {
+			let bytes = self.next_u32().to_le_bytes();
+			chunk.copy_from_slice(&bytes[..chunk.len()]);
+		}
+	}
+
+	/// Returns a random element of `slice`, or `None` if it is empty.
+	pub fn choose<'a, T>(&mut self, slice: &'a [T]) -> Option<&'a T> {
+		if slice.is_empty() {
+			None
+		} else {
+			slice.get(self.below(slice.len() as u32) as usize)
+		}
+	}
+
+	/// Puts `slice` in a random order, with every order equally likely.
+	pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+		for i in (1..slice.len()).rev() {
+			slice.swap(i, self.below(i as u32 + 1) as usize);
+		}
+	}
+}
+
+#[cfg(feature = "rand_core")]
+impl rand_core::RngCore for Rng {
+	fn next_u32(&mut self) -> u32 {
+		Rng::next_u32(self)
+	}
+
+	fn next_u64(&mut self) -> u64 {
+		Rng::next_u64(self)
+	}
+
+	fn fill_bytes(&mut self, dest: &mut [u8]) {
+		Rng::fill_bytes(self, dest)
+	}
+
+	fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+		Rng::fill_bytes(self, dest);
+		Ok(())
+	}
+}
+
+#[cfg(feature = "rand_core")]
+impl rand_core::SeedableRng for Rng {
+	type Seed = [u8; 16];
+
+	fn from_seed(seed: Self::Seed) -> Self {
+		let mut state = [0; 4];
+		for (word, bytes) in state.iter_mut().zip(seed.chunks(4)) {
+			*word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+		}
+		if state == [0; 4] {
+			// xoshiro would only ever return 0
+			return Rng::from_seed(0);
+		}
+		Self { state }
+	}
+
+	fn seed_from_u64(state: u64) -> Self {
+		Rng::from_seed(state)
+	}
+}
@@ -0,0 +1,173 @@
+//! # Replays
+//! Records a fixed-timestep simulation and checks that it runs the same way
+//! again.
+//!
+//! Replays and link-cable multiplayer only work if game logic is
+//! deterministic: given the same seed and the same input each tick, it must
+//! reach the same state. A [`Recording`] stores the seed, the input of each
+//! tick, and a hash of the state after each tick. [`verify`] runs the
+//! simulation again from the recording and reports the first tick where the
+//! state differs:
+//!
+//! ```
+//! use core::hash::Hash;
+//! use ndless::replay::{self, Recording, Simulation};
+//!
+//! #[derive(Hash)]
+//! struct World { /* ... */ }
+//!
+//! impl Simulation for World {
+//!     type Input = u32;
+//!     fn new(seed: u64) -> Self { World::generate(seed) }
+//!     fn step(&mut self, input: &u32) { /* ... */ }
+//!     fn state_hash(&self) -> u64 { replay::hash(self) }
+//! }
+//!
+//! let mut recording = Recording::new(seed);
+//! let mut world = World::new(seed);
+//! loop {
+//!     let input = read_input();
+//!     world.step(&input);
+//!     recording.push(input, world.state_hash());
+//! #   break;
+//! }
+//! replay::verify::<World>(&recording).expect("game logic is not deterministic");
+//! ```
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
+use crate::error::Error;
+
+/// Game logic that advances in fixed steps.
+pub trait Simulation {
+	/// Everything that affects one step from outside the simulation, such as
+	/// the keys held during that tick
+	type Input;
+
+	/// Creates the initial state. Any randomness must come from `seed`.
+	fn new(seed: u64) -> Self;
+
+	/// Advances by one tick.
+	fn step(&mut self, input: &Self::Input);
+
+	/// Hashes everything that should be reproduced exactly. Types that derive
+	/// `Hash` can use [`hash`].
+	fn state_hash(&self) -> u64;
+}
+
+/// The inputs and state hashes of a run of a [`Simulation`].
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct Recording<I> {
+	seed: u64,
+	inputs: Vec<I>,
+	hashes: Vec<u64>,
+}
+
+impl<I> Recording<I> {
+	pub fn new(seed: u64) -> Self {
+		Self {
+			seed,
+			inputs: Vec::new(),
+			hashes: Vec::new(),
+		}
+	}
+
+	/// Adds a tick: the input it was given, and the state hash after it.
+	pub fn push(&mut self, input: I, state_hash: u64) {
+		self.inputs.push(input);
+		self.hashes.push(state_hash);
+	}
+
+	pub fn seed(&self) -> u64 {
+		self.seed
+	}
+
+	pub fn inputs(&self) -> &[I] {
+		&self.inputs
+	}
+
+	pub fn hashes(&self) -> &[u64] {
+		&self.hashes
+	}
+
+	/// The number of ticks recorded.
+	pub fn len(&self) -> usize {
+		self.inputs.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.inputs.is_empty()
+	}
+}
+
+/// Where a replay stopped matching its recording.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Desync {
+	/// The first tick whose state differed, counting from 0
+	pub tick: usize,
+	pub expected: u64,
+	pub actual: u64,
+}
+
+impl fmt::Display for Desync {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"replay desynced at tick {}: expected state {:016x}, got {:016x}",
+			self.tick, self.expected, self.actual
+		)
+	}
+}
+
+impl Error for Desync {}
+
+/// Runs `S` again from a recording, comparing the state hash after every tick.
+/// Returns the number of ticks checked, or where the first difference was.
+pub fn verify<S: Simulation>(recording: &Recording<S::Input>) -> Result<usize, Desync> {
+	let mut sim = S::new(recording.seed);
+	for (tick, (input, &expected)) in recording.inputs.iter().zip(&recording.hashes).enumerate() {
+		sim.step(input);
+		let actual = sim.state_hash();
+		if actual != expected {
+			return Err(Desync {
+				tick,
+				expected,
+				actual,
+			});
+		}
+	}
+	Ok(recording.len())
+}
+
+/// A 64-bit FNV-1a hasher. Unlike the hashers used by hash maps, its output
+/// is the same on every run and every calculator.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct StateHasher(u64);
+
+impl Default for StateHasher {
+	fn default() -> Self {
+		Self(0xCBF2_9CE4_8422_2325)
+	}
+}
+
+impl Hasher for StateHasher {
+	fn finish(&self) -> u64 {
+		self.0
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
+			self.0 ^= u64::from(byte);
+			self.0 = self.0.wrapping_mul(0x0000_0100_0000_01B3);
+		}
+	}
+}
+
+/// Hashes a value with [`StateHasher`].
+pub fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+	let mut hasher = StateHasher::default();
+	value.hash(&mut hasher);
+	hasher.finish()
+}
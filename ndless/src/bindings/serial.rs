@@ -0,0 +1,345 @@
+//! # Serial port
+//! Talks to external hardware, or to another calculator, over the UART on the
+//! dock connector. A [`Uart`] implements [`Read`][io::Read] and
+//! [`Write`][io::Write]:
+//!
+//! ```
+//! use ndless::io::{Read, Write};
+//! use ndless::serial::Uart;
+//!
+//! let mut uart = Uart::new(9600).expect("the serial port is already in use");
+//! uart.write_all(b"PING\n")?;
+//! let mut reply = [0; 5];
+//! uart.read_exact(&mut reply)?;
+//! ```
+//!
+//! Reads wait for at least one byte, unless the port is made non-blocking with
+//! [`Uart::set_nonblocking`], in which case they fail with
+//! [`WouldBlock`][io::ErrorKind::WouldBlock] when nothing has arrived. The
+//! hardware only holds a few bytes, so programs that don't read often should
+//! call [`Uart::enable_rx_interrupt`], which moves bytes into a larger buffer
+//! as they arrive.
+//!
+//! [`println!`][crate::println] also writes to this port, at the OS's 115200
+//! baud. The settings are put back when the `Uart` is dropped, or by
+//! [`restore`] when the program exits.
+//!
+//! The user is asked before the port is taken over, with
+//! [`Capability::Serial`].
+
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::consent::{self, Capability};
+use crate::hw::has_colors;
+use crate::interrupt::{self, Irq, Registration};
+use crate::io;
+
+const BASE: usize = 0x9002_0000;
+/// The clock the baud rate is divided from
+const CLOCK: u32 = 12_000_000;
+
+/// The size of the buffer filled by the RX interrupt.
+pub const RX_BUFFER_SIZE: usize = 256;
+
+/// Bytes received by the RX interrupt, as a ring buffer
+static mut RX_BUFFER: [u8; RX_BUFFER_SIZE] = [0; RX_BUFFER_SIZE];
+static mut RX_HEAD: usize = 0;
+static mut RX_LEN: usize = 0;
+/// Set when a byte arrived while the buffer was full
+static mut RX_OVERFLOW: bool = false;
+static mut IN_USE: bool = false;
+/// The registers as the OS left them, while a [`Uart`] exists
+static mut SAVED: Option<[u32; 5]> = None;
+
+fn register(offset: usize) -> *mut u32 {
+	(BASE + offset) as *mut u32
+}
+
+fn read(offset: usize) -> u32 {
+	unsafe { read_volatile(register(offset)) }
+}
+
+fn write(offset: usize, value: u32) {
+	unsafe { write_volatile(register(offset), value) }
+}
+
+/// CX models have an ARM PL011, and classic ones a 16550.
+mod pl011 {
+	pub const DATA: usize = 0x00;
+	pub const FLAGS: usize = 0x18;
+	pub const INTEGER_DIVISOR: usize = 0x24;
+	pub const FRACTIONAL_DIVISOR: usize = 0x28;
+	pub const LINE_CONTROL: usize = 0x2C;
+	pub const CONTROL: usize = 0x30;
+	pub const INT_MASK: usize = 0x38;
+
+	pub const FLAG_RX_EMPTY: u32 = 1 << 4;
+	pub const FLAG_TX_FULL: u32 = 1 << 5;
+	/// 8 data bits, with the FIFOs enabled
+	pub const LINE_8N1: u32 = 0b11 << 5 | 1 << 4;
+	/// The receive and receive timeout interrupts
+	pub const INT_RX: u32 = 1 << 4 | 1 << 6;
+}
+
+mod uart16550 {
+	pub const DATA: usize = 0x00;
+	pub const DIVISOR_LOW: usize = 0x00;
+	pub const INT_ENABLE: usize = 0x04;
+	pub const DIVISOR_HIGH: usize = 0x04;
+	pub const LINE_CONTROL: usize = 0x0C;
+	pub const LINE_STATUS: usize = 0x14;
+
+	pub const STATUS_DATA_READY: u32 = 1;
+	pub const STATUS_TX_EMPTY: u32 = 1 << 5;
+	pub const LINE_DIVISOR_LATCH: u32 = 1 << 7;
+	pub const LINE_8N1: u32 = 0b11;
+	pub const INT_RX: u32 = 1;
+}
+
+fn try_read_hw() -> Option<u8> {
+	if has_colors() {
+		if read(pl011::FLAGS) & pl011::FLAG_RX_EMPTY != 0 {
+			return None;
+		}
+		Some(read(pl011::DATA) as u8)
+	} else {
+		if read(uart16550::LINE_STATUS) & uart16550::STATUS_DATA_READY == 0 {
+			return None;
+		}
+		Some(read(uart16550::DATA) as u8)
+	}
+}
+
+fn on_interrupt() -> bool {
+	let mut received = false;
+	while let Some(byte) = try_read_hw() {
+		received = true;
+		unsafe {
+			if RX_LEN == RX_BUFFER_SIZE {
+				RX_OVERFLOW = true;
+			} else {
+				RX_BUFFER[(RX_HEAD + RX_LEN) % RX_BUFFER_SIZE] = byte;
+				RX_LEN += 1;
+			}
+		}
+	}
+	received
+}
+
+/// The serial port. See the [module documentation][self].
+#[derive(Debug)]
+pub struct Uart {
+	baud_rate: u32,
+	nonblocking: bool,
+	rx_interrupt: Option<Registration>,
+}
+
+impl Uart {
+	/// Takes over the serial port, at `baud_rate` with 8 data bits, no parity
+	/// and one stop bit. Returns `None` if another `Uart` exists, or if the
+	/// user doesn't allow it when asked with [`Capability::Serial`].
+	pub fn new(baud_rate: u32) -> Option<Self> {
+		if unsafe { IN_USE }
+			|| !consent::request(Capability::Serial, "to talk to a connected device")
+		{
+			return None;
+		}
+		unsafe { IN_USE = true }
+		let saved = if has_colors() {
+			[
+				read(pl011::INTEGER_DIVISOR),
+				read(pl011::FRACTIONAL_DIVISOR),
+				read(pl011::LINE_CONTROL),
+				read(pl011::CONTROL),
+				read(pl011::INT_MASK),
+			]
+		} else {
+			let line = read(uart16550::LINE_CONTROL);
+			write(
+				uart16550::LINE_CONTROL,
+				line | uart16550::LINE_DIVISOR_LATCH,
+			);
+			let divisor = [read(uart16550::DIVISOR_LOW), read(uart16550::DIVISOR_HIGH)];
+			write(uart16550::LINE_CONTROL, line);
+			[divisor[0], divisor[1], line, 0, read(uart16550::INT_ENABLE)]
+		};
+		unsafe { SAVED = Some(saved) }
+		let mut uart = Self {
+			baud_rate,
+			nonblocking: false,
+			rx_interrupt: None,
+		};
+		uart.set_baud_rate(baud_rate);
+		Some(uart)
+	}
+
+	pub fn baud_rate(&self) -> u32 {
+		self.baud_rate
+	}
+
+	/// Changes the baud rate. Bytes being sent may be garbled.
+	pub fn set_baud_rate(&mut self, baud_rate: u32) {
+		let baud_rate = baud_rate.max(1);
+		self.baud_rate = baud_rate;
+		if has_colors() {
+			// The divisor has 6 fractional bits
+			let divisor = (CLOCK * 4 + baud_rate / 2) / baud_rate;
+			write(pl011::CONTROL, 0);
+			write(pl011::INTEGER_DIVISOR, divisor >> 6);
+			write(pl011::FRACTIONAL_DIVISOR, divisor & 0x3F);
+			// The divisors only take effect when the line control is written
+			write(pl011::LINE_CONTROL, pl011::LINE_8N1);
+			// Enabled, with both transmit and receive
+			write(pl011::CONTROL, 1 | 1 << 8 | 1 << 9);
+		} else {
+			let divisor = (CLOCK / 16 + baud_rate / 2) / baud_rate;
+			write(uart16550::LINE_CONTROL, uart16550::LINE_DIVISOR_LATCH);
+			write(uart16550::DIVISOR_LOW, divisor & 0xFF);
+			write(uart16550::DIVISOR_HIGH, divisor >> 8 & 0xFF);
+			write(uart16550::LINE_CONTROL, uart16550::LINE_8N1);
+		}
+	}
+
+	/// Makes reads fail with [`WouldBlock`][io::ErrorKind::WouldBlock]
+	/// instead of waiting when nothing has been received.
+	pub fn set_nonblocking(&mut self, nonblocking: bool) {
+		self.nonblocking = nonblocking;
+	}
+
+	/// Moves received bytes into a buffer of [`RX_BUFFER_SIZE`] bytes as
+	/// they arrive, instead of leaving them in the hardware's few bytes.
+	/// Returns false if the interrupt is in use.
+	pub fn enable_rx_interrupt(&mut self) -> bool {
+		if self.rx_interrupt.is_some() {
+			return true;
+		}
+		self.rx_interrupt = interrupt::register(Irq::UART, on_interrupt);
+		if self.rx_interrupt.is_some() {
+			if has_colors() {
+				write(pl011::INT_MASK, pl011::INT_RX);
+			} else {
+				write(uart16550::INT_ENABLE, uart16550::INT_RX);
+			}
+		}
+		self.rx_interrupt.is_some()
+	}
+
+	/// Stops the RX interrupt. Bytes already in its buffer can still be read.
+	pub fn disable_rx_interrupt(&mut self) {
+		if self.rx_interrupt.take().is_some() {
+			if has_colors() {
+				write(pl011::INT_MASK, 0);
+			} else {
+				write(uart16550::INT_ENABLE, 0);
+			}
+		}
+	}
+
+	/// Returns true, and clears the flag, if bytes were lost because the RX
+	/// interrupt's buffer was full.
+	pub fn take_overflow(&mut self) -> bool {
+		interrupt::free(|| unsafe { core::mem::replace(&mut RX_OVERFLOW, false) })
+	}
+
+	/// Returns a received byte, if there is one, without waiting.
+	pub fn try_read_byte(&mut self) -> Option<u8> {
+		// Bytes the interrupt buffered came first
+		interrupt::free(|| unsafe {
+			if RX_LEN == 0 {
+				return try_read_hw();
+			}
+			let byte = RX_BUFFER[RX_HEAD];
+			RX_HEAD = (RX_HEAD + 1) % RX_BUFFER_SIZE;
+			RX_LEN -= 1;
+			Some(byte)
+		})
+	}
+
+	/// Sends a byte, waiting for room in the hardware's buffer.
+	pub fn write_byte(&mut self, byte: u8) {
+		if has_colors() {
+			while read(pl011::FLAGS) & pl011::FLAG_TX_FULL != 0 {}
+			write(pl011::DATA, byte.into());
+		} else {
+			while read(uart16550::LINE_STATUS) & uart16550::STATUS_TX_EMPTY == 0 {}
+			write(uart16550::DATA, byte.into());
+		}
+	}
+}
+
+impl io::Read for Uart {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		let first = loop {
+			if let Some(byte) = self.try_read_byte() {
+				break byte;
+			}
+			if self.nonblocking {
+				return Err(io::ErrorKind::WouldBlock.into());
+			}
+		};
+		buf[0] = first;
+		let mut len = 1;
+		while len < buf.len() {
+			match self.try_read_byte() {
+				Some(byte) => buf[len] = byte,
+				None => break,
+			}
+			len += 1;
+		}
+		Ok(len)
+	}
+}
+
+impl io::Write for Uart {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		for &byte in buf {
+			self.write_byte(byte);
+		}
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+impl Drop for Uart {
+	fn drop(&mut self) {
+		self.disable_rx_interrupt();
+		restore();
+	}
+}
+
+/// Puts the serial port's settings back as the OS left them, if a [`Uart`]
+/// changed them. This is done when the `Uart` is dropped, and by
+/// [`process::exit`][crate::process::exit] and
+/// [`abort`][crate::process::abort], which don't run destructors.
+pub fn restore() {
+	let saved = match unsafe { SAVED.take() } {
+		Some(saved) => saved,
+		None => return,
+	};
+	if has_colors() {
+		write(pl011::CONTROL, 0);
+		write(pl011::INTEGER_DIVISOR, saved[0]);
+		write(pl011::FRACTIONAL_DIVISOR, saved[1]);
+		write(pl011::LINE_CONTROL, saved[2]);
+		write(pl011::CONTROL, saved[3]);
+		write(pl011::INT_MASK, saved[4]);
+	} else {
+		write(uart16550::LINE_CONTROL, uart16550::LINE_DIVISOR_LATCH);
+		write(uart16550::DIVISOR_LOW, saved[0]);
+		write(uart16550::DIVISOR_HIGH, saved[1]);
+		write(uart16550::LINE_CONTROL, saved[2]);
+		write(uart16550::INT_ENABLE, saved[4]);
+	}
+	unsafe {
+		RX_HEAD = 0;
+		RX_LEN = 0;
+		RX_OVERFLOW = false;
+		IN_USE = false;
+	}
+}
@@ -0,0 +1,287 @@
+//! # Telemetry
+//! Named counters and gauges that survive crashes, for learning how a program
+//! is used on real calculators.
+//!
+//! Nothing is recorded until [`enable`] is called, which should only happen
+//! once the user has agreed to it. After that, updates are cheap, and are
+//! written to a log file at most once a second:
+//!
+//! ```
+//! use ndless::telemetry;
+//!
+//! if user_agreed {
+//!     telemetry::enable("/documents/game/telemetry.tns")?;
+//! }
+//! telemetry::incr("launches");
+//! // ...
+//! telemetry::incr("levels_completed");
+//! telemetry::gauge("highest_level", level);
+//! ```
+//!
+//! The log only ever has lines added to it, so a crash loses at most the last
+//! second of updates. Pending updates are also written when the program exits,
+//! or after the panic screen. When it grows too large, it is compacted, using
+//! [`write_atomic`][crate::autosave::write_atomic]. [`export`] formats every
+//! value as text, for the user to send back in any way the program supports,
+//! after asking for their [consent][crate::consent].
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write as _;
+
+use crate::autosave::write_atomic;
+use crate::consent::{self, Capability};
+use crate::fs::{self, OpenOptions};
+use crate::io::{self, Write};
+use crate::path::{Path, PathBuf};
+use crate::process;
+use crate::timer::{get_ticks, has_time_passed, TICKS_PER_SECOND};
+
+/// The log is compacted once it grows past this many bytes.
+const MAX_LOG_SIZE: u64 = 4096;
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+enum Kind {
+	Counter,
+	Gauge,
+}
+
+struct Value {
+	kind: Kind,
+	value: i64,
+	/// Changed since the last flush
+	dirty: bool,
+}
+
+/// Set once [`flush`] is registered to run at exit
+static mut FLUSH_AT_EXIT: bool = false;
+
+struct Telemetry {
+	path: PathBuf,
+	values: BTreeMap<String, Value>,
+	next_flush: u32,
+}
+
+static mut TELEMETRY: Option<Telemetry> = None;
+
+/// Reads a log, ignoring a final line cut off by a crash.
+fn parse(log: &str) -> BTreeMap<String, Value> {
+	let mut values = BTreeMap::new();
+	let complete = match log.rfind('\n') {
+		Some(end) => &log[..end],
+		None => "",
+	};
+	for line in complete.lines() {
+		let mut parts = line.splitn(3, ' ');
+		let kind = match parts.next() {
+			Some("c") => Kind::Counter,
+			Some("g") => Kind::Gauge,
+			_ => continue,
+		};
+		let value = match parts.next().and_then(|value| value.parse().ok()) {
+			Some(value) => value,
+			None => continue,
+		};
+		if let Some(name) = parts.next() {
+			values.insert(
+				name.into(),
+				Value {
+					kind,
+					value,
+					dirty: false,
+				},
+			);
+		}
+	}
+	values
+}
+
+fn line(out: &mut String, name: &str, value: &Value) {
+	let kind = match value.kind {
+		Kind::Counter => 'c',
+		Kind::Gauge => 'g',
+	};
+	let _ = writeln!(out, "{} {} {}", kind, value.value, name);
+}
+
+/// Starts recording, to a log at `path`. Values already in the log are kept.
+pub fn enable(path: impl AsRef<Path>) -> io::Result<()> {
+	let path = path.as_ref();
+	let values = match fs::read_to_string(path) {
+		Ok(log) => parse(&log),
+		Err(err) if err.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+		Err(err) => return Err(err),
+	};
+	unsafe {
+		TELEMETRY = Some(Telemetry {
+			path: path.into(),
+			values,
+			next_flush: get_ticks(),
+		});
+		if !FLUSH_AT_EXIT {
+			FLUSH_AT_EXIT = true;
+			process::at_exit(|| {
+				let _ = flush();
+			});
+		}
+	}
+	Ok(())
+}
+
+/// Writes any pending updates, then stops recording. If `delete` is true, the
+/// log is removed too.
+pub fn disable(delete: bool) -> io::Result<()> {
+	let result = flush();
+	if let Some(telemetry) = unsafe { TELEMETRY.take() } {
+		if delete {
+			return fs::remove_file_unchecked(&telemetry.path);
+		}
+	}
+	result
+}
+
+/// Returns true if [`enable`] has been called.
+pub fn is_enabled() -> bool {
+	unsafe { TELEMETRY.is_some() }
+}
+
+/// Names are the last field of a line in the log, so they can't contain
+/// spaces or newlines
+fn check_name(name: &str) {
+	assert!(
+		!name.is_empty() && !name.contains(&[' ', '\n', '\r'][..]),
+		"invalid telemetry name {:?}",
+		name
+	);
+}
+
+fn update(name: &str, kind: Kind, f: impl FnOnce(i64) -> i64) {
+	check_name(name);
+	let telemetry = match unsafe { TELEMETRY.as_mut() } {
+		Some(telemetry) => telemetry,
+		None => return,
+	};
+	match telemetry.values.get_mut(name) {
+		Some(value) => {
+			value.kind = kind;
+			value.value = f(value.value);
+			value.dirty = true;
+		}
+		None => {
+			telemetry.values.insert(
+				name.into(),
+				Value {
+					kind,
+					value: f(0),
+					dirty: true,
+				},
+			);
+		}
+	}
+	if has_time_passed(telemetry.next_flush) {
+		let _ = flush();
+	}
+}
+
+/// Adds one to a counter.
+///
+/// # Panics
+///
+/// Panics if `name` is empty, or contains a space or a line break.
+pub fn incr(name: &str) {
+	add(name, 1);
+}
+
+/// Adds `amount` to a counter.
+///
+/// # Panics
+///
+/// Panics if `name` is empty, or contains a space or a line break.
+pub fn add(name: &str, amount: i64) {
+	update(name, Kind::Counter, |value| value.saturating_add(amount));
+}
+
+/// Sets a gauge, which holds the last value it was set to.
+///
+/// # Panics
+///
+/// Panics if `name` is empty, or contains a space or a line break.
+pub fn gauge(name: &str, value: i64) {
+	update(name, Kind::Gauge, |_| value);
+}
+
+/// Returns the current value of a counter or gauge.
+pub fn get(name: &str) -> Option<i64> {
+	unsafe { TELEMETRY.as_ref() }?
+		.values
+		.get(name)
+		.map(|value| value.value)
+}
+
+/// Writes pending updates to the log now, rather than waiting for the next
+/// update a second later.
+pub fn flush() -> io::Result<()> {
+	let telemetry = match unsafe { TELEMETRY.as_mut() } {
+		Some(telemetry) => telemetry,
+		None => return Ok(()),
+	};
+	telemetry.next_flush = get_ticks().wrapping_add(TICKS_PER_SECOND);
+	let mut pending = String::new();
+	for (name, value) in telemetry.values.iter().filter(|(_, value)| value.dirty) {
+		line(&mut pending, name, value);
+	}
+	if pending.is_empty() {
+		return Ok(());
+	}
+
+	let size = fs::metadata(&telemetry.path).map_or(0, |meta| meta.len());
+	if size + pending.len() as u64 > MAX_LOG_SIZE {
+		let mut snapshot = String::new();
+		for (name, value) in &telemetry.values {
+			line(&mut snapshot, name, value);
+		}
+		write_atomic(&telemetry.path, snapshot)?;
+	} else {
+		OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(&telemetry.path)?
+			.write_all(pending.as_bytes())?;
+	}
+	for value in telemetry.values.values_mut() {
+		value.dirty = false;
+	}
+	Ok(())
+}
+
+/// Returns every value, sorted by name.
+pub fn values() -> Vec<(String, i64)> {
+	unsafe { TELEMETRY.as_ref() }.map_or_else(Vec::new, |telemetry| {
+		telemetry
+			.values
+			.iter()
+			.map(|(name, value)| (name.clone(), value.value))
+			.collect()
+	})
+}
+
+/// Formats every value as `name=value` lines, ready to be shown or sent.
+///
+/// The user is asked first, with [`Capability::TelemetryExport`]. Returns
+/// `None` if they refuse.
+pub fn export() -> Option<String> {
+	if !consent::request(
+		Capability::TelemetryExport,
+		"so they can be sent to its developer",
+	) {
+		return None;
+	}
+	Some(
+		values()
+			.iter()
+			.map(|(name, value)| format!("{}={}\n", name, value))
+			.collect(),
+	)
+}
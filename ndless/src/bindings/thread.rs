@@ -3,7 +3,11 @@
 use core::time::Duration;
 
 use crate::hw::idle;
-use crate::timer::{configure_sleep, disable_sleep, get_ticks, has_time_passed, Ticks};
+use crate::time::record_idle;
+use crate::timer::cooperative::{dispatch, next_due};
+use crate::timer::{
+	configure_sleep, disable_sleep, get_ticks, has_time_passed, set_sleep_ticks, Ticks,
+};
 
 /// Puts the current thread to sleep for at least the specified amount of time.
 ///
@@ -15,6 +19,9 @@ use crate::timer::{configure_sleep, disable_sleep, get_ticks, has_time_passed, T
 ///
 /// This function has a resolution of 30 μs.
 ///
+/// Callbacks registered with [`cooperative::every`][crate::timer::cooperative::every] are run
+/// while sleeping.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -28,9 +35,20 @@ use crate::timer::{configure_sleep, disable_sleep, get_ticks, has_time_passed, T
 pub fn sleep(dur: Duration) {
 	let ticks = dur.as_ticks();
 	let wanted_time = get_ticks().wrapping_add(ticks);
-	configure_sleep(ticks);
+	// Saves the OS's timer settings, which disable_sleep puts back
+	configure_sleep(ticks.max(1));
 	while !has_time_passed(wanted_time) {
+		// Wake up early for any timer callbacks
+		let remaining = wanted_time.wrapping_sub(get_ticks());
+		set_sleep_ticks(
+			next_due()
+				.map_or(remaining, |due| due.min(remaining))
+				.max(1),
+		);
+		let idle_start = get_ticks();
 		idle();
+		record_idle(get_ticks().wrapping_sub(idle_start));
+		dispatch();
 	}
 	disable_sleep();
 }
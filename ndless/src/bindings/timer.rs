@@ -10,6 +10,8 @@ use ndless_static_vars::*;
 use crate::hw::has_colors;
 use crate::time::Duration;
 
+pub mod cooperative;
+
 pub const TICKS_PER_SECOND: u32 = 32768;
 pub const TICKS_PER_MILLISECOND: u32 = 33;
 pub const MICROSECONDS_PER_TICK: u32 = 1000 / TICKS_PER_MILLISECOND;
@@ -21,6 +23,7 @@ pub fn __init() {
 			let value = 0x900C0004 as *mut u32;
 			let control = 0x900C0008 as *mut u32;
 			let clock_source = 0x900C0080 as *mut u32;
+			ORIG_TICK_TIMER = Some((read_volatile(control), read_volatile(clock_source)));
 			write_volatile(clock_source, 0xA);
 			write_volatile(control, 0b10000010);
 			START_VALUE = read_volatile(value);
@@ -28,6 +31,7 @@ pub fn __init() {
 			let value = 0x900C0000 as *mut u32;
 			let control = 0x900C0008 as *mut u32;
 			let divider = 0x900C0004 as *mut u32;
+			ORIG_TICK_TIMER = Some((read_volatile(control), read_volatile(divider)));
 			write_volatile(divider, 1);
 			write_volatile(control, 0b00001111);
 			write_volatile(value, 0);
@@ -36,6 +40,32 @@ pub fn __init() {
 	}
 }
 
+/// Puts the timer used by [`get_ticks`] back the way the OS had it. This is
+/// done when the program exits, so it rarely needs to be called directly.
+pub fn restore() {
+	unsafe {
+		if let Some((orig_control, orig_source)) = ORIG_TICK_TIMER.take() {
+			let control = 0x900C0008 as *mut u32;
+			if has_colors() {
+				let clock_source = 0x900C0080 as *mut u32;
+				write_volatile(control, 0);
+				write_volatile(clock_source, orig_source);
+			} else {
+				let divider = 0x900C0004 as *mut u32;
+				write_volatile(divider, orig_source);
+			}
+			write_volatile(control, orig_control);
+		}
+	}
+}
+
+/// TRUE if the timer used by [`get_ticks`] was started, which it isn't with
+/// [`Timers::Off`][crate::process::Timers::Off]. Without it, waiting for the
+/// tick count to change would never finish.
+pub(crate) fn is_running() -> bool {
+	unsafe { ORIG_TICK_TIMER.is_some() }
+}
+
 /// Returns the number of ticks since the program started, based on
 /// a 32768Hz timer (i.e. 32768 ticks per second).
 pub fn get_ticks() -> u32 {
@@ -52,8 +82,37 @@ pub fn get_ticks() -> u32 {
 	}
 }
 
+/// Returns the number of microseconds since the program started.
+///
+/// This is derived from the same 32768Hz timer as [`get_ticks`], so it only
+/// changes about every 30μs, but it is extended to 64 bits so it will not
+/// overflow. [`get_ticks`] wraps around after about 36 hours.
+///
+/// At least one call to this function (or
+/// [`Instant::now`][crate::time::Instant::now]) needs to be made every 36
+/// hours to notice each wrap-around.
+pub fn ticks() -> u64 {
+	let ticks = unsafe {
+		let ticks = get_ticks();
+		if ticks < LAST_TICKS {
+			HIGH_TICKS += 1 << 32;
+		}
+		LAST_TICKS = ticks;
+		HIGH_TICKS | u64::from(ticks)
+	};
+	ticks * 1_000_000 / u64::from(TICKS_PER_SECOND)
+}
+
+/// Set while [`configure_sleep`] has saved the OS's timer settings, so that
+/// they're only saved once
+static mut SLEEP_CONFIGURED: bool = false;
+
 fn init_sleep() {
 	unsafe {
+		if SLEEP_CONFIGURED {
+			return;
+		}
+		SLEEP_CONFIGURED = true;
 		if has_colors() {
 			let control = 0x900D0008 as *mut u32;
 			let load = 0x900D0000 as *mut u32;
@@ -72,9 +131,18 @@ fn init_sleep() {
 
 /// Prepares the system for sleep. [`idle`][crate::hw::idle] must be
 /// called to actually sleep.
+///
+/// The OS's settings for the timer are saved the first time, and put back by
+/// [`disable_sleep`]. Calling this again before then only changes when the
+/// timer fires.
 pub fn configure_sleep(ticks: u32) {
+	init_sleep();
+	set_sleep_ticks(ticks);
+}
+
+/// Changes when the timer set up by [`configure_sleep`] fires
+pub(crate) fn set_sleep_ticks(ticks: u32) {
 	unsafe {
-		init_sleep();
 		if has_colors() {
 			let control = 0x900D0008 as *mut u32;
 			let load = 0x900D0000 as *mut u32;
@@ -89,7 +157,7 @@ pub fn configure_sleep(ticks: u32) {
 			let divider = 0x900D0004 as *mut u32;
 			write_volatile(control, 0);
 			write_volatile(divider, 1);
-			write_volatile(timer, ticks.max(2u32.pow(16) - 1));
+			write_volatile(timer, ticks.min(2u32.pow(16) - 1));
 		}
 	}
 }
@@ -97,6 +165,10 @@ pub fn configure_sleep(ticks: u32) {
 /// Resets the sleep timer so it may be used normally.
 pub fn disable_sleep() {
 	unsafe {
+		if !SLEEP_CONFIGURED {
+			return;
+		}
+		SLEEP_CONFIGURED = false;
 		if has_colors() {
 			let control = 0x900D0008 as *mut u32;
 			let load = 0x900D0000 as *mut u32;
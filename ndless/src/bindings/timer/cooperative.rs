@@ -0,0 +1,227 @@
+//! Cooperative timer callbacks
+//!
+//! Callbacks registered with [`every`] and [`after`] are not run by an
+//! interrupt. They only run when the program calls [`dispatch`], which is
+//! done while sleeping with [`thread::sleep`][crate::thread::sleep], waiting
+//! for a key, or waiting in an [`EventLoop`][crate::event::EventLoop]. A
+//! program that spends a long time computing without doing any of these
+//! should call [`dispatch`] now and then, or its callbacks will run late.
+//!
+//! Because they run from the program rather than an interrupt, callbacks can
+//! allocate, draw, and sleep like any other code.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::time::Duration;
+use crate::timer::{get_ticks, has_time_passed, Ticks};
+
+struct Entry {
+	id: usize,
+	at_tick: u32,
+	/// 0 for callbacks that only run once
+	interval: u32,
+	callback: Box<dyn FnMut()>,
+}
+
+static mut NEXT_ID: usize = 0;
+static mut ENTRIES: Vec<Entry> = Vec::new();
+/// Entries removed while [`dispatch`] is running callbacks
+static mut REMOVED: Option<Vec<usize>> = None;
+/// Entries taken out of the list by [`dispatch`]
+static mut RUNNING: Vec<usize> = Vec::new();
+/// Set while [`dispatch`] is running callbacks
+static mut DISPATCHING: bool = false;
+
+fn register(at_tick: u32, interval: u32, callback: Box<dyn FnMut()>) -> usize {
+	unsafe {
+		let id = NEXT_ID;
+		NEXT_ID += 1;
+		ENTRIES.push(Entry {
+			id,
+			at_tick,
+			interval,
+			callback,
+		});
+		id
+	}
+}
+
+/// Returns true if the entry was still registered
+fn unregister(id: usize) -> bool {
+	unsafe {
+		let len = ENTRIES.len();
+		ENTRIES.retain(|entry| entry.id != id);
+		if ENTRIES.len() != len {
+			return true;
+		}
+		match &mut REMOVED {
+			Some(removed) if RUNNING.contains(&id) && !removed.contains(&id) => {
+				removed.push(id);
+				true
+			}
+			_ => false,
+		}
+	}
+}
+
+fn is_registered(id: usize) -> bool {
+	unsafe {
+		ENTRIES.iter().any(|entry| entry.id == id)
+			|| (RUNNING.contains(&id)
+				&& !REMOVED
+					.as_ref()
+					.map_or(false, |removed| removed.contains(&id)))
+	}
+}
+
+/// Unregisters its callback when dropped. Returned by [`every`].
+#[must_use = "the callback is unregistered when the handle is dropped"]
+#[derive(Debug)]
+pub struct IntervalHandle {
+	id: usize,
+}
+
+impl IntervalHandle {
+	/// Keeps the callback registered for the rest of the program.
+	pub fn forget(self) {
+		core::mem::forget(self)
+	}
+}
+
+impl Drop for IntervalHandle {
+	fn drop(&mut self) {
+		unregister(self.id);
+	}
+}
+
+/// Calls `callback` every `interval`, until the returned handle is dropped.
+///
+/// Callbacks run from [`dispatch`], which is called automatically while
+/// sleeping with [`thread::sleep`][crate::thread::sleep]. Programs that don't
+/// sleep should call [`dispatch`] once per frame. See the
+/// [module documentation][self]. If the program falls behind, missed calls
+/// are skipped rather than run all at once.
+///
+/// ```
+/// use ndless::time::Duration;
+/// use ndless::timer::cooperative;
+///
+/// let mut visible = true;
+/// let _blink = cooperative::every(Duration::from_millis(500), move || visible = !visible);
+/// ```
+pub fn every(interval: Duration, callback: impl FnMut() + 'static) -> IntervalHandle {
+	let interval = interval.as_ticks().max(1);
+	IntervalHandle {
+		id: register(
+			get_ticks().wrapping_add(interval),
+			interval,
+			Box::new(callback),
+		),
+	}
+}
+
+/// Can cancel a callback before it runs. Returned by [`after`].
+///
+/// Unlike [`IntervalHandle`], dropping this handle does not cancel the
+/// callback.
+#[derive(Debug)]
+pub struct TimeoutHandle {
+	id: usize,
+}
+
+impl TimeoutHandle {
+	/// Cancels the callback. Returns false if it already ran or was cancelled.
+	pub fn cancel(self) -> bool {
+		unregister(self.id)
+	}
+
+	/// Returns true if the callback hasn't run yet.
+	pub fn is_pending(&self) -> bool {
+		is_registered(self.id)
+	}
+}
+
+/// Calls `callback` once, after `delay`. Like [`every`], this runs from
+/// [`dispatch`].
+///
+/// Restarting a timeout each time a key is pressed debounces the input:
+///
+/// ```
+/// use ndless::time::Duration;
+/// use ndless::timer::cooperative::{self, TimeoutHandle};
+///
+/// let mut search: Option<TimeoutHandle> = None;
+/// // When the query changes:
+/// if let Some(pending) = search.take() {
+///     pending.cancel();
+/// }
+/// search = Some(cooperative::after(Duration::from_millis(300), move || run_search()));
+/// ```
+pub fn after(delay: Duration, callback: impl FnOnce() + 'static) -> TimeoutHandle {
+	let mut callback = Some(callback);
+	TimeoutHandle {
+		id: register(
+			get_ticks().wrapping_add(delay.as_ticks()),
+			0,
+			Box::new(move || {
+				if let Some(callback) = callback.take() {
+					callback()
+				}
+			}),
+		),
+	}
+}
+
+/// Returns the number of ticks until the next callback is due, or `None` if
+/// there are no callbacks.
+pub fn next_due() -> Option<u32> {
+	let now = get_ticks();
+	unsafe { ENTRIES.iter() }
+		.map(|entry| {
+			if has_time_passed(entry.at_tick) {
+				0
+			} else {
+				entry.at_tick.wrapping_sub(now)
+			}
+		})
+		.min()
+}
+
+/// Runs every callback that is due.
+///
+/// Calls from inside a callback, such as when it sleeps, return straight away,
+/// so a callback never runs again before it returns.
+pub fn dispatch() {
+	if unsafe { core::mem::replace(&mut DISPATCHING, true) } {
+		return;
+	}
+	let mut entries = unsafe {
+		REMOVED = Some(Vec::new());
+		RUNNING = ENTRIES.iter().map(|entry| entry.id).collect();
+		core::mem::take(&mut ENTRIES)
+	};
+	for entry in entries.iter_mut() {
+		let removed =
+			unsafe { REMOVED.as_ref() }.map_or(false, |removed| removed.contains(&entry.id));
+		if !removed && has_time_passed(entry.at_tick) {
+			if entry.interval == 0 {
+				unregister(entry.id);
+			}
+			(entry.callback)();
+			entry.at_tick = entry.at_tick.wrapping_add(entry.interval);
+			if has_time_passed(entry.at_tick) {
+				entry.at_tick = get_ticks().wrapping_add(entry.interval);
+			}
+		}
+	}
+	unsafe {
+		if let Some(removed) = REMOVED.take() {
+			entries.retain(|entry| !removed.contains(&entry.id));
+		}
+		RUNNING.clear();
+		entries.append(&mut ENTRIES);
+		ENTRIES = entries;
+		DISPATCHING = false;
+	}
+}
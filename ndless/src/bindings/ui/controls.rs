@@ -0,0 +1,147 @@
+//! Controls help screens
+//!
+//! Generates a "controls" screen that draws the calculator's keypad, as
+//! described by [`input::layout`][crate::input::layout], with each bound key
+//! labeled with what it does:
+//!
+//! ```
+//! use ndless::input::Key;
+//! use ndless::ui::controls::Controls;
+//!
+//! Controls::current()
+//!     .bind(Key::Enter, "Jump")
+//!     .bind(Key::Esc, "Pause")
+//!     .draw(&mut canvas, 320, 240);
+//! ```
+//!
+//! `canvas` is anything that implements [`KeypadCanvas`]. `ndless-sdl`
+//! provides one for SDL surfaces.
+
+use alloc::vec::Vec;
+
+use crate::input::layout::{self, Layout};
+use crate::input::Key;
+
+/// The position and size of a key on the screen, in pixels.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct KeyRect {
+	pub x: i32,
+	pub y: i32,
+	pub w: u32,
+	pub h: u32,
+}
+
+/// Something that a keypad can be drawn on.
+pub trait KeypadCanvas {
+	/// Draws a single key. `legend` is what is printed on the physical key,
+	/// and `action` is what it has been bound to, if anything.
+	fn draw_key(&mut self, rect: KeyRect, legend: &str, action: Option<&str>);
+}
+
+/// A set of key bindings to show on a keypad.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Controls<'a> {
+	layout: Layout,
+	bindings: Vec<(Key, &'a str)>,
+	show_unbound: bool,
+}
+
+impl<'a> Controls<'a> {
+	/// Creates an empty set of bindings for a specific layout.
+	pub fn new(layout: Layout) -> Self {
+		Self {
+			layout,
+			bindings: Vec::new(),
+			show_unbound: true,
+		}
+	}
+
+	/// Creates an empty set of bindings for the calculator the program is
+	/// running on.
+	pub fn current() -> Self {
+		Self::new(layout::current())
+	}
+
+	/// Labels `key` with `action`. Binding the same key again replaces the
+	/// label.
+	pub fn bind(&mut self, key: Key, action: &'a str) -> &mut Self {
+		match self.bindings.iter_mut().find(|(other, _)| *other == key) {
+			Some(binding) => binding.1 = action,
+			None => self.bindings.push((key, action)),
+		}
+		self
+	}
+
+	/// Labels every key in `bindings`.
+	pub fn bind_all(&mut self, bindings: impl IntoIterator<Item = (Key, &'a str)>) -> &mut Self {
+		for (key, action) in bindings {
+			self.bind(key, action);
+		}
+		self
+	}
+
+	/// Whether to draw keys that have no binding. Defaults to true.
+	pub fn show_unbound(&mut self, show: bool) -> &mut Self {
+		self.show_unbound = show;
+		self
+	}
+
+	/// Returns what `key` is bound to.
+	pub fn action(&self, key: Key) -> Option<&'a str> {
+		self.bindings
+			.iter()
+			.find(|(other, _)| *other == key)
+			.map(|(_, action)| *action)
+	}
+
+	/// Returns the bindings for keys that don't exist on this keypad. These
+	/// can't be drawn, and usually indicate a control that can't be used on
+	/// this model.
+	pub fn missing(&self) -> impl Iterator<Item = (Key, &'a str)> + '_ {
+		let layout = self.layout;
+		self.bindings
+			.iter()
+			.copied()
+			.filter(move |(key, _)| !layout.has_key(*key))
+	}
+
+	/// Draws the keypad, filling a `width` by `height` area starting at the
+	/// top left of `canvas`. The touchpad or clickpad arrows are drawn in the
+	/// first row.
+	pub fn draw(&self, canvas: &mut impl KeypadCanvas, width: u32, height: u32) {
+		let rows = self.layout.row_count() as u32 + 1;
+		let row_height = height / rows;
+		self.draw_row(canvas, self.layout.arrows(), 0, width, row_height);
+		for row in self.layout.rows() {
+			let keys: Vec<Key> = row.iter().map(|info| info.key).collect();
+			let y = (row.index() as u32 + 1) * row_height;
+			self.draw_row(canvas, &keys, y, width, row_height);
+		}
+	}
+
+	fn draw_row(
+		&self,
+		canvas: &mut impl KeypadCanvas,
+		keys: &[Key],
+		y: u32,
+		width: u32,
+		height: u32,
+	) {
+		let columns = self.layout.max_columns().max(keys.len()).max(1);
+		let key_width = width / columns as u32;
+		let offset = (width - key_width * keys.len() as u32) / 2;
+		for (col, &key) in keys.iter().enumerate() {
+			let action = self.action(key);
+			if action.is_none() && !self.show_unbound {
+				continue;
+			}
+			let rect = KeyRect {
+				x: (offset + col as u32 * key_width) as i32,
+				y: y as i32,
+				w: key_width,
+				h: height,
+			};
+			canvas.draw_key(rect, layout::legend(key), action);
+		}
+	}
+}
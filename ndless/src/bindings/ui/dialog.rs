@@ -0,0 +1,88 @@
+//! The OS's dialog boxes
+//!
+//! Unlike the rest of [`ui`][crate::ui], these are drawn by the OS, on top of
+//! whatever is on the screen, and block until they're closed. The LCD must be
+//! in the mode the OS uses, so a [`Screen`][crate::video::Screen] has to be
+//! dropped first.
+//!
+//! ```
+//! use ndless::ui::dialog::{self, Button};
+//!
+//! if dialog::ask2("Quit", "Save before quitting?", ["Save", "Discard"]) == Some(Button::One) {
+//!     save();
+//! }
+//! let name = dialog::input("New game", "Your name:", "Player").unwrap_or_default();
+//! let level = dialog::input_number("New game", "Level", "Start at:", 1..=10);
+//! ```
+//!
+//! These are wrappers over the bindings in [`msg`][crate::msg]. Text is
+//! passed to the OS as C strings, so any nul bytes in it are left out rather
+//! than causing a panic as they do there.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::ops::RangeInclusive;
+
+use crate::msg;
+
+pub use crate::msg::Button;
+
+/// Leaves out any nul bytes, which the OS can't be given.
+fn without_nul(text: &str) -> Cow<str> {
+	if text.contains('\0') {
+		Cow::Owned(text.replace('\0', ""))
+	} else {
+		Cow::Borrowed(text)
+	}
+}
+
+/// Shows `text` with an "OK" button.
+pub fn message(title: &str, text: &str) {
+	msg::msg(&without_nul(title), &without_nul(text));
+}
+
+/// Shows `text` with two buttons, returning the one pressed, or `None` if the
+/// dialog was closed without pressing either.
+pub fn ask2(title: &str, text: &str, buttons: [&str; 2]) -> Option<Button> {
+	let [one, two] = buttons;
+	let (one, two) = (without_nul(one), without_nul(two));
+	msg::show_msgbox(&without_nul(title), &without_nul(text), &[&*one, &*two])
+}
+
+/// Shows `text` with three buttons, returning the one pressed, or `None` if
+/// the dialog was closed without pressing any.
+pub fn ask3(title: &str, text: &str, buttons: [&str; 3]) -> Option<Button> {
+	let [one, two, three] = buttons;
+	let (one, two, three) = (without_nul(one), without_nul(two), without_nul(three));
+	msg::show_msgbox(
+		&without_nul(title),
+		&without_nul(text),
+		&[&*one, &*two, &*three],
+	)
+}
+
+/// Asks for a line of text, starting out as `default`. Returns `None` if the
+/// dialog was cancelled.
+pub fn input(title: &str, text: &str, default: &str) -> Option<String> {
+	msg::msg_input(
+		&without_nul(title),
+		&without_nul(text),
+		&without_nul(default),
+	)
+}
+
+/// Asks for a whole number in `range`, starting at 0, or the end of `range`
+/// nearest to it. Returns `None` if the dialog was cancelled.
+pub fn input_number(
+	title: &str,
+	subtitle: &str,
+	text: &str,
+	range: RangeInclusive<i32>,
+) -> Option<i32> {
+	msg::msg_numeric(
+		&without_nul(title),
+		&without_nul(subtitle),
+		&without_nul(text),
+		(*range.start(), *range.end()),
+	)
+}
@@ -0,0 +1,88 @@
+//! Showing errors to the user
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::env::get_documents_dir;
+use crate::error::Error;
+use crate::fs::OpenOptions;
+use crate::io::{self, Write};
+use crate::msg::{msg, msg_3b, Button};
+use crate::path::PathBuf;
+
+/// The name of the file, in the documents directory, that errors are logged to.
+pub const LOG_FILE: &str = "errors.log.tns";
+
+/// Shows a message box with the error, letting the user expand it to see each
+/// error in its [`source`][Error::source] chain, or save it to [`LOG_FILE`].
+///
+/// # Example
+/// ```
+/// use ndless::fs::File;
+/// use ndless::ui::report_error;
+///
+/// if let Err(err) = File::open("level.dat") {
+///     report_error(&err);
+/// }
+/// ```
+pub fn report_error(err: &(dyn Error + 'static)) {
+	let chain = chain(err);
+	loop {
+		match msg_3b("Error", &chain[0], "OK", "Details", "Save log") {
+			Button::One => return,
+			Button::Two => show_details(&chain),
+			Button::Three => {
+				match log_chain(&chain) {
+					Ok(path) => msg("Error", &format!("Saved to {}", path.display())),
+					Err(err) => msg("Error", &format!("Could not save the log: {}", err)),
+				}
+				return;
+			}
+		}
+	}
+}
+
+/// Appends the error and its sources to [`LOG_FILE`], returning the path
+/// written to.
+pub fn log_error(err: &(dyn Error + 'static)) -> io::Result<PathBuf> {
+	log_chain(&chain(err))
+}
+
+fn chain(err: &(dyn Error + 'static)) -> Vec<String> {
+	let mut chain = Vec::new();
+	let mut next = Some(err);
+	while let Some(err) = next {
+		chain.push(err.to_string());
+		next = err.source();
+	}
+	chain
+}
+
+/// Pages through each error in the chain, one per message box.
+fn show_details(chain: &[String]) {
+	let mut page = 0;
+	loop {
+		let title = format!("Details ({}/{})", page + 1, chain.len());
+		let text = if page == 0 {
+			chain[0].clone()
+		} else {
+			format!("Caused by:\n{}", chain[page])
+		};
+		match msg_3b(&title, &text, "Back", "Previous", "Next") {
+			Button::One => return,
+			Button::Two => page = page.saturating_sub(1),
+			Button::Three => page = (page + 1).min(chain.len() - 1),
+		}
+	}
+}
+
+fn log_chain(chain: &[String]) -> io::Result<PathBuf> {
+	let path = get_documents_dir()?.join(LOG_FILE);
+	let mut file = OpenOptions::new().append(true).create(true).open(&path)?;
+	writeln!(file, "error: {}", chain[0])?;
+	for cause in &chain[1..] {
+		writeln!(file, "  caused by: {}", cause)?;
+	}
+	Ok(path)
+}
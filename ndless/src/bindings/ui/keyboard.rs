@@ -0,0 +1,328 @@
+//! On-screen keyboard
+//!
+//! Lets the user type free text by picking characters from a grid with the
+//! arrows, which is easier than finding letters on the keypad, and works the
+//! same on every model:
+//!
+//! ```
+//! use ndless::ui::VirtualKeyboard;
+//!
+//! if let Some(name) = VirtualKeyboard::new().max_len(16).run(&mut canvas, 320, 240) {
+//!     // ...
+//! }
+//! ```
+//!
+//! `canvas` is anything that implements [`KeyboardCanvas`]. `ndless-sdl`
+//! provides one for SDL surfaces. Keys on the physical keypad can still be
+//! used to type while the keyboard is shown.
+
+use alloc::string::String;
+
+use crate::input::events::{clear_events, poll_events, scan, KeyEvent};
+use crate::input::line::char_for;
+use crate::input::shortcuts::ModifierTracker;
+use crate::input::Key;
+use crate::thread::sleep;
+use crate::time::Duration;
+use crate::ui::controls::KeyRect;
+
+const COLUMNS: u32 = 10;
+
+const LOWER: [&str; 4] = ["1234567890", "qwertyuiop", "asdfghjkl'", "zxcvbnm,.?"];
+const UPPER: [&str; 4] = ["1234567890", "QWERTYUIOP", "ASDFGHJKL\"", "ZXCVBNM;:!"];
+const SYMBOLS: [&str; 4] = ["1234567890", "!@#$%^&*()", "-_=+[]{}\\|", ";:'\"<>/?~`"];
+
+/// Something that a [`VirtualKeyboard`] can be drawn on.
+pub trait KeyboardCanvas {
+	/// Draws the text typed so far in `rect`, with the cursor at the end.
+	fn draw_text(&mut self, rect: KeyRect, text: &str);
+	/// Draws a key. `selected` is true for the key that the arrows are on.
+	fn draw_cell(&mut self, rect: KeyRect, label: &str, selected: bool);
+	/// Called after everything has been drawn, such as to flip the screen.
+	fn present(&mut self) {}
+}
+
+/// What a key on the bottom row does
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+enum Special {
+	Shift,
+	Symbols,
+	Space,
+	Backspace,
+	Done,
+}
+
+/// The bottom row, with the width of each key in columns
+const BOTTOM: [(Special, u32); 5] = [
+	(Special::Shift, 2),
+	(Special::Symbols, 2),
+	(Special::Space, 3),
+	(Special::Backspace, 1),
+	(Special::Done, 2),
+];
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+enum Page {
+	Lower,
+	Upper,
+	Symbols,
+}
+
+/// An on-screen keyboard. See the [module documentation][self].
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct VirtualKeyboard {
+	text: String,
+	max_len: Option<usize>,
+	page: Page,
+	row: usize,
+	col: usize,
+	modifiers: ModifierTracker,
+}
+
+/// Returned by [`VirtualKeyboard::handle`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum KeyboardEvent {
+	/// Something changed, so the keyboard should be redrawn.
+	Changed,
+	Unchanged,
+	/// The user chose "OK" or pressed enter.
+	Done,
+	/// The user pressed escape.
+	Cancelled,
+}
+
+impl Default for VirtualKeyboard {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl VirtualKeyboard {
+	pub fn new() -> Self {
+		Self {
+			text: String::new(),
+			max_len: None,
+			page: Page::Lower,
+			row: 1,
+			col: 0,
+			modifiers: ModifierTracker::new(),
+		}
+	}
+
+	/// Starts with `text` already typed.
+	pub fn text(mut self, text: impl Into<String>) -> Self {
+		self.text = text.into();
+		self
+	}
+
+	/// Limits the number of characters that can be typed.
+	pub fn max_len(mut self, max_len: usize) -> Self {
+		self.max_len = Some(max_len);
+		self
+	}
+
+	/// The text typed so far.
+	pub fn value(&self) -> &str {
+		&self.text
+	}
+
+	fn rows(&self) -> [&'static str; 4] {
+		match self.page {
+			Page::Lower => LOWER,
+			Page::Upper => UPPER,
+			Page::Symbols => SYMBOLS,
+		}
+	}
+
+	fn row_len(&self, row: usize) -> usize {
+		if row < 4 {
+			self.rows()[row].chars().count()
+		} else {
+			BOTTOM.len()
+		}
+	}
+
+	/// Index of the key in the bottom row that covers column `col`
+	fn bottom_index(col: usize) -> usize {
+		let mut end = 0;
+		for (i, (_, span)) in BOTTOM.iter().enumerate() {
+			end += *span as usize;
+			if col < end {
+				return i;
+			}
+		}
+		BOTTOM.len() - 1
+	}
+
+	/// First column covered by the key at `index` in the bottom row
+	fn bottom_column(index: usize) -> usize {
+		BOTTOM[..index].iter().map(|(_, span)| *span as usize).sum()
+	}
+
+	fn type_char(&mut self, ch: char) {
+		if self
+			.max_len
+			.map_or(true, |max| self.text.chars().count() < max)
+		{
+			self.text.push(ch);
+		}
+		if self.page == Page::Upper {
+			self.page = Page::Lower;
+		}
+	}
+
+	fn activate(&mut self) -> KeyboardEvent {
+		if self.row < 4 {
+			if let Some(ch) = self.rows()[self.row].chars().nth(self.col) {
+				self.type_char(ch);
+			}
+			return KeyboardEvent::Changed;
+		}
+		match BOTTOM[self.col].0 {
+			Special::Shift => {
+				self.page = match self.page {
+					Page::Lower => Page::Upper,
+					_ => Page::Lower,
+				}
+			}
+			Special::Symbols => {
+				self.page = match self.page {
+					Page::Symbols => Page::Lower,
+					_ => Page::Symbols,
+				}
+			}
+			Special::Space => self.type_char(' '),
+			Special::Backspace => {
+				self.text.pop();
+			}
+			Special::Done => return KeyboardEvent::Done,
+		}
+		KeyboardEvent::Changed
+	}
+
+	fn move_vertically(&mut self, down: bool) {
+		let column = if self.row == 4 {
+			Self::bottom_column(self.col)
+		} else {
+			self.col
+		};
+		self.row = if down {
+			(self.row + 1) % 5
+		} else {
+			(self.row + 4) % 5
+		};
+		self.col = if self.row == 4 {
+			Self::bottom_index(column)
+		} else {
+			column.min(self.row_len(self.row) - 1)
+		};
+	}
+
+	/// Updates the keyboard with a key event.
+	pub fn handle(&mut self, event: KeyEvent) -> KeyboardEvent {
+		let chord = match self.modifiers.handle(event) {
+			Some(chord) => chord,
+			None => return KeyboardEvent::Unchanged,
+		};
+		match chord.key {
+			Key::Esc => return KeyboardEvent::Cancelled,
+			Key::Enter | Key::Return => return KeyboardEvent::Done,
+			Key::Click => return self.activate(),
+			Key::Left => {
+				let len = self.row_len(self.row);
+				self.col = (self.col + len - 1) % len;
+			}
+			Key::Right => self.col = (self.col + 1) % self.row_len(self.row),
+			Key::Up => self.move_vertically(false),
+			Key::Down => self.move_vertically(true),
+			Key::Del => {
+				self.text.pop();
+			}
+			_ => match char_for(chord) {
+				Some(ch) => self.type_char(ch),
+				None => return KeyboardEvent::Unchanged,
+			},
+		}
+		KeyboardEvent::Changed
+	}
+
+	/// Draws the text field and keys, filling a `width` by `height` area
+	/// starting at the top left of `canvas`.
+	pub fn draw(&self, canvas: &mut impl KeyboardCanvas, width: u32, height: u32) {
+		let row_height = height / 6;
+		let key_width = width / COLUMNS;
+		let offset = (width - key_width * COLUMNS) / 2;
+		canvas.draw_text(
+			KeyRect {
+				x: 0,
+				y: 0,
+				w: width,
+				h: row_height,
+			},
+			&self.text,
+		);
+		let mut label = [0; 4];
+		for (row, keys) in self.rows().iter().enumerate() {
+			for (col, ch) in keys.chars().enumerate() {
+				let rect = KeyRect {
+					x: (offset + col as u32 * key_width) as i32,
+					y: ((row as u32 + 1) * row_height) as i32,
+					w: key_width,
+					h: row_height,
+				};
+				let selected = self.row == row && self.col == col;
+				canvas.draw_cell(rect, ch.encode_utf8(&mut label), selected);
+			}
+		}
+		let mut x = offset;
+		for (i, &(special, span)) in BOTTOM.iter().enumerate() {
+			let label = match special {
+				Special::Shift if self.page == Page::Upper => "abc",
+				Special::Shift => "Shift",
+				Special::Symbols if self.page == Page::Symbols => "abc",
+				Special::Symbols => "#+=",
+				Special::Space => "Space",
+				Special::Backspace => "Del",
+				Special::Done => "OK",
+			};
+			let rect = KeyRect {
+				x: x as i32,
+				y: (5 * row_height) as i32,
+				w: key_width * span,
+				h: row_height,
+			};
+			canvas.draw_cell(rect, label, self.row == 4 && self.col == i);
+			x += key_width * span;
+		}
+		canvas.present();
+	}
+
+	/// Shows the keyboard until the user chooses "OK" or presses enter, and
+	/// returns the text. Returns `None` if escape is pressed.
+	pub fn run(
+		mut self,
+		canvas: &mut impl KeyboardCanvas,
+		width: u32,
+		height: u32,
+	) -> Option<String> {
+		// Ignore keys that are already held
+		scan();
+		clear_events();
+		self.draw(canvas, width, height);
+		loop {
+			let mut changed = false;
+			for event in poll_events() {
+				match self.handle(event) {
+					KeyboardEvent::Changed => changed = true,
+					KeyboardEvent::Unchanged => {}
+					KeyboardEvent::Done => return Some(self.text),
+					KeyboardEvent::Cancelled => return None,
+				}
+			}
+			if changed {
+				self.draw(canvas, width, height);
+			}
+			sleep(Duration::from_millis(10));
+		}
+	}
+}
@@ -0,0 +1,340 @@
+//! Menus
+//!
+//! [`Menu`] is a vertical list of items, chosen with the arrows:
+//!
+//! ```
+//! use ndless::ui::Menu;
+//!
+//! let menu = Menu::new("Game")
+//!     .item("Continue")
+//!     .item("New game")
+//!     .submenu("Options", Menu::new("Options").item("Sound").item("Controls"))
+//!     .disabled("Online");
+//! match menu.run(&mut screen, 320, 240).as_deref() {
+//!     Some([0]) => resume(),
+//!     Some([1]) => new_game(),
+//!     Some([2, 1]) => show_controls(),
+//!     _ => {}
+//! }
+//! ```
+//!
+//! Up and down move between items, skipping disabled ones and wrapping around
+//! at the ends. Enter, click or right chooses an item, or opens its submenu.
+//! Left or escape closes a submenu, and escape in the outermost menu cancels.
+//!
+//! The choice is the index of the item in each menu, from the outermost in.
+//!
+//! `screen` is anything that implements [`MenuCanvas`]. It's implemented for a
+//! [`Screen`], to draw straight on the framebuffer, and `ndless-sdl` provides
+//! one for SDL surfaces.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::gfx::{Canvas, Color, Font};
+use crate::input::events::{clear_events, poll_events, scan, KeyEvent, KeyState};
+use crate::input::Key;
+use crate::thread::sleep;
+use crate::time::Duration;
+use crate::ui::controls::KeyRect;
+use crate::video::Screen;
+
+/// How an item is drawn
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct ItemState {
+	/// The arrows are on this item
+	pub selected: bool,
+	/// The item can be chosen
+	pub enabled: bool,
+	/// Choosing the item opens a submenu
+	pub submenu: bool,
+}
+
+/// Something that a [`Menu`] can be drawn on.
+pub trait MenuCanvas {
+	/// Draws the menu's title in `rect`.
+	fn draw_title(&mut self, rect: KeyRect, title: &str);
+	/// Draws an item, or an empty row below the last one if `label` is empty.
+	fn draw_item(&mut self, rect: KeyRect, label: &str, state: ItemState);
+	/// Called after everything has been drawn, such as to flip the screen.
+	fn present(&mut self) {}
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+struct Item {
+	label: String,
+	enabled: bool,
+	submenu: Option<Menu>,
+}
+
+/// Returned by [`Menu::handle`].
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub enum MenuEvent {
+	/// Something changed, so the menu should be redrawn.
+	Changed,
+	Unchanged,
+	/// The user chose an item. See the [module documentation][self].
+	Chosen(Vec<usize>),
+	/// The user pressed escape in the outermost menu.
+	Cancelled,
+}
+
+/// A menu. See the [module documentation][self].
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct Menu {
+	title: String,
+	items: Vec<Item>,
+	/// `None` if every item is disabled
+	selected: Option<usize>,
+	/// The selected item's submenu is shown
+	open: bool,
+	/// The first item on screen
+	scroll: usize,
+}
+
+impl Menu {
+	pub fn new(title: impl Into<String>) -> Self {
+		Self {
+			title: title.into(),
+			items: Vec::new(),
+			selected: None,
+			open: false,
+			scroll: 0,
+		}
+	}
+
+	fn push(mut self, label: impl Into<String>, enabled: bool, submenu: Option<Menu>) -> Self {
+		self.items.push(Item {
+			label: label.into(),
+			enabled,
+			submenu,
+		});
+		if enabled && self.selected.is_none() {
+			self.selected = Some(self.items.len() - 1);
+		}
+		self
+	}
+
+	/// Adds an item.
+	pub fn item(self, label: impl Into<String>) -> Self {
+		self.push(label, true, None)
+	}
+
+	/// Adds an item that is shown, but can't be chosen.
+	pub fn disabled(self, label: impl Into<String>) -> Self {
+		self.push(label, false, None)
+	}
+
+	/// Adds an item that opens `menu`.
+	pub fn submenu(self, label: impl Into<String>, menu: Menu) -> Self {
+		self.push(label, true, Some(menu))
+	}
+
+	/// Sets whether the item at `index` can be chosen.
+	pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+		if let Some(item) = self.items.get_mut(index) {
+			item.enabled = enabled;
+		}
+		if self.selected == Some(index) && !enabled {
+			self.step(true);
+		} else if self.selected.is_none() && enabled {
+			self.selected = Some(index);
+		}
+	}
+
+	/// The index of the item the arrows are on, in this menu.
+	pub fn selected(&self) -> Option<usize> {
+		self.selected
+	}
+
+	/// Moves to the next enabled item, wrapping around
+	fn step(&mut self, down: bool) {
+		let len = self.items.len();
+		let start = self.selected.unwrap_or(0);
+		self.selected = (1..=len)
+			.map(|i| {
+				if down {
+					(start + i) % len
+				} else {
+					(start + len * 2 - i) % len
+				}
+			})
+			.find(|&i| self.items[i].enabled);
+	}
+
+	/// The submenu being shown, if any
+	fn open_submenu(&mut self) -> Option<&mut Menu> {
+		if !self.open {
+			return None;
+		}
+		let selected = self.selected?;
+		self.items[selected].submenu.as_mut()
+	}
+
+	fn choose(&mut self) -> MenuEvent {
+		let selected = match self.selected {
+			Some(selected) => selected,
+			None => return MenuEvent::Unchanged,
+		};
+		if self.items[selected].submenu.is_some() {
+			self.open = true;
+			MenuEvent::Changed
+		} else {
+			MenuEvent::Chosen(vec![selected])
+		}
+	}
+
+	/// Updates the menu with a key event.
+	pub fn handle(&mut self, event: KeyEvent) -> MenuEvent {
+		if event.state == KeyState::Released {
+			return MenuEvent::Unchanged;
+		}
+		let selected = self.selected;
+		if let Some(submenu) = self.open_submenu() {
+			return match submenu.handle(event) {
+				MenuEvent::Cancelled => {
+					self.open = false;
+					MenuEvent::Changed
+				}
+				// The submenu has no submenu of its own open
+				MenuEvent::Unchanged if event.key == Key::Left => {
+					self.open = false;
+					MenuEvent::Changed
+				}
+				MenuEvent::Chosen(mut path) => {
+					path.insert(0, selected.unwrap_or(0));
+					MenuEvent::Chosen(path)
+				}
+				event => event,
+			};
+		}
+		match event.key {
+			Key::Esc => MenuEvent::Cancelled,
+			Key::Enter | Key::Return | Key::Click | Key::Right => self.choose(),
+			Key::Up | Key::Down if self.selected.is_some() => {
+				self.step(event.key == Key::Down);
+				MenuEvent::Changed
+			}
+			_ => MenuEvent::Unchanged,
+		}
+	}
+
+	/// Draws the innermost open menu, filling a `width` by `height` area
+	/// starting at the top left of `canvas`. The list scrolls to keep the
+	/// selected item in view.
+	pub fn draw(&mut self, canvas: &mut impl MenuCanvas, width: u32, height: u32) {
+		if let Some(submenu) = self.open_submenu() {
+			return submenu.draw(canvas, width, height);
+		}
+		let row_height = 20;
+		let rows = (height / row_height).saturating_sub(1).max(1) as usize;
+		let selected = self.selected.unwrap_or(0);
+		if selected < self.scroll {
+			self.scroll = selected;
+		} else if selected >= self.scroll + rows {
+			self.scroll = selected + 1 - rows;
+		}
+		canvas.draw_title(
+			KeyRect {
+				x: 0,
+				y: 0,
+				w: width,
+				h: row_height,
+			},
+			&self.title,
+		);
+		for row in 0..rows {
+			let rect = KeyRect {
+				x: 0,
+				y: ((row as u32 + 1) * row_height) as i32,
+				w: width,
+				h: row_height,
+			};
+			let index = self.scroll + row;
+			match self.items.get(index) {
+				Some(item) => {
+					let state = ItemState {
+						selected: self.selected == Some(index),
+						enabled: item.enabled,
+						submenu: item.submenu.is_some(),
+					};
+					canvas.draw_item(rect, &item.label, state);
+				}
+				None => {
+					let state = ItemState {
+						selected: false,
+						enabled: false,
+						submenu: false,
+					};
+					canvas.draw_item(rect, "", state);
+				}
+			}
+		}
+		canvas.present();
+	}
+
+	/// Shows the menu until the user chooses an item, and returns it. See the
+	/// [module documentation][self]. Returns `None` if escape is pressed in
+	/// the outermost menu.
+	pub fn run(
+		mut self,
+		canvas: &mut impl MenuCanvas,
+		width: u32,
+		height: u32,
+	) -> Option<Vec<usize>> {
+		// Ignore keys that are already held
+		scan();
+		clear_events();
+		self.draw(canvas, width, height);
+		loop {
+			let mut changed = false;
+			for event in poll_events() {
+				match self.handle(event) {
+					MenuEvent::Changed => changed = true,
+					MenuEvent::Unchanged => {}
+					MenuEvent::Chosen(path) => return Some(path),
+					MenuEvent::Cancelled => return None,
+				}
+			}
+			if changed {
+				self.draw(canvas, width, height);
+			}
+			sleep(Duration::from_millis(10));
+		}
+	}
+}
+
+const BACKGROUND: Color = Color::WHITE;
+const HEADER: Color = Color::rgb(64, 64, 64);
+const TEXT: Color = Color::BLACK;
+const DISABLED: Color = Color::rgb(160, 160, 160);
+const SELECTED: Color = Color::rgb(255, 230, 120);
+
+impl MenuCanvas for Screen {
+	fn draw_title(&mut self, rect: KeyRect, title: &str) {
+		let font = Font::builtin();
+		let mut canvas = Canvas::new(self);
+		canvas.fill_rect(rect.x, rect.y, rect.w, rect.h, HEADER);
+		let y = rect.y + (rect.h as i32 - font.line_height() as i32) / 2;
+		canvas.draw_text(&font, rect.x + 4, y, Color::WHITE, title);
+	}
+
+	fn draw_item(&mut self, rect: KeyRect, label: &str, state: ItemState) {
+		let font = Font::builtin();
+		let mut canvas = Canvas::new(self);
+		let fill = if state.selected { SELECTED } else { BACKGROUND };
+		canvas.fill_rect(rect.x, rect.y, rect.w, rect.h, fill);
+		let color = if state.enabled { TEXT } else { DISABLED };
+		let y = rect.y + (rect.h as i32 - font.line_height() as i32) / 2;
+		canvas.draw_text(&font, rect.x + 4, y, color, label);
+		if state.submenu {
+			let x = rect.x + rect.w as i32 - 4 - font.measure(">") as i32;
+			canvas.draw_text(&font, x, y, color, ">");
+		}
+	}
+
+	fn present(&mut self) {
+		Screen::present(self);
+	}
+}
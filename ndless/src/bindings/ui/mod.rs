@@ -0,0 +1,23 @@
+//! # User interface helpers
+//! This module contains building blocks for interfaces that are drawn by the
+//! program itself, rather than by the OS. Drawing is left to a backend, such as
+//! `ndless-sdl`, so these work no matter how the screen is being updated.
+//! [`dialog`] is the exception, wrapping the OS's own dialog boxes.
+
+pub mod controls;
+pub mod dialog;
+mod error;
+pub mod keyboard;
+pub mod menu;
+pub mod picker;
+pub mod progress;
+pub mod toast;
+pub mod wizard;
+
+pub use error::{log_error, report_error, LOG_FILE};
+pub use keyboard::VirtualKeyboard;
+pub use menu::Menu;
+pub use picker::FilePicker;
+pub use progress::Progress;
+pub use toast::toast;
+pub use wizard::Wizard;
@@ -0,0 +1,319 @@
+//! File picker
+//!
+//! The OS has no open-file dialog that programs can use. [`FilePicker`] lists
+//! the documents folder, with its folders first, and lets the user move
+//! through it with the keypad:
+//!
+//! ```
+//! use ndless::ui::FilePicker;
+//!
+//! let picker = FilePicker::new().extension("png").extension("bmp");
+//! if let Some(path) = picker.run(&mut screen, 320, 240) {
+//!     open_image(&path);
+//! }
+//! ```
+//!
+//! Up and down choose an entry, and enter, click or right opens it. Left or
+//! del goes back to the folder above, and escape cancels.
+//!
+//! `screen` is anything that implements [`PickerCanvas`]. It's implemented
+//! for a [`Screen`], to draw straight on the framebuffer, and `ndless-sdl`
+//! provides one for SDL surfaces.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::env;
+use crate::fs;
+use crate::gfx::{Canvas, Color, Font};
+use crate::input::events::{clear_events, poll_events, scan, KeyEvent, KeyState};
+use crate::input::Key;
+use crate::path::{Path, PathBuf};
+use crate::thread::sleep;
+use crate::time::Duration;
+use crate::ui::controls::KeyRect;
+use crate::video::Screen;
+
+/// Something that a [`FilePicker`] can be drawn on.
+pub trait PickerCanvas {
+	/// Draws the path of the folder being shown in `rect`.
+	fn draw_header(&mut self, rect: KeyRect, path: &str);
+	/// Draws an entry of the folder. `selected` is true for the one that the
+	/// arrows are on.
+	fn draw_entry(&mut self, rect: KeyRect, name: &str, is_dir: bool, selected: bool);
+	/// Called after everything has been drawn, such as to flip the screen.
+	fn present(&mut self) {}
+}
+
+/// A file or folder in the list
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+struct Entry {
+	name: String,
+	is_dir: bool,
+}
+
+/// Returned by [`FilePicker::handle`].
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub enum PickerEvent {
+	/// Something changed, so the picker should be redrawn.
+	Changed,
+	Unchanged,
+	/// The user chose a file.
+	Picked(PathBuf),
+	/// The user pressed escape.
+	Cancelled,
+}
+
+/// A file picker. See the [module documentation][self].
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct FilePicker {
+	root: PathBuf,
+	dir: PathBuf,
+	extensions: Vec<String>,
+	entries: Vec<Entry>,
+	selected: usize,
+	/// The first entry on screen
+	scroll: usize,
+}
+
+impl Default for FilePicker {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl FilePicker {
+	/// Starts in the documents folder, and doesn't let the user go above it.
+	pub fn new() -> Self {
+		let root = env::get_documents_dir().unwrap_or_else(|_| PathBuf::from("/documents"));
+		let mut picker = Self {
+			dir: root.clone(),
+			root,
+			extensions: Vec::new(),
+			entries: Vec::new(),
+			selected: 0,
+			scroll: 0,
+		};
+		picker.load();
+		picker
+	}
+
+	/// Starts in `dir`. The user still can't go above the documents folder,
+	/// unless `dir` is outside it.
+	pub fn dir(mut self, dir: impl AsRef<Path>) -> Self {
+		self.dir = dir.as_ref().to_path_buf();
+		if !self.dir.starts_with(&self.root) {
+			self.root = self.dir.clone();
+		}
+		self.load();
+		self
+	}
+
+	/// Only shows files ending in `.{ext}.tns`, or `.{ext}`. Can be called
+	/// more than once to allow several extensions. Folders are always shown.
+	pub fn extension(mut self, ext: impl Into<String>) -> Self {
+		self.extensions.push(ext.into());
+		self.load();
+		self
+	}
+
+	/// The folder being shown.
+	pub fn current_dir(&self) -> &Path {
+		&self.dir
+	}
+
+	fn matches(&self, name: &str) -> bool {
+		let name = name.strip_suffix(".tns").unwrap_or(name);
+		self.extensions.is_empty()
+			|| self.extensions.iter().any(|ext| {
+				name.len() > ext.len() + 1
+					&& name.ends_with(ext.as_str())
+					&& name[..name.len() - ext.len()].ends_with('.')
+			})
+	}
+
+	/// Reads the current folder
+	fn load(&mut self) {
+		let mut entries = fs::read_dir(&self.dir)
+			.map(|dir| {
+				dir.filter_map(Result::ok)
+					.map(|entry| Entry {
+						name: entry.file_name().to_string_lossy().to_string(),
+						is_dir: entry.file_type().map_or(false, |ty| ty.is_dir()),
+					})
+					.filter(|entry| entry.name != "." && entry.name != "..")
+					.filter(|entry| entry.is_dir || self.matches(&entry.name))
+					.collect::<Vec<_>>()
+			})
+			.unwrap_or_default();
+		entries.sort_by(|a, b| {
+			b.is_dir
+				.cmp(&a.is_dir)
+				.then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+		});
+		self.entries = entries;
+		self.selected = 0;
+		self.scroll = 0;
+	}
+
+	fn open(&mut self) -> PickerEvent {
+		let entry = match self.entries.get(self.selected) {
+			Some(entry) => entry,
+			None => return PickerEvent::Unchanged,
+		};
+		let path = self.dir.join(&entry.name);
+		if !entry.is_dir {
+			return PickerEvent::Picked(path);
+		}
+		self.dir = path;
+		self.load();
+		PickerEvent::Changed
+	}
+
+	fn back(&mut self) -> PickerEvent {
+		if self.dir == self.root {
+			return PickerEvent::Unchanged;
+		}
+		let child = self
+			.dir
+			.file_name()
+			.map(|name| name.to_string_lossy().to_string());
+		match self.dir.parent() {
+			Some(parent) => self.dir = parent.to_path_buf(),
+			None => return PickerEvent::Unchanged,
+		}
+		self.load();
+		// Keep the folder that was just left selected
+		if let Some(index) = self
+			.entries
+			.iter()
+			.position(|entry| Some(&entry.name) == child.as_ref())
+		{
+			self.selected = index;
+		}
+		PickerEvent::Changed
+	}
+
+	/// Updates the picker with a key event.
+	pub fn handle(&mut self, event: KeyEvent) -> PickerEvent {
+		if event.state == KeyState::Released {
+			return PickerEvent::Unchanged;
+		}
+		let len = self.entries.len();
+		match event.key {
+			Key::Esc => PickerEvent::Cancelled,
+			Key::Enter | Key::Return | Key::Click | Key::Right => self.open(),
+			Key::Left | Key::Del => self.back(),
+			Key::Up if len > 0 => {
+				self.selected = (self.selected + len - 1) % len;
+				PickerEvent::Changed
+			}
+			Key::Down if len > 0 => {
+				self.selected = (self.selected + 1) % len;
+				PickerEvent::Changed
+			}
+			_ => PickerEvent::Unchanged,
+		}
+	}
+
+	/// Draws the folder's path and as many entries as fit, filling a `width`
+	/// by `height` area starting at the top left of `canvas`. The list
+	/// scrolls to keep the selected entry in view.
+	pub fn draw(&mut self, canvas: &mut impl PickerCanvas, width: u32, height: u32) {
+		let row_height = 16;
+		let rows = (height / row_height).saturating_sub(1).max(1) as usize;
+		if self.selected < self.scroll {
+			self.scroll = self.selected;
+		} else if self.selected >= self.scroll + rows {
+			self.scroll = self.selected + 1 - rows;
+		}
+		canvas.draw_header(
+			KeyRect {
+				x: 0,
+				y: 0,
+				w: width,
+				h: row_height,
+			},
+			&self.dir.to_string_lossy(),
+		);
+		for row in 0..rows {
+			let rect = KeyRect {
+				x: 0,
+				y: ((row as u32 + 1) * row_height) as i32,
+				w: width,
+				h: row_height,
+			};
+			let index = self.scroll + row;
+			match self.entries.get(index) {
+				Some(entry) => {
+					canvas.draw_entry(rect, &entry.name, entry.is_dir, index == self.selected)
+				}
+				None => canvas.draw_entry(rect, "", false, false),
+			}
+		}
+		canvas.present();
+	}
+
+	/// Shows the picker until the user chooses a file, and returns its path.
+	/// Returns `None` if escape is pressed.
+	pub fn run(
+		mut self,
+		canvas: &mut impl PickerCanvas,
+		width: u32,
+		height: u32,
+	) -> Option<PathBuf> {
+		// Ignore keys that are already held
+		scan();
+		clear_events();
+		self.draw(canvas, width, height);
+		loop {
+			let mut changed = false;
+			for event in poll_events() {
+				match self.handle(event) {
+					PickerEvent::Changed => changed = true,
+					PickerEvent::Unchanged => {}
+					PickerEvent::Picked(path) => return Some(path),
+					PickerEvent::Cancelled => return None,
+				}
+			}
+			if changed {
+				self.draw(canvas, width, height);
+			}
+			sleep(Duration::from_millis(10));
+		}
+	}
+}
+
+const BACKGROUND: Color = Color::WHITE;
+const HEADER: Color = Color::rgb(64, 64, 64);
+const TEXT: Color = Color::BLACK;
+const SELECTED: Color = Color::rgb(255, 230, 120);
+const FOLDER: Color = Color::rgb(0, 64, 160);
+
+impl PickerCanvas for Screen {
+	fn draw_header(&mut self, rect: KeyRect, path: &str) {
+		let font = Font::builtin();
+		let mut canvas = Canvas::new(self);
+		canvas.fill_rect(rect.x, rect.y, rect.w, rect.h, HEADER);
+		let y = rect.y + (rect.h as i32 - font.line_height() as i32) / 2;
+		canvas.draw_text(&font, rect.x + 4, y, Color::WHITE, path);
+	}
+
+	fn draw_entry(&mut self, rect: KeyRect, name: &str, is_dir: bool, selected: bool) {
+		let font = Font::builtin();
+		let mut canvas = Canvas::new(self);
+		let fill = if selected { SELECTED } else { BACKGROUND };
+		canvas.fill_rect(rect.x, rect.y, rect.w, rect.h, fill);
+		let y = rect.y + (rect.h as i32 - font.line_height() as i32) / 2;
+		if is_dir {
+			let (x, _) = canvas.draw_text(&font, rect.x + 4, y, FOLDER, name);
+			canvas.draw_text(&font, x, y, FOLDER, "/");
+		} else {
+			canvas.draw_text(&font, rect.x + 4, y, TEXT, name);
+		}
+	}
+
+	fn present(&mut self) {
+		Screen::present(self);
+	}
+}
@@ -0,0 +1,165 @@
+//! Progress bars
+//!
+//! [`Progress`] shows a title, a label and a bar for long operations, such as
+//! extracting an archive, and lets the user cancel with Esc:
+//!
+//! ```
+//! use ndless::ui::Progress;
+//!
+//! let mut progress = Progress::new("Extracting");
+//! for (i, entry) in archive.entries().enumerate() {
+//!     progress.set_label(entry.name());
+//!     progress.set_fraction(i as f32 / archive.len() as f32);
+//!     if progress.is_cancelled() {
+//!         break;
+//!     }
+//!     entry.extract()?;
+//! }
+//! ```
+//!
+//! [`Progress::new`] takes over the LCD with a [`Screen`], and gives it back
+//! when the `Progress` is dropped. A program that already has a `Screen` can
+//! draw the progress into it instead, with [`Progress::draw`].
+//!
+//! When there is no way to tell how far along an operation is, use
+//! [`Progress::busy`], which shows a block sliding back and forth instead.
+//!
+//! Redrawing takes a few milliseconds, so the screen is updated at most every
+//! [`REDRAW_INTERVAL`], however often the progress changes.
+
+use alloc::string::String;
+
+use crate::gfx::{Canvas, Color, Font};
+use crate::input::{is_pressed, Key};
+use crate::time::Duration;
+use crate::timer::{get_ticks, has_time_passed, Ticks};
+use crate::video::{Screen, HEIGHT, WIDTH};
+
+/// The shortest time between two updates of the screen.
+pub const REDRAW_INTERVAL: Duration = Duration::from_millis(50);
+
+const BACKGROUND: Color = Color::WHITE;
+const TEXT: Color = Color::BLACK;
+const BAR: Color = Color::rgb(0, 96, 192);
+const TRACK: Color = Color::rgb(208, 208, 208);
+
+const BAR_WIDTH: u32 = 240;
+const BAR_HEIGHT: u32 = 12;
+/// The width of the sliding block when busy, as a fraction of the bar
+const BUSY_BLOCK: f32 = 0.25;
+/// How long the block takes to cross the bar when busy
+const BUSY_PERIOD: Duration = Duration::from_millis(1500);
+
+/// A progress bar. See the [module documentation][self].
+pub struct Progress {
+	screen: Option<Screen>,
+	title: String,
+	label: String,
+	/// `None` when busy
+	fraction: Option<f32>,
+	cancelled: bool,
+	next_redraw: u32,
+}
+
+impl Progress {
+	/// Shows a progress bar at 0, taking over the LCD if no [`Screen`] has it.
+	pub fn new(title: impl Into<String>) -> Self {
+		let mut progress = Progress {
+			screen: Screen::new(),
+			title: title.into(),
+			label: String::new(),
+			fraction: Some(0.0),
+			cancelled: false,
+			next_redraw: get_ticks(),
+		};
+		progress.redraw();
+		progress
+	}
+
+	/// Shows a busy indicator, for operations with no known length.
+	pub fn busy(title: impl Into<String>) -> Self {
+		let mut progress = Self::new(title);
+		progress.fraction = None;
+		progress
+	}
+
+	/// Sets how far along the operation is, from 0 to 1, and switches from
+	/// the busy indicator to a bar.
+	pub fn set_fraction(&mut self, fraction: f32) {
+		self.fraction = Some(fraction.max(0.0).min(1.0));
+		self.update();
+	}
+
+	/// Sets the text shown under the title, such as the current file.
+	pub fn set_label(&mut self, label: impl Into<String>) {
+		self.label = label.into();
+		self.update();
+	}
+
+	/// Redraws the busy indicator. Call this regularly while busy, so that it
+	/// keeps moving.
+	pub fn tick(&mut self) {
+		self.update();
+	}
+
+	/// Returns true once Esc has been pressed. Stays true afterwards.
+	pub fn is_cancelled(&mut self) -> bool {
+		self.cancelled |= is_pressed(Key::Esc);
+		self.cancelled
+	}
+
+	fn update(&mut self) {
+		self.cancelled |= is_pressed(Key::Esc);
+		if has_time_passed(self.next_redraw) {
+			self.redraw();
+		}
+	}
+
+	fn redraw(&mut self) {
+		self.next_redraw = get_ticks().wrapping_add(REDRAW_INTERVAL.as_ticks());
+		if let Some(mut screen) = self.screen.take() {
+			self.draw(&mut Canvas::new(&mut screen));
+			screen.present();
+			self.screen = Some(screen);
+		}
+	}
+
+	/// Draws the progress in the middle of `canvas`. This is only needed if
+	/// the program kept the LCD, so [`new`][Progress::new] couldn't take it.
+	pub fn draw(&self, canvas: &mut Canvas<'_>) {
+		let font = Font::builtin();
+		let line = font.line_height() as i32;
+		let x = (WIDTH as u32 - BAR_WIDTH) as i32 / 2;
+		let y = HEIGHT as i32 / 2 - line * 2;
+		canvas.fill_rect(
+			x - 8,
+			y - 8,
+			BAR_WIDTH + 16,
+			line as u32 * 3 + BAR_HEIGHT + 24,
+			BACKGROUND,
+		);
+		canvas.draw_text(&font, x, y, TEXT, &self.title);
+		canvas.draw_text(&font, x, y + line, TEXT, &self.label);
+		let bar_y = y + line * 2 + 4;
+		canvas.fill_rect(x, bar_y, BAR_WIDTH, BAR_HEIGHT, TRACK);
+		let (start, width) = match self.fraction {
+			Some(fraction) => (0.0, fraction),
+			None => {
+				let period = BUSY_PERIOD.as_ticks();
+				let phase = (get_ticks() % (2 * period)) as f32 / period as f32;
+				// Back and forth, rather than jumping back to the start
+				let phase = if phase > 1.0 { 2.0 - phase } else { phase };
+				(phase * (1.0 - BUSY_BLOCK), BUSY_BLOCK)
+			}
+		};
+		canvas.fill_rect(
+			x + (start * BAR_WIDTH as f32) as i32,
+			bar_y,
+			(width * BAR_WIDTH as f32) as u32,
+			BAR_HEIGHT,
+			BAR,
+		);
+		let hint_y = bar_y + BAR_HEIGHT as i32 + 4;
+		canvas.draw_text(&font, x, hint_y, TEXT, "Esc: cancel");
+	}
+}
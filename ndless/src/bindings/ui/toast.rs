@@ -0,0 +1,134 @@
+//! Non-blocking notifications
+//!
+//! Toasts are short messages, such as "Saved", that are shown on top of
+//! whatever the program is drawing and disappear on their own. Queue one from
+//! anywhere with [`toast`], and call [`draw`] once per frame, after the rest of
+//! the frame has been drawn:
+//!
+//! ```
+//! use ndless::ui::toast;
+//!
+//! toast::toast("Saved");
+//! loop {
+//!     // Draw the frame...
+//!     toast::draw(&mut overlay);
+//!     // ...and show it
+//! }
+//! ```
+//!
+//! `overlay` is anything that implements [`Overlay`]. A [`Canvas`] is one, for
+//! programs that draw on the framebuffer of a
+//! [`Screen`][crate::video::Screen]. It uses the built-in font, in white on
+//! dark gray. `ndless-sdl` provides one for SDL surfaces.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+
+use crate::gfx::{Canvas, Color, Font};
+use crate::time::Duration;
+use crate::timer::{get_ticks, Ticks};
+use crate::video::{HEIGHT, WIDTH};
+
+/// How long a toast is shown for with [`toast`], including the fade-out.
+pub const DEFAULT_DURATION: Duration = Duration::from_millis(2000);
+
+/// How long a toast takes to fade out at the end of its duration.
+pub const FADE_DURATION: Duration = Duration::from_millis(500);
+
+/// The maximum number of toasts on the screen at once. Extra toasts wait in
+/// the queue until there is space.
+pub const MAX_VISIBLE: usize = 3;
+
+/// Something that toasts can be drawn on.
+pub trait Overlay {
+	/// Draws a single toast. `index` is its position on the screen, starting at
+	/// 0 for the oldest visible toast. `opacity` goes from 255 (fully visible)
+	/// down to 0 as the toast fades out.
+	fn draw_toast(&mut self, text: &str, index: usize, opacity: u8);
+}
+
+/// Space between a toast's text and its edges, in pixels
+const PADDING: i32 = 4;
+/// Space between toasts, and below the bottom one, in pixels
+const MARGIN: i32 = 6;
+
+impl Overlay for Canvas<'_> {
+	fn draw_toast(&mut self, text: &str, index: usize, opacity: u8) {
+		let font = Font::builtin();
+		let background = Color::rgb(40, 40, 40);
+		let lines = text.lines().count().max(1) as i32;
+		let w = font.measure(text) as i32 + PADDING * 2;
+		let h = font.line_height() as i32 * lines + PADDING * 2;
+		let x = (WIDTH as i32 - w) / 2;
+		let y = HEIGHT as i32 - (h + MARGIN) * (index as i32 + 1);
+		for py in y..y + h {
+			for px in x..x + w {
+				self.blend_pixel(px, py, background, opacity);
+			}
+		}
+		// Text can't be drawn translucent, so it fades into the background
+		let color = background.blend(Color::WHITE, opacity);
+		self.draw_text(&font, x + PADDING, y + PADDING, color, text);
+	}
+}
+
+struct Toast {
+	text: String,
+	ticks: u32,
+	/// `None` until the toast is first drawn
+	shown_at: Option<u32>,
+}
+
+static mut QUEUE: Option<VecDeque<Toast>> = None;
+
+fn queue() -> &'static mut VecDeque<Toast> {
+	unsafe { QUEUE.get_or_insert_with(VecDeque::new) }
+}
+
+/// Shows `text` for [`DEFAULT_DURATION`].
+pub fn toast(text: impl Into<String>) {
+	toast_for(text, DEFAULT_DURATION)
+}
+
+/// Shows `text` for `duration`, including the fade-out.
+pub fn toast_for(text: impl Into<String>, duration: Duration) {
+	queue().push_back(Toast {
+		text: text.into(),
+		ticks: duration.as_ticks().max(1),
+		shown_at: None,
+	});
+}
+
+/// Removes all pending and visible toasts.
+pub fn clear() {
+	queue().clear();
+}
+
+/// Returns true if there are any toasts waiting to be shown or still on the
+/// screen.
+pub fn is_active() -> bool {
+	!queue().is_empty()
+}
+
+/// Draws the visible toasts onto `overlay`, and removes any that have expired.
+/// This should be called once per frame.
+pub fn draw(overlay: &mut impl Overlay) {
+	let now = get_ticks();
+	let queue = queue();
+	queue.retain(|toast| {
+		toast
+			.shown_at
+			.map_or(true, |shown_at| now.wrapping_sub(shown_at) < toast.ticks)
+	});
+	let fade = FADE_DURATION.as_ticks();
+	for (index, toast) in queue.iter_mut().take(MAX_VISIBLE).enumerate() {
+		let elapsed = now.wrapping_sub(*toast.shown_at.get_or_insert(now));
+		let remaining = toast.ticks - elapsed;
+		let opacity = if remaining >= fade {
+			255
+		} else {
+			(remaining * 255 / fade) as u8
+		};
+		overlay.draw_toast(&toast.text, index, opacity);
+	}
+}
@@ -0,0 +1,184 @@
+//! # Multi-page dialogs
+//! A [`Wizard`] walks the user through several pages, such as the first-run
+//! setup of a program, with the ability to go back to previous pages. Pages are
+//! shown with the OS's dialogs.
+//!
+//! ```
+//! use ndless::ui::wizard::{Step, Wizard};
+//!
+//! #[derive(Default)]
+//! struct Settings {
+//!     name: String,
+//!     difficulty: i32,
+//! }
+//!
+//! let settings = Wizard::new("Setup")
+//!     .input("Your name", |s: &mut Settings| &mut s.name)
+//!     .validate(|s| if s.name.is_empty() { Err("Enter a name".into()) } else { Ok(()) })
+//!     .numeric("Difficulty", (1, 3), |s| &mut s.difficulty)
+//!     .summary(|s| format!("Name: {}\nDifficulty: {}", s.name, s.difficulty))
+//!     .run(Settings::default());
+//! ```
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::msg::{msg, msg_2b, msg_3b, msg_input, msg_numeric, Button};
+
+/// Where to go after a page is shown.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Step {
+	Next,
+	Back,
+	/// Stops the wizard, returning `None` from [`Wizard::run`].
+	Cancel,
+}
+
+type Show<S> = Box<dyn FnMut(&str, &mut S) -> Step>;
+type Validate<S> = Box<dyn Fn(&S) -> Result<(), String>>;
+
+struct Page<S> {
+	show: Show<S>,
+	validate: Option<Validate<S>>,
+}
+
+/// A series of pages that edit a state of type `S`.
+pub struct Wizard<S> {
+	title: String,
+	pages: Vec<Page<S>>,
+	summary: Option<Box<dyn Fn(&S) -> String>>,
+}
+
+impl<S: 'static> Wizard<S> {
+	pub fn new(title: impl Into<String>) -> Self {
+		Self {
+			title: title.into(),
+			pages: Vec::new(),
+			summary: None,
+		}
+	}
+
+	/// Adds a custom page. It is given the title of the wizard and the state,
+	/// and returns where to go next.
+	pub fn page(mut self, show: impl FnMut(&str, &mut S) -> Step + 'static) -> Self {
+		self.pages.push(Page {
+			show: Box::new(show),
+			validate: None,
+		});
+		self
+	}
+
+	/// Adds a page with a text input. Cancelling the dialog goes back.
+	pub fn input(
+		self,
+		prompt: impl Into<String>,
+		field: impl Fn(&mut S) -> &mut String + 'static,
+	) -> Self {
+		let prompt = prompt.into();
+		self.page(move |title, state| {
+			let value = field(state);
+			match msg_input(title, &prompt, value) {
+				Some(input) => {
+					*value = input;
+					Step::Next
+				}
+				None => Step::Back,
+			}
+		})
+	}
+
+	/// Adds a page with a numeric input within `range`. Cancelling the dialog
+	/// goes back.
+	pub fn numeric(
+		self,
+		prompt: impl Into<String>,
+		range: (i32, i32),
+		field: impl Fn(&mut S) -> &mut i32 + 'static,
+	) -> Self {
+		let prompt = prompt.into();
+		self.page(
+			move |title, state| match msg_numeric(title, "", &prompt, range) {
+				Some(num) => {
+					*field(state) = num;
+					Step::Next
+				}
+				None => Step::Back,
+			},
+		)
+	}
+
+	/// Adds a page that asks the user to pick one of two options, with a third
+	/// button to go back.
+	pub fn choice(
+		self,
+		prompt: impl Into<String>,
+		options: (&'static str, &'static str),
+		field: impl Fn(&mut S) -> &mut bool + 'static,
+	) -> Self {
+		let prompt = prompt.into();
+		self.page(move |title, state| {
+			match msg_3b(title, &prompt, options.0, options.1, "Back") {
+				Button::One => *field(state) = true,
+				Button::Two => *field(state) = false,
+				Button::Three => return Step::Back,
+			}
+			Step::Next
+		})
+	}
+
+	/// Checks the state after the most recently added page. If it returns an
+	/// error, the message is shown and the page is shown again.
+	///
+	/// # Panics
+	/// Panics if no pages have been added yet.
+	pub fn validate(mut self, validate: impl Fn(&S) -> Result<(), String> + 'static) -> Self {
+		self.pages
+			.last_mut()
+			.expect("validate must be called after adding a page")
+			.validate = Some(Box::new(validate));
+		self
+	}
+
+	/// Shows a summary of the state after the last page, where the user can go
+	/// back or finish.
+	pub fn summary(mut self, summary: impl Fn(&S) -> String + 'static) -> Self {
+		self.summary = Some(Box::new(summary));
+		self
+	}
+
+	/// Shows the pages, starting with the first. Returns the final state, or
+	/// `None` if the wizard was cancelled, or the user went back from the first
+	/// page.
+	pub fn run(mut self, mut state: S) -> Option<S> {
+		let mut i = 0;
+		loop {
+			if i == self.pages.len() {
+				let summary = match &self.summary {
+					Some(summary) => summary(&state),
+					None => return Some(state),
+				};
+				match msg_2b(&self.title, &summary, "Finish", "Back") {
+					Button::One => return Some(state),
+					_ if i == 0 => return None,
+					_ => i -= 1,
+				}
+				continue;
+			}
+			let page = &mut self.pages[i];
+			match (page.show)(&self.title, &mut state) {
+				Step::Next => {
+					if let Some(Err(err)) = page.validate.as_ref().map(|validate| validate(&state))
+					{
+						msg(&self.title, &err);
+						continue;
+					}
+					i += 1;
+				}
+				Step::Back if i == 0 => return None,
+				Step::Back => i -= 1,
+				Step::Cancel => return None,
+			}
+		}
+	}
+}
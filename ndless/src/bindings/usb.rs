@@ -0,0 +1,566 @@
+//! # USB serial
+//! Makes the calculator show up on a computer as a serial port, such as
+//! `/dev/ttyACM0` on Linux or a `COM` port on Windows, without any link
+//! software. A [`CdcAcm`] implements [`Read`][io::Read] and
+//! [`Write`][io::Write]:
+//!
+//! ```
+//! use ndless::io::Write;
+//! use ndless::usb::CdcAcm;
+//!
+//! let mut port = CdcAcm::new().expect("USB is already in use");
+//! writeln!(port, "score: {}", score)?;
+//! ```
+//!
+//! The computer only sees the port while the program keeps calling
+//! [`CdcAcm::poll`], or reads or writes, which poll while they wait. Reads and
+//! writes wait until a computer opens the port, unless it's made non-blocking
+//! with [`CdcAcm::set_nonblocking`].
+//!
+//! This takes the USB controller away from the OS, so the TI link software
+//! can't connect while it's in use. When the `CdcAcm` is dropped, the OS's
+//! settings are put back, and the computer sees the calculator reconnect.
+//! Programs that exit without running destructors, with
+//! [`process::exit`][crate::process::exit] or
+//! [`abort`][crate::process::abort], call [`restore`] instead.
+//!
+//! The port uses the [pid.codes](https://pid.codes) vendor ID, with its
+//! product ID for testing.
+
+use alloc::boxed::Box;
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::hw::clear_cache;
+use crate::io;
+use crate::timer::{get_ticks, has_time_passed, TICKS_PER_MILLISECOND};
+
+/// The USB controller, a ChipIdea OTG controller
+const BASE: usize = 0xB000_0000;
+const USBCMD: usize = 0x140;
+const USBSTS: usize = 0x144;
+const USBINTR: usize = 0x148;
+const DEVICEADDR: usize = 0x154;
+const ENDPOINTLISTADDR: usize = 0x158;
+const PORTSC1: usize = 0x184;
+const USBMODE: usize = 0x1A8;
+const ENDPTSETUPSTAT: usize = 0x1AC;
+const ENDPTPRIME: usize = 0x1B0;
+const ENDPTFLUSH: usize = 0x1B4;
+const ENDPTCOMPLETE: usize = 0x1BC;
+const ENDPTCTRL0: usize = 0x1C0;
+
+const CMD_RUN: u32 = 1;
+const CMD_RESET: u32 = 1 << 1;
+/// Set while reading a setup packet, and cleared by the controller if another
+/// one arrives in the meantime
+const CMD_SETUP_TRIPWIRE: u32 = 1 << 13;
+const STS_RESET: u32 = 1 << 6;
+/// Keeps the port at full speed, so every packet fits in 64 bytes
+const PORTSC_FORCE_FULL_SPEED: u32 = 1 << 24;
+const MODE_DEVICE: u32 = 0b10;
+const MODE_SETUP_LOCKOUT_OFF: u32 = 1 << 3;
+/// Applies the address after the status stage of SET_ADDRESS
+const ADDRESS_ADVANCE: u32 = 1 << 24;
+
+const CTRL_RX_STALL: u32 = 1;
+const CTRL_RX_BULK: u32 = 0b10 << 2;
+const CTRL_RX_RESET: u32 = 1 << 6;
+const CTRL_RX_ENABLE: u32 = 1 << 7;
+const CTRL_TX_STALL: u32 = 1 << 16;
+const CTRL_TX_INTERRUPT: u32 = 0b11 << 18;
+const CTRL_TX_BULK: u32 = 0b10 << 18;
+const CTRL_TX_RESET: u32 = 1 << 22;
+const CTRL_TX_ENABLE: u32 = 1 << 23;
+
+const HEAD_INTERRUPT_ON_SETUP: u32 = 1 << 15;
+const HEAD_NO_ZERO_LENGTH: u32 = 1 << 29;
+const TOKEN_ACTIVE: u32 = 1 << 7;
+const TOKEN_INTERRUPT_ON_COMPLETE: u32 = 1 << 15;
+const TERMINATE: u32 = 1;
+
+/// The size of every packet
+const PACKET: usize = 64;
+/// Endpoint 1 sends notifications, which are never needed, and endpoint 2
+/// carries the data
+const DATA_ENDPOINT: usize = 2;
+
+const VENDOR_ID: u16 = 0x1209;
+const PRODUCT_ID: u16 = 0x0001;
+
+#[rustfmt::skip]
+const DEVICE_DESCRIPTOR: [u8; 18] = [
+	18, 1, 0x00, 0x02, // USB 2.0
+	0x02, 0, 0, PACKET as u8, // Communications device
+	VENDOR_ID as u8, (VENDOR_ID >> 8) as u8,
+	PRODUCT_ID as u8, (PRODUCT_ID >> 8) as u8,
+	0x00, 0x01, 1, 2, 0, 1,
+];
+
+#[rustfmt::skip]
+const CONFIGURATION_DESCRIPTOR: [u8; 67] = [
+	9, 2, 67, 0, 2, 1, 0, 0x80, 50,
+	// Communications interface: abstract control model, AT commands
+	9, 4, 0, 0, 1, 0x02, 0x02, 0x01, 0,
+	5, 0x24, 0x00, 0x10, 0x01, // Header
+	5, 0x24, 0x01, 0x00, 1, // Call management
+	4, 0x24, 0x02, 0x02, // Abstract control management: line coding
+	5, 0x24, 0x06, 0, 1, // Union of interfaces 0 and 1
+	7, 5, 0x81, 0x03, 16, 0, 255, // Notifications
+	// Data interface
+	9, 4, 1, 0, 2, 0x0A, 0, 0, 0,
+	7, 5, 0x02, 0x02, PACKET as u8, 0, 0,
+	7, 5, 0x82, 0x02, PACKET as u8, 0, 0,
+];
+
+const STRINGS: [&str; 2] = ["Ndless", "TI-Nspire"];
+
+static mut IN_USE: bool = false;
+/// USBCMD, USBINTR, USBMODE, ENDPOINTLISTADDR, DEVICEADDR and PORTSC1, as the
+/// OS set them
+static mut SAVED: Option<[u32; 6]> = None;
+
+fn read(offset: usize) -> u32 {
+	unsafe { read_volatile((BASE + offset) as *const u32) }
+}
+
+fn write(offset: usize, value: u32) {
+	unsafe { write_volatile((BASE + offset) as *mut u32, value) }
+}
+
+/// Waits up to 10ms for `done`.
+fn wait(mut done: impl FnMut() -> bool) {
+	let timeout = get_ticks().wrapping_add(10 * TICKS_PER_MILLISECOND);
+	while !done() && !has_time_passed(timeout) {}
+}
+
+fn reset_controller() {
+	write(USBCMD, read(USBCMD) & !CMD_RUN);
+	write(USBCMD, read(USBCMD) | CMD_RESET);
+	wait(|| read(USBCMD) & CMD_RESET == 0);
+}
+
+/// Describes the transfers of one endpoint in one direction, read by the
+/// controller
+#[repr(C, align(64))]
+#[derive(Clone, Copy, Default)]
+struct QueueHead {
+	capabilities: u32,
+	current: u32,
+	next: u32,
+	token: u32,
+	buffers: [u32; 5],
+	_reserved: u32,
+	setup: [u8; 8],
+	_padding: [u32; 4],
+}
+
+/// One transfer, read and updated by the controller
+#[repr(C, align(32))]
+#[derive(Clone, Copy, Default)]
+struct TransferDescriptor {
+	next: u32,
+	token: u32,
+	buffers: [u32; 5],
+	_padding: u32,
+}
+
+/// Everything the controller reads or writes. The queue heads must be 2KB
+/// aligned.
+#[repr(C, align(2048))]
+struct Memory {
+	/// The OUT and IN queue heads of each endpoint, in that order
+	heads: [QueueHead; 6],
+	transfers: [TransferDescriptor; 6],
+	control_in: [u8; 128],
+	control_out: [u8; PACKET],
+	rx: [u8; PACKET],
+	tx: [u8; PACKET],
+}
+
+/// A virtual serial port over USB. See the [module documentation][self].
+pub struct CdcAcm {
+	memory: Box<Memory>,
+	configured: bool,
+	/// Set by the computer when it opens the port
+	dtr: bool,
+	line_coding: [u8; 7],
+	/// The control OUT transfer carries a new line coding
+	line_coding_pending: bool,
+	nonblocking: bool,
+	rx_primed: bool,
+	rx_len: usize,
+	rx_pos: usize,
+	tx_busy: bool,
+}
+
+impl core::fmt::Debug for CdcAcm {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("CdcAcm")
+			.field("configured", &self.configured)
+			.field("dtr", &self.dtr)
+			.finish()
+	}
+}
+
+impl CdcAcm {
+	/// Takes over the USB controller. Returns `None` if another `CdcAcm`
+	/// exists.
+	pub fn new() -> Option<Self> {
+		unsafe {
+			if IN_USE {
+				return None;
+			}
+			IN_USE = true;
+			SAVED = Some([
+				read(USBCMD),
+				read(USBINTR),
+				read(USBMODE),
+				read(ENDPOINTLISTADDR),
+				read(DEVICEADDR),
+				read(PORTSC1),
+			]);
+		}
+		let mut port = Self {
+			memory: Box::new(Memory {
+				heads: [QueueHead::default(); 6],
+				transfers: [TransferDescriptor::default(); 6],
+				control_in: [0; 128],
+				control_out: [0; PACKET],
+				rx: [0; PACKET],
+				tx: [0; PACKET],
+			}),
+			configured: false,
+			dtr: false,
+			// 115200 baud, 1 stop bit, no parity, 8 data bits
+			line_coding: [0x00, 0xC2, 0x01, 0x00, 0, 0, 8],
+			line_coding_pending: false,
+			nonblocking: false,
+			rx_primed: false,
+			rx_len: 0,
+			rx_pos: 0,
+			tx_busy: false,
+		};
+		reset_controller();
+		write(USBMODE, MODE_DEVICE | MODE_SETUP_LOCKOUT_OFF);
+		write(PORTSC1, read(PORTSC1) | PORTSC_FORCE_FULL_SPEED);
+		port.reset_endpoints();
+		write(ENDPOINTLISTADDR, port.memory.heads.as_ptr() as u32);
+		write(USBINTR, 0);
+		write(USBCMD, read(USBCMD) | CMD_RUN);
+		Some(port)
+	}
+
+	fn reset_endpoints(&mut self) {
+		for head in self.memory.heads.iter_mut() {
+			*head = QueueHead::default();
+			head.next = TERMINATE;
+		}
+		let control = (PACKET as u32) << 16 | HEAD_INTERRUPT_ON_SETUP;
+		self.memory.heads[0].capabilities = control;
+		self.memory.heads[1].capabilities = control;
+		clear_cache();
+		self.configured = false;
+		self.dtr = false;
+		self.rx_primed = false;
+		self.rx_len = 0;
+		self.rx_pos = 0;
+		self.tx_busy = false;
+	}
+
+	/// True once a computer has set up the port.
+	pub fn is_configured(&self) -> bool {
+		self.configured
+	}
+
+	/// True while a program on the computer has the port open.
+	pub fn is_open(&self) -> bool {
+		self.configured && self.dtr
+	}
+
+	/// The baud rate the computer set. It has no effect on the speed of USB.
+	pub fn baud_rate(&self) -> u32 {
+		let coding = self.line_coding;
+		u32::from_le_bytes([coding[0], coding[1], coding[2], coding[3]])
+	}
+
+	/// Makes reads and writes fail with
+	/// [`WouldBlock`][io::ErrorKind::WouldBlock] instead of waiting.
+	pub fn set_nonblocking(&mut self, nonblocking: bool) {
+		self.nonblocking = nonblocking;
+	}
+
+	/// Starts a transfer on queue head `index`, of `len` bytes at `buffer`.
+	fn prime(&mut self, index: usize, buffer: *const u8, len: usize) {
+		let buffer = buffer as u32;
+		let transfer = &mut self.memory.transfers[index];
+		transfer.next = TERMINATE;
+		transfer.token = (len as u32) << 16 | TOKEN_INTERRUPT_ON_COMPLETE | TOKEN_ACTIVE;
+		transfer.buffers[0] = buffer;
+		for (page, pointer) in transfer.buffers.iter_mut().enumerate().skip(1) {
+			*pointer = (buffer & !0xFFF) + page as u32 * 0x1000;
+		}
+		let transfer = transfer as *const TransferDescriptor as u32;
+		let head = &mut self.memory.heads[index];
+		head.next = transfer;
+		head.token = 0;
+		clear_cache();
+		let endpoint = index / 2;
+		let bit = if index % 2 == 1 {
+			16 + endpoint
+		} else {
+			endpoint
+		};
+		write(ENDPTPRIME, 1 << bit);
+	}
+
+	/// Sends the data stage of a control request, followed by its status
+	/// stage, limited to the length the computer asked for.
+	fn control_send(&mut self, data: &[u8], max: usize) {
+		let len = data.len().min(max).min(self.memory.control_in.len());
+		self.memory.control_in[..len].copy_from_slice(&data[..len]);
+		let buffer = self.memory.control_in.as_ptr();
+		self.prime(1, buffer, len);
+		self.prime(0, buffer, 0);
+	}
+
+	/// Acknowledges a control request without a data stage.
+	fn control_status(&mut self) {
+		let buffer = self.memory.control_in.as_ptr();
+		self.prime(1, buffer, 0);
+	}
+
+	fn stall(&mut self) {
+		write(ENDPTCTRL0, read(ENDPTCTRL0) | CTRL_TX_STALL | CTRL_RX_STALL);
+	}
+
+	fn send_string(&mut self, index: usize, max: usize) {
+		let mut descriptor = [0; 66];
+		let len = if index == 0 {
+			// US English
+			descriptor[2..4].copy_from_slice(&[0x09, 0x04]);
+			4
+		} else if let Some(string) = STRINGS.get(index - 1) {
+			let mut len = 2;
+			for unit in string.encode_utf16().take(32) {
+				descriptor[len..len + 2].copy_from_slice(&unit.to_le_bytes());
+				len += 2;
+			}
+			len
+		} else {
+			return self.stall();
+		};
+		descriptor[0] = len as u8;
+		descriptor[1] = 3;
+		self.control_send(&descriptor[..len], max);
+	}
+
+	fn configure(&mut self, configuration: u16) {
+		self.configured = configuration != 0;
+		if !self.configured {
+			return;
+		}
+		let bulk = (PACKET as u32) << 16 | HEAD_NO_ZERO_LENGTH;
+		self.memory.heads[3].capabilities = 16 << 16 | HEAD_NO_ZERO_LENGTH;
+		self.memory.heads[2 * DATA_ENDPOINT].capabilities = bulk;
+		self.memory.heads[2 * DATA_ENDPOINT + 1].capabilities = bulk;
+		clear_cache();
+		write(
+			ENDPTCTRL0 + 4,
+			CTRL_TX_ENABLE | CTRL_TX_RESET | CTRL_TX_INTERRUPT,
+		);
+		write(
+			ENDPTCTRL0 + 4 * DATA_ENDPOINT,
+			CTRL_TX_ENABLE
+				| CTRL_TX_RESET
+				| CTRL_TX_BULK
+				| CTRL_RX_ENABLE
+				| CTRL_RX_RESET
+				| CTRL_RX_BULK,
+		);
+		self.rx_primed = false;
+		self.tx_busy = false;
+	}
+
+	fn handle_setup(&mut self, setup: [u8; 8]) {
+		let value = u16::from_le_bytes([setup[2], setup[3]]);
+		let max = u16::from_le_bytes([setup[6], setup[7]]) as usize;
+		match (setup[0], setup[1]) {
+			// GET_STATUS
+			(0x80, 0) => self.control_send(&[0, 0], max),
+			// SET_ADDRESS
+			(0x00, 5) => {
+				write(DEVICEADDR, u32::from(value) << 25 | ADDRESS_ADVANCE);
+				self.control_status();
+			}
+			// GET_DESCRIPTOR
+			(0x80, 6) => match value >> 8 {
+				1 => self.control_send(&DEVICE_DESCRIPTOR, max),
+				2 => self.control_send(&CONFIGURATION_DESCRIPTOR, max),
+				3 => self.send_string((value & 0xFF) as usize, max),
+				_ => self.stall(),
+			},
+			// GET_CONFIGURATION
+			(0x80, 8) => self.control_send(&[self.configured as u8], max),
+			// SET_CONFIGURATION
+			(0x00, 9) => {
+				self.configure(value);
+				self.control_status();
+			}
+			// SET_LINE_CODING
+			(0x21, 0x20) => {
+				self.line_coding_pending = true;
+				let buffer = self.memory.control_out.as_ptr();
+				self.prime(0, buffer, self.line_coding.len());
+				self.control_status();
+			}
+			// GET_LINE_CODING
+			(0xA1, 0x21) => {
+				let coding = self.line_coding;
+				self.control_send(&coding, max);
+			}
+			// SET_CONTROL_LINE_STATE
+			(0x21, 0x22) => {
+				self.dtr = value & 1 != 0;
+				self.control_status();
+			}
+			// Other class requests, like SEND_BREAK, are accepted and ignored
+			(0x21, _) => self.control_status(),
+			_ => self.stall(),
+		}
+	}
+
+	/// Answers the computer's requests, and collects finished transfers. Reads
+	/// and writes do this while they wait, but the computer only sees the port
+	/// if this is called regularly while the program isn't reading or
+	/// writing.
+	pub fn poll(&mut self) {
+		let status = read(USBSTS);
+		write(USBSTS, status);
+		if status & STS_RESET != 0 {
+			write(ENDPTSETUPSTAT, read(ENDPTSETUPSTAT));
+			write(ENDPTCOMPLETE, read(ENDPTCOMPLETE));
+			write(ENDPTFLUSH, 0xFFFF_FFFF);
+			write(DEVICEADDR, 0);
+			self.reset_endpoints();
+		}
+		// Whatever the controller wrote must be read from memory, not the cache
+		clear_cache();
+		if read(ENDPTSETUPSTAT) & 1 != 0 {
+			let setup = loop {
+				write(USBCMD, read(USBCMD) | CMD_SETUP_TRIPWIRE);
+				clear_cache();
+				let setup = unsafe { read_volatile(&self.memory.heads[0].setup) };
+				if read(USBCMD) & CMD_SETUP_TRIPWIRE != 0 {
+					break setup;
+				}
+			};
+			write(USBCMD, read(USBCMD) & !CMD_SETUP_TRIPWIRE);
+			write(ENDPTSETUPSTAT, 1);
+			wait(|| read(ENDPTSETUPSTAT) & 1 == 0);
+			self.line_coding_pending = false;
+			self.handle_setup(setup);
+		}
+		let complete = read(ENDPTCOMPLETE);
+		write(ENDPTCOMPLETE, complete);
+		if complete & 1 != 0 && self.line_coding_pending {
+			self.line_coding_pending = false;
+			let len = self.line_coding.len();
+			self.line_coding
+				.copy_from_slice(&self.memory.control_out[..len]);
+		}
+		if complete & 1 << DATA_ENDPOINT != 0 && self.rx_primed {
+			let token = unsafe { read_volatile(&self.memory.transfers[2 * DATA_ENDPOINT].token) };
+			self.rx_primed = false;
+			self.rx_len = PACKET - (token >> 16 & 0x7FFF) as usize;
+			self.rx_pos = 0;
+		}
+		if complete & 1 << (16 + DATA_ENDPOINT) != 0 {
+			self.tx_busy = false;
+		}
+		if self.configured && !self.rx_primed && self.rx_pos == self.rx_len {
+			self.rx_primed = true;
+			let buffer = self.memory.rx.as_ptr();
+			self.prime(2 * DATA_ENDPOINT, buffer, PACKET);
+		}
+	}
+}
+
+impl io::Read for CdcAcm {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		loop {
+			self.poll();
+			if self.rx_pos < self.rx_len {
+				break;
+			}
+			if self.nonblocking {
+				return Err(io::ErrorKind::WouldBlock.into());
+			}
+		}
+		let len = buf.len().min(self.rx_len - self.rx_pos);
+		buf[..len].copy_from_slice(&self.memory.rx[self.rx_pos..self.rx_pos + len]);
+		self.rx_pos += len;
+		self.poll();
+		Ok(len)
+	}
+}
+
+impl io::Write for CdcAcm {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		loop {
+			self.poll();
+			if self.configured && !self.tx_busy {
+				break;
+			}
+			if self.nonblocking {
+				return Err(io::ErrorKind::WouldBlock.into());
+			}
+		}
+		let len = buf.len().min(PACKET);
+		self.memory.tx[..len].copy_from_slice(&buf[..len]);
+		self.tx_busy = true;
+		let buffer = self.memory.tx.as_ptr();
+		self.prime(2 * DATA_ENDPOINT + 1, buffer, len);
+		Ok(len)
+	}
+
+	/// Waits for the last write to reach the computer.
+	fn flush(&mut self) -> io::Result<()> {
+		while self.tx_busy && self.configured {
+			if self.nonblocking {
+				return Err(io::ErrorKind::WouldBlock.into());
+			}
+			self.poll();
+		}
+		Ok(())
+	}
+}
+
+impl Drop for CdcAcm {
+	fn drop(&mut self) {
+		restore();
+	}
+}
+
+/// Gives the USB controller back to the OS, if a [`CdcAcm`] took it over.
+/// This is done when the `CdcAcm` is dropped, and when the program exits, so
+/// it rarely needs to be called directly.
+pub fn restore() {
+	unsafe {
+		if let Some([command, interrupts, mode, list, address, port]) = SAVED.take() {
+			reset_controller();
+			write(USBMODE, mode);
+			write(PORTSC1, port);
+			write(ENDPOINTLISTADDR, list);
+			write(DEVICEADDR, address);
+			write(USBINTR, interrupts);
+			write(USBCMD, command);
+		}
+		IN_USE = false;
+	}
+}
@@ -0,0 +1,70 @@
+//! # Versioned save data
+//! Save files outlive the programs that write them. This module helps load data
+//! written by older versions of a program, by upgrading it step by step to the
+//! current format.
+//!
+//! Each version of the format is its own type, deriving [`Versioned`] along
+//! with `serde`'s `Deserialize`. Every version except the first names the one
+//! before it, and converts from it with [`From`]:
+//!
+//! ```
+//! use ndless::versioned::Versioned;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, Versioned)]
+//! #[versioned(version = 1)]
+//! struct SaveV1 {
+//!     score: u16,
+//! }
+//!
+//! #[derive(Deserialize, Versioned)]
+//! #[versioned(version = 2, previous = "SaveV1")]
+//! struct Save {
+//!     score: u32,
+//!     level: u8,
+//! }
+//!
+//! impl From<SaveV1> for Save {
+//!     fn from(old: SaveV1) -> Self {
+//!         Save { score: old.score.into(), level: 1 }
+//!     }
+//! }
+//! ```
+//!
+//! The version is stored next to the data, such as at the start of the file.
+//! When loading, pass it to [`deserialize_version`], which deserializes the
+//! matching type and upgrades it through every newer version:
+//!
+//! ```
+//! use ndless::io::ReadBytesExt;
+//!
+//! let mut file = File::open("/documents/game/save.dat.tns")?;
+//! let version = file.read_u32_le()?;
+//! let save = Save::deserialize_version(version, &mut Deserializer::new(file))?;
+//! ```
+//!
+//! The chain is checked at compile time: a missing [`From`] impl, or a
+//! `previous` type that isn't older, is an error.
+//!
+//! This module requires the `serde` feature. The derive refers to `serde`
+//! through `ndless::serde`, so it works even if the program renames its own
+//! `serde` dependency.
+//!
+//! [`deserialize_version`]: Versioned::deserialize_version
+
+pub use ndless_macros::Versioned;
+
+/// A type stored with a version number. Derive it with
+/// `#[derive(Versioned)]`; see the [module-level documentation][self].
+pub trait Versioned: Sized {
+	/// The version of the format this type represents.
+	const VERSION: u32;
+
+	/// Deserializes data that was saved as `version`, upgrading it to this
+	/// type. Returns an error if `version` is newer than this type, or older
+	/// than the first version.
+	fn deserialize_version<'de, D: serde::Deserializer<'de>>(
+		version: u32,
+		deserializer: D,
+	) -> Result<Self, D::Error>;
+}
@@ -0,0 +1,148 @@
+//! # Virtual filesystems
+//! This module allows loading assets the same way whether they are embedded in
+//! the .tns file or installed next to it as separate files.
+//!
+//! Write loading code against the [`Vfs`] trait, then pass in either an
+//! [`Archive`] built with [`include_archive!`][crate::include_archive], or a
+//! [`Fs`] pointing to a directory:
+//!
+//! ```
+//! use ndless::vfs::{Fs, Vfs};
+//!
+//! fn load_level(assets: &impl Vfs, n: u32) -> ndless::io::Result<Vec<u8>> {
+//!     assets.read(format!("levels/{}.dat", n))
+//! }
+//!
+//! let embedded = ndless::include_archive! {
+//!     "levels/1.dat" => "../assets/levels/1.dat",
+//!     "levels/2.dat" => "../assets/levels/2.dat",
+//! };
+//! let installed = Fs::new("/documents/mygame");
+//! assert_eq!(load_level(&embedded, 1)?, load_level(&installed, 1)?);
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::fs::File;
+use crate::io::{self, Cursor, ErrorKind, Read, Seek};
+use crate::path::{Path, PathBuf};
+
+/// A source of files.
+pub trait Vfs {
+	/// The type of an opened file
+	type File: Read + Seek;
+
+	/// Opens the file at `path` for reading.
+	fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<Self::File>;
+
+	/// Returns true if there is a file at `path`.
+	fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+		self.open(path).is_ok()
+	}
+
+	/// Reads the entire contents of a file into a bytes vector.
+	fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+		let mut bytes = Vec::new();
+		self.open(path)?.read_to_end(&mut bytes)?;
+		Ok(bytes)
+	}
+
+	/// Reads the entire contents of a file into a string.
+	fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+		let mut string = String::new();
+		self.open(path)?.read_to_string(&mut string)?;
+		Ok(string)
+	}
+}
+
+/// Files on the calculator's filesystem, relative to a root directory.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Fs {
+	root: PathBuf,
+}
+
+impl Fs {
+	pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+		Self { root: root.into() }
+	}
+
+	pub fn root(&self) -> &Path {
+		&self.root
+	}
+}
+
+impl Vfs for Fs {
+	type File = File;
+
+	fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+		File::open(self.root.join(path))
+	}
+
+	fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+		self.root.join(path).is_file()
+	}
+}
+
+/// Files embedded in the program. Create one with
+/// [`include_archive!`][crate::include_archive].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Archive {
+	files: &'static [(&'static str, &'static [u8])],
+}
+
+impl Archive {
+	/// Creates an archive from a list of file names and their contents.
+	pub const fn new(files: &'static [(&'static str, &'static [u8])]) -> Self {
+		Self { files }
+	}
+
+	/// Returns the contents of the file at `path` without copying it.
+	pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&'static [u8]> {
+		let path = path.as_ref();
+		self.files
+			.iter()
+			.find(|(name, _)| Path::new(name) == path)
+			.map(|(_, contents)| *contents)
+	}
+
+	/// Iterates over the names of every file in the archive.
+	pub fn names(&self) -> impl Iterator<Item = &'static str> {
+		self.files.iter().map(|(name, _)| *name)
+	}
+}
+
+impl Vfs for Archive {
+	type File = Cursor<&'static [u8]>;
+
+	fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<Self::File> {
+		self.get(path)
+			.map(Cursor::new)
+			.ok_or_else(|| ErrorKind::NotFound.into())
+	}
+
+	fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+		self.get(path).is_some()
+	}
+
+	fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+		self.open(path).map(|file| file.into_inner().to_vec())
+	}
+}
+
+/// Creates an [`Archive`][crate::vfs::Archive] of files embedded with
+/// [`include_bytes!`]. Each file is given as `"name" => "path"`, where the name
+/// is what it is opened with, and the path is relative to the current source
+/// file.
+///
+/// ```
+/// static ASSETS: ndless::vfs::Archive = ndless::include_archive! {
+///     "player.bmp" => "../assets/player.bmp",
+/// };
+/// ```
+#[macro_export]
+macro_rules! include_archive {
+	($($name:expr => $path:expr),* $(,)?) => {
+		$crate::vfs::Archive::new(&[$(($name, include_bytes!($path) as &[u8])),*])
+	};
+}
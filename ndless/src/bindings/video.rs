@@ -0,0 +1,317 @@
+//! # Drawing to the screen
+//! [`Screen`] takes over the LCD, and gives the program a framebuffer to draw
+//! in, in the best format for the calculator:
+//!
+//! ```
+//! use ndless::video::{Framebuffer, Screen};
+//!
+//! let mut screen = Screen::new().expect("the screen is already in use");
+//! match screen.framebuffer() {
+//!     // 16 bit color on CX models
+//!     Framebuffer::Rgb565(pixels) => pixels.iter_mut().for_each(|pixel| *pixel = 0xF800),
+//!     // 4 bit grayscale on classic models, two pixels per byte
+//!     Framebuffer::Gray4(pixels) => pixels.iter_mut().for_each(|pair| *pair = 0x88),
+//! }
+//! screen.present();
+//! ```
+//!
+//! The framebuffer is always 320 pixels wide and 240 high, one row after the
+//! other. Nothing appears until [`Screen::present`] is called, which copies it
+//! to the LCD with `lcd_blit`, which also turns it to fit CX revisions with a
+//! portrait LCD.
+//!
+//! Copying while the LCD is being refreshed can show the top of one frame and
+//! the bottom of another. [`Screen::swap`] waits for the start of the next
+//! refresh first, which avoids this. With [`Buffering::Triple`], on models
+//! where the LCD can show the framebuffer directly, `swap` doesn't copy or
+//! wait at all: the LCD is pointed at the finished frame, and the program
+//! draws the next one in another buffer.
+//!
+//! When the `Screen` is dropped, the LCD goes back to the mode the OS uses.
+//! [`exit`][crate::process::exit] and [`abort`][crate::process::abort] don't
+//! run destructors, so they call [`restore`] instead, as does the panic
+//! handler before drawing the panic screen.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ptr::{read_volatile, write_volatile};
+use core::slice;
+
+use crate::hw::has_colors;
+use crate::hw::screen::{lcd_type, Screen as LcdType};
+use crate::timer::{get_ticks, has_time_passed, TICKS_PER_MILLISECOND};
+
+/// The width of the framebuffer, in pixels.
+pub const WIDTH: usize = 320;
+/// The height of the framebuffer, in pixels.
+pub const HEIGHT: usize = 240;
+
+/// The LCD controller's registers
+const LCD_UPBASE: *mut u32 = 0xC000_0010 as *mut u32;
+pub(crate) const LCD_CONTROL: *mut u32 = 0xC000_0018 as *mut u32;
+const LCD_RIS: *const u32 = 0xC000_0020 as *const u32;
+const LCD_ICR: *mut u32 = 0xC000_0028 as *mut u32;
+/// Set once the LCD has started using a new base address
+const INT_BASE_UPDATE: u32 = 1 << 2;
+/// Set at the start of each refresh
+const INT_VCOMP: u32 = 1 << 3;
+
+static mut IN_USE: bool = false;
+/// The address the LCD showed before [`Buffering::Triple`] changed it
+static mut OS_BASE: Option<u32> = None;
+
+/// The format of a [`Screen`]'s framebuffer.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Mode {
+	/// 16 bits per pixel: 5 bits of red, 6 of green, and 5 of blue.
+	Rgb565,
+	/// 4 bits per pixel, from 0 for black to 15 for white. The left pixel of
+	/// each pair is in the high nibble.
+	Gray4,
+}
+
+impl Mode {
+	fn scr_type(self) -> ndless_sys::scr_type_t {
+		match self {
+			Mode::Rgb565 => ndless_sys::scr_type_t_SCR_320x240_565,
+			Mode::Gray4 => ndless_sys::scr_type_t_SCR_320x240_4,
+		}
+	}
+}
+
+/// How a [`Screen`] gets frames to the LCD. Set with
+/// [`Screen::set_buffering`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Buffering {
+	/// The program draws in one buffer, which [`Screen::swap`] copies to the
+	/// LCD. Works on every model.
+	Double,
+	/// The program draws in one of three buffers, and the LCD shows another.
+	/// [`Screen::swap`] never waits, but each buffer holds the frame from two
+	/// swaps earlier when drawing starts. Only CX models with a landscape LCD
+	/// support this.
+	Triple,
+}
+
+/// Returned by [`Screen::framebuffer`].
+#[derive(Eq, PartialEq, Debug, Hash)]
+pub enum Framebuffer<'a> {
+	Rgb565(&'a mut [u16]),
+	Gray4(&'a mut [u8]),
+}
+
+/// Waits for the LCD to start a refresh. Gives up after 50ms, in case the
+/// interrupt never comes, such as in some emulators.
+pub fn wait_vsync() {
+	unsafe {
+		// Signal at the start of vertical sync
+		write_volatile(LCD_CONTROL, read_volatile(LCD_CONTROL) & !(0b11 << 12));
+		write_volatile(LCD_ICR, INT_VCOMP);
+	}
+	wait_for(INT_VCOMP);
+}
+
+fn wait_for(interrupt: u32) {
+	let timeout = get_ticks().wrapping_add(50 * TICKS_PER_MILLISECOND);
+	while unsafe { read_volatile(LCD_RIS) } & interrupt == 0 && !has_time_passed(timeout) {}
+}
+
+/// A buffer the LCD can read from directly, which must be 8 byte aligned
+type Page = Vec<u64>;
+
+/// Control of the LCD. See the [module documentation][self].
+pub struct Screen {
+	mode: Mode,
+	buffering: Buffering,
+	pages: Vec<Page>,
+	/// The page being drawn in
+	back: usize,
+	/// A new base address was written, and the LCD may not be using it yet
+	flip_pending: bool,
+}
+
+impl Screen {
+	/// Takes over the LCD, in [`Mode::Rgb565`] on color models, and
+	/// [`Mode::Gray4`] on classic ones. The framebuffer starts black.
+	///
+	/// Returns `None` if another `Screen` exists, or if the LCD can't be set
+	/// up.
+	pub fn new() -> Option<Self> {
+		if unsafe { IN_USE } {
+			return None;
+		}
+		let mode = if has_colors() {
+			Mode::Rgb565
+		} else {
+			Mode::Gray4
+		};
+		if !unsafe { ndless_sys::lcd_init(mode.scr_type()) } {
+			return None;
+		}
+		unsafe { IN_USE = true }
+		let mut screen = Self {
+			mode,
+			buffering: Buffering::Double,
+			pages: Vec::new(),
+			back: 0,
+			flip_pending: false,
+		};
+		screen.pages.push(screen.page());
+		Some(screen)
+	}
+
+	fn page(&self) -> Page {
+		let bytes = match self.mode {
+			Mode::Rgb565 => WIDTH * HEIGHT * 2,
+			Mode::Gray4 => WIDTH * HEIGHT / 2,
+		};
+		vec![0; bytes / 8]
+	}
+
+	/// Returns true if the LCD can show a framebuffer directly, without it
+	/// being converted or turned.
+	fn can_flip(&self) -> bool {
+		self.mode == Mode::Rgb565 && lcd_type() == LcdType::Screen320x240x565
+	}
+
+	pub fn buffering(&self) -> Buffering {
+		self.buffering
+	}
+
+	/// Changes how frames get to the LCD, returning the buffering that is
+	/// used, which is [`Buffering::Double`] if the calculator doesn't support
+	/// the one asked for. The framebuffer keeps its contents.
+	pub fn set_buffering(&mut self, buffering: Buffering) -> Buffering {
+		let buffering = if buffering == Buffering::Triple && self.can_flip() {
+			Buffering::Triple
+		} else {
+			Buffering::Double
+		};
+		if buffering == self.buffering {
+			return buffering;
+		}
+		if buffering == Buffering::Double {
+			// Before the pages the LCD might be showing are freed
+			restore_base();
+			self.flip_pending = false;
+		}
+		let back = self.pages.swap_remove(self.back);
+		self.pages.clear();
+		self.pages.push(back);
+		self.back = 0;
+		match buffering {
+			Buffering::Double => {}
+			Buffering::Triple => {
+				self.pages.push(self.page());
+				self.pages.push(self.page());
+				unsafe {
+					if OS_BASE.is_none() {
+						OS_BASE = Some(read_volatile(LCD_UPBASE));
+					}
+				}
+			}
+		}
+		self.buffering = buffering;
+		buffering
+	}
+
+	pub fn mode(&self) -> Mode {
+		self.mode
+	}
+
+	/// The pixels to draw in. See [`Mode`] for their format.
+	pub fn framebuffer(&mut self) -> Framebuffer<'_> {
+		let page = &mut self.pages[self.back];
+		let len = page.len() * 8;
+		let ptr = page.as_mut_ptr();
+		// Pages are only ever viewed as the format of the screen's mode
+		unsafe {
+			match self.mode {
+				Mode::Rgb565 => {
+					Framebuffer::Rgb565(slice::from_raw_parts_mut(ptr as *mut u16, len / 2))
+				}
+				Mode::Gray4 => Framebuffer::Gray4(slice::from_raw_parts_mut(ptr as *mut u8, len)),
+			}
+		}
+	}
+
+	/// The framebuffer, if it is in [`Mode::Rgb565`].
+	pub fn rgb565(&mut self) -> Option<&mut [u16]> {
+		match self.framebuffer() {
+			Framebuffer::Rgb565(pixels) => Some(pixels),
+			Framebuffer::Gray4(_) => None,
+		}
+	}
+
+	/// The framebuffer, if it is in [`Mode::Gray4`].
+	pub fn gray4(&mut self) -> Option<&mut [u8]> {
+		match self.framebuffer() {
+			Framebuffer::Rgb565(_) => None,
+			Framebuffer::Gray4(pixels) => Some(pixels),
+		}
+	}
+
+	/// Copies the framebuffer to the LCD right away. With
+	/// [`Buffering::Triple`], this is the same as [`swap`][Screen::swap].
+	pub fn present(&mut self) {
+		match self.buffering {
+			Buffering::Double => {
+				let buffer = self.pages[self.back].as_mut_ptr() as *mut cty::c_void;
+				unsafe { ndless_sys::lcd_blit(buffer, self.mode.scr_type()) }
+			}
+			Buffering::Triple => self.swap(),
+		}
+	}
+
+	/// Shows the frame that was drawn, without tearing. See the
+	/// [module documentation][self].
+	pub fn swap(&mut self) {
+		match self.buffering {
+			Buffering::Double => {
+				wait_vsync();
+				self.present();
+			}
+			Buffering::Triple => {
+				// Wait until the LCD shows the page queued by the last swap, so
+				// the one about to be drawn in is no longer on screen
+				if self.flip_pending {
+					wait_for(INT_BASE_UPDATE);
+				}
+				self.flip_pending = true;
+				unsafe {
+					write_volatile(LCD_ICR, INT_BASE_UPDATE);
+					write_volatile(LCD_UPBASE, self.pages[self.back].as_ptr() as u32);
+				}
+				self.back = (self.back + 1) % self.pages.len();
+			}
+		}
+	}
+}
+
+impl Drop for Screen {
+	fn drop(&mut self) {
+		restore();
+	}
+}
+
+/// Points the LCD back at the OS's framebuffer, if triple buffering moved it.
+pub(crate) fn restore_base() {
+	unsafe {
+		if let Some(base) = OS_BASE.take() {
+			write_volatile(LCD_UPBASE, base);
+		}
+	}
+}
+
+/// Puts the LCD back in the mode the OS uses, if a [`Screen`] took it over.
+/// This is done when the `Screen` is dropped, and by the panic handler, so it
+/// rarely needs to be called directly.
+pub fn restore() {
+	unsafe {
+		if IN_USE {
+			restore_base();
+			ndless_sys::lcd_init(ndless_sys::scr_type_t_SCR_TYPE_INVALID);
+			IN_USE = false;
+		}
+	}
+}
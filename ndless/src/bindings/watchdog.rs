@@ -0,0 +1,186 @@
+//! # Watchdog
+//! Detects when the program has stopped responding, such as when stuck in an
+//! infinite loop, instead of forcing the user to pull the batteries.
+//!
+//! Start a [`Watchdog`], and [`feed`][Watchdog::feed] it regularly, such as
+//! once per frame. If it isn't fed within its window, the program is
+//! interrupted, a message with the location it was stuck at is shown, and the
+//! calculator is reset.
+//!
+//! ```
+//! use ndless::time::Duration;
+//! use ndless::watchdog::Watchdog;
+//!
+//! let watchdog = Watchdog::start(Duration::from_secs(5));
+//! loop {
+//!     watchdog.feed();
+//!     // ...
+//! }
+//! ```
+//!
+//! The watchdog uses the hardware watchdog timer's interrupt, so it works even
+//! if the program never returns to the OS. The window is measured with the
+//! real time clock, which counts whole seconds, so it's rounded up to a whole
+//! number of seconds, and a hang is noticed up to a second after it ends.
+//!
+//! The watchdog is stopped, and its interrupt hook removed, when the
+//! `Watchdog` is dropped, and by [`restore`] when the program exits,
+//! including through [`process::exit`][crate::process::exit],
+//! [`abort`][crate::process::abort] or a panic.
+
+use alloc::format;
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::hw::irq::{self, Frame};
+use crate::msg::msg;
+use crate::time::Duration;
+
+const LOAD: *mut u32 = 0x9006_0000 as *mut u32;
+const CONTROL: *mut u32 = 0x9006_0008 as *mut u32;
+const INT_CLEAR: *mut u32 = 0x9006_000C as *mut u32;
+const MASKED_STATUS: *const u32 = 0x9006_0014 as *const u32;
+const LOCK: *mut u32 = 0x9006_0C00 as *mut u32;
+const UNLOCK_KEY: u32 = 0x1ACC_E551;
+
+const CONTROL_INTERRUPT: u32 = 1;
+const CONTROL_RESET: u32 = 1 << 1;
+const IRQ_LINE: u32 = 3;
+
+/// The real time clock, in seconds. Unlike the tick timer, reading it doesn't
+/// change anything, so it's safe to read from the interrupt handler.
+const RTC_VALUE: *const u32 = 0x9009_0000 as *const u32;
+
+/// Watchdog clock cycles between interrupts. The window itself is measured
+/// with the real time clock, so this only needs to be short enough to notice
+/// a missed deadline promptly.
+const INTERRUPT_INTERVAL: u32 = 0x0040_0000;
+
+static mut ACTIVE: bool = false;
+/// The window, in seconds
+static mut WINDOW: u32 = 0;
+/// When the watchdog was last fed, in seconds of the real time clock
+static mut FED_AT: u32 = 0;
+static mut HUNG_AT: Option<Frame> = None;
+
+fn configure(load: u32, control: u32) {
+	unsafe {
+		write_volatile(LOCK, UNLOCK_KEY);
+		write_volatile(LOAD, load);
+		write_volatile(CONTROL, control);
+		write_volatile(LOCK, 0);
+	}
+}
+
+fn on_interrupt(frame: &mut Frame) -> bool {
+	unsafe {
+		if read_volatile(MASKED_STATUS) & 1 == 0 {
+			return false;
+		}
+		write_volatile(LOCK, UNLOCK_KEY);
+		write_volatile(INT_CLEAR, 1);
+		write_volatile(LOCK, 0);
+		let starved = read_volatile(RTC_VALUE).wrapping_sub(read_volatile(&FED_AT));
+		if HUNG_AT.is_none() && starved > read_volatile(&WINDOW) {
+			HUNG_AT = Some(*frame);
+			// Instead of resuming the stuck code, continue in `hung`
+			frame.pc = hung as usize as u32;
+		}
+	}
+	true
+}
+
+fn stop() {
+	configure(0, 0);
+	irq::disable(IRQ_LINE);
+	irq::unregister(on_interrupt);
+}
+
+/// Stops the watchdog, if one is running, and removes its interrupt hook.
+/// This is done when the program exits, so it rarely needs to be called
+/// directly.
+pub fn restore() {
+	if unsafe { ACTIVE } {
+		stop();
+		unsafe { ACTIVE = false }
+	}
+}
+
+extern "C" fn hung() -> ! {
+	stop();
+	let frame = unsafe { HUNG_AT }.unwrap_or(Frame { r: [0; 13], pc: 0 });
+	let r = &frame.r;
+	msg(
+		"Program not responding",
+		&format!(
+			"The program stopped responding, and the calculator will be reset.\n\n\
+			 PC: {:08X}\n\
+			 R0: {:08X}  R1: {:08X}  R2: {:08X}  R3: {:08X}\n\
+			 R4: {:08X}  R5: {:08X}  R6: {:08X}  R7: {:08X}\n\
+			 R8: {:08X}  R9: {:08X}  R10: {:08X}  R11: {:08X}\n\
+			 R12: {:08X}",
+			frame.pc,
+			r[0],
+			r[1],
+			r[2],
+			r[3],
+			r[4],
+			r[5],
+			r[6],
+			r[7],
+			r[8],
+			r[9],
+			r[10],
+			r[11],
+			r[12],
+		),
+	);
+	// Let the watchdog reset the calculator almost immediately
+	configure(1, CONTROL_INTERRUPT | CONTROL_RESET);
+	loop {}
+}
+
+/// Resets the calculator if it isn't fed in time. Only one can run at a time.
+#[must_use = "the watchdog is stopped when dropped"]
+#[derive(Debug)]
+pub struct Watchdog {
+	_private: (),
+}
+
+impl Watchdog {
+	/// Starts the watchdog. It must be fed at least once every `window`,
+	/// rounded up to whole seconds.
+	///
+	/// # Panics
+	/// Panics if a watchdog is already running.
+	pub fn start(window: Duration) -> Self {
+		unsafe {
+			assert!(!ACTIVE, "a watchdog is already running");
+			ACTIVE = true;
+			let partial = window.subsec_nanos() > 0;
+			WINDOW = (window.as_secs() + partial as u64)
+				.max(1)
+				.min(u64::from(u32::MAX)) as u32;
+			HUNG_AT = None;
+		}
+		let watchdog = Self { _private: () };
+		watchdog.feed();
+		assert!(
+			irq::register(on_interrupt),
+			"too many interrupt handlers are registered"
+		);
+		configure(INTERRUPT_INTERVAL, CONTROL_INTERRUPT);
+		irq::enable(IRQ_LINE);
+		watchdog
+	}
+
+	/// Restarts the window.
+	pub fn feed(&self) {
+		unsafe { write_volatile(&mut FED_AT, read_volatile(RTC_VALUE)) }
+	}
+}
+
+impl Drop for Watchdog {
+	fn drop(&mut self) {
+		restore();
+	}
+}
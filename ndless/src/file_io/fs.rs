@@ -1379,9 +1379,25 @@ impl AsInner<fs_imp::DirEntry> for DirEntry {
 ///     Ok(())
 /// }
 /// ```
+///
+/// # Consent
+///
+/// Files outside the program's folder are only removed if the user allows it,
+/// when asked with
+/// [`Capability::DeleteOutsideProgram`][crate::consent::Capability::DeleteOutsideProgram].
+/// If they refuse, an error of the kind
+/// [`PermissionDenied`][io::ErrorKind::PermissionDenied] is returned.
 
 pub fn remove_file<P: AsRef<Path>>(path: P) -> io::Result<()> {
-	fs_imp::unlink(path.as_ref())
+	let path = path.as_ref();
+	crate::consent::check_remove(path)?;
+	fs_imp::unlink(path)
+}
+
+/// Removes a file without asking the user, for files the library manages
+/// itself, such as the ones it saves atomically.
+pub(crate) fn remove_file_unchecked(path: &Path) -> io::Result<()> {
+	fs_imp::unlink(path)
 }
 
 /// Given a path, query the file system to get information about a file,
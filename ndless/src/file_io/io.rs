@@ -273,11 +273,13 @@ use crate::file_io::sys;
 
 pub use self::buffered::IntoInnerError;
 pub use self::buffered::{BufReader, BufWriter, LineWriter};
+pub use self::bytes::{ReadBytesExt, WriteBytesExt};
 pub use self::cursor::Cursor;
 pub use self::error::{Error, ErrorKind, Result};
 pub use self::util::{copy, empty, repeat, sink, Empty, Repeat, Sink};
 
 mod buffered;
+mod bytes;
 mod cursor;
 mod error;
 mod impls;
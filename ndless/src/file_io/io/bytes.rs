@@ -0,0 +1,237 @@
+//! Extension traits for reading and writing primitive integers in a fixed byte
+//! order.
+//!
+//! These are useful for hand-rolled binary formats, such as save files, where
+//! the layout must stay the same no matter how the program was compiled.
+
+use alloc::vec::Vec;
+
+use crate::io::{Read, Result, Write};
+
+macro_rules! read_impl {
+	($($ty:ident: $le:ident, $be:ident;)*) => {
+		$(
+			#[doc = concat!("Reads a little-endian `", stringify!($ty), "`.")]
+			#[inline]
+			fn $le(&mut self) -> Result<$ty> {
+				let mut buf = [0; core::mem::size_of::<$ty>()];
+				self.read_exact(&mut buf)?;
+				Ok($ty::from_le_bytes(buf))
+			}
+
+			#[doc = concat!("Reads a big-endian `", stringify!($ty), "`.")]
+			#[inline]
+			fn $be(&mut self) -> Result<$ty> {
+				let mut buf = [0; core::mem::size_of::<$ty>()];
+				self.read_exact(&mut buf)?;
+				Ok($ty::from_be_bytes(buf))
+			}
+		)*
+	};
+}
+
+macro_rules! write_impl {
+	($($ty:ident: $le:ident, $be:ident;)*) => {
+		$(
+			#[doc = concat!("Writes a little-endian `", stringify!($ty), "`.")]
+			#[inline]
+			fn $le(&mut self, n: $ty) -> Result<()> {
+				self.write_all(&n.to_le_bytes())
+			}
+
+			#[doc = concat!("Writes a big-endian `", stringify!($ty), "`.")]
+			#[inline]
+			fn $be(&mut self, n: $ty) -> Result<()> {
+				self.write_all(&n.to_be_bytes())
+			}
+		)*
+	};
+}
+
+/// Extends [`Read`] with methods for reading numbers.
+///
+/// This is implemented for every type that implements [`Read`].
+///
+/// # Examples
+///
+/// ```
+/// use ndless::io::{Cursor, ReadBytesExt};
+///
+/// let mut save = Cursor::new(vec![0x02, 0x01, 0x00, 0x00, 0x01, 0x00]);
+/// assert_eq!(save.read_u16_le().unwrap(), 0x0102);
+/// assert_eq!(save.read_i32_be().unwrap(), 0x0100);
+/// ```
+pub trait ReadBytesExt: Read {
+	/// Reads a single `u8`.
+	#[inline]
+	fn read_u8(&mut self) -> Result<u8> {
+		let mut buf = [0; 1];
+		self.read_exact(&mut buf)?;
+		Ok(buf[0])
+	}
+
+	/// Reads a single `i8`.
+	#[inline]
+	fn read_i8(&mut self) -> Result<i8> {
+		self.read_u8().map(|n| n as i8)
+	}
+
+	read_impl! {
+		u16: read_u16_le, read_u16_be;
+		i16: read_i16_le, read_i16_be;
+		u32: read_u32_le, read_u32_be;
+		i32: read_i32_le, read_i32_be;
+		u64: read_u64_le, read_u64_be;
+		i64: read_i64_le, read_i64_be;
+		u128: read_u128_le, read_u128_be;
+		i128: read_i128_le, read_i128_be;
+	}
+
+	/// Reads a little-endian `f32`.
+	#[inline]
+	fn read_f32_le(&mut self) -> Result<f32> {
+		self.read_u32_le().map(f32::from_bits)
+	}
+
+	/// Reads a big-endian `f32`.
+	#[inline]
+	fn read_f32_be(&mut self) -> Result<f32> {
+		self.read_u32_be().map(f32::from_bits)
+	}
+
+	/// Reads a little-endian `f64`.
+	#[inline]
+	fn read_f64_le(&mut self) -> Result<f64> {
+		self.read_u64_le().map(f64::from_bits)
+	}
+
+	/// Reads a big-endian `f64`.
+	#[inline]
+	fn read_f64_be(&mut self) -> Result<f64> {
+		self.read_u64_be().map(f64::from_bits)
+	}
+
+	/// Reads exactly `len` bytes into a newly allocated [`Vec`].
+	///
+	/// # Errors
+	///
+	/// If the reader hits EOF before `len` bytes have been read, an error of
+	/// the kind [`ErrorKind::UnexpectedEof`] is returned, as with
+	/// [`read_exact`].
+	///
+	/// [`ErrorKind::UnexpectedEof`]: crate::io::ErrorKind::UnexpectedEof
+	/// [`read_exact`]: Read::read_exact
+	fn read_exact_vec(&mut self, len: usize) -> Result<Vec<u8>> {
+		let mut buf = alloc::vec![0; len];
+		self.read_exact(&mut buf)?;
+		Ok(buf)
+	}
+}
+
+impl<R: Read + ?Sized> ReadBytesExt for R {}
+
+/// Extends [`Write`] with methods for writing numbers.
+///
+/// This is implemented for every type that implements [`Write`].
+///
+/// # Examples
+///
+/// ```
+/// use ndless::io::WriteBytesExt;
+///
+/// let mut save = vec![];
+/// save.write_u16_le(0x0102).unwrap();
+/// save.write_i32_be(0x0100).unwrap();
+/// assert_eq!(save, [0x02, 0x01, 0x00, 0x00, 0x01, 0x00]);
+/// ```
+pub trait WriteBytesExt: Write {
+	/// Writes a single `u8`.
+	#[inline]
+	fn write_u8(&mut self, n: u8) -> Result<()> {
+		self.write_all(&[n])
+	}
+
+	/// Writes a single `i8`.
+	#[inline]
+	fn write_i8(&mut self, n: i8) -> Result<()> {
+		self.write_u8(n as u8)
+	}
+
+	write_impl! {
+		u16: write_u16_le, write_u16_be;
+		i16: write_i16_le, write_i16_be;
+		u32: write_u32_le, write_u32_be;
+		i32: write_i32_le, write_i32_be;
+		u64: write_u64_le, write_u64_be;
+		i64: write_i64_le, write_i64_be;
+		u128: write_u128_le, write_u128_be;
+		i128: write_i128_le, write_i128_be;
+	}
+
+	/// Writes a little-endian `f32`.
+	#[inline]
+	fn write_f32_le(&mut self, n: f32) -> Result<()> {
+		self.write_u32_le(n.to_bits())
+	}
+
+	/// Writes a big-endian `f32`.
+	#[inline]
+	fn write_f32_be(&mut self, n: f32) -> Result<()> {
+		self.write_u32_be(n.to_bits())
+	}
+
+	/// Writes a little-endian `f64`.
+	#[inline]
+	fn write_f64_le(&mut self, n: f64) -> Result<()> {
+		self.write_u64_le(n.to_bits())
+	}
+
+	/// Writes a big-endian `f64`.
+	#[inline]
+	fn write_f64_be(&mut self, n: f64) -> Result<()> {
+		self.write_u64_be(n.to_bits())
+	}
+}
+
+impl<W: Write + ?Sized> WriteBytesExt for W {}
+
+#[cfg(test)]
+mod tests {
+	use alloc::vec::Vec;
+
+	use crate::io::{Cursor, ErrorKind, ReadBytesExt, WriteBytesExt};
+
+	#[test]
+	fn round_trip() {
+		let mut w = Vec::new();
+		w.write_u8(0xAB).unwrap();
+		w.write_u16_le(0x0102).unwrap();
+		w.write_u16_be(0x0102).unwrap();
+		w.write_i32_le(-2).unwrap();
+		w.write_i32_be(-2).unwrap();
+		w.write_u64_be(0x0102_0304_0506_0708).unwrap();
+		w.write_f32_le(1.5).unwrap();
+		assert_eq!(&w[..5], &[0xAB, 0x02, 0x01, 0x01, 0x02]);
+
+		let mut r = Cursor::new(w);
+		assert_eq!(r.read_u8().unwrap(), 0xAB);
+		assert_eq!(r.read_u16_le().unwrap(), 0x0102);
+		assert_eq!(r.read_u16_be().unwrap(), 0x0102);
+		assert_eq!(r.read_i32_le().unwrap(), -2);
+		assert_eq!(r.read_i32_be().unwrap(), -2);
+		assert_eq!(r.read_u64_be().unwrap(), 0x0102_0304_0506_0708);
+		assert_eq!(r.read_f32_le().unwrap(), 1.5);
+		assert_eq!(r.read_u8().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+	}
+
+	#[test]
+	fn read_exact_vec() {
+		let mut r: &[u8] = b"hello world";
+		assert_eq!(r.read_exact_vec(5).unwrap(), b"hello");
+		assert_eq!(r, b" world");
+		assert_eq!(
+			r.read_exact_vec(10).unwrap_err().kind(),
+			ErrorKind::UnexpectedEof
+		);
+	}
+}
@@ -124,12 +124,15 @@ mod inner {
 	use crate::file_io::sys::cvt;
 	use crate::libc;
 	use crate::time::Duration;
+	use crate::timer::ticks;
 
 	use super::Timespec;
 
+	/// Microseconds from [`ticks`], which is based on a free-running hardware
+	/// timer.
 	#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 	pub struct Instant {
-		t: Timespec,
+		micros: u64,
 	}
 
 	#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -141,38 +144,34 @@ mod inner {
 		t: Timespec::zero(),
 	};
 
+	fn duration_to_micros(dur: &Duration) -> Option<u64> {
+		dur.as_secs()
+			.checked_mul(1_000_000)?
+			.checked_add(u64::from(dur.subsec_micros()))
+	}
+
 	impl Instant {
+		/// Unlike [`SystemTime`], this is based on a hardware timer instead of
+		/// the clock, so it is unaffected by the user changing the time.
 		pub fn now() -> Instant {
-			use core::ptr;
-
-			let mut s = libc::timeval {
-				tv_sec: 0,
-				tv_usec: 0,
-			};
-			cvt(unsafe { libc::gettimeofday(&mut s, ptr::null_mut()) }).unwrap();
-			Instant {
-				t: Timespec {
-					t: libc::timespec {
-						tv_sec: s.tv_sec,
-						tv_nsec: s.tv_usec * 1000,
-					},
-				},
-			}
+			Instant { micros: ticks() }
 		}
 
 		pub fn checked_sub_instant(&self, other: &Instant) -> Option<Duration> {
-			self.t.sub_timespec(&other.t).ok()
+			self.micros
+				.checked_sub(other.micros)
+				.map(Duration::from_micros)
 		}
 
 		pub fn checked_add_duration(&self, other: &Duration) -> Option<Instant> {
 			Some(Instant {
-				t: self.t.checked_add_duration(other)?,
+				micros: self.micros.checked_add(duration_to_micros(other)?)?,
 			})
 		}
 
 		pub fn checked_sub_duration(&self, other: &Duration) -> Option<Instant> {
 			Some(Instant {
-				t: self.t.checked_sub_duration(other)?,
+				micros: self.micros.checked_sub(duration_to_micros(other)?)?,
 			})
 		}
 	}
@@ -180,8 +179,7 @@ mod inner {
 	impl fmt::Debug for Instant {
 		fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 			f.debug_struct("Instant")
-				.field("tv_sec", &self.t.t.tv_sec)
-				.field("tv_nsec", &self.t.t.tv_nsec)
+				.field("micros", &self.micros)
 				.finish()
 		}
 	}
@@ -10,13 +10,28 @@
 //! assert_eq!(Duration::new(5, 0), Duration::from_secs(5));
 //! ```
 
+use core::convert::TryFrom;
 use core::fmt;
 use core::ops::{Add, AddAssign, Sub, SubAssign};
+use core::ptr::{read_volatile, write_volatile};
 pub use core::time::Duration;
 
 use crate::error::Error;
 use crate::file_io::sys::time;
 use crate::file_io::sys_common::FromInner;
+use crate::io;
+
+pub use self::date::{is_leap_year, DateTime, Weekday};
+pub(crate) use self::delay::cpu_mhz;
+pub use self::delay::{calibrate, delay_cycles, delay_us};
+pub use self::frame::FrameTimer;
+pub use self::profile::cpu_time;
+pub(crate) use self::profile::record_idle;
+
+mod date;
+mod delay;
+mod frame;
+pub mod profile;
 
 /// A measurement of a monotonically nondecreasing clock.
 /// Opaque and useful only with `Duration`.
@@ -39,6 +54,10 @@ use crate::file_io::sys_common::FromInner;
 /// The size of an `Instant` struct may vary depending on the target operating
 /// system.
 ///
+/// On the calculator, instants are read from a free-running 32768Hz hardware
+/// timer rather than the clock, so they don't jump if the user changes the
+/// time. Only [`SystemTime`] follows the real-time clock.
+///
 /// Example:
 ///
 /// ```no_run
@@ -542,3 +561,73 @@ impl FromInner<time::SystemTime> for SystemTime {
 		SystemTime(time)
 	}
 }
+
+/// Seconds between the Unix epoch and the epoch of the calculator's real-time
+/// clock, January 1st, 1997.
+const RTC_EPOCH: u64 = 852_076_800;
+
+/// Sets the calculator's real-time clock, which is what [`SystemTime::now`]
+/// reads from.
+///
+/// The clock only counts whole seconds, so any fraction of a second is
+/// dropped.
+///
+/// # Errors
+///
+/// Returns an error of the kind [`InvalidInput`][crate::io::ErrorKind::InvalidInput]
+/// if the time is before 1997 or after 2133, which can't be represented by the
+/// clock.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ndless::time::{self, DateTime};
+///
+/// let time = DateTime::new(2020, 6, 21, 14, 5, 9).unwrap();
+/// time::set_system_time(time.into())?;
+/// ```
+pub fn set_system_time(time: SystemTime) -> io::Result<()> {
+	let secs = time
+		.duration_since(UNIX_EPOCH)
+		.ok()
+		.and_then(|since| since.as_secs().checked_sub(RTC_EPOCH))
+		.and_then(|secs| u32::try_from(secs).ok())
+		.ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"time out of range of the clock",
+			)
+		})?;
+	let load = 0x9009_0008 as *mut u32;
+	let status = 0x9009_0014 as *mut u32;
+	unsafe {
+		// Wait for any previous update to finish
+		while read_volatile(status) & 0b1111 != 0 {}
+		write_volatile(load, secs);
+		while read_volatile(status) & 0b1111 != 0 {}
+	}
+	Ok(())
+}
+
+/// Puts the program to sleep for at least the specified amount of time.
+///
+/// The second hardware timer is programmed to wake the CPU when the time is
+/// up, and the CPU is [idled][crate::hw::idle] until then rather than
+/// busy-waiting, which saves battery in game loops. This is the same as
+/// [`thread::sleep`][crate::thread::sleep].
+///
+/// # Examples
+///
+/// ```no_run
+/// use ndless::time::{self, Duration};
+///
+/// time::sleep(Duration::from_millis(16));
+/// ```
+pub fn sleep(dur: Duration) {
+	crate::thread::sleep(dur)
+}
+
+/// Puts the program to sleep for at least `ms` milliseconds. See [`sleep`].
+pub fn sleep_ms(ms: u32) {
+	sleep(Duration::from_millis(ms.into()))
+}
@@ -0,0 +1,347 @@
+//! Calendar dates and times
+
+use alloc::string::String;
+use core::fmt::{self, Write};
+
+use super::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECS_PER_DAY: i64 = 86400;
+
+/// A day of the week.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Weekday {
+	Monday,
+	Tuesday,
+	Wednesday,
+	Thursday,
+	Friday,
+	Saturday,
+	Sunday,
+}
+
+impl Weekday {
+	/// The full English name, such as "Monday".
+	pub fn name(self) -> &'static str {
+		match self {
+			Weekday::Monday => "Monday",
+			Weekday::Tuesday => "Tuesday",
+			Weekday::Wednesday => "Wednesday",
+			Weekday::Thursday => "Thursday",
+			Weekday::Friday => "Friday",
+			Weekday::Saturday => "Saturday",
+			Weekday::Sunday => "Sunday",
+		}
+	}
+
+	/// The number of days since Monday, from 0 to 6.
+	pub fn days_from_monday(self) -> u8 {
+		self as u8
+	}
+}
+
+const MONTHS: [&str; 12] = [
+	"January",
+	"February",
+	"March",
+	"April",
+	"May",
+	"June",
+	"July",
+	"August",
+	"September",
+	"October",
+	"November",
+	"December",
+];
+
+/// A date and time in UTC, broken down into calendar fields.
+///
+/// # Examples
+///
+/// ```
+/// use ndless::time::{DateTime, SystemTime};
+///
+/// let now = DateTime::from(SystemTime::now());
+/// println!("{}", now.format("%A, %B %d %Y at %H:%M"));
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct DateTime {
+	pub year: i32,
+	/// From 1 to 12
+	pub month: u8,
+	/// From 1 to 31
+	pub day: u8,
+	/// From 0 to 23
+	pub hour: u8,
+	/// From 0 to 59
+	pub minute: u8,
+	/// From 0 to 59
+	pub second: u8,
+	pub nanosecond: u32,
+}
+
+// Based on http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i32, month: u8, day: u8) -> i64 {
+	let year = i64::from(year) - if month <= 2 { 1 } else { 0 };
+	let era = if year >= 0 { year } else { year - 399 } / 400;
+	let year_of_era = year - era * 400;
+	let month = i64::from(month);
+	let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i32, u8, u8) {
+	let days = days + 719468;
+	let era = if days >= 0 { days } else { days - 146096 } / 146097;
+	let day_of_era = days - era * 146097;
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let mp = (5 * day_of_year + 2) / 153;
+	let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u8;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+	let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+	(year as i32, month, day)
+}
+
+/// Returns true if `year` is a leap year.
+pub fn is_leap_year(year: i32) -> bool {
+	year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+impl DateTime {
+	/// Creates a new date and time, or returns `None` if any field is out of
+	/// range.
+	pub fn new(year: i32, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Option<Self> {
+		let days_in_month = match month {
+			1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+			4 | 6 | 9 | 11 => 30,
+			2 if is_leap_year(year) => 29,
+			2 => 28,
+			_ => return None,
+		};
+		if day == 0 || day > days_in_month || hour > 23 || minute > 59 || second > 59 {
+			return None;
+		}
+		Some(Self {
+			year,
+			month,
+			day,
+			hour,
+			minute,
+			second,
+			nanosecond: 0,
+		})
+	}
+
+	/// Creates a date and time from a number of seconds since the Unix epoch.
+	pub fn from_timestamp(secs: i64, nanosecond: u32) -> Self {
+		let days = secs.div_euclid(SECS_PER_DAY);
+		let secs_of_day = secs.rem_euclid(SECS_PER_DAY);
+		let (year, month, day) = civil_from_days(days);
+		Self {
+			year,
+			month,
+			day,
+			hour: (secs_of_day / 3600) as u8,
+			minute: (secs_of_day / 60 % 60) as u8,
+			second: (secs_of_day % 60) as u8,
+			nanosecond,
+		}
+	}
+
+	/// Returns the number of seconds since the Unix epoch.
+	pub fn timestamp(&self) -> i64 {
+		days_from_civil(self.year, self.month, self.day) * SECS_PER_DAY
+			+ i64::from(self.hour) * 3600
+			+ i64::from(self.minute) * 60
+			+ i64::from(self.second)
+	}
+
+	/// Converts back into a [`SystemTime`].
+	pub fn to_system_time(&self) -> SystemTime {
+		let timestamp = self.timestamp();
+		if timestamp >= 0 {
+			UNIX_EPOCH + Duration::new(timestamp as u64, self.nanosecond)
+		} else {
+			UNIX_EPOCH - Duration::from_secs((-timestamp) as u64)
+				+ Duration::from_nanos(u64::from(self.nanosecond))
+		}
+	}
+
+	pub fn weekday(&self) -> Weekday {
+		// January 1st, 1970 was a Thursday
+		match (days_from_civil(self.year, self.month, self.day) + 3).rem_euclid(7) {
+			0 => Weekday::Monday,
+			1 => Weekday::Tuesday,
+			2 => Weekday::Wednesday,
+			3 => Weekday::Thursday,
+			4 => Weekday::Friday,
+			5 => Weekday::Saturday,
+			_ => Weekday::Sunday,
+		}
+	}
+
+	/// The day of the year, from 1 to 366.
+	pub fn ordinal(&self) -> u16 {
+		(days_from_civil(self.year, self.month, self.day) - days_from_civil(self.year, 1, 1) + 1)
+			as u16
+	}
+
+	/// Formats as RFC 3339, such as `2020-06-21T14:05:09Z`.
+	pub fn to_rfc3339(&self) -> String {
+		self.format("%Y-%m-%dT%H:%M:%SZ")
+	}
+
+	/// The English name of the month, or `None` if it isn't between 1 and 12
+	fn month_name(&self) -> Option<&'static str> {
+		MONTHS.get(usize::from(self.month).checked_sub(1)?).copied()
+	}
+
+	/// Formats using a subset of `strftime`'s specifiers:
+	///
+	/// | Specifier | Example | Description |
+	/// |-----------|---------|-------------|
+	/// | `%Y` | `2020` | Year |
+	/// | `%y` | `20` | Last two digits of the year |
+	/// | `%m` | `06` | Month, zero-padded |
+	/// | `%B` | `June` | Month name |
+	/// | `%b` | `Jun` | Abbreviated month name |
+	/// | `%d` | `21` | Day of the month, zero-padded |
+	/// | `%j` | `173` | Day of the year, zero-padded to 3 digits |
+	/// | `%A` | `Sunday` | Weekday name |
+	/// | `%a` | `Sun` | Abbreviated weekday name |
+	/// | `%H` | `14` | Hour (00-23) |
+	/// | `%I` | `02` | Hour (01-12) |
+	/// | `%p` | `PM` | AM or PM |
+	/// | `%M` | `05` | Minute |
+	/// | `%S` | `09` | Second |
+	/// | `%%` | `%` | A literal `%` |
+	///
+	/// Unknown specifiers are copied to the output unchanged. Month names are
+	/// shown as `?` if [`month`][DateTime::month] isn't between 1 and 12.
+	pub fn format(&self, fmt: &str) -> String {
+		let mut out = String::new();
+		let _ = self.write_format(&mut out, fmt);
+		out
+	}
+
+	fn write_format(&self, out: &mut impl Write, fmt: &str) -> fmt::Result {
+		let mut chars = fmt.chars();
+		while let Some(c) = chars.next() {
+			if c != '%' {
+				out.write_char(c)?;
+				continue;
+			}
+			let hour12 = match self.hour % 12 {
+				0 => 12,
+				hour => hour,
+			};
+			match chars.next() {
+				Some('Y') => write!(out, "{}", self.year)?,
+				Some('y') => write!(out, "{:02}", self.year.rem_euclid(100))?,
+				Some('m') => write!(out, "{:02}", self.month)?,
+				Some('B') => out.write_str(self.month_name().unwrap_or("?"))?,
+				Some('b') => out.write_str(self.month_name().map_or("?", |name| &name[..3]))?,
+				Some('d') => write!(out, "{:02}", self.day)?,
+				Some('j') => write!(out, "{:03}", self.ordinal())?,
+				Some('A') => out.write_str(self.weekday().name())?,
+				Some('a') => out.write_str(&self.weekday().name()[..3])?,
+				Some('H') => write!(out, "{:02}", self.hour)?,
+				Some('I') => write!(out, "{:02}", hour12)?,
+				Some('p') => out.write_str(if self.hour < 12 { "AM" } else { "PM" })?,
+				Some('M') => write!(out, "{:02}", self.minute)?,
+				Some('S') => write!(out, "{:02}", self.second)?,
+				Some('%') => out.write_char('%')?,
+				Some(other) => {
+					out.write_char('%')?;
+					out.write_char(other)?;
+				}
+				None => out.write_char('%')?,
+			}
+		}
+		Ok(())
+	}
+}
+
+impl From<SystemTime> for DateTime {
+	fn from(time: SystemTime) -> Self {
+		match time.duration_since(UNIX_EPOCH) {
+			Ok(since) => Self::from_timestamp(since.as_secs() as i64, since.subsec_nanos()),
+			Err(err) => {
+				let before = err.duration();
+				if before.subsec_nanos() == 0 {
+					Self::from_timestamp(-(before.as_secs() as i64), 0)
+				} else {
+					Self::from_timestamp(
+						-(before.as_secs() as i64) - 1,
+						1_000_000_000 - before.subsec_nanos(),
+					)
+				}
+			}
+		}
+	}
+}
+
+impl From<DateTime> for SystemTime {
+	fn from(time: DateTime) -> Self {
+		time.to_system_time()
+	}
+}
+
+impl fmt::Display for DateTime {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.write_format(f, "%Y-%m-%d %H:%M:%S")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{DateTime, Weekday};
+
+	#[test]
+	fn epoch() {
+		let epoch = DateTime::from_timestamp(0, 0);
+		assert_eq!(epoch, DateTime::new(1970, 1, 1, 0, 0, 0).unwrap());
+		assert_eq!(epoch.weekday(), Weekday::Thursday);
+	}
+
+	#[test]
+	fn round_trip() {
+		for &timestamp in &[0, 951_782_400, 1_592_748_309, -86_401, 4_102_444_800] {
+			assert_eq!(
+				DateTime::from_timestamp(timestamp, 0).timestamp(),
+				timestamp
+			);
+		}
+	}
+
+	#[test]
+	fn leap_day() {
+		let date = DateTime::from_timestamp(951_782_400, 0);
+		assert_eq!((date.year, date.month, date.day), (2000, 2, 29));
+		assert_eq!(date.ordinal(), 60);
+		assert!(DateTime::new(1900, 2, 29, 0, 0, 0).is_none());
+	}
+
+	#[test]
+	fn format() {
+		let date = DateTime::new(2020, 6, 21, 14, 5, 9).unwrap();
+		assert_eq!(date.to_rfc3339(), "2020-06-21T14:05:09Z");
+		assert_eq!(
+			date.format("%a %b %d, %I:%M %p %%"),
+			"Sun Jun 21, 02:05 PM %"
+		);
+		assert_eq!(date.weekday(), Weekday::Sunday);
+	}
+
+	#[test]
+	fn format_invalid_month() {
+		let mut date = DateTime::new(2020, 6, 21, 14, 5, 9).unwrap();
+		date.month = 0;
+		assert_eq!(date.format("%B %b %m"), "? ? 00");
+		date.month = 13;
+		assert_eq!(date.format("%B"), "?");
+	}
+}
@@ -0,0 +1,115 @@
+//! Short, calibrated busy-wait delays
+
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::timer::{get_ticks, is_running, TICKS_PER_SECOND};
+
+/// CPU cycles taken by one iteration of [`spin`]: one for `subs` and three
+/// for the taken branch on the ARM926EJ-S.
+const CYCLES_PER_LOOP: u32 = 4;
+
+/// Timer ticks to measure for. About a millisecond.
+const CALIBRATION_TICKS: u32 = 32;
+
+/// Iterations of [`spin`] per microsecond when the timer isn't running to
+/// measure against, as a 16.16 fixed point number. This is for the CX II's
+/// 396MHz, the fastest of any model, so delays are never too short.
+const FALLBACK_LOOPS_PER_US: u32 = (396 / CYCLES_PER_LOOP) << 16;
+
+/// Iterations of [`spin`] per microsecond, as a 16.16 fixed point number.
+/// Zero until calibrated.
+static mut LOOPS_PER_US: u32 = 0;
+
+#[inline(always)]
+fn spin(mut loops: u32) {
+	if loops == 0 {
+		return;
+	}
+	unsafe {
+		llvm_asm!("
+			1:
+			subs $0, $0, #1
+			bne 1b
+			"
+			: "+r"(loops)
+			:
+			: "cc"
+			: "volatile"
+		)
+	}
+}
+
+/// Measures how fast the CPU runs the delay loop, against the 32768Hz timer.
+/// This is done the first time it's needed, but must be repeated if the CPU
+/// clock is changed. [`set_cpu_speed`][crate::hw::power::set_cpu_speed] does
+/// that already.
+///
+/// With [`Timers::Off`][crate::process::Timers::Off] there's nothing to
+/// measure against, so the speed of the fastest model is assumed, and delays
+/// on slower ones take longer than asked.
+pub fn calibrate() {
+	if !is_running() {
+		unsafe { write_volatile(&mut LOOPS_PER_US, FALLBACK_LOOPS_PER_US) }
+		return;
+	}
+	let mut loops = 1024;
+	let ticks = loop {
+		// Start right after a tick, so that a partial tick isn't counted
+		let start = get_ticks();
+		while get_ticks() == start {}
+		let start = get_ticks();
+		spin(loops);
+		let ticks = get_ticks().wrapping_sub(start);
+		if ticks >= CALIBRATION_TICKS {
+			break ticks;
+		}
+		loops *= 2;
+	};
+	let per_us =
+		(u64::from(loops) << 16) * u64::from(TICKS_PER_SECOND) / (u64::from(ticks) * 1_000_000);
+	unsafe { write_volatile(&mut LOOPS_PER_US, per_us.max(1) as u32) }
+}
+
+fn loops_per_us() -> u32 {
+	let mut per_us = unsafe { read_volatile(&LOOPS_PER_US) };
+	if per_us == 0 {
+		calibrate();
+		per_us = unsafe { read_volatile(&LOOPS_PER_US) };
+	}
+	per_us
+}
+
+/// The speed of the CPU in MHz, as measured by [`calibrate`].
+pub(crate) fn cpu_mhz() -> u32 {
+	(loops_per_us() * CYCLES_PER_LOOP + (1 << 15)) >> 16
+}
+
+/// Busy-waits for at least `us` microseconds.
+///
+/// Unlike [`sleep`][super::sleep], this doesn't depend on the 32768Hz timer,
+/// so it can wait much less than its 30μs resolution, such as for bit-banged
+/// protocols. The CPU is kept busy, and interrupts can make the delay longer,
+/// so use [`sleep`][super::sleep] for anything longer than a millisecond.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ndless::time::delay_us;
+///
+/// set_clock_pin(true);
+/// delay_us(5);
+/// set_clock_pin(false);
+/// ```
+pub fn delay_us(us: u32) {
+	spin(((u64::from(us) * u64::from(loops_per_us())) >> 16) as u32);
+}
+
+/// Busy-waits for about `cycles` CPU cycles, rounded down to a multiple of
+/// four.
+///
+/// The Nspire's ARM926EJ-S has no cycle counter, so this counts iterations of
+/// a loop with a known length. It is exact when the loop is in the instruction
+/// cache and no interrupt arrives, and doesn't need calibration.
+pub fn delay_cycles(cycles: u32) {
+	spin(cycles / CYCLES_PER_LOOP);
+}
@@ -0,0 +1,120 @@
+//! Frame timing for game loops
+
+use super::{sleep, Duration, Instant};
+
+/// The number of frames averaged by [`FrameTimer::fps`].
+const SAMPLES: usize = 32;
+
+/// Measures the time between frames, and optionally limits the frame rate.
+///
+/// Call [`tick`][FrameTimer::tick] once per frame. It returns how long the
+/// previous frame took, which can be used to move things at the same speed no
+/// matter the frame rate.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ndless::time::FrameTimer;
+///
+/// let mut timer = FrameTimer::new().cap(30);
+/// loop {
+///     let delta = timer.tick();
+///     player.x += player.speed * delta.as_secs_f32();
+///     // draw...
+///     println!("{:.1} FPS", timer.fps());
+/// }
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct FrameTimer {
+	start: Instant,
+	last: Instant,
+	delta: Duration,
+	frame_len: Option<Duration>,
+	frames: u64,
+	/// Recent frame lengths in microseconds
+	samples: [u32; SAMPLES],
+	sample_sum: u64,
+}
+
+impl Default for FrameTimer {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl FrameTimer {
+	/// Creates a timer with no frame rate cap. The first frame starts now.
+	pub fn new() -> Self {
+		let now = Instant::now();
+		Self {
+			start: now,
+			last: now,
+			delta: Duration::from_secs(0),
+			frame_len: None,
+			frames: 0,
+			samples: [0; SAMPLES],
+			sample_sum: 0,
+		}
+	}
+
+	/// Limits the frame rate to `fps` frames per second. [`tick`] sleeps
+	/// until the frame has lasted at least `1 / fps` seconds, saving battery.
+	/// Passing 0 removes the cap.
+	///
+	/// [`tick`]: FrameTimer::tick
+	pub fn cap(mut self, fps: u32) -> Self {
+		self.frame_len = if fps == 0 {
+			None
+		} else {
+			Some(Duration::from_secs(1) / fps)
+		};
+		self
+	}
+
+	/// Ends the current frame, sleeping first if the frame rate is capped, and
+	/// returns how long it took.
+	pub fn tick(&mut self) -> Duration {
+		if let Some(frame_len) = self.frame_len {
+			let elapsed = self.last.elapsed();
+			if elapsed < frame_len {
+				sleep(frame_len - elapsed);
+			}
+		}
+		let now = Instant::now();
+		self.delta = now.duration_since(self.last);
+		self.last = now;
+
+		let micros = self.delta.as_micros().min(u128::from(u32::MAX)) as u32;
+		let slot = (self.frames % SAMPLES as u64) as usize;
+		self.sample_sum = self.sample_sum - u64::from(self.samples[slot]) + u64::from(micros);
+		self.samples[slot] = micros;
+		self.frames += 1;
+		self.delta
+	}
+
+	/// The length of the previous frame, as returned by the last call to
+	/// [`tick`][FrameTimer::tick].
+	pub fn delta(&self) -> Duration {
+		self.delta
+	}
+
+	/// The average frame rate over the last 32 frames, or 0 before the first
+	/// frame.
+	pub fn fps(&self) -> f32 {
+		let count = self.frames.min(SAMPLES as u64);
+		if self.sample_sum == 0 {
+			return 0.;
+		}
+		count as f32 * 1_000_000. / self.sample_sum as f32
+	}
+
+	/// The number of frames so far.
+	pub fn frames(&self) -> u64 {
+		self.frames
+	}
+
+	/// The time since the timer was created.
+	pub fn elapsed(&self) -> Duration {
+		self.start.elapsed()
+	}
+}
@@ -0,0 +1,155 @@
+//! On-calculator profiling
+//!
+//! Wrap code in [`profile!`][crate::profile] to measure how long it takes.
+//! Measurements are grouped by label, and can be read with [`stats`] or
+//! printed with [`report`] before the program exits:
+//!
+//! ```no_run
+//! use ndless::profile;
+//! use ndless::time::profile;
+//!
+//! loop {
+//!     profile!("physics", {
+//!         world.step();
+//!     });
+//!     profile!("draw", {
+//!         world.draw(&screen);
+//!     });
+//! #   break;
+//! }
+//! ndless::msg::msg("Profile", &profile::report());
+//! ```
+//!
+//! Times come from the 32768Hz timer, so a block that takes less than about
+//! 30μs may measure as zero. Sums over many calls are still accurate on
+//! average.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use super::Duration;
+use crate::timer::{get_ticks, ticks, Ticks, TICKS_PER_SECOND};
+
+static mut IDLE_TICKS: u64 = 0;
+static mut STATS: Vec<Stats> = Vec::new();
+
+pub(crate) fn record_idle(ticks: u32) {
+	unsafe { IDLE_TICKS += u64::from(ticks) }
+}
+
+/// Returns how long the program has spent running, not counting time the CPU
+/// spent idle in [`sleep`][super::sleep].
+///
+/// Nothing else runs while a program does, so apart from sleeping this is the
+/// same as the time since the program started.
+pub fn cpu_time() -> Duration {
+	let idle = unsafe { IDLE_TICKS } * 1_000_000 / u64::from(TICKS_PER_SECOND);
+	Duration::from_micros(ticks().saturating_sub(idle))
+}
+
+/// Measurements of one label.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Stats {
+	pub label: &'static str,
+	/// The number of times the block ran
+	pub calls: u32,
+	pub total: Duration,
+	pub min: Duration,
+	pub max: Duration,
+}
+
+impl Stats {
+	/// The average time of one call.
+	pub fn mean(&self) -> Duration {
+		if self.calls == 0 {
+			Duration::from_secs(0)
+		} else {
+			self.total / self.calls
+		}
+	}
+}
+
+/// Records how long a block takes when dropped. Created by
+/// [`profile!`][crate::profile].
+#[must_use = "the time is recorded when the span is dropped"]
+#[derive(Debug)]
+pub struct Span {
+	label: &'static str,
+	start: u32,
+}
+
+impl Span {
+	pub fn enter(label: &'static str) -> Self {
+		Self {
+			label,
+			start: get_ticks(),
+		}
+	}
+}
+
+impl Drop for Span {
+	fn drop(&mut self) {
+		record(
+			self.label,
+			Duration::from_ticks(get_ticks().wrapping_sub(self.start)),
+		);
+	}
+}
+
+/// Adds one measurement to a label.
+pub fn record(label: &'static str, time: Duration) {
+	let stats = unsafe { &mut STATS };
+	match stats.iter_mut().find(|stats| stats.label == label) {
+		Some(stats) => {
+			stats.calls += 1;
+			stats.total += time;
+			stats.min = stats.min.min(time);
+			stats.max = stats.max.max(time);
+		}
+		None => stats.push(Stats {
+			label,
+			calls: 1,
+			total: time,
+			min: time,
+			max: time,
+		}),
+	}
+}
+
+/// Returns the measurements of each label, slowest in total first.
+pub fn stats() -> Vec<Stats> {
+	let mut stats = unsafe { STATS.clone() };
+	stats.sort_by(|a, b| b.total.cmp(&a.total));
+	stats
+}
+
+/// Clears all measurements.
+pub fn reset() {
+	unsafe { STATS.clear() }
+}
+
+/// Formats the measurements as a table, with the share of [`cpu_time`] that
+/// each label took.
+pub fn report() -> String {
+	let cpu = cpu_time().as_secs_f32().max(f32::EPSILON);
+	let mut out = String::new();
+	let _ = writeln!(
+		out,
+		"{:<12} {:>6} {:>9} {:>9} {:>5}",
+		"label", "calls", "total", "mean", "%"
+	);
+	for stats in stats() {
+		let _ = writeln!(
+			out,
+			"{:<12} {:>6} {:>9} {:>9} {:>5.1}",
+			stats.label,
+			stats.calls,
+			format!("{:.1}ms", stats.total.as_secs_f32() * 1000.),
+			format!("{:.2}ms", stats.mean().as_secs_f32() * 1000.),
+			stats.total.as_secs_f32() / cpu * 100.,
+		);
+	}
+	out
+}
@@ -7,8 +7,10 @@
 #![no_std]
 #![allow(clippy::tabs_in_doc_comments, clippy::needless_doctest_main)]
 #![feature(core_intrinsics)]
+#![feature(global_asm)]
 #![feature(llvm_asm)]
 #![feature(never_type)]
+#![feature(panic_info_message)]
 pub extern crate alloc;
 
 pub use bindings::*;
@@ -25,6 +27,8 @@ pub mod ffi {
 }
 
 pub use cty;
+#[cfg(feature = "serde")]
+pub use serde;
 
 #[macro_export]
 macro_rules! print {
@@ -72,6 +76,47 @@ macro_rules! dbg {
     };
 }
 
+/// Shows a message box titled "Error" with the formatted text, paginated if
+/// it's long. The program keeps running once it's closed.
+///
+/// ```no_run
+/// # let path = "save.dat";
+/// ndless::error_dialog!("Couldn't open {}", path);
+/// ```
+#[macro_export]
+macro_rules! error_dialog {
+	($($arg:tt)*) => {
+		$crate::msg::__error_dialog(format_args!($($arg)*))
+	};
+}
+
+/// Like `assert!`, but if the condition is false, shows the message and
+/// location in a message box, then exits with
+/// [`process::exit`][crate::process::exit], running exit hooks.
+///
+/// ```no_run
+/// # let level = 3;
+/// ndless::msg_assert!(level < 10, "level {} doesn't exist", level);
+/// ```
+#[macro_export]
+macro_rules! msg_assert {
+	($cond:expr $(,)?) => {
+		if !$cond {
+			$crate::msg::__assert_failed(stringify!($cond), None, file!(), line!());
+		}
+	};
+	($cond:expr, $($arg:tt)+) => {
+		if !$cond {
+			$crate::msg::__assert_failed(
+				stringify!($cond),
+				Some(format_args!($($arg)+)),
+				file!(),
+				line!(),
+			);
+		}
+	};
+}
+
 pub mod prelude {
 	//! # Ndless prelude
 	//! At the top of your code, add
@@ -91,6 +136,20 @@ pub mod prelude {
 	pub use crate::math::Float;
 }
 
+/// Measures how long a block takes, adding it to the statistics for `label`.
+/// The block's value is returned. See [`time::profile`].
+///
+/// ```no_run
+/// let sum = ndless::profile!("sum", { (0..1000).sum::<u32>() });
+/// ```
+#[macro_export]
+macro_rules! profile {
+	($label:expr, $body:block) => {{
+		let _span = $crate::time::profile::Span::enter($label);
+		$body
+	}};
+}
+
 /// This macro takes a string and returns a CString
 #[macro_export]
 macro_rules! cstr {
@@ -104,10 +163,17 @@ pub use ndless_static_vars::ARGUMENTS;
 
 #[doc(hidden)]
 pub unsafe fn __init(args: &'static [*const cty::c_char]) {
+	__init_with(args, process::Startup::DEFAULT);
+}
+
+#[doc(hidden)]
+pub unsafe fn __init_with(args: &'static [*const cty::c_char], startup: process::Startup) {
+	#[cfg(feature = "arena-allocator")]
+	heap::__init_global_arena(heap::DEFAULT_ARENA_SIZE);
 	ARGUMENTS = Some(args);
 	env::args()
 		.next()
 		.map(path::PathBuf::from)
 		.and_then(|path| path.parent().map(env::set_current_dir));
-	timer::__init();
+	process::__startup(startup);
 }